@@ -0,0 +1,58 @@
+-- Restore per-row balance recalculation without the skip switch
+CREATE OR REPLACE FUNCTION update_account_balance()
+RETURNS TRIGGER AS $$
+DECLARE
+    v_old_asset_id INTEGER;
+    v_new_asset_id INTEGER;
+    v_balance DECIMAL(15, 2);
+BEGIN
+    IF TG_OP = 'DELETE' THEN
+        v_old_asset_id := OLD.asset_id;
+    ELSIF TG_OP = 'UPDATE' THEN
+        v_old_asset_id := OLD.asset_id;
+        v_new_asset_id := NEW.asset_id;
+    ELSIF TG_OP = 'INSERT' THEN
+        v_new_asset_id := NEW.asset_id;
+    END IF;
+
+    IF v_old_asset_id IS NOT NULL AND (TG_OP = 'DELETE' OR OLD.parent_operation_id IS NULL) THEN
+        v_balance := calculate_account_balance(v_old_asset_id);
+        UPDATE assets 
+        SET current_valuation = v_balance
+        WHERE id = v_old_asset_id;
+    END IF;
+
+    IF v_new_asset_id IS NOT NULL 
+       AND (v_old_asset_id IS NULL OR v_new_asset_id != v_old_asset_id)
+       AND (TG_OP = 'INSERT' AND NEW.parent_operation_id IS NULL) THEN
+        v_balance := calculate_account_balance(v_new_asset_id);
+        UPDATE assets 
+        SET current_valuation = v_balance
+        WHERE id = v_new_asset_id;
+    END IF;
+
+    IF TG_OP = 'DELETE' THEN
+        RETURN OLD;
+    ELSE
+        RETURN NEW;
+    END IF;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE FUNCTION delete_linked_transfer()
+RETURNS TRIGGER AS $$
+BEGIN
+    IF OLD.linked_operation_id IS NOT NULL THEN
+        DELETE FROM operations WHERE id = OLD.linked_operation_id;
+    END IF;
+    
+    RETURN OLD;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS trigger_delete_linked_transfer ON operations;
+CREATE TRIGGER trigger_delete_linked_transfer
+    BEFORE DELETE ON operations
+    FOR EACH ROW
+    WHEN (OLD.linked_operation_id IS NOT NULL)
+    EXECUTE FUNCTION delete_linked_transfer();
@@ -0,0 +1,77 @@
+-- Support for bulk operation endpoints
+
+-- Allow a transaction to defer balance recalculation:
+-- SELECT set_config('home_budget.skip_balance_trigger', 'on', true)
+-- Bulk handlers recalculate each affected asset once before committing.
+CREATE OR REPLACE FUNCTION update_account_balance()
+RETURNS TRIGGER AS $$
+DECLARE
+    v_old_asset_id INTEGER;
+    v_new_asset_id INTEGER;
+    v_balance DECIMAL(15, 2);
+BEGIN
+    -- Skip per-row recalculation when the caller will recalculate itself
+    IF current_setting('home_budget.skip_balance_trigger', true) = 'on' THEN
+        IF TG_OP = 'DELETE' THEN
+            RETURN OLD;
+        END IF;
+        RETURN NEW;
+    END IF;
+
+    -- Determine which asset_id(s) to update
+    IF TG_OP = 'DELETE' THEN
+        v_old_asset_id := OLD.asset_id;
+    ELSIF TG_OP = 'UPDATE' THEN
+        v_old_asset_id := OLD.asset_id;
+        v_new_asset_id := NEW.asset_id;
+    ELSIF TG_OP = 'INSERT' THEN
+        v_new_asset_id := NEW.asset_id;
+    END IF;
+
+    -- Update old asset if it exists and operation is not a split child
+    IF v_old_asset_id IS NOT NULL AND (TG_OP = 'DELETE' OR OLD.parent_operation_id IS NULL) THEN
+        v_balance := calculate_account_balance(v_old_asset_id);
+        UPDATE assets 
+        SET current_valuation = v_balance
+        WHERE id = v_old_asset_id;
+    END IF;
+
+    -- Update new asset if it exists and is different from old, and operation is not a split child
+    IF v_new_asset_id IS NOT NULL 
+       AND (v_old_asset_id IS NULL OR v_new_asset_id != v_old_asset_id)
+       AND (TG_OP = 'INSERT' AND NEW.parent_operation_id IS NULL) THEN
+        v_balance := calculate_account_balance(v_new_asset_id);
+        UPDATE assets 
+        SET current_valuation = v_balance
+        WHERE id = v_new_asset_id;
+    END IF;
+
+    -- Return appropriate value based on operation
+    IF TG_OP = 'DELETE' THEN
+        RETURN OLD;
+    ELSE
+        RETURN NEW;
+    END IF;
+END;
+$$ LANGUAGE plpgsql;
+
+-- Deleting one side of a transfer failed: the BEFORE DELETE trigger removed the
+-- partner, whose ON DELETE SET NULL then modified the row being deleted.
+-- Run it AFTER DELETE so the partner is removed once the first side is gone.
+CREATE OR REPLACE FUNCTION delete_linked_transfer()
+RETURNS TRIGGER AS $$
+BEGIN
+    IF OLD.linked_operation_id IS NOT NULL THEN
+        DELETE FROM operations WHERE id = OLD.linked_operation_id;
+    END IF;
+
+    RETURN OLD;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS trigger_delete_linked_transfer ON operations;
+CREATE TRIGGER trigger_delete_linked_transfer
+    AFTER DELETE ON operations
+    FOR EACH ROW
+    WHEN (OLD.linked_operation_id IS NOT NULL)
+    EXECUTE FUNCTION delete_linked_transfer();
@@ -0,0 +1,4 @@
+DROP FUNCTION IF EXISTS match_payee(TEXT);
+DROP INDEX IF EXISTS idx_operations_payee_id;
+ALTER TABLE operations DROP COLUMN IF EXISTS payee_id;
+DROP TABLE IF EXISTS payees;
@@ -0,0 +1,28 @@
+-- Payees (counterparties) with aliases used to recognise them in descriptions
+CREATE TABLE payees (
+    id SERIAL PRIMARY KEY,
+    name VARCHAR(100) NOT NULL,
+    aliases TEXT[] NOT NULL DEFAULT '{}',
+    default_category_id INTEGER REFERENCES categories(id) ON DELETE SET NULL,
+    default_hashtag VARCHAR(50),
+    created_date TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE UNIQUE INDEX idx_payees_name_lower ON payees (LOWER(name));
+
+ALTER TABLE operations ADD COLUMN payee_id INTEGER REFERENCES payees(id) ON DELETE SET NULL;
+CREATE INDEX idx_operations_payee_id ON operations(payee_id);
+
+-- Find the payee whose name or alias occurs in the description (case-insensitive).
+-- The longest matching alias wins so "Biedronka Express" beats "Biedronka".
+CREATE OR REPLACE FUNCTION match_payee(p_description TEXT)
+RETURNS INTEGER AS $$
+    SELECT p.id
+    FROM payees p
+    CROSS JOIN LATERAL unnest(array_append(p.aliases, p.name::TEXT)) AS alias
+    WHERE p_description IS NOT NULL
+      AND alias <> ''
+      AND POSITION(LOWER(alias) IN LOWER(p_description)) > 0
+    ORDER BY LENGTH(alias) DESC, p.id
+    LIMIT 1;
+$$ LANGUAGE sql STABLE;
@@ -0,0 +1,4 @@
+DROP TRIGGER IF EXISTS trigger_queue_attachment_file_deletion ON attachments;
+DROP FUNCTION IF EXISTS queue_attachment_file_deletion();
+DROP TABLE IF EXISTS attachment_file_deletions;
+DROP TABLE IF EXISTS attachments;
@@ -0,0 +1,36 @@
+-- Receipts and documents attached to operations.
+-- File contents live in the attachment storage backend under storage_key.
+CREATE TABLE attachments (
+    id SERIAL PRIMARY KEY,
+    operation_id INTEGER NOT NULL REFERENCES operations(id) ON DELETE CASCADE,
+    file_name VARCHAR(255) NOT NULL,
+    content_type VARCHAR(100) NOT NULL,
+    size_bytes BIGINT NOT NULL CHECK (size_bytes >= 0),
+    checksum_sha256 CHAR(64) NOT NULL,
+    storage_key VARCHAR(100) NOT NULL UNIQUE,
+    created_date TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_attachments_operation_id ON attachments(operation_id);
+
+-- Files whose rows are gone (directly or through operation/asset/user cascades)
+-- and still have to be removed from storage by the backend
+CREATE TABLE attachment_file_deletions (
+    storage_key VARCHAR(100) PRIMARY KEY,
+    deleted_date TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE OR REPLACE FUNCTION queue_attachment_file_deletion()
+RETURNS TRIGGER AS $$
+BEGIN
+    INSERT INTO attachment_file_deletions (storage_key)
+    VALUES (OLD.storage_key)
+    ON CONFLICT (storage_key) DO NOTHING;
+    RETURN OLD;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER trigger_queue_attachment_file_deletion
+AFTER DELETE ON attachments
+FOR EACH ROW
+EXECUTE FUNCTION queue_attachment_file_deletion();
@@ -0,0 +1,7 @@
+DROP TRIGGER IF EXISTS trigger_prevent_reconciled_operation_changes ON operations;
+DROP FUNCTION IF EXISTS prevent_reconciled_operation_changes();
+DROP INDEX IF EXISTS idx_operations_asset_clearing;
+ALTER TABLE operations DROP COLUMN IF EXISTS reconciliation_id;
+ALTER TABLE operations DROP COLUMN IF EXISTS clearing_status;
+DROP TABLE IF EXISTS reconciliations;
+DROP TYPE IF EXISTS clearing_status;
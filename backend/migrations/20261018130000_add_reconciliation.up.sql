@@ -0,0 +1,70 @@
+-- Reconciliation of operations against bank statements
+
+DO $$
+BEGIN
+    IF NOT EXISTS (SELECT 1 FROM pg_type WHERE typname = 'clearing_status') THEN
+        CREATE TYPE clearing_status AS ENUM ('uncleared', 'cleared', 'reconciled');
+    END IF;
+END$$;
+
+CREATE TABLE reconciliations (
+    id SERIAL PRIMARY KEY,
+    asset_id INTEGER NOT NULL REFERENCES assets(id) ON DELETE CASCADE,
+    statement_date DATE NOT NULL,
+    statement_balance DECIMAL(15, 2) NOT NULL,
+    status VARCHAR(20) NOT NULL DEFAULT 'open' CHECK (status IN ('open', 'finished')),
+    created_date TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    finished_date TIMESTAMP
+);
+
+-- At most one open session per asset
+CREATE UNIQUE INDEX idx_reconciliations_open_asset ON reconciliations(asset_id) WHERE status = 'open';
+
+ALTER TABLE operations
+    ADD COLUMN clearing_status clearing_status NOT NULL DEFAULT 'uncleared',
+    ADD COLUMN reconciliation_id INTEGER REFERENCES reconciliations(id) ON DELETE SET NULL;
+
+CREATE INDEX idx_operations_asset_clearing ON operations(asset_id, clearing_status);
+
+-- Reconciled operations are locked: amount, date, type, asset and split
+-- structure can't change and the row can't be deleted. Category, description
+-- and payee stay editable. Deleting the whole asset is still allowed.
+CREATE OR REPLACE FUNCTION prevent_reconciled_operation_changes()
+RETURNS TRIGGER AS $$
+BEGIN
+    -- Unlocked rows, or the asset itself is being deleted (cascade)
+    IF OLD.clearing_status <> 'reconciled'
+        OR NOT EXISTS (SELECT 1 FROM assets WHERE id = OLD.asset_id)
+    THEN
+        IF TG_OP = 'DELETE' THEN
+            RETURN OLD;
+        END IF;
+        RETURN NEW;
+    END IF;
+
+    IF TG_OP = 'DELETE' THEN
+        RAISE EXCEPTION 'Operation % is reconciled and cannot be deleted', OLD.id
+            USING ERRCODE = 'object_not_in_prerequisite_state';
+    END IF;
+
+    IF NEW.amount IS DISTINCT FROM OLD.amount
+        OR NEW.operation_date IS DISTINCT FROM OLD.operation_date
+        OR NEW.operation_type IS DISTINCT FROM OLD.operation_type
+        OR NEW.asset_id IS DISTINCT FROM OLD.asset_id
+        OR NEW.parent_operation_id IS DISTINCT FROM OLD.parent_operation_id
+        OR NEW.is_split IS DISTINCT FROM OLD.is_split
+        OR NEW.clearing_status IS DISTINCT FROM OLD.clearing_status
+        OR NEW.reconciliation_id IS DISTINCT FROM OLD.reconciliation_id
+    THEN
+        RAISE EXCEPTION 'Operation % is reconciled and cannot be changed', OLD.id
+            USING ERRCODE = 'object_not_in_prerequisite_state';
+    END IF;
+
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER trigger_prevent_reconciled_operation_changes
+BEFORE UPDATE OR DELETE ON operations
+FOR EACH ROW
+EXECUTE FUNCTION prevent_reconciled_operation_changes();
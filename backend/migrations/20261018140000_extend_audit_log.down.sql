@@ -0,0 +1,34 @@
+CREATE OR REPLACE FUNCTION delete_linked_transfer()
+RETURNS TRIGGER AS $$
+BEGIN
+    IF OLD.linked_operation_id IS NOT NULL THEN
+        DELETE FROM operations WHERE id = OLD.linked_operation_id;
+    END IF;
+
+    RETURN OLD;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS trigger_audit_categories ON categories;
+DROP TRIGGER IF EXISTS trigger_audit_budgets ON budgets;
+DROP TRIGGER IF EXISTS trigger_audit_assets ON assets;
+DROP TRIGGER IF EXISTS trigger_audit_operations ON operations;
+DROP FUNCTION IF EXISTS audit_row_change();
+DROP TRIGGER IF EXISTS trigger_protect_audit_log ON audit_log;
+DROP FUNCTION IF EXISTS protect_audit_log();
+
+DROP INDEX IF EXISTS idx_audit_log_action_date;
+DROP INDEX IF EXISTS idx_audit_log_request;
+DROP INDEX IF EXISTS idx_audit_log_record;
+
+DELETE FROM audit_log;
+ALTER TABLE audit_log
+    DROP CONSTRAINT IF EXISTS audit_log_action_check,
+    DROP COLUMN IF EXISTS reverted_at,
+    DROP COLUMN IF EXISTS request_id,
+    DROP COLUMN IF EXISTS after_data,
+    DROP COLUMN IF EXISTS before_data,
+    ALTER COLUMN action_date DROP NOT NULL,
+    ALTER COLUMN action TYPE VARCHAR(255),
+    ALTER COLUMN id TYPE INTEGER;
+ALTER SEQUENCE audit_log_id_seq AS INTEGER;
@@ -0,0 +1,111 @@
+-- Turn the unused audit_log table into an append-only log of changes to
+-- financial data. Rows are written by triggers so every write path (handlers,
+-- cascades, other triggers) is covered. The acting user and request id come
+-- from the home_budget.user_id / home_budget.request_id settings the backend
+-- puts on each connection it hands out.
+ALTER TABLE audit_log
+    ALTER COLUMN id TYPE BIGINT,
+    ALTER COLUMN action TYPE VARCHAR(10),
+    ALTER COLUMN action_date SET NOT NULL,
+    ADD COLUMN before_data JSONB,
+    ADD COLUMN after_data JSONB,
+    ADD COLUMN request_id VARCHAR(64),
+    ADD COLUMN reverted_at TIMESTAMP,
+    ADD CONSTRAINT audit_log_action_check CHECK (action IN ('create', 'update', 'delete'));
+
+ALTER SEQUENCE audit_log_id_seq AS BIGINT;
+
+CREATE INDEX idx_audit_log_record ON audit_log(table_name, record_id);
+CREATE INDEX idx_audit_log_request ON audit_log(request_id);
+CREATE INDEX idx_audit_log_action_date ON audit_log(action_date);
+
+-- Entries are immutable; only the revert marker may be set and user_id may be
+-- cleared when the user is deleted
+CREATE OR REPLACE FUNCTION protect_audit_log()
+RETURNS TRIGGER AS $$
+BEGIN
+    IF TG_OP = 'DELETE'
+       OR (NEW.user_id IS NOT NULL AND NEW.user_id IS DISTINCT FROM OLD.user_id)
+       OR (to_jsonb(NEW) - 'reverted_at' - 'user_id') <> (to_jsonb(OLD) - 'reverted_at' - 'user_id') THEN
+        RAISE EXCEPTION 'audit_log is append-only';
+    END IF;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER trigger_protect_audit_log
+BEFORE UPDATE OR DELETE ON audit_log
+FOR EACH ROW
+EXECUTE FUNCTION protect_audit_log();
+
+-- Arguments after the first are derived columns (balances kept by triggers)
+-- whose changes alone don't produce an entry
+CREATE OR REPLACE FUNCTION audit_row_change()
+RETURNS TRIGGER AS $$
+DECLARE
+    v_before JSONB;
+    v_after JSONB;
+    v_ignored TEXT[] := TG_ARGV[1:TG_NARGS - 1];
+    v_user_id INTEGER;
+BEGIN
+    IF TG_OP IN ('UPDATE', 'DELETE') THEN
+        v_before := to_jsonb(OLD);
+    END IF;
+    IF TG_OP IN ('INSERT', 'UPDATE') THEN
+        v_after := to_jsonb(NEW);
+    END IF;
+
+    IF TG_OP = 'UPDATE' AND (v_before - v_ignored) = (v_after - v_ignored) THEN
+        RETURN NULL;
+    END IF;
+
+    -- An unknown user id is recorded as anonymous rather than failing the write
+    SELECT id INTO v_user_id FROM users
+    WHERE id = NULLIF(current_setting('home_budget.user_id', true), '')::INTEGER;
+
+    INSERT INTO audit_log (user_id, action, table_name, record_id, before_data, after_data, request_id)
+    VALUES (
+        v_user_id,
+        CASE TG_OP WHEN 'INSERT' THEN 'create' WHEN 'UPDATE' THEN 'update' ELSE 'delete' END,
+        TG_TABLE_NAME,
+        (COALESCE(v_after, v_before)->>'id')::INTEGER,
+        v_before,
+        v_after,
+        NULLIF(current_setting('home_budget.request_id', true), '')
+    );
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER trigger_audit_operations
+AFTER INSERT OR UPDATE OR DELETE ON operations
+FOR EACH ROW EXECUTE FUNCTION audit_row_change('operations');
+
+CREATE TRIGGER trigger_audit_assets
+AFTER INSERT OR UPDATE OR DELETE ON assets
+FOR EACH ROW EXECUTE FUNCTION audit_row_change('assets', 'current_valuation', 'quantity', 'average_purchase_price');
+
+CREATE TRIGGER trigger_audit_budgets
+AFTER INSERT OR UPDATE OR DELETE ON budgets
+FOR EACH ROW EXECUTE FUNCTION audit_row_change('budgets');
+
+CREATE TRIGGER trigger_audit_categories
+AFTER INSERT OR UPDATE OR DELETE ON categories
+FOR EACH ROW EXECUTE FUNCTION audit_row_change('categories');
+
+-- A revert replays each logged row change on its own, so the partner of a
+-- transfer must not be deleted implicitly while one is running
+CREATE OR REPLACE FUNCTION delete_linked_transfer()
+RETURNS TRIGGER AS $$
+BEGIN
+    IF current_setting('home_budget.reverting', true) = 'on' THEN
+        RETURN OLD;
+    END IF;
+
+    IF OLD.linked_operation_id IS NOT NULL THEN
+        DELETE FROM operations WHERE id = OLD.linked_operation_id;
+    END IF;
+
+    RETURN OLD;
+END;
+$$ LANGUAGE plpgsql;
@@ -0,0 +1,116 @@
+-- Hashtags become links between operations and hashtags instead of being
+-- parsed out of descriptions on every read. Hashtags typed in a description
+-- are still linked automatically; links can also be added and removed
+-- directly. usage_count is the number of linked operations.
+
+DROP TRIGGER IF EXISTS update_hashtags_on_operation_update ON operations;
+DROP TRIGGER IF EXISTS decrement_hashtag_usage_on_operation_delete ON operations;
+DROP TRIGGER IF EXISTS increment_hashtag_usage_on_operation_insert ON operations;
+DROP FUNCTION IF EXISTS handle_operation_update();
+DROP FUNCTION IF EXISTS decrement_hashtags_from_description();
+DROP FUNCTION IF EXISTS increment_hashtags_from_description();
+
+CREATE TABLE operation_hashtags (
+    operation_id INTEGER NOT NULL REFERENCES operations(id) ON DELETE CASCADE,
+    hashtag_id INTEGER NOT NULL REFERENCES hashtags(id) ON DELETE CASCADE,
+    created_date TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (operation_id, hashtag_id)
+);
+
+CREATE INDEX idx_operation_hashtags_hashtag ON operation_hashtags(hashtag_id);
+
+-- Same rules as extract_hashtags in handlers/operations.rs: whitespace
+-- separated words starting with '#', keeping letters, digits and underscores
+CREATE OR REPLACE FUNCTION extract_hashtags(p_text TEXT)
+RETURNS TEXT[] AS $$
+    SELECT COALESCE(array_agg(DISTINCT tag), '{}')
+    FROM (
+        SELECT left(lower(regexp_replace(ltrim(word, '#'), '[^[:alnum:]_]', '', 'g')), 50) AS tag
+        FROM regexp_split_to_table(COALESCE(p_text, ''), '\s+') AS word
+        WHERE word LIKE '#%'
+    ) words
+    WHERE tag <> '';
+$$ LANGUAGE sql IMMUTABLE;
+
+-- Link an operation to hashtags by name, creating missing hashtags
+CREATE OR REPLACE FUNCTION link_operation_hashtags(p_operation_id INTEGER, p_names TEXT[])
+RETURNS VOID AS $$
+BEGIN
+    IF p_names IS NULL OR cardinality(p_names) = 0 THEN
+        RETURN;
+    END IF;
+
+    INSERT INTO hashtags (name)
+    SELECT DISTINCT unnest(p_names)
+    ON CONFLICT (name) DO NOTHING;
+
+    INSERT INTO operation_hashtags (operation_id, hashtag_id)
+    SELECT p_operation_id, id FROM hashtags WHERE name = ANY(p_names)
+    ON CONFLICT DO NOTHING;
+END;
+$$ LANGUAGE plpgsql;
+
+-- Follow hashtags typed into descriptions: new ones are linked, ones removed
+-- from the text are unlinked. Links added directly are left alone.
+CREATE OR REPLACE FUNCTION sync_description_hashtags()
+RETURNS TRIGGER AS $$
+DECLARE
+    v_old TEXT[] := '{}';
+    v_new TEXT[] := extract_hashtags(NEW.description);
+BEGIN
+    IF TG_OP = 'UPDATE' THEN
+        v_old := extract_hashtags(OLD.description);
+
+        DELETE FROM operation_hashtags oh
+        USING hashtags h
+        WHERE oh.hashtag_id = h.id
+          AND oh.operation_id = NEW.id
+          AND h.name = ANY(v_old)
+          AND NOT h.name = ANY(v_new);
+    END IF;
+
+    PERFORM link_operation_hashtags(NEW.id, ARRAY(SELECT unnest(v_new) EXCEPT SELECT unnest(v_old)));
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER trigger_sync_description_hashtags
+AFTER INSERT OR UPDATE OF description ON operations
+FOR EACH ROW
+EXECUTE FUNCTION sync_description_hashtags();
+
+CREATE OR REPLACE FUNCTION update_hashtag_usage_count()
+RETURNS TRIGGER AS $$
+BEGIN
+    IF TG_OP = 'INSERT' THEN
+        UPDATE hashtags SET usage_count = usage_count + 1 WHERE id = NEW.hashtag_id;
+    ELSE
+        UPDATE hashtags SET usage_count = usage_count - 1 WHERE id = OLD.hashtag_id;
+    END IF;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER trigger_update_hashtag_usage_count
+AFTER INSERT OR DELETE ON operation_hashtags
+FOR EACH ROW
+EXECUTE FUNCTION update_hashtag_usage_count();
+
+-- Backfill links from existing descriptions and recount
+INSERT INTO hashtags (name)
+SELECT DISTINCT unnest(extract_hashtags(description)) FROM operations
+ON CONFLICT (name) DO NOTHING;
+
+INSERT INTO operation_hashtags (operation_id, hashtag_id)
+SELECT o.id, h.id
+FROM operations o
+CROSS JOIN LATERAL unnest(extract_hashtags(o.description)) AS tag(name)
+INNER JOIN hashtags h ON h.name = tag.name
+ON CONFLICT DO NOTHING;
+
+UPDATE hashtags h
+SET usage_count = (SELECT COUNT(*) FROM operation_hashtags oh WHERE oh.hashtag_id = h.id);
+
+ALTER TABLE hashtags
+    ALTER COLUMN usage_count SET DEFAULT 0,
+    ALTER COLUMN usage_count SET NOT NULL;
@@ -0,0 +1 @@
+ALTER TABLE hashtags DROP COLUMN IF EXISTS budget_cap;
@@ -0,0 +1,2 @@
+-- Optional spending limit for a hashtag used as a project or trip budget
+ALTER TABLE hashtags ADD COLUMN budget_cap DECIMAL(15, 2) CHECK (budget_cap > 0);
@@ -0,0 +1,79 @@
+-- Restore the per-row balance trigger
+CREATE OR REPLACE FUNCTION calculate_account_balance(p_asset_id INTEGER)
+RETURNS DECIMAL(15, 2) AS $$
+DECLARE
+    v_balance DECIMAL(15, 2);
+BEGIN
+    -- Calculate balance from operations
+    -- Income adds to balance, expense subtracts from balance
+    -- Skip operations that are children of split operations (parent_operation_id IS NOT NULL)
+    SELECT COALESCE(
+        SUM(amount), 
+        0
+    )
+    INTO v_balance
+    FROM operations
+    WHERE asset_id = p_asset_id
+      AND parent_operation_id IS NULL;  -- Skip split children
+    
+    RETURN v_balance;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE FUNCTION update_account_balance()
+RETURNS TRIGGER AS $$
+DECLARE
+    v_old_asset_id INTEGER;
+    v_new_asset_id INTEGER;
+    v_balance DECIMAL(15, 2);
+BEGIN
+    -- Skip per-row recalculation when the caller will recalculate itself
+    IF current_setting('home_budget.skip_balance_trigger', true) = 'on' THEN
+        IF TG_OP = 'DELETE' THEN
+            RETURN OLD;
+        END IF;
+        RETURN NEW;
+    END IF;
+
+    -- Determine which asset_id(s) to update
+    IF TG_OP = 'DELETE' THEN
+        v_old_asset_id := OLD.asset_id;
+    ELSIF TG_OP = 'UPDATE' THEN
+        v_old_asset_id := OLD.asset_id;
+        v_new_asset_id := NEW.asset_id;
+    ELSIF TG_OP = 'INSERT' THEN
+        v_new_asset_id := NEW.asset_id;
+    END IF;
+
+    -- Update old asset if it exists and operation is not a split child
+    IF v_old_asset_id IS NOT NULL AND (TG_OP = 'DELETE' OR OLD.parent_operation_id IS NULL) THEN
+        v_balance := calculate_account_balance(v_old_asset_id);
+        UPDATE assets 
+        SET current_valuation = v_balance
+        WHERE id = v_old_asset_id;
+    END IF;
+
+    -- Update new asset if it exists and is different from old, and operation is not a split child
+    IF v_new_asset_id IS NOT NULL 
+       AND (v_old_asset_id IS NULL OR v_new_asset_id != v_old_asset_id)
+       AND (TG_OP = 'INSERT' AND NEW.parent_operation_id IS NULL) THEN
+        v_balance := calculate_account_balance(v_new_asset_id);
+        UPDATE assets 
+        SET current_valuation = v_balance
+        WHERE id = v_new_asset_id;
+    END IF;
+
+    -- Return appropriate value based on operation
+    IF TG_OP = 'DELETE' THEN
+        RETURN OLD;
+    ELSE
+        RETURN NEW;
+    END IF;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS trigger_update_account_balance ON operations;
+CREATE TRIGGER trigger_update_account_balance
+    AFTER INSERT OR UPDATE OR DELETE ON operations
+    FOR EACH ROW
+    EXECUTE FUNCTION update_account_balance();
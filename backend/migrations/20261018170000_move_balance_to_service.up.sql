@@ -0,0 +1,5 @@
+-- Asset balances are recalculated by the application (src/balances.rs) once
+-- per request for the assets it touched, instead of a full SUM per row here.
+DROP TRIGGER IF EXISTS trigger_update_account_balance ON operations;
+DROP FUNCTION IF EXISTS update_account_balance();
+DROP FUNCTION IF EXISTS calculate_account_balance(INTEGER);
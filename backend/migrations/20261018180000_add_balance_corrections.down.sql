@@ -0,0 +1,2 @@
+DROP TABLE IF EXISTS balance_corrections;
+-- The Correction categories are kept; operations may still use them
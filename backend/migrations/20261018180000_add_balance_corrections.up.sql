@@ -0,0 +1,57 @@
+-- System categories for balance corrections: Correction > Positive / Negative
+DO $$
+DECLARE
+    v_parent_id INT;
+BEGIN
+    SELECT id INTO v_parent_id FROM categories WHERE name = 'Correction' AND parent_id IS NULL AND is_system = TRUE;
+
+    IF v_parent_id IS NULL THEN
+        INSERT INTO categories (name, parent_id, type, sort_order, is_system)
+        VALUES ('Correction', NULL, 'expense', (SELECT COALESCE(MAX(sort_order), 0) + 1 FROM categories WHERE parent_id IS NULL), TRUE)
+        RETURNING id INTO v_parent_id;
+    END IF;
+
+    IF NOT EXISTS (SELECT 1 FROM categories WHERE name = 'Positive' AND parent_id = v_parent_id) THEN
+        INSERT INTO categories (name, parent_id, type, sort_order, is_system)
+        VALUES ('Positive', v_parent_id, 'income', 1, TRUE);
+    END IF;
+
+    IF NOT EXISTS (SELECT 1 FROM categories WHERE name = 'Negative' AND parent_id = v_parent_id) THEN
+        INSERT INTO categories (name, parent_id, type, sort_order, is_system)
+        VALUES ('Negative', v_parent_id, 'expense', 2, TRUE);
+    END IF;
+END $$;
+
+-- One row per balance correction; the correction itself is an operation
+-- (its asset and amount are read from there)
+CREATE TABLE balance_corrections (
+    id SERIAL PRIMARY KEY,
+    operation_id INTEGER NOT NULL UNIQUE REFERENCES operations(id) ON DELETE CASCADE,
+    correction_date DATE NOT NULL,
+    balance_before DECIMAL(12, 2) NOT NULL,
+    target_balance DECIMAL(12, 2) NOT NULL,
+    note TEXT NOT NULL CHECK (btrim(note) <> ''),
+    created_date TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_balance_corrections_date ON balance_corrections(correction_date);
+
+-- Earlier corrections were stored without a category or note
+UPDATE operations o
+SET category_id = c.id
+FROM categories c
+INNER JOIN categories p ON p.id = c.parent_id AND p.name = 'Correction' AND p.is_system
+WHERE o.description = 'Korekta salda'
+  AND o.category_id IS NULL
+  AND o.parent_operation_id IS NULL
+  AND c.name = CASE WHEN o.amount > 0 THEN 'Positive' ELSE 'Negative' END;
+
+INSERT INTO balance_corrections (operation_id, correction_date, balance_before, target_balance, note)
+SELECT r.id, r.operation_date, r.balance_after - r.amount, r.balance_after, 'Recorded before correction notes'
+FROM (
+    SELECT o.id, o.asset_id, o.operation_date, o.amount, o.description,
+           SUM(o.amount) OVER (PARTITION BY o.asset_id ORDER BY o.operation_date, o.id) AS balance_after
+    FROM operations o
+    WHERE o.parent_operation_id IS NULL
+) r
+WHERE r.description = 'Korekta salda';
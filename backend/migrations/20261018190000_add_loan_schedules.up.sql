@@ -0,0 +1,14 @@
+-- Repayment schedule of a liability: a fixed installment paid from a liquid
+-- asset on a given day of each month. Used by the cash-flow forecast.
+CREATE TABLE loan_schedules (
+    id SERIAL PRIMARY KEY,
+    liability_asset_id INTEGER NOT NULL UNIQUE REFERENCES assets(id) ON DELETE CASCADE,
+    payment_asset_id INTEGER NOT NULL REFERENCES assets(id) ON DELETE CASCADE,
+    installment_amount DECIMAL(12, 2) NOT NULL CHECK (installment_amount > 0),
+    day_of_month INTEGER NOT NULL CHECK (day_of_month BETWEEN 1 AND 31),
+    start_date DATE NOT NULL,
+    end_date DATE CHECK (end_date IS NULL OR end_date >= start_date),
+    created_date TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_loan_schedules_payment_asset ON loan_schedules(payment_asset_id);
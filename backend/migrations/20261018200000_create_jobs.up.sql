@@ -0,0 +1,20 @@
+-- One row per run of a background job
+CREATE TABLE jobs (
+    id BIGSERIAL PRIMARY KEY,
+    name VARCHAR(100) NOT NULL,
+    trigger VARCHAR(20) NOT NULL CHECK (trigger IN ('schedule', 'manual')),
+    status VARCHAR(20) NOT NULL CHECK (status IN ('running', 'succeeded', 'failed')),
+    started_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    finished_at TIMESTAMP,
+    duration_ms BIGINT,
+    summary TEXT,
+    error TEXT
+);
+
+CREATE INDEX idx_jobs_name_started ON jobs(name, started_at DESC);
+
+-- Recurring operations were entered by hand until now; generation starts
+-- after today so existing templates don't back-fill past occurrences
+UPDATE recurring_operations
+SET last_generated = CURRENT_DATE
+WHERE last_generated IS NULL AND start_date <= CURRENT_DATE;
@@ -0,0 +1,40 @@
+-- Alert rules and the notifications they raise
+CREATE TABLE notification_rules (
+    id SERIAL PRIMARY KEY,
+    name VARCHAR(255) NOT NULL,
+    rule_type VARCHAR(30) NOT NULL CHECK (rule_type IN ('budget_threshold', 'large_operation', 'low_balance', 'goal_deadline', 'bill_due')),
+    -- Optional scope; rules without one apply to all budgets, assets or operations
+    category_id INT REFERENCES categories(id) ON DELETE CASCADE,
+    asset_id INT REFERENCES assets(id) ON DELETE CASCADE,
+    -- Percent of the planned amount for budget_threshold, an amount for
+    -- large_operation and low_balance
+    threshold NUMERIC(15,2),
+    -- Look-ahead for goal_deadline, bill_due and (projected) low_balance
+    days_ahead INT CHECK (days_ahead BETWEEN 0 AND 366),
+    webhook_url TEXT,
+    email_to VARCHAR(255),
+    is_active BOOLEAN NOT NULL DEFAULT TRUE,
+    created_date TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    CHECK (rule_type NOT IN ('budget_threshold', 'large_operation', 'low_balance') OR threshold IS NOT NULL)
+);
+
+CREATE TABLE notifications (
+    id BIGSERIAL PRIMARY KEY,
+    rule_id INT NOT NULL REFERENCES notification_rules(id) ON DELETE CASCADE,
+    -- What the notification is about (e.g. "budget:12"); a rule raises one
+    -- notification per key
+    dedup_key VARCHAR(255) NOT NULL,
+    title VARCHAR(255) NOT NULL,
+    message TEXT NOT NULL,
+    is_read BOOLEAN NOT NULL DEFAULT FALSE,
+    read_at TIMESTAMP,
+    delivery_status VARCHAR(20) NOT NULL DEFAULT 'not_required'
+        CHECK (delivery_status IN ('not_required', 'pending', 'sending', 'sent', 'failed')),
+    delivery_attempts INT NOT NULL DEFAULT 0,
+    delivery_error TEXT,
+    last_attempt_at TIMESTAMP,
+    created_date TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE (rule_id, dedup_key)
+);
+
+CREATE INDEX idx_notifications_unread ON notifications(created_date DESC) WHERE NOT is_read;
@@ -0,0 +1,17 @@
+-- Idempotency-Key of requests that create money movements, with the response
+-- sent for it so a retried request gets the same answer instead of a duplicate
+CREATE TABLE idempotency_keys (
+    key VARCHAR(255) PRIMARY KEY,
+    method VARCHAR(10) NOT NULL,
+    path TEXT NOT NULL,
+    -- SHA-256 of the method, path and body
+    request_hash VARCHAR(64) NOT NULL,
+    -- NULL while the first request is still being handled
+    status_code SMALLINT,
+    content_type TEXT,
+    response_body BYTEA,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    completed_at TIMESTAMP
+);
+
+CREATE INDEX idx_idempotency_keys_created_at ON idempotency_keys(created_at);
@@ -0,0 +1,135 @@
+-- Trashed rows would come back without the column, so they are purged first;
+-- the asset cascade takes its reconciled operations along
+DELETE FROM assets WHERE deleted_at IS NOT NULL;
+DELETE FROM operations WHERE deleted_at IS NOT NULL;
+DELETE FROM categories WHERE deleted_at IS NOT NULL;
+
+DROP TRIGGER IF EXISTS trigger_recount_trashed_operation_hashtags ON operations;
+DROP FUNCTION IF EXISTS recount_trashed_operation_hashtags();
+
+CREATE OR REPLACE FUNCTION prevent_reconciled_operation_changes()
+RETURNS TRIGGER AS $$
+BEGIN
+    -- Unlocked rows, or the asset itself is being deleted (cascade)
+    IF OLD.clearing_status <> 'reconciled'
+        OR NOT EXISTS (SELECT 1 FROM assets WHERE id = OLD.asset_id)
+    THEN
+        IF TG_OP = 'DELETE' THEN
+            RETURN OLD;
+        END IF;
+        RETURN NEW;
+    END IF;
+
+    IF TG_OP = 'DELETE' THEN
+        RAISE EXCEPTION 'Operation % is reconciled and cannot be deleted', OLD.id
+            USING ERRCODE = 'object_not_in_prerequisite_state';
+    END IF;
+
+    IF NEW.amount IS DISTINCT FROM OLD.amount
+        OR NEW.operation_date IS DISTINCT FROM OLD.operation_date
+        OR NEW.operation_type IS DISTINCT FROM OLD.operation_type
+        OR NEW.asset_id IS DISTINCT FROM OLD.asset_id
+        OR NEW.parent_operation_id IS DISTINCT FROM OLD.parent_operation_id
+        OR NEW.is_split IS DISTINCT FROM OLD.is_split
+        OR NEW.clearing_status IS DISTINCT FROM OLD.clearing_status
+        OR NEW.reconciliation_id IS DISTINCT FROM OLD.reconciliation_id
+    THEN
+        RAISE EXCEPTION 'Operation % is reconciled and cannot be changed', OLD.id
+            USING ERRCODE = 'object_not_in_prerequisite_state';
+    END IF;
+
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE FUNCTION update_hashtag_usage_count()
+RETURNS TRIGGER AS $$
+BEGIN
+    IF TG_OP = 'INSERT' THEN
+        UPDATE hashtags SET usage_count = usage_count + 1 WHERE id = NEW.hashtag_id;
+    ELSE
+        UPDATE hashtags SET usage_count = usage_count - 1 WHERE id = OLD.hashtag_id;
+    END IF;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP FUNCTION IF EXISTS recount_hashtag_usage(INTEGER[]);
+
+UPDATE hashtags h
+SET usage_count = (SELECT COUNT(*) FROM operation_hashtags oh WHERE oh.hashtag_id = h.id);
+
+CREATE OR REPLACE FUNCTION classify_uncategorized_as_transfers()
+RETURNS TABLE(
+    operation_id INT,
+    old_category_id INT,
+    new_category_id INT,
+    amount NUMERIC,
+    operation_type operation_type
+) AS $$
+DECLARE
+    v_outgoing_category_id INT;
+    v_incoming_category_id INT;
+    v_operation RECORD;
+    v_updated_count INT := 0;
+BEGIN
+    -- Get transfer category IDs
+    SELECT c_out.id, c_in.id 
+    INTO v_outgoing_category_id, v_incoming_category_id
+    FROM categories c_parent
+    LEFT JOIN categories c_out ON c_out.parent_id = c_parent.id AND c_out.name = 'Outgoing'
+    LEFT JOIN categories c_in ON c_in.parent_id = c_parent.id AND c_in.name = 'Incoming'
+    WHERE c_parent.name = 'Transfer' AND c_parent.parent_id IS NULL;
+    
+    IF v_outgoing_category_id IS NULL OR v_incoming_category_id IS NULL THEN
+        RAISE EXCEPTION 'Transfer categories not found. Please ensure migration has been run properly.';
+    END IF;
+    
+    -- Iteruj przez wszystkie operacje bez kategorii
+    FOR v_operation IN 
+        SELECT o.id, o.category_id, o.amount, o.operation_type
+        FROM operations o
+        WHERE o.category_id IS NULL
+    LOOP
+        -- If amount is negative (expense), assign "Outgoing"
+        IF v_operation.amount < 0 THEN
+            UPDATE operations 
+            SET category_id = v_outgoing_category_id,
+                operation_type = 'expense'
+            WHERE id = v_operation.id;
+            
+            operation_id := v_operation.id;
+            old_category_id := v_operation.category_id;
+            new_category_id := v_outgoing_category_id;
+            amount := v_operation.amount;
+            operation_type := 'expense';
+            v_updated_count := v_updated_count + 1;
+            RETURN NEXT;
+            
+        -- If amount is positive (income), assign "Incoming"
+        ELSIF v_operation.amount > 0 THEN
+            UPDATE operations 
+            SET category_id = v_incoming_category_id,
+                operation_type = 'income'
+            WHERE id = v_operation.id;
+            
+            operation_id := v_operation.id;
+            old_category_id := v_operation.category_id;
+            new_category_id := v_incoming_category_id;
+            amount := v_operation.amount;
+            operation_type := 'income';
+            v_updated_count := v_updated_count + 1;
+            RETURN NEXT;
+        END IF;
+    END LOOP;
+    
+    RAISE NOTICE 'Classified % operations as transfers', v_updated_count;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP INDEX IF EXISTS idx_categories_deleted_at;
+DROP INDEX IF EXISTS idx_assets_deleted_at;
+DROP INDEX IF EXISTS idx_operations_deleted_at;
+ALTER TABLE categories DROP COLUMN IF EXISTS deleted_at;
+ALTER TABLE assets DROP COLUMN IF EXISTS deleted_at;
+ALTER TABLE operations DROP COLUMN IF EXISTS deleted_at;
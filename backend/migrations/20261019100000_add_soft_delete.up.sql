@@ -0,0 +1,157 @@
+-- Deleting an operation, asset or category moves it to the trash: the row
+-- stays with deleted_at set and queries skip it. Rows deleted together get the
+-- same time (their transaction's), which is how a restore finds the split
+-- parts, transfer partners and asset operations that belong to an item. The
+-- purge_trash job removes trashed rows for good after the retention period.
+ALTER TABLE operations ADD COLUMN deleted_at TIMESTAMP;
+ALTER TABLE assets ADD COLUMN deleted_at TIMESTAMP;
+ALTER TABLE categories ADD COLUMN deleted_at TIMESTAMP;
+
+CREATE INDEX idx_operations_deleted_at ON operations(deleted_at) WHERE deleted_at IS NOT NULL;
+CREATE INDEX idx_assets_deleted_at ON assets(deleted_at) WHERE deleted_at IS NOT NULL;
+CREATE INDEX idx_categories_deleted_at ON categories(deleted_at) WHERE deleted_at IS NOT NULL;
+
+-- Reconciled operations can't go to the trash on their own either, only with
+-- their asset; the asset is trashed first, so it no longer counts as live
+CREATE OR REPLACE FUNCTION prevent_reconciled_operation_changes()
+RETURNS TRIGGER AS $$
+BEGIN
+    -- Unlocked rows, or the asset itself is being deleted (trash or cascade)
+    IF OLD.clearing_status <> 'reconciled'
+        OR NOT EXISTS (SELECT 1 FROM assets WHERE id = OLD.asset_id AND deleted_at IS NULL)
+    THEN
+        IF TG_OP = 'DELETE' THEN
+            RETURN OLD;
+        END IF;
+        RETURN NEW;
+    END IF;
+
+    IF TG_OP = 'DELETE' OR (NEW.deleted_at IS NOT NULL AND OLD.deleted_at IS NULL) THEN
+        RAISE EXCEPTION 'Operation % is reconciled and cannot be deleted', OLD.id
+            USING ERRCODE = 'object_not_in_prerequisite_state';
+    END IF;
+
+    IF NEW.amount IS DISTINCT FROM OLD.amount
+        OR NEW.operation_date IS DISTINCT FROM OLD.operation_date
+        OR NEW.operation_type IS DISTINCT FROM OLD.operation_type
+        OR NEW.asset_id IS DISTINCT FROM OLD.asset_id
+        OR NEW.parent_operation_id IS DISTINCT FROM OLD.parent_operation_id
+        OR NEW.is_split IS DISTINCT FROM OLD.is_split
+        OR NEW.clearing_status IS DISTINCT FROM OLD.clearing_status
+        OR NEW.reconciliation_id IS DISTINCT FROM OLD.reconciliation_id
+        OR NEW.deleted_at IS DISTINCT FROM OLD.deleted_at
+    THEN
+        RAISE EXCEPTION 'Operation % is reconciled and cannot be changed', OLD.id
+            USING ERRCODE = 'object_not_in_prerequisite_state';
+    END IF;
+
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+-- usage_count counts operations outside the trash. It is recounted instead of
+-- adjusted, so links removed by a purge of trashed operations don't count twice.
+CREATE OR REPLACE FUNCTION recount_hashtag_usage(p_hashtag_ids INTEGER[])
+RETURNS VOID AS $$
+    UPDATE hashtags h
+    SET usage_count = (
+        SELECT COUNT(*) FROM operation_hashtags oh
+        INNER JOIN operations o ON o.id = oh.operation_id
+        WHERE oh.hashtag_id = h.id AND o.deleted_at IS NULL
+    )
+    WHERE h.id = ANY(p_hashtag_ids);
+$$ LANGUAGE sql;
+
+CREATE OR REPLACE FUNCTION update_hashtag_usage_count()
+RETURNS TRIGGER AS $$
+BEGIN
+    IF TG_OP = 'INSERT' THEN
+        PERFORM recount_hashtag_usage(ARRAY[NEW.hashtag_id]);
+    ELSE
+        PERFORM recount_hashtag_usage(ARRAY[OLD.hashtag_id]);
+    END IF;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE FUNCTION recount_trashed_operation_hashtags()
+RETURNS TRIGGER AS $$
+BEGIN
+    PERFORM recount_hashtag_usage(ARRAY(SELECT hashtag_id FROM operation_hashtags WHERE operation_id = NEW.id));
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER trigger_recount_trashed_operation_hashtags
+AFTER UPDATE OF deleted_at ON operations
+FOR EACH ROW
+WHEN (OLD.deleted_at IS DISTINCT FROM NEW.deleted_at)
+EXECUTE FUNCTION recount_trashed_operation_hashtags();
+
+-- Operations in the trash aren't classified
+CREATE OR REPLACE FUNCTION classify_uncategorized_as_transfers()
+RETURNS TABLE(
+    operation_id INT,
+    old_category_id INT,
+    new_category_id INT,
+    amount NUMERIC,
+    operation_type operation_type
+) AS $$
+DECLARE
+    v_outgoing_category_id INT;
+    v_incoming_category_id INT;
+    v_operation RECORD;
+    v_updated_count INT := 0;
+BEGIN
+    -- Get transfer category IDs
+    SELECT c_out.id, c_in.id
+    INTO v_outgoing_category_id, v_incoming_category_id
+    FROM categories c_parent
+    LEFT JOIN categories c_out ON c_out.parent_id = c_parent.id AND c_out.name = 'Outgoing'
+    LEFT JOIN categories c_in ON c_in.parent_id = c_parent.id AND c_in.name = 'Incoming'
+    WHERE c_parent.name = 'Transfer' AND c_parent.parent_id IS NULL;
+
+    IF v_outgoing_category_id IS NULL OR v_incoming_category_id IS NULL THEN
+        RAISE EXCEPTION 'Transfer categories not found. Please ensure migration has been run properly.';
+    END IF;
+
+    FOR v_operation IN
+        SELECT o.id, o.category_id, o.amount, o.operation_type
+        FROM operations o
+        WHERE o.category_id IS NULL AND o.deleted_at IS NULL
+    LOOP
+        -- If amount is negative (expense), assign "Outgoing"
+        IF v_operation.amount < 0 THEN
+            UPDATE operations
+            SET category_id = v_outgoing_category_id,
+                operation_type = 'expense'
+            WHERE id = v_operation.id;
+
+            operation_id := v_operation.id;
+            old_category_id := v_operation.category_id;
+            new_category_id := v_outgoing_category_id;
+            amount := v_operation.amount;
+            operation_type := 'expense';
+            v_updated_count := v_updated_count + 1;
+            RETURN NEXT;
+
+        -- If amount is positive (income), assign "Incoming"
+        ELSIF v_operation.amount > 0 THEN
+            UPDATE operations
+            SET category_id = v_incoming_category_id,
+                operation_type = 'income'
+            WHERE id = v_operation.id;
+
+            operation_id := v_operation.id;
+            old_category_id := v_operation.category_id;
+            new_category_id := v_incoming_category_id;
+            amount := v_operation.amount;
+            operation_type := 'income';
+            v_updated_count := v_updated_count + 1;
+            RETURN NEXT;
+        END IF;
+    END LOOP;
+
+    RAISE NOTICE 'Classified % operations as transfers', v_updated_count;
+END;
+$$ LANGUAGE plpgsql;
@@ -0,0 +1,2 @@
+DROP INDEX IF EXISTS idx_jobs_name_scheduled_for;
+ALTER TABLE jobs DROP COLUMN IF EXISTS scheduled_for;
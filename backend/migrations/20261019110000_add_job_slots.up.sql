@@ -0,0 +1,5 @@
+-- The scheduled time a run was started for; one run per job and time, so two
+-- instances whose schedulers fire just apart can't both run it
+ALTER TABLE jobs ADD COLUMN scheduled_for TIMESTAMP;
+
+CREATE UNIQUE INDEX idx_jobs_name_scheduled_for ON jobs(name, scheduled_for) WHERE scheduled_for IS NOT NULL;
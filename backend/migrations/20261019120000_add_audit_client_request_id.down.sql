@@ -0,0 +1,40 @@
+ALTER TABLE audit_log DROP COLUMN client_request_id;
+
+-- Arguments after the first are derived columns (balances kept by triggers)
+-- whose changes alone don't produce an entry
+CREATE OR REPLACE FUNCTION audit_row_change()
+RETURNS TRIGGER AS $$
+DECLARE
+    v_before JSONB;
+    v_after JSONB;
+    v_ignored TEXT[] := TG_ARGV[1:TG_NARGS - 1];
+    v_user_id INTEGER;
+BEGIN
+    IF TG_OP IN ('UPDATE', 'DELETE') THEN
+        v_before := to_jsonb(OLD);
+    END IF;
+    IF TG_OP IN ('INSERT', 'UPDATE') THEN
+        v_after := to_jsonb(NEW);
+    END IF;
+
+    IF TG_OP = 'UPDATE' AND (v_before - v_ignored) = (v_after - v_ignored) THEN
+        RETURN NULL;
+    END IF;
+
+    -- An unknown user id is recorded as anonymous rather than failing the write
+    SELECT id INTO v_user_id FROM users
+    WHERE id = NULLIF(current_setting('home_budget.user_id', true), '')::INTEGER;
+
+    INSERT INTO audit_log (user_id, action, table_name, record_id, before_data, after_data, request_id)
+    VALUES (
+        v_user_id,
+        CASE TG_OP WHEN 'INSERT' THEN 'create' WHEN 'UPDATE' THEN 'update' ELSE 'delete' END,
+        TG_TABLE_NAME,
+        (COALESCE(v_after, v_before)->>'id')::INTEGER,
+        v_before,
+        v_after,
+        NULLIF(current_setting('home_budget.request_id', true), '')
+    );
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
@@ -0,0 +1,148 @@
+// Asset balances. The balance of an asset is the sum of its top-level
+// operations (split parts are already counted by their parent);
+// `assets.current_valuation` caches it for assets that hold operations.
+// Handlers that write operations call `recalculate` once per request for the
+// assets they touched, before committing.
+use crate::models::{AssetLedger, BalanceDrift, LedgerEntry, Money};
+use chrono::NaiveDate;
+use sqlx::PgConnection;
+
+// Running balance after each amount, starting from `opening`
+pub fn running_balances<'a, I>(opening: &Money, amounts: I) -> Vec<Money>
+where
+    I: IntoIterator<Item = &'a Money>,
+{
+    let mut balance = opening.clone();
+    amounts
+        .into_iter()
+        .map(|amount| {
+            balance += amount;
+            balance.clone()
+        })
+        .collect()
+}
+
+// Balance at the end of the given day, or including all operations
+pub async fn balance_at(
+    conn: &mut PgConnection,
+    asset_id: i32,
+    at: Option<NaiveDate>,
+) -> Result<Money, sqlx::Error> {
+    sqlx::query_scalar(
+        "SELECT COALESCE(SUM(amount), 0) FROM operations
+         WHERE asset_id = $1 AND parent_operation_id IS NULL AND deleted_at IS NULL
+           AND ($2::date IS NULL OR operation_date <= $2)",
+    )
+    .bind(asset_id)
+    .bind(at)
+    .fetch_one(conn)
+    .await
+}
+
+// Store the calculated balance of the given assets; those in the trash keep
+// theirs. Returns the assets whose stored balance was different, with the
+// difference.
+pub async fn recalculate(
+    conn: &mut PgConnection,
+    asset_ids: &[i32],
+) -> Result<Vec<BalanceDrift>, sqlx::Error> {
+    if asset_ids.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut drifts = sqlx::query_as::<_, BalanceDrift>(
+        "WITH calculated AS (
+             SELECT a.id, a.name, a.current_valuation AS stored,
+                    COALESCE(SUM(o.amount) FILTER (WHERE o.parent_operation_id IS NULL), 0) AS calculated
+             FROM assets a
+             LEFT JOIN operations o ON o.asset_id = a.id AND o.deleted_at IS NULL
+             WHERE a.id = ANY($1) AND a.deleted_at IS NULL
+             GROUP BY a.id
+         ),
+         updated AS (
+             UPDATE assets a SET current_valuation = c.calculated
+             FROM calculated c
+             WHERE a.id = c.id AND a.current_valuation IS DISTINCT FROM c.calculated
+             RETURNING a.id
+         )
+         SELECT c.id AS asset_id, c.name AS asset_name, c.stored AS stored_balance,
+                c.calculated AS calculated_balance
+         FROM calculated c
+         WHERE c.id IN (SELECT id FROM updated)",
+    )
+    .bind(asset_ids)
+    .fetch_all(conn)
+    .await?;
+
+    for drift in &mut drifts {
+        drift.difference =
+            &drift.calculated_balance - drift.stored_balance.clone().unwrap_or_else(Money::zero);
+    }
+    drifts.sort_by_key(|d| d.asset_id);
+    Ok(drifts)
+}
+
+// Recalculate every asset that holds operations. Returns how many assets were
+// checked and the ones that were off.
+pub async fn recalculate_all(
+    conn: &mut PgConnection,
+) -> Result<(usize, Vec<BalanceDrift>), sqlx::Error> {
+    let asset_ids: Vec<i32> = sqlx::query_scalar(
+        "SELECT a.id FROM assets a
+         INNER JOIN asset_types at ON a.asset_type_id = at.id
+         WHERE a.deleted_at IS NULL
+           AND (at.allows_operations OR EXISTS (SELECT 1 FROM operations o WHERE o.asset_id = a.id))
+         ORDER BY a.id",
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+
+    let drifts = recalculate(conn, &asset_ids).await?;
+    Ok((asset_ids.len(), drifts))
+}
+
+// Top-level operations of an asset in date order with the balance after each
+pub async fn ledger(
+    conn: &mut PgConnection,
+    asset_id: i32,
+    date_from: Option<NaiveDate>,
+    date_to: Option<NaiveDate>,
+) -> Result<AssetLedger, sqlx::Error> {
+    let opening_balance = match date_from.and_then(|d| d.pred_opt()) {
+        Some(day_before) => balance_at(&mut *conn, asset_id, Some(day_before)).await?,
+        None => Money::zero(),
+    };
+
+    let mut entries = sqlx::query_as::<_, LedgerEntry>(
+        "SELECT o.id AS operation_id, o.operation_date, o.description, o.category_id,
+                c.name AS category_name, o.operation_type::text AS operation_type, o.amount,
+                o.linked_operation_id, o.clearing_status::text AS clearing_status
+         FROM operations o
+         LEFT JOIN categories c ON c.id = o.category_id
+         WHERE o.asset_id = $1 AND o.parent_operation_id IS NULL AND o.deleted_at IS NULL
+           AND ($2::date IS NULL OR o.operation_date >= $2)
+           AND ($3::date IS NULL OR o.operation_date <= $3)
+         ORDER BY o.operation_date, o.id",
+    )
+    .bind(asset_id)
+    .bind(date_from)
+    .bind(date_to)
+    .fetch_all(conn)
+    .await?;
+
+    let balances = running_balances(&opening_balance, entries.iter().map(|e| &e.amount));
+    for (entry, balance) in entries.iter_mut().zip(balances) {
+        entry.balance = balance;
+    }
+    let closing_balance = entries
+        .last()
+        .map(|e| e.balance.clone())
+        .unwrap_or_else(|| opening_balance.clone());
+
+    Ok(AssetLedger {
+        asset_id,
+        opening_balance,
+        closing_balance,
+        entries,
+    })
+}
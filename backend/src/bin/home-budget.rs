@@ -0,0 +1,54 @@
+// Command-line interface, see `home-budget --help` and backend::cli
+use anyhow::bail;
+use backend::{
+    AppState,
+    cli::{self, Cli, Command},
+    config::Config,
+    db,
+    error::AppError,
+};
+use clap::Parser;
+use dotenvy::dotenv;
+use std::process::ExitCode;
+use tracing_subscriber::{EnvFilter, fmt};
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    dotenv().ok();
+    let cli = Cli::parse();
+
+    // Logs go to stderr and stay quiet, stdout is for the output
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("warn"));
+    fmt().with_env_filter(filter).with_writer(std::io::stderr).init();
+
+    match run(cli).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            // Mistakes in the input read like the API's answers; failures keep their details
+            match e.downcast_ref::<AppError>() {
+                Some(app) if !app.status().is_server_error() => eprintln!("Error: {}", app.message()),
+                _ => eprintln!("Error: {:#}", e),
+            }
+            ExitCode::FAILURE
+        }
+    }
+}
+
+async fn run(cli: Cli) -> anyhow::Result<()> {
+    let config = Config::load()?;
+    let pool = db::connect(&config.database).await?;
+
+    // The server applies migrations at startup; here they only run on request
+    if !matches!(cli.command, Command::Migrate) {
+        let status = db::migration_status(&pool).await?;
+        if !status.pending.is_empty() || !status.failed.is_empty() {
+            bail!(
+                "The database schema is not up to date ({} migrations pending); run `home-budget migrate` first",
+                status.pending.len()
+            );
+        }
+    }
+
+    let mut stdout = std::io::stdout().lock();
+    cli::run(cli, AppState::from_config(pool, &config), &mut stdout).await
+}
@@ -0,0 +1,539 @@
+// The `home-budget` command line. Commands call the same handlers as the HTTP
+// API, so the rules, balances and audit log behave the same, and print a table
+// for people or JSON for scripts.
+use crate::{
+    AppState, db, export,
+    error::AppError,
+    handlers::{self, import_templates::ImportTemplateQuery},
+    import::{self, ColumnMapping},
+    jobs,
+    models::*,
+    request_context::RequestContext,
+    validation::{self, ValidJson, Validate},
+};
+use anyhow::{Context, bail};
+use axum::{
+    Json,
+    extract::{Path, Query, State},
+};
+use chrono::{Local, NaiveDate};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use serde::Serialize;
+use serde_json::Value;
+use std::{io::Write, path::PathBuf};
+
+#[derive(Parser)]
+#[command(name = "home-budget", version, about = "Home budget from the command line")]
+pub struct Cli {
+    /// Output format
+    #[arg(long, value_enum, default_value_t = Format::Table, global = true)]
+    pub format: Format,
+    /// User recorded in the audit log; import templates are looked up by name among theirs
+    #[arg(long, env = "HOME_BUDGET_USER", global = true)]
+    pub user: Option<i32>,
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    Table,
+    Json,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// List and add operations
+    #[command(subcommand)]
+    Operations(OperationsCommand),
+    /// Move money from one asset to another
+    Transfer(TransferArgs),
+    /// Import operations from a CSV file with a saved import template
+    Import(ImportArgs),
+    /// Create operations from recurring templates that fell due
+    #[command(subcommand)]
+    Recurring(RecurringCommand),
+    /// Check stored asset balances against the operations
+    #[command(subcommand)]
+    Balances(BalancesCommand),
+    /// Write operations as CSV (semicolon-separated, readable by the import)
+    Export(ExportArgs),
+    /// Write every table and the attachment files to a .tar.gz archive
+    Backup(BackupArgs),
+    /// Manage users
+    #[command(subcommand)]
+    Users(UsersCommand),
+    /// Apply migrations that aren't applied yet
+    Migrate,
+}
+
+#[derive(Subcommand)]
+pub enum OperationsCommand {
+    /// List operations, newest first
+    List(OperationFilter),
+    /// Add an income or expense
+    Add(AddOperationArgs),
+}
+
+#[derive(Args)]
+pub struct OperationFilter {
+    /// First operation date to include
+    #[arg(long)]
+    pub from: Option<NaiveDate>,
+    /// Last operation date to include
+    #[arg(long)]
+    pub to: Option<NaiveDate>,
+    #[arg(long)]
+    pub asset: Option<i32>,
+    #[arg(long)]
+    pub category: Option<i32>,
+    #[arg(long = "type")]
+    pub operation_type: Option<OperationType>,
+    /// Text in the description or payee name
+    #[arg(long)]
+    pub search: Option<String>,
+    /// Show at most this many operations
+    #[arg(long)]
+    pub limit: Option<usize>,
+}
+
+#[derive(Args)]
+pub struct AddOperationArgs {
+    #[arg(long)]
+    pub asset: i32,
+    /// Amount; its sign follows --type when that's given
+    #[arg(long, allow_negative_numbers = true)]
+    pub amount: Money,
+    /// Defaults to expense for negative amounts and income otherwise
+    #[arg(long = "type")]
+    pub operation_type: Option<OperationType>,
+    /// Defaults to today
+    #[arg(long)]
+    pub date: Option<NaiveDate>,
+    #[arg(long)]
+    pub category: Option<i32>,
+    #[arg(long)]
+    pub description: Option<String>,
+    #[arg(long)]
+    pub payee: Option<i32>,
+}
+
+#[derive(Args)]
+pub struct TransferArgs {
+    #[arg(long)]
+    pub from: i32,
+    #[arg(long)]
+    pub to: i32,
+    #[arg(long)]
+    pub amount: Money,
+    #[arg(long = "type", default_value = "liquid_to_liquid")]
+    pub transfer_type: TransferType,
+    /// Part of a liability payment that is interest
+    #[arg(long)]
+    pub interest: Option<Money>,
+    /// Defaults to today
+    #[arg(long)]
+    pub date: Option<NaiveDate>,
+    #[arg(long)]
+    pub description: Option<String>,
+}
+
+#[derive(Args)]
+pub struct ImportArgs {
+    pub file: PathBuf,
+    /// Import template id, or its name among the templates of --user
+    #[arg(long)]
+    pub template: String,
+    /// Put every operation on this asset instead of matching the account column
+    #[arg(long)]
+    pub asset: Option<i32>,
+    #[arg(long, default_value_t = ';')]
+    pub delimiter: char,
+    /// Import nothing when any row fails
+    #[arg(long)]
+    pub atomic: bool,
+    /// Show the operations that would be created without creating them
+    #[arg(long)]
+    pub dry_run: bool,
+}
+
+#[derive(Subcommand)]
+pub enum RecurringCommand {
+    /// Run the generate_recurring job now
+    Generate,
+}
+
+#[derive(Subcommand)]
+pub enum BalancesCommand {
+    /// Recalculate stored balances and repair drift
+    Recalculate,
+}
+
+#[derive(Args)]
+pub struct ExportArgs {
+    #[command(flatten)]
+    pub filter: OperationFilter,
+    /// File to write; standard output without it
+    #[arg(long)]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Args)]
+pub struct BackupArgs {
+    /// Archive to write, e.g. home-budget-2026-10-01.tar.gz
+    pub output: PathBuf,
+}
+
+#[derive(Subcommand)]
+pub enum UsersCommand {
+    List,
+    Add {
+        #[arg(long)]
+        full_name: String,
+        #[arg(long)]
+        nick: String,
+    },
+    Update {
+        id: i32,
+        #[arg(long)]
+        full_name: String,
+        #[arg(long)]
+        nick: String,
+    },
+    /// Delete a user with their assets, operations and templates
+    Delete { id: i32 },
+}
+
+const OPERATION_COLUMNS: &[(&str, &str)] = &[
+    ("ID", "id"),
+    ("DATE", "operation_date"),
+    ("AMOUNT", "amount"),
+    ("TYPE", "operation_type"),
+    ("ASSET", "asset_name"),
+    ("CATEGORY", "category_name"),
+    ("DESCRIPTION", "description"),
+];
+const USER_COLUMNS: &[(&str, &str)] = &[("ID", "id"), ("NAME", "full_name"), ("NICK", "nick"), ("CREATED", "creation_date")];
+const JOB_RUN_COLUMNS: &[(&str, &str)] =
+    &[("RUN", "id"), ("JOB", "name"), ("STATUS", "status"), ("SUMMARY", "summary"), ("ERROR", "error")];
+
+// Runs a command as `cli.user`; changes are audited under a request id of their own
+pub async fn run(cli: Cli, state: AppState, out: &mut dyn Write) -> anyhow::Result<()> {
+    let context = RequestContext {
+        user_id: cli.user,
+        request_id: format!("cli-{}", uuid::Uuid::new_v4()),
+        client_request_id: None,
+    };
+    let mut output = Output { format: cli.format, out };
+    context.scope(execute(cli.command, cli.user, &state, &mut output)).await
+}
+
+async fn execute(command: Command, user: Option<i32>, app: &AppState, output: &mut Output<'_>) -> anyhow::Result<()> {
+    let state = || State(app.clone());
+    match command {
+        Command::Operations(OperationsCommand::List(filter)) => {
+            let operations = list_operations(state(), &filter).await?;
+            output.print(&operations, OPERATION_COLUMNS)
+        }
+        Command::Operations(OperationsCommand::Add(args)) => {
+            let operation_type = args.operation_type.unwrap_or(if args.amount.is_negative() {
+                OperationType::Expense
+            } else {
+                OperationType::Income
+            });
+            let amount = match operation_type {
+                OperationType::Expense => -args.amount.abs(),
+                OperationType::Income => args.amount.abs(),
+            };
+            let payload = CreateOperation {
+                category_id: args.category,
+                description: args.description,
+                asset_id: args.asset,
+                amount,
+                operation_type,
+                operation_date: args.date.unwrap_or_else(today),
+                split_items: None,
+                payee_id: Some(args.payee),
+            };
+            let Json(operation) = handlers::create_operation(state(), valid(payload)?).await?;
+            output.print(&operation, &[("ID", "id"), ("DATE", "operation_date"), ("AMOUNT", "amount"), ("TYPE", "operation_type")])
+        }
+        Command::Transfer(args) => {
+            let payload = TransferRequest {
+                from_asset_id: args.from,
+                to_asset_id: Some(args.to),
+                amount: args.amount,
+                transfer_type: args.transfer_type,
+                description: args.description,
+                operation_date: args.date.unwrap_or_else(today),
+                new_asset: None,
+                investment_quantity: None,
+                interest_amount: args.interest,
+            };
+            let Json(transfer) = handlers::transfer_operation(state(), valid(payload)?).await?;
+            output.print(
+                &transfer,
+                &[("FROM OPERATION", "from_operation_id"), ("TO OPERATION", "to_operation_id"), ("INTEREST OPERATION", "interest_operation_id")],
+            )
+        }
+        Command::Import(args) => import_file(args, user, app, output).await,
+        Command::Recurring(RecurringCommand::Generate) => run_job(app, "generate_recurring", output).await,
+        Command::Balances(BalancesCommand::Recalculate) => {
+            let Json(result) = handlers::recalculate_asset_balances(state()).await?;
+            if output.format == Format::Json {
+                return output.print(&result, &[]);
+            }
+            writeln!(output.out, "{} assets checked, {} repaired", result.checked, result.repaired.len())?;
+            if result.repaired.is_empty() {
+                return Ok(());
+            }
+            output.print(
+                &result.repaired,
+                &[("ASSET", "asset_id"), ("NAME", "asset_name"), ("STORED", "stored_balance"), ("CALCULATED", "calculated_balance")],
+            )
+        }
+        Command::Export(args) => {
+            let operations = list_operations(state(), &args.filter).await?;
+            match &args.output {
+                Some(path) => {
+                    let file = std::fs::File::create(path).with_context(|| format!("Can't create {}", path.display()))?;
+                    export::operations_csv(&operations, file)?;
+                    eprintln!("{} operations written to {}", operations.len(), path.display());
+                }
+                None => export::operations_csv(&operations, &mut *output.out)?,
+            }
+            Ok(())
+        }
+        Command::Backup(args) => {
+            let manifest = export::backup(app, &args.output).await?;
+            for key in &manifest.missing_attachments {
+                eprintln!("Warning: the file of attachment {} is missing", key);
+            }
+            if output.format == Format::Json {
+                return output.print(&manifest, &[]);
+            }
+            let rows: i64 = manifest.tables.values().sum();
+            writeln!(
+                output.out,
+                "Backup written to {}: {} tables, {} rows, {} attachment files",
+                args.output.display(),
+                manifest.tables.len(),
+                rows,
+                manifest.attachments
+            )?;
+            Ok(())
+        }
+        Command::Users(command) => match command {
+            UsersCommand::List => {
+                let Json(users) = handlers::list_users(state()).await?;
+                output.print(&users, USER_COLUMNS)
+            }
+            UsersCommand::Add { full_name, nick } => {
+                let Json(user) = handlers::create_user(state(), valid(CreateUser { full_name, nick })?).await?;
+                output.print(&user, USER_COLUMNS)
+            }
+            UsersCommand::Update { id, full_name, nick } => {
+                let Json(user) = handlers::update_user(state(), Path(id), valid(CreateUser { full_name, nick })?).await?;
+                output.print(&user, USER_COLUMNS)
+            }
+            UsersCommand::Delete { id } => {
+                // Fails with not found instead of quietly deleting nothing
+                let _ = handlers::get_user(state(), Path(id)).await?;
+                handlers::delete_user(state(), Path(id)).await?;
+                output.print(&serde_json::json!({ "deleted": id }), &[("DELETED", "deleted")])
+            }
+        },
+        Command::Migrate => {
+            db::MIGRATOR.run(&app.pool).await?;
+            let status = db::migration_status(&app.pool).await?;
+            output.print(&status, &[("CURRENT", "current"), ("LATEST", "latest")])
+        }
+    }
+}
+
+// Payloads are checked here like the ValidJson extractor does for requests
+fn valid<T: Validate>(payload: T) -> Result<ValidJson<T>, AppError> {
+    validation::validate(&payload)?;
+    Ok(ValidJson(payload))
+}
+
+fn today() -> NaiveDate {
+    Local::now().date_naive()
+}
+
+async fn list_operations(state: State<AppState>, filter: &OperationFilter) -> anyhow::Result<Vec<OperationWithDetails>> {
+    let Json(operations) = handlers::list_operations(state).await?;
+    let search = filter.search.as_ref().map(|s| s.to_lowercase());
+    let contains = |text: &Option<String>, search: &str| text.as_ref().is_some_and(|t| t.to_lowercase().contains(search));
+    let operations = operations.into_iter().filter(|op| {
+        filter.from.is_none_or(|from| op.operation_date >= from)
+            && filter.to.is_none_or(|to| op.operation_date <= to)
+            && filter.asset.is_none_or(|asset| op.asset_id == asset)
+            && filter.category.is_none_or(|category| op.category_id == Some(category))
+            && filter.operation_type.is_none_or(|t| op.operation_type == t.as_str())
+            && search.as_ref().is_none_or(|s| contains(&op.description, s) || contains(&op.payee_name, s))
+    });
+    Ok(operations.take(filter.limit.unwrap_or(usize::MAX)).collect())
+}
+
+async fn import_file(args: ImportArgs, user: Option<i32>, state: &AppState, output: &mut Output<'_>) -> anyhow::Result<()> {
+    let template = match args.template.parse::<i32>() {
+        Ok(id) => handlers::get_import_template(State(state.clone()), Path(id)).await?.0,
+        Err(_) => {
+            let Some(user_id) = user else {
+                bail!("Give --user to look up the template '{}' by name, or use its id", args.template);
+            };
+            let query = ImportTemplateQuery { user_id: Some(user_id) };
+            let Json(templates) = handlers::list_import_templates(State(state.clone()), Query(query)).await?;
+            templates
+                .into_iter()
+                .find(|t| t.name == args.template)
+                .with_context(|| format!("User {} has no import template '{}'", user_id, args.template))?
+        }
+    };
+    let mapping = ColumnMapping::from_template(&template.template_data).map_err(anyhow::Error::msg)?;
+
+    if !args.delimiter.is_ascii() {
+        bail!("The delimiter must be an ASCII character");
+    }
+    let data = std::fs::read(&args.file).with_context(|| format!("Can't read {}", args.file.display()))?;
+    let file = import::parse_csv(&data, args.delimiter as u8).map_err(anyhow::Error::msg)?;
+    let Json(assets) = handlers::list_assets(State(state.clone())).await?;
+    let Json(categories) = handlers::list_categories(State(state.clone())).await?;
+    let operations = import::map_rows(&file, &mapping, &assets, &categories, args.asset)
+        .map_err(|errors| anyhow::anyhow!("Nothing was imported:\n  {}", errors.join("\n  ")))?;
+
+    let columns = &[
+        ("LINE", "line"),
+        ("DATE", "operation_date"),
+        ("AMOUNT", "amount"),
+        ("TYPE", "operation_type"),
+        ("ASSET", "asset_id"),
+        ("CATEGORY", "category_id"),
+        ("DESCRIPTION", "description"),
+    ];
+    if args.dry_run {
+        return output.print(&operations, columns);
+    }
+
+    let payload = BatchCreateOperationsRequest {
+        items: operations.iter().map(|op| op.to_batch_item()).collect(),
+        atomic: Some(args.atomic),
+    };
+    let Json(response) = handlers::batch_create_operations(State(state.clone()), valid(payload)?).await?;
+    let result = ImportResult {
+        template: template.name,
+        created: response.created,
+        failed: response.failed,
+        committed: response.committed,
+        rows: response
+            .results
+            .into_iter()
+            .map(|r| ImportRowResult {
+                line: operations[r.index].line,
+                operation_id: r.operation.map(|op| op.id),
+                error: r.error,
+            })
+            .collect(),
+    };
+    if output.format == Format::Json {
+        output.print(&result, &[])?;
+    } else {
+        writeln!(output.out, "{} operations created, {} failed", result.created, result.failed)?;
+        output.print(&result.rows, &[("LINE", "line"), ("OPERATION", "operation_id"), ("ERROR", "error")])?;
+    }
+    if result.failed > 0 {
+        bail!("{} of {} rows were not imported", result.failed, result.rows.len());
+    }
+    Ok(())
+}
+
+#[derive(Serialize)]
+struct ImportResult {
+    template: String,
+    created: usize,
+    failed: usize,
+    committed: bool,
+    rows: Vec<ImportRowResult>,
+}
+
+#[derive(Serialize)]
+struct ImportRowResult {
+    line: u64,
+    operation_id: Option<i32>,
+    error: Option<String>,
+}
+
+// Through the job runner, so a scheduled run can't overlap and the run is recorded
+async fn run_job(state: &AppState, name: &str, output: &mut Output<'_>) -> anyhow::Result<()> {
+    let run = jobs::run_job(state, name, "manual").await.map_err(|e| anyhow::anyhow!("{}", e))?;
+    output.print(&run, JOB_RUN_COLUMNS)?;
+    if run.status != "succeeded" {
+        bail!("Job {} failed: {}", name, run.error.unwrap_or_default());
+    }
+    Ok(())
+}
+
+struct Output<'a> {
+    format: Format,
+    out: &'a mut dyn Write,
+}
+
+impl Output<'_> {
+    // JSON as the API returns it, or a table of the given (header, field) columns
+    // with a row per array item
+    fn print<T: Serialize>(&mut self, value: &T, columns: &[(&str, &str)]) -> anyhow::Result<()> {
+        let value = serde_json::to_value(value)?;
+        if self.format == Format::Json {
+            serde_json::to_writer_pretty(&mut *self.out, &value)?;
+            writeln!(self.out)?;
+            return Ok(());
+        }
+
+        let rows = match value {
+            Value::Array(items) => items,
+            value => vec![value],
+        };
+        let table: Vec<Vec<String>> = rows
+            .iter()
+            .map(|row| columns.iter().map(|(_, field)| cell(&row[field])).collect())
+            .collect();
+        let widths: Vec<usize> = columns
+            .iter()
+            .enumerate()
+            .map(|(i, (header, _))| table.iter().map(|row| row[i].chars().count()).chain([header.len()]).max().unwrap_or(0))
+            .collect();
+        // Numbers line up on the right
+        let numeric: Vec<bool> = (0..columns.len())
+            .map(|i| {
+                rows.iter().all(|row| match &row[columns[i].1] {
+                    Value::Number(_) | Value::Null => true,
+                    Value::String(s) => Money::parse(s).is_ok(),
+                    _ => false,
+                })
+            })
+            .collect();
+
+        let line = |cells: Vec<&str>| {
+            let cells: Vec<String> = cells
+                .iter()
+                .enumerate()
+                .map(|(i, text)| if numeric[i] { format!("{:>w$}", text, w = widths[i]) } else { format!("{:<w$}", text, w = widths[i]) })
+                .collect();
+            cells.join("  ").trim_end().to_string()
+        };
+        writeln!(self.out, "{}", line(columns.iter().map(|(header, _)| *header).collect()))?;
+        for row in &table {
+            writeln!(self.out, "{}", line(row.iter().map(String::as_str).collect()))?;
+        }
+        Ok(())
+    }
+}
+
+fn cell(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        value => value.to_string(),
+    }
+}
@@ -0,0 +1,442 @@
+// Runtime configuration: defaults, overridden by a TOML file, overridden by
+// environment variables. The file is CONFIG_FILE, or config.toml in the working
+// directory when there is one; config.example.toml lists every setting.
+use crate::{
+    delivery::SmtpTls,
+    idempotency::{IDEMPOTENCY_KEY_HEADER, IDEMPOTENT_REPLAYED_HEADER},
+    request_context::{REQUEST_ID_HEADER, USER_ID_HEADER},
+};
+use axum::http::{HeaderName, HeaderValue, Method, header};
+use lettre::message::Mailbox;
+use serde::Deserialize;
+use std::{
+    fmt,
+    net::{IpAddr, SocketAddr},
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+use tower_http::cors::{AllowOrigin, CorsLayer};
+use tracing_subscriber::EnvFilter;
+
+const DEFAULT_CONFIG_FILE: &str = "config.toml";
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub server: ServerConfig,
+    pub cors: CorsConfig,
+    pub database: DatabaseConfig,
+    pub migrations: MigrationsConfig,
+    pub limits: LimitsConfig,
+    pub log: LogConfig,
+    pub frontend: FrontendConfig,
+    pub attachments: AttachmentsConfig,
+    // Email delivery of notifications; off without a host
+    pub smtp: Option<SmtpConfig>,
+    pub jobs: JobsConfig,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    pub host: String,
+    pub port: u16,
+    // Serve HTTPS directly instead of behind a proxy
+    pub tls: Option<TlsConfig>,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self { host: "127.0.0.1".to_string(), port: 3000, tls: None }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TlsConfig {
+    // PEM files; the certificate file may hold the whole chain
+    pub cert_path: PathBuf,
+    pub key_path: PathBuf,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CorsConfig {
+    // Origins allowed to call the API from a browser; "*" allows any
+    pub allowed_origins: Vec<String>,
+}
+
+impl Default for CorsConfig {
+    // The Vite dev server
+    fn default() -> Self {
+        Self { allowed_origins: vec!["http://localhost:5173".to_string(), "http://127.0.0.1:5173".to_string()] }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DatabaseConfig {
+    pub url: String,
+    pub max_connections: u32,
+    pub min_connections: u32,
+    pub acquire_timeout_secs: u64,
+    pub idle_timeout_secs: u64,
+}
+
+impl Default for DatabaseConfig {
+    fn default() -> Self {
+        Self {
+            url: String::new(),
+            max_connections: 10,
+            min_connections: 0,
+            acquire_timeout_secs: 30,
+            idle_timeout_secs: 600,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MigrationsConfig {
+    // Migrations are built into the binary; turn off when they're run separately
+    pub enabled: bool,
+}
+
+impl Default for MigrationsConfig {
+    fn default() -> Self {
+        Self { enabled: true }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LimitsConfig {
+    // Largest request body; attachment uploads have their own limit
+    pub body_limit_bytes: usize,
+}
+
+impl Default for LimitsConfig {
+    fn default() -> Self {
+        Self { body_limit_bytes: 2 * 1024 * 1024 }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    // Human-readable lines
+    #[default]
+    Pretty,
+    // One JSON object per line, for log collectors
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, String> {
+        match value {
+            "pretty" => Ok(LogFormat::Pretty),
+            "json" => Ok(LogFormat::Json),
+            _ => Err("expected pretty or json".to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LogConfig {
+    pub format: LogFormat,
+    // tracing filter such as "info,backend=debug"; RUST_LOG takes precedence
+    pub filter: String,
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        Self { format: LogFormat::Pretty, filter: "info".to_string() }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FrontendConfig {
+    // Built frontend to serve; without it the copy built into the binary, if any
+    pub dir: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AttachmentsConfig {
+    // Attachment files; back it up together with the database
+    pub dir: PathBuf,
+}
+
+impl Default for AttachmentsConfig {
+    fn default() -> Self {
+        Self { dir: PathBuf::from("attachments") }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SmtpConfig {
+    pub host: String,
+    // 25, 587 or 465 by tls
+    pub port: Option<u16>,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    // home-budget@<host> when not set
+    pub from: Option<String>,
+    pub tls: SmtpTls,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct JobsConfig {
+    // Instances share the work through advisory locks, so this can stay on
+    // everywhere unless jobs should run only on some instances
+    pub enabled: bool,
+}
+
+impl Default for JobsConfig {
+    fn default() -> Self {
+        Self { enabled: true }
+    }
+}
+
+// Everything wrong with the configuration, reported together
+#[derive(Debug)]
+pub struct ConfigError(pub Vec<String>);
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Invalid configuration:")?;
+        for problem in &self.0 {
+            write!(f, "\n  - {}", problem)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl Config {
+    // Configuration of this process: the config file and the environment
+    pub fn load() -> Result<Config, ConfigError> {
+        let path = match std::env::var("CONFIG_FILE") {
+            Ok(path) => Some(PathBuf::from(path)),
+            Err(_) => Some(PathBuf::from(DEFAULT_CONFIG_FILE)).filter(|path| path.exists()),
+        };
+        let config = match path {
+            Some(path) => Config::from_file(&path)?,
+            None => Config::default(),
+        };
+        config.resolve(&|name| std::env::var(name).ok())
+    }
+
+    pub fn from_file(path: &Path) -> Result<Config, ConfigError> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| ConfigError(vec![format!("Can't read {}: {}", path.display(), e)]))?;
+        toml::from_str(&text).map_err(|e| ConfigError(vec![format!("{}: {}", path.display(), e.message())]))
+    }
+
+    // Applies the environment variables over this configuration and checks the result
+    pub fn resolve(mut self, env: &dyn Fn(&str) -> Option<String>) -> Result<Config, ConfigError> {
+        let mut errors = Vec::new();
+        self.apply_env(env, &mut errors);
+        self.validate(&mut errors);
+        if errors.is_empty() { Ok(self) } else { Err(ConfigError(errors)) }
+    }
+
+    fn apply_env(&mut self, env: &dyn Fn(&str) -> Option<String>, errors: &mut Vec<String>) {
+        if let Some(url) = env_value(env, "DATABASE_URL", errors) {
+            self.database.url = url;
+        }
+        if let Some(host) = env_value(env, "HOST", errors) {
+            self.server.host = host;
+        }
+        if let Some(port) = env_value(env, "PORT", errors) {
+            self.server.port = port;
+        }
+        match (env_value(env, "TLS_CERT_PATH", errors), env_value(env, "TLS_KEY_PATH", errors)) {
+            (Some(cert_path), Some(key_path)) => self.server.tls = Some(TlsConfig { cert_path, key_path }),
+            (None, None) => {}
+            _ => errors.push("TLS_CERT_PATH and TLS_KEY_PATH must be set together".to_string()),
+        }
+        if let Some(origins) = env_value::<String>(env, "CORS_ORIGINS", errors) {
+            self.cors.allowed_origins = origins
+                .split(',')
+                .map(str::trim)
+                .filter(|origin| !origin.is_empty())
+                .map(str::to_string)
+                .collect();
+        }
+        if let Some(enabled) = env_value(env, "MIGRATIONS_ENABLED", errors) {
+            self.migrations.enabled = enabled;
+        }
+        if let Some(max) = env_value(env, "DB_MAX_CONNECTIONS", errors) {
+            self.database.max_connections = max;
+        }
+        if let Some(min) = env_value(env, "DB_MIN_CONNECTIONS", errors) {
+            self.database.min_connections = min;
+        }
+        if let Some(secs) = env_value(env, "DB_ACQUIRE_TIMEOUT_SECS", errors) {
+            self.database.acquire_timeout_secs = secs;
+        }
+        if let Some(secs) = env_value(env, "DB_IDLE_TIMEOUT_SECS", errors) {
+            self.database.idle_timeout_secs = secs;
+        }
+        if let Some(bytes) = env_value(env, "BODY_LIMIT_BYTES", errors) {
+            self.limits.body_limit_bytes = bytes;
+        }
+        if let Some(format) = env_value(env, "LOG_FORMAT", errors) {
+            self.log.format = format;
+        }
+        if let Some(dir) = env_value(env, "FRONTEND_DIR", errors) {
+            self.frontend.dir = Some(dir);
+        }
+        if let Some(dir) = env_value(env, "ATTACHMENTS_DIR", errors) {
+            self.attachments.dir = dir;
+        }
+        // Any SMTP_* variable adds to (or starts) the smtp section
+        if let Some(host) = env_value(env, "SMTP_HOST", errors) {
+            self.smtp.get_or_insert_default().host = host;
+        }
+        if let Some(port) = env_value(env, "SMTP_PORT", errors) {
+            self.smtp.get_or_insert_default().port = Some(port);
+        }
+        if let Some(username) = env_value(env, "SMTP_USERNAME", errors) {
+            self.smtp.get_or_insert_default().username = Some(username);
+        }
+        if let Some(password) = env_value(env, "SMTP_PASSWORD", errors) {
+            self.smtp.get_or_insert_default().password = Some(password);
+        }
+        if let Some(from) = env_value(env, "SMTP_FROM", errors) {
+            self.smtp.get_or_insert_default().from = Some(from);
+        }
+        if let Some(tls) = env_value(env, "SMTP_TLS", errors) {
+            self.smtp.get_or_insert_default().tls = tls;
+        }
+        if let Some(enabled) = env_value(env, "JOBS_ENABLED", errors) {
+            self.jobs.enabled = enabled;
+        }
+    }
+
+    fn validate(&self, errors: &mut Vec<String>) {
+        if self.database.url.is_empty() {
+            errors.push("database.url (DATABASE_URL) is required".to_string());
+        }
+        if self.server.host.parse::<IpAddr>().is_err() {
+            errors.push(format!("server.host '{}' is not an IP address", self.server.host));
+        }
+        if let Some(tls) = &self.server.tls {
+            for (name, path) in [("cert_path", &tls.cert_path), ("key_path", &tls.key_path)] {
+                if !path.is_file() {
+                    errors.push(format!("server.tls.{} '{}' is not a file", name, path.display()));
+                }
+            }
+        }
+
+        let origins = &self.cors.allowed_origins;
+        if origins.iter().any(|origin| origin == "*") {
+            if origins.len() > 1 {
+                errors.push("cors.allowed_origins can't combine \"*\" with other origins".to_string());
+            }
+        } else {
+            for origin in origins {
+                let valid = (origin.starts_with("http://") || origin.starts_with("https://"))
+                    && !origin.ends_with('/')
+                    && HeaderValue::from_str(origin).is_ok();
+                if !valid {
+                    errors.push(format!(
+                        "cors.allowed_origins '{}' is not an origin like https://budget.example.com",
+                        origin
+                    ));
+                }
+            }
+        }
+
+        if self.database.max_connections == 0 {
+            errors.push("database.max_connections must be at least 1".to_string());
+        }
+        if self.database.min_connections > self.database.max_connections {
+            errors.push("database.min_connections can't be above max_connections".to_string());
+        }
+        if self.database.acquire_timeout_secs == 0 {
+            errors.push("database.acquire_timeout_secs must be at least 1".to_string());
+        }
+        if self.limits.body_limit_bytes == 0 {
+            errors.push("limits.body_limit_bytes must be above 0".to_string());
+        }
+        if let Some(dir) = &self.frontend.dir
+            && !dir.join("index.html").is_file()
+        {
+            errors.push(format!("frontend.dir '{}' has no index.html; run npm run build", dir.display()));
+        }
+        if let Err(e) = EnvFilter::try_new(&self.log.filter) {
+            errors.push(format!("log.filter '{}': {}", self.log.filter, e));
+        }
+        if self.attachments.dir.as_os_str().is_empty() {
+            errors.push("attachments.dir can't be empty".to_string());
+        }
+        if let Some(smtp) = &self.smtp {
+            if smtp.host.trim().is_empty() {
+                errors.push("smtp.host (SMTP_HOST) is required when other smtp settings are set".to_string());
+            }
+            if smtp.port == Some(0) {
+                errors.push("smtp.port must be above 0".to_string());
+            }
+            if smtp.username.is_some() != smtp.password.is_some() {
+                errors.push("smtp.username and smtp.password must be set together".to_string());
+            }
+            if let Some(from) = &smtp.from
+                && let Err(e) = from.parse::<Mailbox>()
+            {
+                errors.push(format!("smtp.from '{}': {}", from, e));
+            }
+        }
+    }
+
+    pub fn listen_addr(&self) -> SocketAddr {
+        let host: IpAddr = self.server.host.parse().expect("server.host is validated");
+        SocketAddr::new(host, self.server.port)
+    }
+
+    pub fn cors_layer(&self) -> CorsLayer {
+        let origins = &self.cors.allowed_origins;
+        let allow_origin = if origins.iter().any(|origin| origin == "*") {
+            AllowOrigin::any()
+        } else {
+            AllowOrigin::list(origins.iter().filter_map(|origin| HeaderValue::from_str(origin).ok()))
+        };
+        CorsLayer::new()
+            .allow_origin(allow_origin)
+            .allow_methods([Method::GET, Method::POST, Method::PUT, Method::DELETE])
+            .allow_headers([
+                header::CONTENT_TYPE,
+                HeaderName::from_static(USER_ID_HEADER),
+                HeaderName::from_static(REQUEST_ID_HEADER),
+                HeaderName::from_static(IDEMPOTENCY_KEY_HEADER),
+            ])
+            .expose_headers([
+                header::CONTENT_DISPOSITION,
+                HeaderName::from_static(REQUEST_ID_HEADER),
+                HeaderName::from_static(IDEMPOTENT_REPLAYED_HEADER),
+            ])
+    }
+}
+
+// Parsed value of an environment variable; unset and empty are both None
+fn env_value<T: FromStr>(env: &dyn Fn(&str) -> Option<String>, name: &str, errors: &mut Vec<String>) -> Option<T>
+where
+    T::Err: fmt::Display,
+{
+    let value = env(name).filter(|value| !value.trim().is_empty())?;
+    match value.trim().parse() {
+        Ok(parsed) => Some(parsed),
+        Err(e) => {
+            errors.push(format!("{} '{}': {}", name, value, e));
+            None
+        }
+    }
+}
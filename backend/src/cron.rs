@@ -0,0 +1,124 @@
+// Five-field cron schedules: minute hour day-of-month month day-of-week.
+// Fields accept `*`, numbers, ranges (`1-5`), steps (`*/15`, `0-30/10`) and
+// comma-separated lists. Day of week is 0-7 with both 0 and 7 meaning Sunday.
+// As in cron, when both day fields are restricted a day matching either runs.
+use chrono::{Datelike, Duration, NaiveDateTime, Timelike};
+
+#[derive(Debug, Clone)]
+struct Field {
+    allowed: Vec<bool>,
+    restricted: bool,
+}
+
+impl Field {
+    fn parse(spec: &str, min: u32, max: u32) -> Result<Field, String> {
+        let mut allowed = vec![false; max as usize + 1];
+        for part in spec.split(',') {
+            let (range, step) = match part.split_once('/') {
+                Some((range, step)) => {
+                    let step: u32 = step.parse().map_err(|_| format!("Invalid step in '{}'", part))?;
+                    if step == 0 {
+                        return Err(format!("Invalid step in '{}'", part));
+                    }
+                    (range, step)
+                }
+                None => (part, 1),
+            };
+            let (start, end) = if range == "*" {
+                (min, max)
+            } else if let Some((start, end)) = range.split_once('-') {
+                let start = start.parse().map_err(|_| format!("Invalid range '{}'", range))?;
+                let end = end.parse().map_err(|_| format!("Invalid range '{}'", range))?;
+                (start, end)
+            } else {
+                let value = range.parse().map_err(|_| format!("Invalid value '{}'", range))?;
+                // `5/10` means from 5 to the end in steps of 10
+                if step > 1 { (value, max) } else { (value, value) }
+            };
+            if start < min || end > max || start > end {
+                return Err(format!("'{}' is out of range {}-{}", part, min, max));
+            }
+            for value in (start..=end).step_by(step as usize) {
+                allowed[value as usize] = true;
+            }
+        }
+        Ok(Field {
+            allowed,
+            restricted: spec != "*",
+        })
+    }
+
+    fn matches(&self, value: u32) -> bool {
+        self.allowed.get(value as usize).copied().unwrap_or(false)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Schedule {
+    minute: Field,
+    hour: Field,
+    day_of_month: Field,
+    month: Field,
+    day_of_week: Field,
+}
+
+impl Schedule {
+    pub fn parse(spec: &str) -> Result<Schedule, String> {
+        let fields: Vec<&str> = spec.split_whitespace().collect();
+        let [minute, hour, day_of_month, month, day_of_week] = fields[..] else {
+            return Err(format!("Expected 5 fields in '{}'", spec));
+        };
+        let mut day_of_week = Field::parse(day_of_week, 0, 7)?;
+        if day_of_week.allowed[7] {
+            day_of_week.allowed[0] = true;
+        }
+        Ok(Schedule {
+            minute: Field::parse(minute, 0, 59)?,
+            hour: Field::parse(hour, 0, 23)?,
+            day_of_month: Field::parse(day_of_month, 1, 31)?,
+            month: Field::parse(month, 1, 12)?,
+            day_of_week,
+        })
+    }
+
+    fn matches_day(&self, at: NaiveDateTime) -> bool {
+        if !self.month.matches(at.month()) {
+            return false;
+        }
+        let dom = self.day_of_month.matches(at.day());
+        let dow = self.day_of_week.matches(at.weekday().num_days_from_sunday());
+        match (self.day_of_month.restricted, self.day_of_week.restricted) {
+            (true, true) => dom || dow,
+            (true, false) => dom,
+            (false, true) => dow,
+            (false, false) => true,
+        }
+    }
+
+    // Whether the schedule fires in the minute containing `at`
+    pub fn matches(&self, at: NaiveDateTime) -> bool {
+        self.matches_day(at) && self.hour.matches(at.hour()) && self.minute.matches(at.minute())
+    }
+
+    // First minute strictly after `after` at which the schedule fires, looking
+    // at most a little over four years ahead (enough for Feb 29)
+    pub fn next_after(&self, after: NaiveDateTime) -> Option<NaiveDateTime> {
+        let mut at = after.with_second(0)?.with_nanosecond(0)? + Duration::minutes(1);
+        let limit = at + Duration::days(4 * 366);
+        while at <= limit {
+            if !self.matches_day(at) {
+                at = at.date().succ_opt()?.and_hms_opt(0, 0, 0)?;
+                continue;
+            }
+            if !self.hour.matches(at.hour()) {
+                at = at.with_minute(0)? + Duration::hours(1);
+                continue;
+            }
+            if self.minute.matches(at.minute()) {
+                return Some(at);
+            }
+            at += Duration::minutes(1);
+        }
+        None
+    }
+}
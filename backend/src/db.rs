@@ -0,0 +1,111 @@
+// Database checks at startup and for /health and /ready: migrations embedded
+// in the binary and the system rows the handlers look up by name.
+use crate::{
+    config::DatabaseConfig,
+    error::AppError,
+    handlers::categories::{ensure_correction_categories, ensure_debt_categories},
+    models::{AssetCategory, MigrationStatus},
+};
+use sqlx::{PgPool, migrate::Migrator};
+use std::time::Duration;
+
+pub static MIGRATOR: Migrator = sqlx::migrate!("./migrations");
+
+// Connections carry the acting user and request id for the audit log
+pub async fn connect(config: &DatabaseConfig) -> Result<PgPool, sqlx::Error> {
+    crate::request_context::pool_options()
+        .max_connections(config.max_connections)
+        .min_connections(config.min_connections)
+        .acquire_timeout(Duration::from_secs(config.acquire_timeout_secs))
+        .idle_timeout(Duration::from_secs(config.idle_timeout_secs))
+        .connect(&config.url)
+        .await
+}
+
+// System categories as (parent, child); transfers need theirs from the migrations,
+// corrections and debts are created on first use
+const SYSTEM_CATEGORIES: &[(&str, &str)] = &[
+    ("Transfer", "Outgoing"),
+    ("Transfer", "Incoming"),
+    ("Correction", "Positive"),
+    ("Correction", "Negative"),
+    ("Depts", "Interest"),
+];
+
+// Applied migrations compared with the ones in the binary
+pub async fn migration_status(pool: &PgPool) -> Result<MigrationStatus, sqlx::Error> {
+    let exists: bool = sqlx::query_scalar("SELECT to_regclass('_sqlx_migrations') IS NOT NULL")
+        .fetch_one(pool)
+        .await?;
+    let applied: Vec<(i64, bool)> = if exists {
+        sqlx::query_as("SELECT version, success FROM _sqlx_migrations ORDER BY version")
+            .fetch_all(pool)
+            .await?
+    } else {
+        Vec::new()
+    };
+
+    let embedded: Vec<i64> = MIGRATOR
+        .iter()
+        .filter(|m| !m.migration_type.is_down_migration())
+        .map(|m| m.version)
+        .collect();
+    Ok(MigrationStatus {
+        current: applied.iter().filter(|(_, success)| *success).map(|(version, _)| *version).max(),
+        latest: embedded.iter().copied().max(),
+        pending: embedded
+            .into_iter()
+            .filter(|version| !applied.iter().any(|(applied, _)| applied == version))
+            .collect(),
+        failed: applied.iter().filter(|(_, success)| !success).map(|(version, _)| *version).collect(),
+    })
+}
+
+// What's missing of the system categories and asset types, empty when all is in place
+pub async fn missing_system_data(pool: &PgPool) -> Result<Vec<String>, sqlx::Error> {
+    let mut missing = Vec::new();
+    for (parent, child) in SYSTEM_CATEGORIES {
+        let exists: bool = sqlx::query_scalar(
+            "SELECT EXISTS (
+                SELECT 1 FROM categories c
+                JOIN categories p ON p.id = c.parent_id
+                WHERE p.name = $1 AND p.parent_id IS NULL AND c.name = $2
+            )",
+        )
+        .bind(parent)
+        .bind(child)
+        .fetch_one(pool)
+        .await?;
+        if !exists {
+            missing.push(format!("category {} > {}", parent, child));
+        }
+    }
+
+    let categories: Vec<String> = sqlx::query_scalar("SELECT DISTINCT category FROM asset_types")
+        .fetch_all(pool)
+        .await?;
+    for category in AssetCategory::ALL {
+        if !categories.iter().any(|c| c == category.as_str()) {
+            missing.push(format!("asset type of category {}", category));
+        }
+    }
+    Ok(missing)
+}
+
+// Creates the system categories made on first use and fails when anything
+// the migrations should have added is missing
+pub async fn ensure_system_data(pool: &PgPool) -> Result<(), AppError> {
+    let mut conn = pool.acquire().await?;
+    ensure_correction_categories(&mut conn).await?;
+    drop(conn);
+    ensure_debt_categories(pool).await?;
+
+    let missing = missing_system_data(pool).await?;
+    if !missing.is_empty() {
+        return Err(AppError::Internal(format!(
+            "Missing system data ({}); are all migrations applied?",
+            missing.join(", ")
+        )));
+    }
+    Ok(())
+}
@@ -0,0 +1,101 @@
+// Sending notifications outside the app: a JSON POST to a webhook URL or an
+// email over SMTP. SMTP comes from the smtp section of the configuration; without
+// it email delivery is unavailable and reported as a delivery error.
+use crate::config::SmtpConfig;
+use lettre::{
+    AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor,
+    message::header::ContentType,
+    transport::smtp::authentication::Credentials,
+};
+use serde::Deserialize;
+use std::{str::FromStr, time::Duration};
+
+const DELIVERY_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SmtpTls {
+    // Plain connection, for local relays and test servers
+    None,
+    #[default]
+    StartTls,
+    Tls,
+}
+
+impl FromStr for SmtpTls {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, String> {
+        match value {
+            "none" => Ok(SmtpTls::None),
+            "starttls" => Ok(SmtpTls::StartTls),
+            "tls" => Ok(SmtpTls::Tls),
+            _ => Err("expected none, starttls or tls".to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SmtpSettings {
+    pub host: String,
+    pub port: u16,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub from: String,
+    pub tls: SmtpTls,
+}
+
+impl SmtpSettings {
+    // Settings for a validated smtp section, with the defaults filled in
+    pub fn from_config(config: &SmtpConfig) -> Self {
+        let default_port = match config.tls {
+            SmtpTls::None => 25,
+            SmtpTls::StartTls => 587,
+            SmtpTls::Tls => 465,
+        };
+        Self {
+            host: config.host.clone(),
+            port: config.port.unwrap_or(default_port),
+            username: config.username.clone(),
+            password: config.password.clone(),
+            from: config.from.clone().unwrap_or_else(|| format!("home-budget@{}", config.host)),
+            tls: config.tls,
+        }
+    }
+}
+
+pub async fn send_webhook(url: &str, payload: &serde_json::Value) -> Result<(), String> {
+    let client = reqwest::Client::builder()
+        .timeout(DELIVERY_TIMEOUT)
+        .build()
+        .map_err(|e| e.to_string())?;
+    let response = client.post(url).json(payload).send().await.map_err(|e| e.to_string())?;
+    if !response.status().is_success() {
+        return Err(format!("Webhook responded with {}", response.status()));
+    }
+    Ok(())
+}
+
+pub async fn send_email(settings: &SmtpSettings, to: &str, subject: &str, body: &str) -> Result<(), String> {
+    let message = Message::builder()
+        .from(settings.from.parse().map_err(|e| format!("Invalid sender address: {}", e))?)
+        .to(to.parse().map_err(|e| format!("Invalid recipient address: {}", e))?)
+        .subject(subject)
+        .header(ContentType::TEXT_PLAIN)
+        .body(body.to_string())
+        .map_err(|e| e.to_string())?;
+
+    let builder = match settings.tls {
+        SmtpTls::None => AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&settings.host),
+        SmtpTls::StartTls => {
+            AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&settings.host).map_err(|e| e.to_string())?
+        }
+        SmtpTls::Tls => AsyncSmtpTransport::<Tokio1Executor>::relay(&settings.host).map_err(|e| e.to_string())?,
+    };
+    let mut builder = builder.port(settings.port).timeout(Some(DELIVERY_TIMEOUT));
+    if let (Some(username), Some(password)) = (&settings.username, &settings.password) {
+        builder = builder.credentials(Credentials::new(username.clone(), password.clone()));
+    }
+    builder.build().send(message).await.map_err(|e| e.to_string())?;
+    Ok(())
+}
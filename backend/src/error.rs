@@ -0,0 +1,166 @@
+// Errors returned by handlers. Every error is sent as JSON:
+//   {"code": "not_found", "message": "Operation not found", "fields": []}
+// `code` is stable so the frontend can act on it, `message` is meant for
+// people and `fields` lists problems with individual request fields.
+// Database errors are classified here, so handlers can simply use `?`.
+use axum::{
+    Json,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+}
+
+#[derive(Debug)]
+pub enum AppError {
+    BadRequest(String),
+    Validation(Vec<FieldError>),
+    NotFound(String),
+    Conflict(String),
+    Forbidden(String),
+    PayloadTooLarge(String),
+    UnsupportedMediaType(String),
+    Database(sqlx::Error),
+    // Details are logged, never sent to the client
+    Internal(String),
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ErrorBody {
+    #[schema(value_type = String, example = "not_found")]
+    pub code: &'static str,
+    pub message: String,
+    pub fields: Vec<FieldError>,
+}
+
+impl AppError {
+    // Validation error for a single field
+    pub fn field(field: &str, message: impl Into<String>) -> Self {
+        AppError::Validation(vec![FieldError {
+            field: field.to_string(),
+            message: message.into(),
+        }])
+    }
+
+    pub fn internal(e: impl std::fmt::Display) -> Self {
+        AppError::Internal(e.to_string())
+    }
+
+    pub fn status(&self) -> StatusCode {
+        self.parts().0
+    }
+
+    // What the client is told, without internal details
+    pub fn message(&self) -> String {
+        self.parts().2
+    }
+
+    fn parts(&self) -> (StatusCode, &'static str, String) {
+        match self {
+            AppError::BadRequest(message) => (StatusCode::BAD_REQUEST, "bad_request", message.clone()),
+            AppError::Validation(fields) => (
+                StatusCode::BAD_REQUEST,
+                "validation_failed",
+                fields
+                    .iter()
+                    .map(|f| f.message.as_str())
+                    .collect::<Vec<_>>()
+                    .join("; "),
+            ),
+            AppError::NotFound(message) => (StatusCode::NOT_FOUND, "not_found", message.clone()),
+            AppError::Conflict(message) => (StatusCode::CONFLICT, "conflict", message.clone()),
+            AppError::Forbidden(message) => (StatusCode::FORBIDDEN, "forbidden", message.clone()),
+            AppError::PayloadTooLarge(message) => (StatusCode::PAYLOAD_TOO_LARGE, "payload_too_large", message.clone()),
+            AppError::UnsupportedMediaType(message) => {
+                (StatusCode::UNSUPPORTED_MEDIA_TYPE, "unsupported_media_type", message.clone())
+            }
+            AppError::Database(e) => database_error_parts(e),
+            AppError::Internal(_) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "internal_error",
+                "Internal server error".to_string(),
+            ),
+        }
+    }
+}
+
+fn database_error_parts(e: &sqlx::Error) -> (StatusCode, &'static str, String) {
+    let internal = (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        "internal_error",
+        "Internal server error".to_string(),
+    );
+    let db = match e {
+        sqlx::Error::RowNotFound => return (StatusCode::NOT_FOUND, "not_found", "Record not found".to_string()),
+        sqlx::Error::Database(db) => db,
+        _ => return internal,
+    };
+    match db.code().as_deref() {
+        Some("23505") => (
+            StatusCode::CONFLICT,
+            "already_exists",
+            "A record with the same values already exists".to_string(),
+        ),
+        Some("23503") => {
+            let message = if db.message().starts_with("update or delete") {
+                "The record is still referenced by other records"
+            } else {
+                "A referenced record does not exist"
+            };
+            (StatusCode::UNPROCESSABLE_ENTITY, "invalid_reference", message.to_string())
+        }
+        Some("23502" | "23514" | "23P01") => (
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "constraint_violation",
+            match db.constraint() {
+                Some(constraint) => format!("The values break the rule {}", constraint),
+                None => "A required value is missing or not allowed".to_string(),
+            },
+        ),
+        // Raised by the reconciled-operation lock trigger
+        Some("55000") => (StatusCode::CONFLICT, "locked", db.message().to_string()),
+        // Malformed values the database refused to parse
+        Some("22P02" | "22003" | "22007" | "22008") => {
+            (StatusCode::BAD_REQUEST, "invalid_value", db.message().to_string())
+        }
+        _ => internal,
+    }
+}
+
+impl std::fmt::Display for AppError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AppError::Database(e) => write!(f, "DB error: {}", e),
+            AppError::Internal(details) => f.write_str(details),
+            _ => f.write_str(&self.parts().2),
+        }
+    }
+}
+
+impl std::error::Error for AppError {}
+
+impl From<sqlx::Error> for AppError {
+    fn from(e: sqlx::Error) -> Self {
+        AppError::Database(e)
+    }
+}
+
+impl IntoResponse for AppError {
+    fn into_response(self) -> Response {
+        let (status, code, message) = self.parts();
+        if status.is_server_error() {
+            tracing::error!("{}", self);
+        }
+        let fields = match self {
+            AppError::Validation(fields) => fields,
+            _ => Vec::new(),
+        };
+        (status, Json(ErrorBody { code, message, fields })).into_response()
+    }
+}
@@ -0,0 +1,136 @@
+// Data leaving the app: operations as CSV in a layout the importer reads back,
+// and backups holding every table and the stored attachment files.
+use crate::{AppState, db, error::AppError, models::OperationWithDetails};
+use chrono::Utc;
+use flate2::{Compression, write::GzEncoder};
+use serde::Serialize;
+use serde_json::{Value, json};
+use std::{collections::BTreeMap, fs::File, io, path::Path};
+
+pub const CSV_HEADERS: &[&str] = &["Date", "Description", "Amount", "Type", "Account", "Category", "Payee"];
+
+// template_data of an import template that reads the exported CSV
+pub fn csv_import_template() -> Value {
+    json!({
+        "columnMapping": {
+            "date": 0,
+            "dateFormat": "YYYY-MM-DD",
+            "description": 1,
+            "amount": 2,
+            "operationType": 3,
+            "sourceAccount": 4,
+            "category": 5,
+        }
+    })
+}
+
+pub fn operations_csv(operations: &[OperationWithDetails], out: impl io::Write) -> Result<(), AppError> {
+    let mut writer = csv::WriterBuilder::new().delimiter(b';').from_writer(out);
+    writer.write_record(CSV_HEADERS).map_err(AppError::internal)?;
+    for op in operations {
+        writer
+            .write_record([
+                op.operation_date.to_string(),
+                op.description.clone().unwrap_or_default(),
+                op.amount.to_string(),
+                op.operation_type.clone(),
+                op.asset_name.clone().unwrap_or_default(),
+                op.category_name.clone().unwrap_or_default(),
+                op.payee_name.clone().unwrap_or_default(),
+            ])
+            .map_err(AppError::internal)?;
+    }
+    writer.flush().map_err(AppError::internal)
+}
+
+#[derive(Debug, Serialize)]
+pub struct BackupManifest {
+    pub created_at: chrono::DateTime<Utc>,
+    pub version: String,
+    pub migration: Option<i64>,
+    // Rows per table
+    pub tables: BTreeMap<String, i64>,
+    pub attachments: usize,
+    // Storage keys of attachments whose files were gone
+    pub missing_attachments: Vec<String>,
+}
+
+// Writes a .tar.gz with tables/<table>.json (the rows as a JSON array, all
+// read in one snapshot), attachments/<storage key> and manifest.json. The
+// archive appears under `path` only once it's complete.
+pub async fn backup(state: &AppState, path: &Path) -> Result<BackupManifest, AppError> {
+    let mut tx = state.pool.begin().await?;
+    sqlx::query("SET TRANSACTION ISOLATION LEVEL REPEATABLE READ, READ ONLY")
+        .execute(&mut *tx)
+        .await?;
+    let table_names: Vec<String> = sqlx::query_scalar(
+        "SELECT table_name::text FROM information_schema.tables
+         WHERE table_schema = current_schema() AND table_type = 'BASE TABLE'
+         ORDER BY table_name",
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+    let mut tables = Vec::new();
+    for name in table_names {
+        let (count, rows): (i64, String) = sqlx::query_as(&format!(
+            "SELECT COUNT(*), COALESCE(json_agg(t), '[]')::text FROM \"{}\" t",
+            name.replace('"', "\"\"")
+        ))
+        .fetch_one(&mut *tx)
+        .await?;
+        tables.push((name, count, rows));
+    }
+    let storage_keys: Vec<String> = sqlx::query_scalar("SELECT storage_key FROM attachments ORDER BY id")
+        .fetch_all(&mut *tx)
+        .await?;
+    tx.commit().await?;
+
+    let mut manifest = BackupManifest {
+        created_at: Utc::now(),
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        migration: db::migration_status(&state.pool).await?.current,
+        tables: BTreeMap::new(),
+        attachments: 0,
+        missing_attachments: Vec::new(),
+    };
+
+    let partial = path.with_extension("partial");
+    let file = File::create(&partial).map_err(|e| AppError::Internal(format!("Can't create {}: {}", partial.display(), e)))?;
+    let mut archive = tar::Builder::new(GzEncoder::new(file, Compression::default()));
+    let mtime = manifest.created_at.timestamp().max(0) as u64;
+    let result = async {
+        for (name, count, rows) in &tables {
+            append(&mut archive, &format!("tables/{}.json", name), rows.as_bytes(), mtime)?;
+            manifest.tables.insert(name.clone(), *count);
+        }
+        for key in &storage_keys {
+            match state.storage.get(key).await {
+                Ok(data) => {
+                    append(&mut archive, &format!("attachments/{}", key), &data, mtime)?;
+                    manifest.attachments += 1;
+                }
+                Err(e) if e.kind() == io::ErrorKind::NotFound => manifest.missing_attachments.push(key.clone()),
+                Err(e) => return Err(e),
+            }
+        }
+        let text = serde_json::to_vec_pretty(&manifest)?;
+        append(&mut archive, "manifest.json", &text, mtime)?;
+        archive.into_inner()?.finish()?.sync_all()?;
+        std::fs::rename(&partial, path)
+    }
+    .await;
+    if let Err(e) = result {
+        std::fs::remove_file(&partial).ok();
+        return Err(AppError::Internal(format!("Failed to write the backup {}: {}", path.display(), e)));
+    }
+    Ok(manifest)
+}
+
+fn append(archive: &mut tar::Builder<impl io::Write>, name: &str, data: &[u8], mtime: u64) -> io::Result<()> {
+    let mut header = tar::Header::new_gnu();
+    header.set_size(data.len() as u64);
+    header.set_mode(0o644);
+    header.set_mtime(mtime);
+    header.set_cksum();
+    archive.append_data(&mut header, name, data)
+}
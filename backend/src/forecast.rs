@@ -0,0 +1,304 @@
+// Day-by-day cash-flow forecast for active liquid assets. Each account starts
+// from its balance at the end of today and then receives:
+// - operations already entered with a future date,
+// - upcoming occurrences of active recurring operations,
+// - installments of liabilities paid from it (loan_schedules),
+// - the unspent part of this month's expense budgets, spread evenly over the
+//   rest of the month and charged to the account that paid most in the budget's
+//   categories over the last 90 days (recurring operations in those categories
+//   are already counted, so they are taken off the remainder first).
+use crate::{models::*, recurrence};
+use bigdecimal::BigDecimal;
+use chrono::{Datelike, Duration, Months, NaiveDate};
+use sqlx::PgConnection;
+use std::collections::{BTreeMap, HashMap};
+
+#[derive(sqlx::FromRow)]
+struct AccountRow {
+    id: i32,
+    name: String,
+    currency: String,
+    balance: Money,
+}
+
+#[derive(sqlx::FromRow)]
+struct FutureOperationRow {
+    id: i32,
+    asset_id: i32,
+    operation_date: NaiveDate,
+    description: Option<String>,
+    amount: Money,
+}
+
+#[derive(sqlx::FromRow)]
+struct RecurringRow {
+    id: i32,
+    asset_id: i32,
+    category_id: Option<i32>,
+    category_parent_id: Option<i32>,
+    description: Option<String>,
+    amount: Money,
+    frequency: String,
+    start_date: NaiveDate,
+    end_date: Option<NaiveDate>,
+    last_generated: Option<NaiveDate>,
+}
+
+#[derive(sqlx::FromRow)]
+struct InstallmentRow {
+    id: i32,
+    payment_asset_id: i32,
+    liability_name: String,
+    installment_amount: Money,
+    day_of_month: i32,
+    start_date: NaiveDate,
+    end_date: Option<NaiveDate>,
+}
+
+#[derive(sqlx::FromRow)]
+struct BudgetRow {
+    id: i32,
+    category_id: i32,
+    category_name: String,
+    planned_amount: Money,
+    spent: Money,
+    paying_asset_id: Option<i32>,
+}
+
+// Items keyed by account and day
+type Planned = HashMap<i32, BTreeMap<NaiveDate, Vec<ForecastItem>>>;
+
+fn plan(planned: &mut Planned, asset_id: i32, date: NaiveDate, item: ForecastItem) {
+    planned.entry(asset_id).or_default().entry(date).or_default().push(item);
+}
+
+// Split `amount` into `parts` amounts rounded for the currency that add up exactly
+fn spread(amount: &Money, parts: i64, currency: &str) -> Vec<Money> {
+    if parts <= 0 {
+        return Vec::new();
+    }
+    let share = Money::rounded(amount.as_decimal() / BigDecimal::from(parts)).round_for(currency);
+    let mut shares = vec![share.clone(); parts as usize];
+    if let Some(last) = shares.last_mut() {
+        *last = amount - Money::rounded(share.as_decimal() * BigDecimal::from(parts - 1));
+    }
+    shares
+}
+
+pub async fn forecast(
+    conn: &mut PgConnection,
+    today: NaiveDate,
+    days: i64,
+) -> Result<ForecastResponse, sqlx::Error> {
+    let start_date = today + Duration::days(1);
+    let end_date = today + Duration::days(days);
+
+    let accounts = sqlx::query_as::<_, AccountRow>(
+        "SELECT a.id, a.name, COALESCE(a.currency, 'PLN') AS currency,
+                COALESCE(SUM(o.amount) FILTER (WHERE o.parent_operation_id IS NULL AND o.operation_date <= $1), 0) AS balance
+         FROM assets a
+         INNER JOIN asset_types at ON a.asset_type_id = at.id
+         LEFT JOIN operations o ON o.asset_id = a.id AND o.deleted_at IS NULL
+         WHERE at.category = 'liquid' AND a.is_active IS NOT FALSE AND a.deleted_at IS NULL
+         GROUP BY a.id
+         ORDER BY a.sort_order, a.id",
+    )
+    .bind(today)
+    .fetch_all(&mut *conn)
+    .await?;
+    let asset_ids: Vec<i32> = accounts.iter().map(|a| a.id).collect();
+    let mut planned: Planned = HashMap::new();
+
+    let future_operations = sqlx::query_as::<_, FutureOperationRow>(
+        "SELECT id, asset_id, operation_date, description, amount
+         FROM operations
+         WHERE asset_id = ANY($1) AND parent_operation_id IS NULL AND deleted_at IS NULL
+           AND operation_date > $2 AND operation_date <= $3
+         ORDER BY operation_date, id",
+    )
+    .bind(&asset_ids)
+    .bind(today)
+    .bind(end_date)
+    .fetch_all(&mut *conn)
+    .await?;
+    for op in future_operations {
+        plan(&mut planned, op.asset_id, op.operation_date, ForecastItem {
+            source: "operation".to_string(),
+            reference_id: Some(op.id),
+            description: op.description,
+            amount: op.amount,
+        });
+    }
+
+    let recurring = sqlx::query_as::<_, RecurringRow>(
+        "SELECT r.id, r.asset_id, r.category_id, c.parent_id AS category_parent_id, r.description,
+                r.amount, r.frequency::text AS frequency, r.start_date, r.end_date, r.last_generated
+         FROM recurring_operations r
+         LEFT JOIN categories c ON c.id = r.category_id
+         WHERE r.is_active IS NOT FALSE AND r.asset_id = ANY($1)
+         ORDER BY r.id",
+    )
+    .bind(&asset_ids)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    let month_start = today.with_day(1).unwrap_or(today);
+    let month_end = month_start
+        .checked_add_months(Months::new(1))
+        .and_then(|d| d.pred_opt())
+        .unwrap_or(today);
+    // Recurring amounts per category still to come this month
+    let mut recurring_this_month: Vec<(Option<i32>, Option<i32>, Money)> = Vec::new();
+
+    for r in &recurring {
+        // Occurrences up to the last generated one already exist as operations
+        let from = r.last_generated.map_or(start_date, |d| (d + Duration::days(1)).max(start_date));
+        let last = end_date.max(month_end);
+        for date in recurrence::occurrences(&r.frequency, r.start_date, r.end_date, from, last) {
+            if date <= month_end {
+                recurring_this_month.push((r.category_id, r.category_parent_id, r.amount.clone()));
+            }
+            if date <= end_date {
+                plan(&mut planned, r.asset_id, date, ForecastItem {
+                    source: "recurring".to_string(),
+                    reference_id: Some(r.id),
+                    description: r.description.clone(),
+                    amount: r.amount.clone(),
+                });
+            }
+        }
+    }
+
+    let installments = sqlx::query_as::<_, InstallmentRow>(
+        "SELECT ls.id, ls.payment_asset_id, a.name AS liability_name, ls.installment_amount,
+                ls.day_of_month, ls.start_date, ls.end_date
+         FROM loan_schedules ls
+         INNER JOIN assets a ON a.id = ls.liability_asset_id
+         WHERE ls.payment_asset_id = ANY($1) AND a.deleted_at IS NULL
+         ORDER BY ls.id",
+    )
+    .bind(&asset_ids)
+    .fetch_all(&mut *conn)
+    .await?;
+    for i in &installments {
+        let day = u32::try_from(i.day_of_month).unwrap_or(1);
+        for date in recurrence::monthly_on_day(day, i.start_date, i.end_date, start_date, end_date) {
+            plan(&mut planned, i.payment_asset_id, date, ForecastItem {
+                source: "installment".to_string(),
+                reference_id: Some(i.id),
+                description: Some(format!("Installment: {}", i.liability_name)),
+                amount: -i.installment_amount.clone(),
+            });
+        }
+    }
+
+    if start_date <= month_end {
+        let budgets = sqlx::query_as::<_, BudgetRow>(
+            "SELECT b.id, b.category_id, c.name AS category_name, b.planned_amount,
+                    COALESCE((
+                        SELECT -SUM(o.amount)
+                        FROM operations o
+                        INNER JOIN categories oc ON oc.id = o.category_id
+                        WHERE (oc.id = b.category_id OR oc.parent_id = b.category_id)
+                          AND o.is_split IS NOT TRUE AND o.deleted_at IS NULL
+                          AND o.operation_date >= $1 AND o.operation_date <= $2
+                    ), 0) AS spent,
+                    (
+                        SELECT o.asset_id
+                        FROM operations o
+                        INNER JOIN categories oc ON oc.id = o.category_id
+                        WHERE (oc.id = b.category_id OR oc.parent_id = b.category_id)
+                          AND o.asset_id = ANY($4) AND o.deleted_at IS NULL
+                          AND o.operation_date > $3 - 90 AND o.operation_date <= $3
+                        GROUP BY o.asset_id
+                        ORDER BY SUM(ABS(o.amount)) DESC, o.asset_id
+                        LIMIT 1
+                    ) AS paying_asset_id
+             FROM budgets b
+             INNER JOIN categories c ON c.id = b.category_id
+             WHERE b.month >= $1 AND b.month <= $2 AND c.type = 'expense'
+             ORDER BY b.id",
+        )
+        .bind(month_start)
+        .bind(month_end)
+        .bind(today)
+        .bind(&asset_ids)
+        .fetch_all(&mut *conn)
+        .await?;
+
+        let budget_days: Vec<NaiveDate> = start_date.iter_days().take_while(|d| *d <= month_end).collect();
+        for budget in budgets {
+            let Some(asset_id) = budget.paying_asset_id.or_else(|| asset_ids.first().copied()) else {
+                continue;
+            };
+            let scheduled: Money = recurring_this_month
+                .iter()
+                .filter(|(category_id, parent_id, _)| {
+                    *category_id == Some(budget.category_id) || *parent_id == Some(budget.category_id)
+                })
+                .map(|(_, _, amount)| -amount)
+                .sum();
+            let remaining = &budget.planned_amount - &budget.spent - scheduled;
+            if !remaining.is_positive() {
+                continue;
+            }
+            let currency = accounts.iter().find(|a| a.id == asset_id).map_or("PLN", |a| a.currency.as_str());
+            let shares = spread(&remaining, budget_days.len() as i64, currency);
+            for (date, share) in budget_days.iter().zip(shares) {
+                if *date > end_date || share.is_zero() {
+                    continue;
+                }
+                plan(&mut planned, asset_id, *date, ForecastItem {
+                    source: "budget".to_string(),
+                    reference_id: Some(budget.id),
+                    description: Some(format!("Budget: {}", budget.category_name)),
+                    amount: -share,
+                });
+            }
+        }
+    }
+
+    let accounts = accounts
+        .into_iter()
+        .map(|account| {
+            let mut items_by_day = planned.remove(&account.id).unwrap_or_default();
+            let mut balance = account.balance.clone();
+            let mut lowest_balance = account.balance.clone();
+            let mut lowest_balance_date = None;
+            let mut first_negative_date = None;
+            let days: Vec<ForecastDay> = start_date
+                .iter_days()
+                .take_while(|d| *d <= end_date)
+                .map(|date| {
+                    let items = items_by_day.remove(&date).unwrap_or_default();
+                    for item in &items {
+                        balance += &item.amount;
+                    }
+                    let is_negative = balance.is_negative();
+                    if is_negative && first_negative_date.is_none() {
+                        first_negative_date = Some(date);
+                    }
+                    if balance < lowest_balance {
+                        lowest_balance = balance.clone();
+                        lowest_balance_date = Some(date);
+                    }
+                    ForecastDay { date, items, balance: balance.clone(), is_negative }
+                })
+                .collect();
+
+            AccountForecast {
+                asset_id: account.id,
+                asset_name: account.name,
+                currency: account.currency,
+                starting_balance: account.balance,
+                ending_balance: balance,
+                lowest_balance,
+                lowest_balance_date,
+                first_negative_date,
+                days,
+            }
+        })
+        .collect();
+
+    Ok(ForecastResponse { start_date, end_date, accounts })
+}
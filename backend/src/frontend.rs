@@ -0,0 +1,102 @@
+// The built frontend (frontend/dist) served next to the API, so one binary runs
+// the whole app. Files come from a directory, or from the binary itself when
+// built with the embed-frontend feature. Paths that aren't files get
+// index.html and are left to the React router.
+use crate::error::AppError;
+use axum::{
+    body::Body,
+    http::{Method, Uri, header},
+    response::{IntoResponse, Response},
+};
+use std::{borrow::Cow, io, path::PathBuf};
+
+// Vite gives files here hashed names, so a name never changes content
+const HASHED_ASSETS_DIR: &str = "assets/";
+
+#[cfg(feature = "embed-frontend")]
+#[derive(rust_embed::Embed)]
+#[folder = "../frontend/dist"]
+struct Embedded;
+
+#[derive(Clone)]
+pub enum Frontend {
+    Dir(PathBuf),
+    #[cfg(feature = "embed-frontend")]
+    Embedded,
+}
+
+impl Frontend {
+    // The configured directory, else the copy in the binary when there is one
+    pub fn new(dir: Option<PathBuf>) -> Option<Frontend> {
+        match dir {
+            Some(dir) => Some(Frontend::Dir(dir)),
+            #[cfg(feature = "embed-frontend")]
+            None => Some(Frontend::Embedded),
+            #[cfg(not(feature = "embed-frontend"))]
+            None => None,
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        match self {
+            Frontend::Dir(dir) => format!("from {}", dir.display()),
+            #[cfg(feature = "embed-frontend")]
+            Frontend::Embedded => "embedded in the binary".to_string(),
+        }
+    }
+
+    async fn file(&self, path: &str) -> io::Result<Option<Cow<'static, [u8]>>> {
+        match self {
+            Frontend::Dir(dir) => match tokio::fs::read(dir.join(path)).await {
+                Ok(data) => Ok(Some(Cow::Owned(data))),
+                Err(e) if matches!(e.kind(), io::ErrorKind::NotFound | io::ErrorKind::IsADirectory) => Ok(None),
+                Err(e) => Err(e),
+            },
+            #[cfg(feature = "embed-frontend")]
+            Frontend::Embedded => Ok(Embedded::get(path).map(|file| file.data)),
+        }
+    }
+
+    pub async fn serve(&self, method: Method, uri: Uri) -> Result<Response, AppError> {
+        let path = uri.path().trim_start_matches('/');
+        // Unknown API paths stay JSON errors instead of becoming pages
+        if path == "api" || path.starts_with("api/") {
+            return Err(AppError::NotFound(format!("No API endpoint at {}", uri.path())));
+        }
+        if method != Method::GET && method != Method::HEAD {
+            return Err(AppError::NotFound(format!("No endpoint for {} {}", method, uri.path())));
+        }
+        // Never step out of the frontend directory
+        if path.split('/').any(|part| part.starts_with('.') || part.contains('\\')) {
+            return Err(AppError::NotFound("File not found".to_string()));
+        }
+
+        let path = if path.is_empty() { "index.html" } else { path };
+        if let Some(data) = self.file(path).await.map_err(AppError::internal)? {
+            return Ok(file_response(path, data));
+        }
+        // A missing file is a 404, anything else is a page of the app
+        if path.rsplit('/').next().is_some_and(|name| name.contains('.')) {
+            return Err(AppError::NotFound("File not found".to_string()));
+        }
+        match self.file("index.html").await.map_err(AppError::internal)? {
+            Some(data) => Ok(file_response("index.html", data)),
+            None => Err(AppError::Internal("The frontend has no index.html".to_string())),
+        }
+    }
+}
+
+fn file_response(path: &str, data: Cow<'static, [u8]>) -> Response {
+    let content_type = mime_guess::from_path(path).first_or_octet_stream();
+    // Everything else is checked again on each load, so a new build shows up at once
+    let cache_control = if path.starts_with(HASHED_ASSETS_DIR) {
+        "public, max-age=31536000, immutable"
+    } else {
+        "no-cache"
+    };
+    (
+        [(header::CONTENT_TYPE, content_type.as_ref()), (header::CACHE_CONTROL, cache_control)],
+        Body::from(data),
+    )
+        .into_response()
+}
@@ -1,19 +1,18 @@
 use axum::{extract::{State, Path}, Json};
-use crate::{AppState, utils::db_err};
+use crate::{AppState, error::AppError};
 
 /// BACKWARD COMPATIBILITY: /accounts endpoints that map to liquid assets
-pub async fn list_accounts_compat(State(state): State<AppState>) -> Result<Json<Vec<serde_json::Value>>, (axum::http::StatusCode, String)> {
+pub async fn list_accounts_compat(State(state): State<AppState>) -> Result<Json<Vec<serde_json::Value>>, AppError> {
     // Get liquid assets and format them as old Account objects
     let rows = sqlx::query!(
         "SELECT a.id, a.name, a.user_id, a.account_number, a.is_active as \"is_active!\"
          FROM assets a
          INNER JOIN asset_types at ON a.asset_type_id = at.id
-         WHERE at.category = 'liquid'
+         WHERE at.category = 'liquid' AND a.deleted_at IS NULL
          ORDER BY a.id"
     )
     .fetch_all(&state.pool)
-    .await
-    .map_err(db_err)?;
+    .await?;
     
     let accounts: Vec<serde_json::Value> = rows.into_iter().map(|row| {
         serde_json::json!({
@@ -28,15 +27,14 @@ pub async fn list_accounts_compat(State(state): State<AppState>) -> Result<Json<
     Ok(Json(accounts))
 }
 
-pub async fn toggle_account_closed_compat(State(state): State<AppState>, Path(id): Path<i32>) -> Result<Json<serde_json::Value>, (axum::http::StatusCode, String)> {
+pub async fn toggle_account_closed_compat(State(state): State<AppState>, Path(id): Path<i32>) -> Result<Json<serde_json::Value>, AppError> {
     // Toggle is_active on asset
     let row = sqlx::query!(
-        "UPDATE assets SET is_active = NOT is_active WHERE id = $1
+        "UPDATE assets SET is_active = NOT is_active WHERE id = $1 AND deleted_at IS NULL
          RETURNING id, name, user_id, account_number, is_active as \"is_active!\""
     ,id)
     .fetch_one(&state.pool)
-    .await
-    .map_err(db_err)?;
+    .await?;
     
     Ok(Json(serde_json::json!({
         "id": row.id,
@@ -0,0 +1,251 @@
+use crate::{AppState, error::AppError, models::*};
+use axum::{
+    Json,
+    extract::{Multipart, Path, State},
+    http::header,
+    response::IntoResponse,
+};
+use sha2::{Digest, Sha256};
+
+pub const MAX_ATTACHMENT_SIZE: usize = 10 * 1024 * 1024;
+
+const ATTACHMENT_COLUMNS: &str =
+    "id, operation_id, file_name, content_type, size_bytes, checksum_sha256, storage_key, created_date";
+
+// Accepted content types and the leading bytes their files must start with
+fn content_matches_type(content_type: &str, data: &[u8]) -> bool {
+    match content_type {
+        "application/pdf" => data.starts_with(b"%PDF-"),
+        "image/jpeg" => data.starts_with(&[0xFF, 0xD8, 0xFF]),
+        "image/png" => data.starts_with(&[0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A]),
+        "image/webp" => data.len() >= 12 && &data[0..4] == b"RIFF" && &data[8..12] == b"WEBP",
+        "image/heic" => {
+            data.len() >= 12
+                && &data[4..8] == b"ftyp"
+                && matches!(&data[8..12], b"heic" | b"heix" | b"mif1" | b"msf1")
+        }
+        _ => false,
+    }
+}
+
+fn sanitize_file_name(name: &str) -> String {
+    // Browsers may send a full path; keep the last component only
+    let base = name.rsplit(['/', '\\']).next().unwrap_or("");
+    let cleaned: String = base.chars().filter(|c| !c.is_control()).take(255).collect();
+    if cleaned.trim().is_empty() {
+        "attachment".to_string()
+    } else {
+        cleaned
+    }
+}
+
+fn content_disposition(file_name: &str) -> String {
+    let ascii: String = file_name
+        .chars()
+        .map(|c| if c.is_ascii() && c != '"' && c != '\\' { c } else { '_' })
+        .collect();
+    let encoded: String = file_name
+        .bytes()
+        .map(|b| {
+            if b.is_ascii_alphanumeric() || b"-._~".contains(&b) {
+                (b as char).to_string()
+            } else {
+                format!("%{:02X}", b)
+            }
+        })
+        .collect();
+    format!("attachment; filename=\"{}\"; filename*=UTF-8''{}", ascii, encoded)
+}
+
+fn multipart_err(e: axum::extract::multipart::MultipartError) -> AppError {
+    if e.status() == axum::http::StatusCode::PAYLOAD_TOO_LARGE {
+        AppError::PayloadTooLarge(e.body_text())
+    } else {
+        AppError::BadRequest(e.body_text())
+    }
+}
+
+fn sha256_hex(data: &[u8]) -> String {
+    Sha256::digest(data).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+// Remove files of deleted attachments from storage. Rows are queued by a
+// trigger, so cascaded deletes (operation, asset, user) are covered too.
+// Failures are logged and retried on the next call.
+pub async fn purge_deleted_attachment_files(state: &AppState) {
+    let keys: Vec<String> = match sqlx::query_scalar("SELECT storage_key FROM attachment_file_deletions")
+        .fetch_all(&state.pool)
+        .await
+    {
+        Ok(keys) => keys,
+        Err(e) => {
+            tracing::warn!("Failed to load deleted attachment files: {}", e);
+            return;
+        }
+    };
+
+    for key in keys {
+        if let Err(e) = state.storage.delete(&key).await {
+            tracing::warn!("Failed to delete attachment file {}: {}", key, e);
+            continue;
+        }
+        if let Err(e) = sqlx::query("DELETE FROM attachment_file_deletions WHERE storage_key = $1")
+            .bind(&key)
+            .execute(&state.pool)
+            .await
+        {
+            tracing::warn!("Failed to dequeue attachment file {}: {}", key, e);
+        }
+    }
+}
+
+pub async fn list_attachments(
+    State(state): State<AppState>,
+    Path(operation_id): Path<i32>,
+) -> Result<Json<Vec<Attachment>>, AppError> {
+    let rows = sqlx::query_as::<_, Attachment>(&format!(
+        "SELECT {ATTACHMENT_COLUMNS} FROM attachments WHERE operation_id = $1 ORDER BY id"
+    ))
+    .bind(operation_id)
+    .fetch_all(&state.pool)
+    .await?;
+    Ok(Json(rows))
+}
+
+// Multipart upload with a single "file" field
+pub async fn upload_attachment(
+    State(state): State<AppState>,
+    Path(operation_id): Path<i32>,
+    mut multipart: Multipart,
+) -> Result<Json<Attachment>, AppError> {
+    let exists: bool = sqlx::query_scalar("SELECT EXISTS(SELECT 1 FROM operations WHERE id = $1 AND deleted_at IS NULL)")
+        .bind(operation_id)
+        .fetch_one(&state.pool)
+        .await?;
+    if !exists {
+        return Err(AppError::NotFound("Operation not found".to_string()));
+    }
+
+    let mut file = None;
+    while let Some(field) = multipart
+        .next_field()
+        .await
+        .map_err(multipart_err)?
+    {
+        if field.name() != Some("file") {
+            continue;
+        }
+        let file_name = sanitize_file_name(field.file_name().unwrap_or(""));
+        let content_type = field
+            .content_type()
+            .unwrap_or("application/octet-stream")
+            .to_lowercase();
+        let data = field.bytes().await.map_err(multipart_err)?;
+        file = Some((file_name, content_type, data));
+        break;
+    }
+
+    let (file_name, content_type, data) =
+        file.ok_or(AppError::BadRequest("Missing 'file' field".to_string()))?;
+
+    if data.is_empty() {
+        return Err(AppError::BadRequest("File is empty".to_string()));
+    }
+    if data.len() > MAX_ATTACHMENT_SIZE {
+        return Err(AppError::PayloadTooLarge(
+            format!("File exceeds the {} MB limit", MAX_ATTACHMENT_SIZE / 1024 / 1024),
+        ));
+    }
+    if !content_matches_type(&content_type, &data) {
+        return Err(AppError::UnsupportedMediaType(
+            "Only PDF, JPEG, PNG, WebP and HEIC files are allowed, and the content must match the declared type".to_string(),
+        ));
+    }
+
+    let checksum = sha256_hex(&data);
+    let storage_key = uuid::Uuid::new_v4().to_string();
+
+    state.storage.put(&storage_key, &data).await.map_err(AppError::internal)?;
+
+    let inserted = sqlx::query_as::<_, Attachment>(&format!(
+        "INSERT INTO attachments (operation_id, file_name, content_type, size_bytes, checksum_sha256, storage_key)
+         VALUES ($1, $2, $3, $4, $5, $6)
+         RETURNING {ATTACHMENT_COLUMNS}"
+    ))
+    .bind(operation_id)
+    .bind(&file_name)
+    .bind(&content_type)
+    .bind(data.len() as i64)
+    .bind(&checksum)
+    .bind(&storage_key)
+    .fetch_one(&state.pool)
+    .await;
+
+    match inserted {
+        Ok(attachment) => Ok(Json(attachment)),
+        Err(e) => {
+            // Don't leave an unreferenced file behind
+            state.storage.delete(&storage_key).await.ok();
+            Err(AppError::from(e))
+        }
+    }
+}
+
+async fn fetch_attachment(
+    state: &AppState,
+    operation_id: i32,
+    attachment_id: i32,
+) -> Result<Attachment, AppError> {
+    sqlx::query_as::<_, Attachment>(&format!(
+        "SELECT {ATTACHMENT_COLUMNS} FROM attachments WHERE id = $1 AND operation_id = $2"
+    ))
+    .bind(attachment_id)
+    .bind(operation_id)
+    .fetch_optional(&state.pool)
+    .await?
+    .ok_or(AppError::NotFound("Attachment not found".to_string()))
+}
+
+pub async fn download_attachment(
+    State(state): State<AppState>,
+    Path((operation_id, attachment_id)): Path<(i32, i32)>,
+) -> Result<impl IntoResponse, AppError> {
+    let attachment = fetch_attachment(&state, operation_id, attachment_id).await?;
+
+    let data = state.storage.get(&attachment.storage_key).await.map_err(|e| {
+        if e.kind() == std::io::ErrorKind::NotFound {
+            AppError::NotFound("Attachment file is missing from storage".to_string())
+        } else {
+            AppError::internal(e)
+        }
+    })?;
+
+    // Refuse to serve a file that no longer matches what was uploaded
+    if sha256_hex(&data) != attachment.checksum_sha256 {
+        return Err(AppError::Internal("Attachment file failed checksum verification".to_string()));
+    }
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, attachment.content_type.clone()),
+            (header::CONTENT_DISPOSITION, content_disposition(&attachment.file_name)),
+            (header::ETAG, format!("\"{}\"", attachment.checksum_sha256)),
+        ],
+        data,
+    ))
+}
+
+pub async fn delete_attachment(
+    State(state): State<AppState>,
+    Path((operation_id, attachment_id)): Path<(i32, i32)>,
+) -> Result<(), AppError> {
+    let attachment = fetch_attachment(&state, operation_id, attachment_id).await?;
+
+    sqlx::query("DELETE FROM attachments WHERE id = $1")
+        .bind(attachment.id)
+        .execute(&state.pool)
+        .await?;
+
+    purge_deleted_attachment_files(&state).await;
+    Ok(())
+}
@@ -0,0 +1,370 @@
+use crate::{
+    AppState, balances,
+    error::AppError,
+    models::*,
+};
+use axum::{
+    Json,
+    extract::{Path, Query, State},
+};
+use sqlx::{Connection, PgConnection, Postgres, QueryBuilder};
+
+const AUDIT_COLUMNS: &str =
+    "id, user_id, action, table_name, record_id, action_date, before_data, after_data, request_id, client_request_id, reverted_at";
+
+// Audited tables and their derived columns (balances recalculated by the
+// balance service, investment positions kept by triggers). Those are ignored when comparing and restoring rows.
+fn derived_columns(table_name: &str) -> Option<&'static [&'static str]> {
+    match table_name {
+        "operations" | "budgets" | "categories" => Some(&[]),
+        "assets" => Some(&["current_valuation", "quantity", "average_purchase_price"]),
+        _ => None,
+    }
+}
+
+enum RevertError {
+    Conflict(String),
+    // The row doesn't exist (yet); another entry of the same request may restore it
+    Missing(String),
+    Db(sqlx::Error),
+}
+
+impl From<sqlx::Error> for RevertError {
+    fn from(e: sqlx::Error) -> Self {
+        RevertError::Db(e)
+    }
+}
+
+// Errors that may go away once other entries of the same request are reverted
+fn can_retry(e: &RevertError) -> bool {
+    match e {
+        RevertError::Missing(_) => true,
+        RevertError::Db(sqlx::Error::Database(db)) => db.is_foreign_key_violation(),
+        _ => false,
+    }
+}
+
+fn revert_err(e: RevertError) -> AppError {
+    match e {
+        RevertError::Conflict(message) | RevertError::Missing(message) => {
+            AppError::Conflict(message)
+        }
+        RevertError::Db(sqlx::Error::Database(db)) if db.is_unique_violation() => AppError::Conflict(
+            "A record with the same id or name already exists".to_string(),
+        ),
+        RevertError::Db(sqlx::Error::Database(db)) if db.is_foreign_key_violation() => AppError::Conflict(
+            format!("Change can't be reverted: {}", db.message()),
+        ),
+        RevertError::Db(e) => AppError::from(e),
+    }
+}
+
+// Columns of the snapshot that still exist in the table, minus the excluded ones
+async fn snapshot_columns(
+    conn: &mut PgConnection,
+    table_name: &str,
+    snapshot: &serde_json::Value,
+    excluded: &[&str],
+) -> Result<Vec<String>, sqlx::Error> {
+    let existing: Vec<String> = sqlx::query_scalar(
+        "SELECT column_name::text FROM information_schema.columns
+         WHERE table_schema = current_schema() AND table_name = $1
+         ORDER BY ordinal_position",
+    )
+    .bind(table_name)
+    .fetch_all(conn)
+    .await?;
+
+    Ok(existing
+        .into_iter()
+        .filter(|c| snapshot.get(c).is_some() && !excluded.contains(&c.as_str()))
+        .collect())
+}
+
+// Some(true) when the current row still looks like the snapshot taken after
+// the change, None when the row doesn't exist
+async fn row_matches(
+    conn: &mut PgConnection,
+    table_name: &str,
+    record_id: i32,
+    snapshot: &serde_json::Value,
+    derived: &[&str],
+) -> Result<Option<bool>, sqlx::Error> {
+    sqlx::query_scalar(&format!(
+        "SELECT (SELECT jsonb_object_agg(key, value) FROM jsonb_each(to_jsonb(t))
+                 WHERE $2::jsonb ? key AND key <> ALL($3::text[])) = ($2::jsonb - $3::text[])
+         FROM \"{table_name}\" t WHERE id = $1 FOR UPDATE"
+    ))
+    .bind(record_id)
+    .bind(snapshot)
+    .bind(derived)
+    .fetch_optional(conn)
+    .await
+}
+
+async fn ensure_unchanged(
+    conn: &mut PgConnection,
+    entry: &AuditEntry,
+    derived: &[&str],
+) -> Result<(), RevertError> {
+    let after = entry.after_data.as_ref().ok_or_else(|| {
+        RevertError::Conflict(format!("Audit entry {} has no snapshot to compare", entry.id))
+    })?;
+    match row_matches(conn, &entry.table_name, entry.record_id, after, derived).await? {
+        Some(true) => Ok(()),
+        Some(false) => Err(RevertError::Conflict(format!(
+            "{} {} was changed later; revert the later changes first",
+            entry.table_name, entry.record_id
+        ))),
+        None => Err(RevertError::Missing(format!(
+            "{} {} no longer exists",
+            entry.table_name, entry.record_id
+        ))),
+    }
+}
+
+// Removing a created row must not take data with it that the log can't bring back
+async fn ensure_no_unaudited_dependents(
+    conn: &mut PgConnection,
+    entry: &AuditEntry,
+) -> Result<(), RevertError> {
+    let query = match entry.table_name.as_str() {
+        "operations" => "SELECT EXISTS(SELECT 1 FROM attachments WHERE operation_id = $1)",
+        "assets" => {
+            "SELECT EXISTS(SELECT 1 FROM operations WHERE asset_id = $1)
+                 OR EXISTS(SELECT 1 FROM investment_transactions WHERE asset_id = $1)
+                 OR EXISTS(SELECT 1 FROM asset_valuations WHERE asset_id = $1)
+                 OR EXISTS(SELECT 1 FROM reconciliations WHERE asset_id = $1)
+                 OR EXISTS(SELECT 1 FROM goals WHERE asset_id = $1)
+                 OR EXISTS(SELECT 1 FROM recurring_operations WHERE asset_id = $1)"
+        }
+        _ => return Ok(()),
+    };
+    let has_dependents: bool = sqlx::query_scalar(query)
+        .bind(entry.record_id)
+        .fetch_one(conn)
+        .await?;
+    if has_dependents {
+        return Err(RevertError::Conflict(format!(
+            "{} {} has related data (attachments, operations or investment records) that would be lost",
+            entry.table_name, entry.record_id
+        )));
+    }
+    Ok(())
+}
+
+async fn revert_entry(conn: &mut PgConnection, entry: &AuditEntry) -> Result<(), RevertError> {
+    let table_name = entry.table_name.as_str();
+    let derived = derived_columns(table_name).ok_or_else(|| {
+        RevertError::Conflict(format!("Changes to {} can't be reverted", table_name))
+    })?;
+
+    match entry.action.as_str() {
+        "create" => {
+            ensure_unchanged(conn, entry, derived).await?;
+            ensure_no_unaudited_dependents(conn, entry).await?;
+            sqlx::query(&format!("DELETE FROM \"{table_name}\" WHERE id = $1"))
+                .bind(entry.record_id)
+                .execute(conn)
+                .await?;
+        }
+        "update" => {
+            ensure_unchanged(conn, entry, derived).await?;
+            let before = entry.before_data.as_ref().ok_or_else(|| {
+                RevertError::Conflict(format!("Audit entry {} has no snapshot to restore", entry.id))
+            })?;
+            let mut excluded = derived.to_vec();
+            excluded.push("id");
+            let columns = snapshot_columns(conn, table_name, before, &excluded).await?;
+            if columns.is_empty() {
+                return Ok(());
+            }
+            let column_list = quoted_list(&columns);
+            sqlx::query(&format!(
+                "UPDATE \"{table_name}\" SET ({column_list}) =
+                     (SELECT {column_list} FROM jsonb_populate_record(NULL::\"{table_name}\", $1))
+                 WHERE id = $2"
+            ))
+            .bind(before)
+            .bind(entry.record_id)
+            .execute(conn)
+            .await?;
+        }
+        "delete" => {
+            // Purging an asset also removed its investment history, which isn't logged
+            if table_name == "assets" {
+                return Err(RevertError::Conflict(
+                    "Purged assets can't be restored".to_string(),
+                ));
+            }
+            let before = entry.before_data.as_ref().ok_or_else(|| {
+                RevertError::Conflict(format!("Audit entry {} has no snapshot to restore", entry.id))
+            })?;
+            let columns = snapshot_columns(conn, table_name, before, &[]).await?;
+            let column_list = quoted_list(&columns);
+            sqlx::query(&format!(
+                "INSERT INTO \"{table_name}\" ({column_list})
+                 SELECT {column_list} FROM jsonb_populate_record(NULL::\"{table_name}\", $1)"
+            ))
+            .bind(before)
+            .execute(conn)
+            .await?;
+        }
+        other => {
+            return Err(RevertError::Conflict(format!("Unknown audit action '{}'", other)));
+        }
+    }
+    Ok(())
+}
+
+fn quoted_list(columns: &[String]) -> String {
+    columns
+        .iter()
+        .map(|c| format!("\"{}\"", c))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+pub async fn list_audit_entries(
+    State(state): State<AppState>,
+    Query(params): Query<AuditQuery>,
+) -> Result<Json<Vec<AuditEntry>>, AppError> {
+    if let Some(action) = &params.action
+        && !matches!(action.as_str(), "create" | "update" | "delete")
+    {
+        return Err(AppError::BadRequest("action must be one of: create, update, delete".to_string()));
+    }
+    let limit = params.limit.unwrap_or(100).clamp(1, 1000);
+
+    let mut builder: QueryBuilder<Postgres> =
+        QueryBuilder::new(format!("SELECT {AUDIT_COLUMNS} FROM audit_log WHERE TRUE"));
+    if let Some(table_name) = &params.table_name {
+        builder.push(" AND table_name = ").push_bind(table_name.clone());
+    }
+    if let Some(record_id) = params.record_id {
+        builder.push(" AND record_id = ").push_bind(record_id);
+    }
+    if let Some(action) = &params.action {
+        builder.push(" AND action = ").push_bind(action.clone());
+    }
+    if let Some(user_id) = params.user_id {
+        builder.push(" AND user_id = ").push_bind(user_id);
+    }
+    if let Some(request_id) = &params.request_id {
+        builder.push(" AND request_id = ").push_bind(request_id.clone());
+    }
+    if let Some(client_request_id) = &params.client_request_id {
+        builder.push(" AND client_request_id = ").push_bind(client_request_id.clone());
+    }
+    if let Some(date_from) = params.date_from {
+        builder.push(" AND action_date >= ").push_bind(date_from);
+    }
+    if let Some(date_to) = params.date_to {
+        builder.push(" AND action_date < ").push_bind(date_to).push("::date + 1");
+    }
+    // Keyset pagination: pass the smallest id of the previous page
+    if let Some(before_id) = params.before_id {
+        builder.push(" AND id < ").push_bind(before_id);
+    }
+    builder.push(" ORDER BY id DESC LIMIT ").push_bind(limit);
+
+    let rows = builder
+        .build_query_as::<AuditEntry>()
+        .fetch_all(&state.pool)
+        .await?;
+    Ok(Json(rows))
+}
+
+// Undo the change recorded by an entry. All entries of the same request are
+// reverted together (a transfer, a split, cascaded deletes), newest first,
+// and only if the affected rows haven't been changed since. The revert is
+// itself logged, so it can be reverted too.
+pub async fn revert_audit_entry(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<Json<Vec<AuditEntry>>, AppError> {
+    let mut tx = state.pool.begin().await?;
+
+    let entry = sqlx::query_as::<_, AuditEntry>(&format!(
+        "SELECT {AUDIT_COLUMNS} FROM audit_log WHERE id = $1 FOR UPDATE"
+    ))
+    .bind(id)
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or(AppError::NotFound("Audit entry not found".to_string()))?;
+
+    if entry.reverted_at.is_some() {
+        return Err(AppError::Conflict("This change has already been reverted".to_string()));
+    }
+
+    let entries = match &entry.request_id {
+        Some(request_id) => sqlx::query_as::<_, AuditEntry>(&format!(
+            "SELECT {AUDIT_COLUMNS} FROM audit_log
+             WHERE request_id = $1 AND reverted_at IS NULL
+             ORDER BY id DESC
+             FOR UPDATE"
+        ))
+        .bind(request_id)
+        .fetch_all(&mut *tx)
+        .await?,
+        None => vec![entry],
+    };
+
+    sqlx::query("SELECT set_config('home_budget.reverting', 'on', true)")
+        .execute(&mut *tx)
+        .await?;
+
+    // Rows referencing each other (transfer partners, split children) may need
+    // their counterpart restored first; retry those until nothing progresses
+    let mut pending: Vec<&AuditEntry> = entries.iter().collect();
+    while !pending.is_empty() {
+        let mut postponed = Vec::new();
+        let mut last_error = None;
+        for entry in &pending {
+            let mut savepoint = tx.begin().await?;
+            match revert_entry(&mut savepoint, entry).await {
+                Ok(()) => savepoint.commit().await?,
+                Err(e) if can_retry(&e) => {
+                    savepoint.rollback().await?;
+                    postponed.push(*entry);
+                    last_error = Some(e);
+                }
+                Err(e) => return Err(revert_err(e)),
+            }
+        }
+        if postponed.len() == pending.len()
+            && let Some(e) = last_error
+        {
+            return Err(revert_err(e));
+        }
+        pending = postponed;
+    }
+
+    // Restored or removed operations change the balance of their assets
+    let mut asset_ids: Vec<i32> = entries
+        .iter()
+        .filter(|e| e.table_name == "operations")
+        .flat_map(|e| [&e.before_data, &e.after_data])
+        .filter_map(|data| data.as_ref()?.get("asset_id")?.as_i64())
+        .filter_map(|id| i32::try_from(id).ok())
+        .collect();
+    asset_ids.sort_unstable();
+    asset_ids.dedup();
+    balances::recalculate(&mut tx, &asset_ids)
+        .await?;
+
+    let ids: Vec<i64> = entries.iter().map(|e| e.id).collect();
+    let mut reverted = sqlx::query_as::<_, AuditEntry>(&format!(
+        "UPDATE audit_log SET reverted_at = CURRENT_TIMESTAMP
+         WHERE id = ANY($1)
+         RETURNING {AUDIT_COLUMNS}"
+    ))
+    .bind(&ids)
+    .fetch_all(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+    crate::notifications::check_after_write(&state);
+    reverted.sort_by_key(|e| std::cmp::Reverse(e.id));
+    Ok(Json(reverted))
+}
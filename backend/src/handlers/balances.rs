@@ -0,0 +1,66 @@
+use crate::{AppState, balances, error::AppError, models::*};
+use axum::{
+    Json,
+    extract::{Path, Query, State},
+};
+
+// Balances only make sense for assets that hold operations
+async fn ensure_operations_asset(
+    conn: &mut sqlx::PgConnection,
+    asset_id: i32,
+) -> Result<(), AppError> {
+    let allows_operations: Option<bool> = sqlx::query_scalar(
+        "SELECT at.allows_operations FROM assets a
+         INNER JOIN asset_types at ON a.asset_type_id = at.id
+         WHERE a.id = $1 AND a.deleted_at IS NULL",
+    )
+    .bind(asset_id)
+    .fetch_optional(conn)
+    .await?;
+
+    match allows_operations {
+        Some(true) => Ok(()),
+        Some(false) => Err(AppError::BadRequest("Asset does not have operations".to_string())),
+        None => Err(AppError::NotFound("Asset not found".to_string())),
+    }
+}
+
+pub async fn get_asset_balance(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+    Query(params): Query<AssetBalanceQuery>,
+) -> Result<Json<AssetBalance>, AppError> {
+    let mut conn = state.pool.acquire().await?;
+    ensure_operations_asset(&mut conn, id).await?;
+
+    let balance = balances::balance_at(&mut conn, id, params.at)
+        .await?;
+    Ok(Json(AssetBalance {
+        asset_id: id,
+        at: params.at,
+        balance,
+    }))
+}
+
+pub async fn get_asset_ledger(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+    Query(params): Query<LedgerQuery>,
+) -> Result<Json<AssetLedger>, AppError> {
+    let mut conn = state.pool.acquire().await?;
+    ensure_operations_asset(&mut conn, id).await?;
+
+    let ledger = balances::ledger(&mut conn, id, params.date_from, params.date_to)
+        .await?;
+    Ok(Json(ledger))
+}
+
+// Repair stored balances that drifted from the operations
+pub async fn recalculate_asset_balances(
+    State(state): State<AppState>,
+) -> Result<Json<RecalculateBalancesResponse>, AppError> {
+    let mut tx = state.pool.begin().await?;
+    let (checked, repaired) = balances::recalculate_all(&mut tx).await?;
+    tx.commit().await?;
+    Ok(Json(RecalculateBalancesResponse { checked, repaired }))
+}
@@ -1,14 +1,20 @@
-use crate::{AppState, models::*, utils::db_err};
+use crate::{AppState, error::AppError, models::*, money::AMOUNT_DIGITS, validation::{ValidJson, Validate, Validator}};
 use axum::{
     Json,
     extract::{Path, State},
 };
 use chrono::Datelike;
 
+impl Validate for CreateBudget {
+    fn validate(&self, v: &mut Validator) {
+        v.amount("planned_amount", &self.planned_amount, AMOUNT_DIGITS);
+    }
+}
+
 pub async fn create_budget(
     State(state): State<AppState>,
-    Json(payload): Json<CreateBudget>,
-) -> Result<Json<Budget>, (axum::http::StatusCode, String)> {
+    ValidJson(payload): ValidJson<CreateBudget>,
+) -> Result<Json<Budget>, AppError> {
     let row = sqlx::query_as::<_, Budget>(
         "INSERT INTO budgets (category_id, month, planned_amount, description)
          VALUES ($1, $2, $3, $4)
@@ -19,38 +25,37 @@ pub async fn create_budget(
     .bind(payload.planned_amount)
     .bind(payload.description)
     .fetch_one(&state.pool)
-    .await
-    .map_err(db_err)?;
+    .await?;
+    crate::notifications::check_after_write(&state);
     Ok(Json(row))
 }
 
 pub async fn list_budgets(
     State(state): State<AppState>,
-) -> Result<Json<Vec<Budget>>, (axum::http::StatusCode, String)> {
+) -> Result<Json<Vec<Budget>>, AppError> {
     let rows = sqlx::query_as::<_, Budget>(
         "SELECT id, category_id, month, planned_amount, description FROM budgets ORDER BY month DESC, id"
-    ).fetch_all(&state.pool).await.map_err(db_err)?;
+    ).fetch_all(&state.pool).await?;
     Ok(Json(rows))
 }
 
 pub async fn get_budget(
     State(state): State<AppState>,
     Path(id): Path<i32>,
-) -> Result<Json<Budget>, (axum::http::StatusCode, String)> {
+) -> Result<Json<Budget>, AppError> {
     let row = sqlx::query_as::<_, Budget>(
         "SELECT id, category_id, month, planned_amount, description FROM budgets WHERE id = $1",
     )
     .bind(id)
     .fetch_one(&state.pool)
-    .await
-    .map_err(db_err)?;
+    .await?;
     Ok(Json(row))
 }
 
 pub async fn update_budgets(
     State(state): State<AppState>,
-    Json(payload): Json<Vec<CreateBudget>>,
-) -> Result<Json<Vec<Budget>>, (axum::http::StatusCode, String)> {
+    ValidJson(payload): ValidJson<Vec<CreateBudget>>,
+) -> Result<Json<Vec<Budget>>, AppError> {
     let mut updated = Vec::new();
 
     for item in payload {
@@ -70,8 +75,7 @@ pub async fn update_budgets(
             .bind(item.description)
             .bind(id)
             .fetch_one(&state.pool)
-            .await
-            .map_err(db_err)?
+            .await?
         } else {
             sqlx::query_as::<_, Budget>(
                 "INSERT INTO budgets (category_id, month, planned_amount, description)
@@ -83,25 +87,24 @@ pub async fn update_budgets(
             .bind(item.planned_amount)
             .bind(item.description)
             .fetch_one(&state.pool)
-            .await
-            .map_err(db_err)?
+            .await?
         };
 
         updated.push(row);
     }
 
+    crate::notifications::check_after_write(&state);
     Ok(Json(updated))
 }
 
 pub async fn delete_budget(
     State(state): State<AppState>,
     Path(id): Path<i32>,
-) -> Result<(), (axum::http::StatusCode, String)> {
+) -> Result<(), AppError> {
     sqlx::query("DELETE FROM budgets WHERE id = $1")
         .bind(id)
         .execute(&state.pool)
-        .await
-        .map_err(db_err)?;
+        .await?;
     Ok(())
 }
 
@@ -109,14 +112,11 @@ pub async fn delete_budget(
 pub async fn get_budget_data_for_month(
     State(state): State<AppState>,
     Path(month): Path<String>,
-) -> Result<Json<BudgetDataResponse>, (axum::http::StatusCode, String)> {
+) -> Result<Json<BudgetDataResponse>, AppError> {
     // Parse month (format: YYYY-MM)
     let month_date = chrono::NaiveDate::parse_from_str(&format!("{}-01", month), "%Y-%m-%d")
         .map_err(|_| {
-            (
-                axum::http::StatusCode::BAD_REQUEST,
-                "Invalid month format, expected YYYY-MM".to_string(),
-            )
+            AppError::BadRequest("Invalid month format, expected YYYY-MM".to_string())
         })?;
 
     // Get budgets with category info using JOIN
@@ -128,7 +128,7 @@ pub async fn get_budget_data_for_month(
         category_type: String,
         parent_id: Option<i32>,
         month: chrono::NaiveDate,
-        planned_amount: bigdecimal::BigDecimal,
+        planned_amount: Money,
         description: Option<String>,
     }
 
@@ -149,8 +149,7 @@ pub async fn get_budget_data_for_month(
     )
     .bind(month_date)
     .fetch_all(&state.pool)
-    .await
-    .map_err(db_err)?;
+    .await?;
 
     let budgets: Vec<BudgetWithCategory> = budget_rows
         .into_iter()
@@ -172,29 +171,26 @@ pub async fn get_budget_data_for_month(
         .unwrap_or(month_end);
 
     let spending_rows = sqlx::query!(
-        "SELECT 
+        r#"SELECT 
             category_id,
-            SUM(ABS(amount)) as total_amount
+            SUM(ABS(amount)) as "total_amount: Money"
          FROM operations
-         WHERE category_id IS NOT NULL
+         WHERE category_id IS NOT NULL AND deleted_at IS NULL
            AND operation_date >= $1
            AND operation_date < $2
-         GROUP BY category_id",
+         GROUP BY category_id"#,
         month_date,
         month_end
     )
     .fetch_all(&state.pool)
-    .await
-    .map_err(db_err)?;
+    .await?;
 
     let spending: Vec<CategorySpending> = spending_rows
         .into_iter()
         .filter_map(|row| {
             row.category_id.map(|cat_id| CategorySpending {
                 category_id: cat_id,
-                amount: row
-                    .total_amount
-                    .unwrap_or_else(|| bigdecimal::BigDecimal::from(0)),
+                amount: row.total_amount.unwrap_or_else(Money::zero),
             })
         })
         .collect();
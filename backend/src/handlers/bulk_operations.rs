@@ -0,0 +1,473 @@
+use crate::{AppState, balances, error::AppError, handlers::hashtags::is_valid_hashtag, handlers::operation_rules::{check_category, check_operation, check_split, check_split_items}, handlers::operations::{extract_hashtags, insert_split_children}, handlers::payees::assign_payee, models::*, trash, validation::{self, ValidJson, Validate, Validator}};
+use axum::{Json, extract::State};
+use sqlx::{Connection, PgConnection, Postgres, QueryBuilder};
+use std::collections::{BTreeSet, HashMap};
+
+impl Validate for BulkOperationRequest {
+    fn validate(&self, v: &mut Validator) {
+        if self.ids.is_none() && self.filter.is_none() {
+            v.error("ids", "Either ids or filter is required");
+        }
+        if let BulkAction::ShiftDates { days: 0 } = self.action {
+            v.error("action.days", "days must not be 0");
+        }
+    }
+}
+
+impl Validate for BatchCreateOperationsRequest {
+    fn validate(&self, v: &mut Validator) {
+        v.check(!self.items.is_empty(), "items", "At least one item is required");
+    }
+}
+
+// Minimal row data needed to apply a bulk action
+#[derive(sqlx::FromRow)]
+struct BulkTargetRow {
+    id: i32,
+    asset_id: i32,
+    description: Option<String>,
+    category_id: Option<i32>,
+    operation_type: String,
+    is_split: bool,
+    parent_operation_id: Option<i32>,
+    linked_operation_id: Option<i32>,
+}
+
+fn push_filter(builder: &mut QueryBuilder<'_, Postgres>, filter: &OperationFilter) -> Result<(), AppError> {
+    let mut has_condition = false;
+
+    if let Some(asset_id) = filter.asset_id {
+        builder.push(" AND asset_id = ").push_bind(asset_id);
+        has_condition = true;
+    }
+    if let Some(category_id) = filter.category_id {
+        builder.push(" AND category_id = ").push_bind(category_id);
+        has_condition = true;
+    }
+    if filter.uncategorized == Some(true) {
+        builder.push(" AND category_id IS NULL");
+        has_condition = true;
+    }
+    if let Some(operation_type) = &filter.operation_type {
+        builder.push(" AND operation_type::text = ").push_bind(operation_type.as_str());
+        has_condition = true;
+    }
+    if let Some(date_from) = filter.date_from {
+        builder.push(" AND operation_date >= ").push_bind(date_from);
+        has_condition = true;
+    }
+    if let Some(date_to) = filter.date_to {
+        builder.push(" AND operation_date <= ").push_bind(date_to);
+        has_condition = true;
+    }
+    if let Some(text) = &filter.description_contains {
+        builder.push(" AND description ILIKE ").push_bind(format!("%{}%", text));
+        has_condition = true;
+    }
+    if let Some(payee_id) = filter.payee_id {
+        builder.push(" AND payee_id = ").push_bind(payee_id);
+        has_condition = true;
+    }
+
+    // Refuse to touch every operation because of an empty filter
+    if !has_condition {
+        return Err(AppError::BadRequest("Filter requires at least one condition".to_string()));
+    }
+    Ok(())
+}
+
+pub(crate) fn normalize_hashtags(names: &[String]) -> Result<Vec<String>, AppError> {
+    let mut result: Vec<String> = Vec::new();
+    for name in names {
+        let name = name.trim_start_matches('#').to_lowercase();
+        if !is_valid_hashtag(&name) {
+            return Err(AppError::BadRequest(format!("Invalid hashtag: {}", name)));
+        }
+        if !result.contains(&name) {
+            result.push(name);
+        }
+    }
+    if result.is_empty() {
+        return Err(AppError::BadRequest("At least one hashtag is required".to_string()));
+    }
+    Ok(result)
+}
+
+// Append missing hashtags to the end of a description
+pub(crate) fn add_hashtags_to_description(description: Option<&str>, hashtags: &[String]) -> String {
+    let mut text = description.unwrap_or("").trim_end().to_string();
+    let existing = extract_hashtags(&text);
+
+    for hashtag in hashtags {
+        if !existing.contains(hashtag) {
+            if !text.is_empty() {
+                text.push(' ');
+            }
+            text.push('#');
+            text.push_str(hashtag);
+        }
+    }
+    text
+}
+
+// Drop words that are one of the given hashtags
+fn remove_hashtags_from_description(description: &str, hashtags: &[String]) -> Option<String> {
+    let text = description
+        .split_whitespace()
+        .filter(|w| {
+            !(w.starts_with('#')
+                && extract_hashtags(w)
+                    .first()
+                    .is_some_and(|h| hashtags.contains(h)))
+        })
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    if text.is_empty() { None } else { Some(text) }
+}
+
+async fn apply_bulk_action(
+    conn: &mut PgConnection,
+    row: &BulkTargetRow,
+    action: &BulkAction,
+    target_asset_id: Option<i32>,
+) -> Result<(), String> {
+    match action {
+        BulkAction::SetCategory { category_id } => {
+            if row.is_split {
+                return Err("Cannot set category on a split operation; edit its children instead".to_string());
+            }
+            check_category(&mut *conn, *category_id, &row.operation_type, row.category_id)
+                .await
+                .map_err(|e| e.to_string())?;
+            sqlx::query("UPDATE operations SET category_id = $1 WHERE id = $2")
+                .bind(category_id)
+                .bind(row.id)
+                .execute(conn)
+                .await
+                .map_err(|e| e.to_string())?;
+        }
+        BulkAction::AddHashtags { hashtags } => {
+            let description = add_hashtags_to_description(row.description.as_deref(), hashtags);
+            sqlx::query("UPDATE operations SET description = $1 WHERE id = $2")
+                .bind(description)
+                .bind(row.id)
+                .execute(conn)
+                .await
+                .map_err(|e| e.to_string())?;
+        }
+        BulkAction::RemoveHashtags { hashtags } => {
+            if let Some(desc) = &row.description {
+                let description = remove_hashtags_from_description(desc, hashtags);
+                sqlx::query("UPDATE operations SET description = $1 WHERE id = $2")
+                    .bind(description)
+                    .bind(row.id)
+                    .execute(conn)
+                    .await
+                    .map_err(|e| e.to_string())?;
+            }
+        }
+        BulkAction::MoveToAsset { .. } => {
+            if row.parent_operation_id.is_some() {
+                return Err("Cannot move a split child; move its parent instead".to_string());
+            }
+            // Split children always follow their parent
+            sqlx::query("UPDATE operations SET asset_id = $1 WHERE id = $2 OR parent_operation_id = $2")
+                .bind(target_asset_id)
+                .bind(row.id)
+                .execute(conn)
+                .await
+                .map_err(|e| e.to_string())?;
+        }
+        BulkAction::SetPayee { payee_id } => {
+            sqlx::query("UPDATE operations SET payee_id = $1 WHERE id = $2")
+                .bind(payee_id)
+                .bind(row.id)
+                .execute(conn)
+                .await
+                .map_err(|e| e.to_string())?;
+        }
+        BulkAction::ShiftDates { days } => {
+            if row.parent_operation_id.is_some() {
+                return Err("Cannot shift a split child; shift its parent instead".to_string());
+            }
+            sqlx::query("UPDATE operations SET operation_date = operation_date + $1 WHERE id = $2 OR parent_operation_id = $2")
+                .bind(days)
+                .bind(row.id)
+                .execute(conn)
+                .await
+                .map_err(|e| e.to_string())?;
+        }
+        BulkAction::Delete => {
+            trash::trash_operations(conn, &[row.id])
+                .await
+                .map_err(|e| e.to_string())?;
+        }
+    }
+    Ok(())
+}
+
+// Apply one action to many operations in a single transaction
+pub async fn bulk_update_operations(
+    State(state): State<AppState>,
+    ValidJson(mut payload): ValidJson<BulkOperationRequest>,
+) -> Result<Json<BulkOperationResponse>, AppError> {
+    if let BulkAction::AddHashtags { hashtags } | BulkAction::RemoveHashtags { hashtags } = &mut payload.action {
+        *hashtags = normalize_hashtags(hashtags)?;
+    }
+
+    let mut tx = state.pool.begin().await?;
+
+    if let BulkAction::MoveToAsset { asset_id } = &payload.action {
+        let allows_operations: Option<bool> = sqlx::query_scalar(
+            "SELECT at.allows_operations FROM assets a
+             INNER JOIN asset_types at ON a.asset_type_id = at.id
+             WHERE a.id = $1 AND a.deleted_at IS NULL"
+        )
+        .bind(asset_id)
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        match allows_operations {
+            Some(true) => {}
+            Some(false) => {
+                return Err(AppError::BadRequest("Target asset does not allow operations".to_string()));
+            }
+            None => {
+                return Err(AppError::NotFound("Target asset not found".to_string()));
+            }
+        }
+    }
+
+    // Resolve target rows from explicit ids and/or a filter
+    let mut builder: QueryBuilder<Postgres> = QueryBuilder::new(
+        "SELECT id, asset_id, description, category_id, operation_type::text, is_split, parent_operation_id, linked_operation_id
+         FROM operations WHERE deleted_at IS NULL",
+    );
+    if let Some(ids) = &payload.ids {
+        builder.push(" AND id = ANY(").push_bind(ids.clone()).push(")");
+    }
+    if let Some(filter) = &payload.filter {
+        push_filter(&mut builder, filter)?;
+        // Filters work on top-level rows, like the operations list
+        if payload.ids.is_none() {
+            builder.push(" AND parent_operation_id IS NULL");
+        }
+    }
+    builder.push(" ORDER BY id");
+
+    let rows = builder
+        .build_query_as::<BulkTargetRow>()
+        .fetch_all(&mut *tx)
+        .await?;
+
+    // Deleting one side of a transfer also trashes its partner, whose asset
+    // needs recalculating as well
+    let partner_assets: HashMap<i32, i32> = if matches!(payload.action, BulkAction::Delete) {
+        let linked_ids: Vec<i32> = rows.iter().filter_map(|r| r.linked_operation_id).collect();
+        sqlx::query_as::<_, (i32, i32)>("SELECT id, asset_id FROM operations WHERE id = ANY($1)")
+            .bind(linked_ids)
+            .fetch_all(&mut *tx)
+            .await?
+            .into_iter()
+            .collect()
+    } else {
+        HashMap::new()
+    };
+
+    let target_asset_id = match &payload.action {
+        BulkAction::MoveToAsset { asset_id } => Some(*asset_id),
+        _ => None,
+    };
+
+    let mut results: Vec<BulkItemResult> = Vec::new();
+    let mut affected_assets: BTreeSet<i32> = BTreeSet::new();
+    let mut deleted_partners: BTreeSet<i32> = BTreeSet::new();
+
+    // Requested ids that don't exist
+    if let Some(ids) = &payload.ids {
+        for id in ids {
+            if !rows.iter().any(|r| r.id == *id) {
+                results.push(BulkItemResult {
+                    id: *id,
+                    success: false,
+                    error: Some("Operation not found".to_string()),
+                });
+            }
+        }
+    }
+
+    for row in &rows {
+        // Already in the trash together with its transfer partner
+        if matches!(payload.action, BulkAction::Delete) && deleted_partners.contains(&row.id) {
+            results.push(BulkItemResult { id: row.id, success: true, error: None });
+            continue;
+        }
+
+        // Savepoint per item so one failure doesn't abort the rest
+        let mut sp = Connection::begin(&mut *tx).await?;
+        match apply_bulk_action(&mut sp, row, &payload.action, target_asset_id).await {
+            Ok(()) => {
+                sp.commit().await?;
+                affected_assets.insert(row.asset_id);
+                if let Some(asset_id) = target_asset_id {
+                    affected_assets.insert(asset_id);
+                }
+                if matches!(payload.action, BulkAction::Delete)
+                    && let Some(linked_id) = row.linked_operation_id
+                {
+                    deleted_partners.insert(linked_id);
+                    if let Some(asset_id) = partner_assets.get(&linked_id) {
+                        affected_assets.insert(*asset_id);
+                    }
+                }
+                results.push(BulkItemResult { id: row.id, success: true, error: None });
+            }
+            Err(e) => {
+                sp.rollback().await?;
+                results.push(BulkItemResult { id: row.id, success: false, error: Some(e) });
+            }
+        }
+    }
+
+    let failed = results.iter().filter(|r| !r.success).count();
+    let succeeded = results.len() - failed;
+    let atomic = payload.atomic.unwrap_or(false);
+
+    if atomic && failed > 0 {
+        tx.rollback().await?;
+        return Ok(Json(BulkOperationResponse {
+            matched: rows.len(),
+            succeeded: 0,
+            failed,
+            committed: false,
+            results,
+            recalculated_asset_ids: Vec::new(),
+        }));
+    }
+
+    let recalculated_asset_ids: Vec<i32> = affected_assets.into_iter().collect();
+    balances::recalculate(&mut tx, &recalculated_asset_ids).await?;
+    tx.commit().await?;
+    crate::notifications::check_after_write(&state);
+
+    Ok(Json(BulkOperationResponse {
+        matched: rows.len(),
+        succeeded,
+        failed,
+        committed: true,
+        results,
+        recalculated_asset_ids,
+    }))
+}
+
+async fn insert_batch_item(conn: &mut PgConnection, item: serde_json::Value) -> Result<Operation, String> {
+    let item: CreateOperation = validation::from_value(item).map_err(|e| match e {
+        AppError::Validation(fields) => fields
+            .iter()
+            .map(|f| format!("{}: {}", f.field, f.message))
+            .collect::<Vec<_>>()
+            .join("; "),
+        e => e.to_string(),
+    })?;
+    let mut split_items = item.split_items.clone().unwrap_or_default();
+    if item.split_items.is_some() {
+        check_split(&item.amount, &mut split_items).map_err(|e| e.to_string())?;
+    }
+
+    let assignment = assign_payee(
+        &mut *conn,
+        item.payee_id.flatten(),
+        item.category_id,
+        item.description.as_deref(),
+        item.operation_type.as_str(),
+        !split_items.is_empty(),
+    )
+    .await
+    .map_err(|e| e.to_string())?;
+
+    check_operation(&mut *conn, item.operation_type.as_str(), &item.amount, assignment.category_id, None)
+        .await
+        .map_err(|e| e.to_string())?;
+    check_split_items(&mut *conn, item.operation_type.as_str(), &split_items)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let op = sqlx::query_as::<_, Operation>(
+        "INSERT INTO operations (category_id, description, asset_id, amount, operation_type, operation_date, is_split, payee_id)
+         VALUES ($1, $2, $3, $4, $5::operation_type, $6::date, $7, $8)
+         RETURNING id, creation_date, category_id, description, asset_id, amount, operation_type::text, operation_date, parent_operation_id, is_split, linked_operation_id, payee_id, clearing_status::text"
+    )
+    .bind(assignment.category_id)
+    .bind(&assignment.description)
+    .bind(item.asset_id)
+    .bind(&item.amount)
+    .bind(item.operation_type.as_str())
+    .bind(item.operation_date)
+    .bind(!split_items.is_empty())
+    .bind(assignment.payee_id)
+    .fetch_one(&mut *conn)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    insert_split_children(&mut *conn, &op, &split_items)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    Ok(op)
+}
+
+// Create many operations in a single transaction
+pub async fn batch_create_operations(
+    State(state): State<AppState>,
+    ValidJson(payload): ValidJson<BatchCreateOperationsRequest>,
+) -> Result<Json<BatchCreateOperationsResponse>, AppError> {
+    let mut tx = state.pool.begin().await?;
+    let mut results: Vec<BatchCreateItemResult> = Vec::new();
+    let mut affected_assets: BTreeSet<i32> = BTreeSet::new();
+
+    let atomic = payload.atomic.unwrap_or(false);
+    for (index, item) in payload.items.into_iter().enumerate() {
+        let mut sp = Connection::begin(&mut *tx).await?;
+        match insert_batch_item(&mut sp, item).await {
+            Ok(op) => {
+                sp.commit().await?;
+                affected_assets.insert(op.asset_id);
+                results.push(BatchCreateItemResult { index, success: true, operation: Some(op), error: None });
+            }
+            Err(e) => {
+                sp.rollback().await?;
+                results.push(BatchCreateItemResult { index, success: false, operation: None, error: Some(e) });
+            }
+        }
+    }
+
+    let failed = results.iter().filter(|r| !r.success).count();
+
+    if atomic && failed > 0 {
+        tx.rollback().await?;
+        for result in &mut results {
+            result.operation = None;
+        }
+        return Ok(Json(BatchCreateOperationsResponse {
+            created: 0,
+            failed,
+            committed: false,
+            results,
+            recalculated_asset_ids: Vec::new(),
+        }));
+    }
+
+    let recalculated_asset_ids: Vec<i32> = affected_assets.into_iter().collect();
+    balances::recalculate(&mut tx, &recalculated_asset_ids).await?;
+    tx.commit().await?;
+    crate::notifications::check_after_write(&state);
+
+    Ok(Json(BatchCreateOperationsResponse {
+        created: results.len() - failed,
+        failed,
+        committed: true,
+        results,
+        recalculated_asset_ids,
+    }))
+}
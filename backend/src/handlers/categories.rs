@@ -1,15 +1,267 @@
-use axum::{extract::{State, Path}, Json};
-use crate::{AppState, models::*, utils::db_err};
+use axum::{extract::{State, Path, Query}, Json};
+use crate::{AppState, error::AppError, models::*, validation::{ValidJson, Validate, Validator}};
+
+impl Validate for CreateCategory {
+    fn validate(&self, v: &mut Validator) {
+        v.required("name", &self.name, 255);
+    }
+}
+
+impl Validate for MoveCategory {}
+impl Validate for ReorderCategories {}
+
+// Main categories and one level of subcategories
+const MAX_CATEGORY_DEPTH: i32 = 2;
+
+const CATEGORY_COLUMNS: &str = "id, name, parent_id, type::text, sort_order, is_system, is_hidden";
+
+async fn fetch_category(conn: &mut sqlx::PgConnection, id: i32) -> Result<Category, AppError> {
+    sqlx::query_as::<_, Category>(&format!("SELECT {CATEGORY_COLUMNS} FROM categories WHERE id = $1 AND deleted_at IS NULL FOR UPDATE"))
+        .bind(id)
+        .fetch_optional(conn)
+        .await?
+        .ok_or(AppError::NotFound("Category not found".to_string()))
+}
+
+// Depth of a category counted from the top level (a main category is 1)
+async fn category_depth(conn: &mut sqlx::PgConnection, id: i32) -> Result<i32, AppError> {
+    sqlx::query_scalar(
+        "WITH RECURSIVE up AS (
+             SELECT id, parent_id, 1 AS depth FROM categories WHERE id = $1
+             UNION ALL
+             SELECT c.id, c.parent_id, up.depth + 1 FROM categories c
+             INNER JOIN up ON c.id = up.parent_id
+             WHERE up.depth <= 100
+         )
+         SELECT MAX(depth) FROM up"
+    )
+    .bind(id)
+    .fetch_one(conn)
+    .await
+    .map_err(AppError::from)
+}
+
+// Whether `other` is the category itself or below it, and how many levels the subtree spans
+async fn subtree_info(conn: &mut sqlx::PgConnection, id: i32, other: i32) -> Result<(bool, i32), AppError> {
+    sqlx::query_as(
+        "WITH RECURSIVE down AS (
+             SELECT id, 1 AS level FROM categories WHERE id = $1
+             UNION ALL
+             SELECT c.id, down.level + 1 FROM categories c
+             INNER JOIN down ON c.parent_id = down.id
+             WHERE down.level <= 100
+         )
+         SELECT COALESCE(bool_or(id = $2), FALSE), COALESCE(MAX(level), 1) FROM down"
+    )
+    .bind(id)
+    .bind(other)
+    .fetch_one(conn)
+    .await
+    .map_err(AppError::from)
+}
+
+// A new parent must exist, must not be the category or one of its
+// descendants, and must leave the subtree within the depth limit
+async fn validate_parent(conn: &mut sqlx::PgConnection, category_id: Option<i32>, parent_id: Option<i32>) -> Result<(), AppError> {
+    let Some(parent_id) = parent_id else {
+        return Ok(());
+    };
+    let exists: bool = sqlx::query_scalar("SELECT EXISTS(SELECT 1 FROM categories WHERE id = $1 AND deleted_at IS NULL)")
+        .bind(parent_id)
+        .fetch_one(&mut *conn)
+        .await?;
+    if !exists {
+        return Err(AppError::BadRequest("Parent category not found".to_string()));
+    }
+
+    let height = match category_id {
+        Some(id) => {
+            let (is_descendant, height) = subtree_info(conn, id, parent_id).await?;
+            if is_descendant {
+                return Err(AppError::BadRequest(
+                    "A category can't be moved under itself or its subcategory".to_string(),
+                ));
+            }
+            height
+        }
+        None => 1,
+    };
+
+    if category_depth(conn, parent_id).await? + height > MAX_CATEGORY_DEPTH {
+        return Err(AppError::BadRequest(
+            format!("Categories can be nested at most {} levels deep", MAX_CATEGORY_DEPTH),
+        ));
+    }
+    Ok(())
+}
+
+// Renumber siblings 1..n in the given order
+async fn set_sibling_order(conn: &mut sqlx::PgConnection, ids: &[i32]) -> Result<(), AppError> {
+    sqlx::query(
+        "UPDATE categories c SET sort_order = o.ord::int
+         FROM UNNEST($1::int[]) WITH ORDINALITY AS o(id, ord)
+         WHERE c.id = o.id AND c.sort_order IS DISTINCT FROM o.ord::int"
+    )
+    .bind(ids)
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+async fn sibling_ids(conn: &mut sqlx::PgConnection, parent_id: Option<i32>, except: i32) -> Result<Vec<i32>, AppError> {
+    sqlx::query_scalar(
+        "SELECT id FROM categories
+         WHERE parent_id IS NOT DISTINCT FROM $1 AND id <> $2 AND deleted_at IS NULL
+         ORDER BY sort_order, id"
+    )
+    .bind(parent_id)
+    .bind(except)
+    .fetch_all(conn)
+    .await
+    .map_err(AppError::from)
+}
+
+// Place a category (and its subtree) under a new parent at the given position,
+// renumbering both the old and the new siblings
+async fn move_category_to(
+    conn: &mut sqlx::PgConnection,
+    category: &Category,
+    parent_id: Option<i32>,
+    position: Option<usize>,
+) -> Result<(), AppError> {
+    if parent_id != category.parent_id {
+        validate_parent(conn, Some(category.id), parent_id).await?;
+        sqlx::query("UPDATE categories SET parent_id = $1 WHERE id = $2")
+            .bind(parent_id)
+            .bind(category.id)
+            .execute(&mut *conn)
+            .await?;
+        let old_siblings = sibling_ids(conn, category.parent_id, category.id).await?;
+        set_sibling_order(conn, &old_siblings).await?;
+    }
+
+    let mut siblings = sibling_ids(conn, parent_id, category.id).await?;
+    let position = position.unwrap_or(siblings.len()).min(siblings.len());
+    siblings.insert(position, category.id);
+    set_sibling_order(conn, &siblings).await
+}
+
+// Move everything that uses `source` over to `target` and move `source` to the
+// trash. Trashed operations and subcategories follow too, so they can still be
+// restored, but only live ones are counted.
+async fn merge_category_into(
+    conn: &mut sqlx::PgConnection,
+    source_id: i32,
+    target_id: i32,
+) -> Result<CategoryMergeResult, AppError> {
+    if source_id == target_id {
+        return Err(AppError::BadRequest("A category can't be merged into itself".to_string()));
+    }
+    let source = fetch_category(conn, source_id).await?;
+    let target = fetch_category(conn, target_id).await?;
+
+    if source.is_system {
+        return Err(AppError::Forbidden("Cannot merge or delete system category".to_string()));
+    }
+    if source.r#type != target.r#type {
+        return Err(AppError::BadRequest(
+            format!("Cannot merge a {} category into a {} category", source.r#type, target.r#type),
+        ));
+    }
+
+    // Subcategories of the source move under the target
+    let (target_inside_source, height) = subtree_info(conn, source_id, target_id).await?;
+    if target_inside_source {
+        return Err(AppError::BadRequest("A category can't be merged into its own subcategory".to_string()));
+    }
+    if height > 1 && category_depth(conn, target_id).await? + height - 1 > MAX_CATEGORY_DEPTH {
+        return Err(AppError::BadRequest(
+            "Target category can't hold the subcategories of the merged category; move them first".to_string(),
+        ));
+    }
+    let subcategories_moved: i64 = sqlx::query_scalar(
+        "WITH moved AS (UPDATE categories SET parent_id = $1 WHERE parent_id = $2 RETURNING deleted_at)
+         SELECT COUNT(*) FROM moved WHERE deleted_at IS NULL"
+    )
+    .bind(target_id)
+    .bind(source_id)
+    .fetch_one(&mut *conn)
+    .await?;
+    if subcategories_moved > 0 {
+        let children = sibling_ids(conn, Some(target_id), 0).await?;
+        set_sibling_order(conn, &children).await?;
+    }
+
+    let operations_moved: i64 = sqlx::query_scalar(
+        "WITH moved AS (UPDATE operations SET category_id = $1 WHERE category_id = $2 RETURNING deleted_at)
+         SELECT COUNT(*) FROM moved WHERE deleted_at IS NULL"
+    )
+    .bind(target_id)
+    .bind(source_id)
+    .fetch_one(&mut *conn)
+    .await?;
+
+    // Budgets for a month the target already has are added to the target's budget
+    let budgets_combined = sqlx::query(
+        "WITH combined AS (
+             UPDATE budgets t SET planned_amount = t.planned_amount + s.total
+             FROM (SELECT month, SUM(planned_amount) AS total FROM budgets WHERE category_id = $2 GROUP BY month) s
+             WHERE t.id = (SELECT MIN(id) FROM budgets WHERE category_id = $1 AND month = s.month)
+             RETURNING t.month
+         )
+         DELETE FROM budgets WHERE category_id = $2 AND month IN (SELECT month FROM combined)"
+    )
+    .bind(target_id)
+    .bind(source_id)
+    .execute(&mut *conn)
+    .await?
+    .rows_affected();
+    let budgets_moved = budgets_combined
+        + sqlx::query("UPDATE budgets SET category_id = $1 WHERE category_id = $2")
+            .bind(target_id)
+            .bind(source_id)
+            .execute(&mut *conn)
+            .await?
+            .rows_affected();
+
+    let recurring_operations_moved = sqlx::query("UPDATE recurring_operations SET category_id = $1 WHERE category_id = $2")
+        .bind(target_id)
+        .bind(source_id)
+        .execute(&mut *conn)
+        .await?
+        .rows_affected();
+
+    sqlx::query("UPDATE payees SET default_category_id = $1 WHERE default_category_id = $2")
+        .bind(target_id)
+        .bind(source_id)
+        .execute(&mut *conn)
+        .await?;
+
+    sqlx::query("UPDATE categories SET deleted_at = CURRENT_TIMESTAMP WHERE id = $1")
+        .bind(source_id)
+        .execute(&mut *conn)
+        .await?;
+    let siblings = sibling_ids(conn, source.parent_id, source_id).await?;
+    set_sibling_order(conn, &siblings).await?;
+
+    let category = fetch_category(conn, target_id).await?;
+    Ok(CategoryMergeResult {
+        category,
+        operations_moved: operations_moved as u64,
+        budgets_moved,
+        recurring_operations_moved,
+        subcategories_moved: subcategories_moved as u64,
+    })
+}
 
 // Helper function to ensure debt categories exist
-pub async fn ensure_debt_categories(pool: &sqlx::PgPool) -> Result<(i32, i32), (axum::http::StatusCode, String)> {
+pub async fn ensure_debt_categories(pool: &sqlx::PgPool) -> Result<(i32, i32), AppError> {
     // Check if "Depts" category exists
     let debt_category: Option<Category> = sqlx::query_as::<_, Category>(
         "SELECT id, name, parent_id, type::text, sort_order, is_system, is_hidden FROM categories WHERE name = 'Depts' AND parent_id IS NULL"
     )
     .fetch_optional(pool)
-    .await
-    .map_err(db_err)?;
+    .await?;
 
     let debt_category_id = if let Some(cat) = debt_category {
         cat.id
@@ -31,8 +283,7 @@ pub async fn ensure_debt_categories(pool: &sqlx::PgPool) -> Result<(i32, i32), (
         )
         .bind(next_order)
         .fetch_one(pool)
-        .await
-        .map_err(db_err)?;
+        .await?;
         
         new_cat.id
     };
@@ -43,8 +294,7 @@ pub async fn ensure_debt_categories(pool: &sqlx::PgPool) -> Result<(i32, i32), (
     )
     .bind(debt_category_id)
     .fetch_optional(pool)
-    .await
-    .map_err(db_err)?;
+    .await?;
 
     let interest_category_id = if let Some(cat) = interest_category {
         cat.id
@@ -68,8 +318,7 @@ pub async fn ensure_debt_categories(pool: &sqlx::PgPool) -> Result<(i32, i32), (
         .bind(debt_category_id)
         .bind(next_order)
         .fetch_one(pool)
-        .await
-        .map_err(db_err)?;
+        .await?;
         
         new_cat.id
     };
@@ -77,102 +326,216 @@ pub async fn ensure_debt_categories(pool: &sqlx::PgPool) -> Result<(i32, i32), (
     Ok((debt_category_id, interest_category_id))
 }
 
-pub async fn create_category(State(state): State<AppState>, Json(payload): Json<CreateCategory>) -> Result<Json<Category>, (axum::http::StatusCode, String)> {
+// Find a system category by name under the given parent, creating it at the end if missing
+async fn ensure_system_category(
+    conn: &mut sqlx::PgConnection,
+    name: &str,
+    parent_id: Option<i32>,
+    category_type: &str,
+) -> Result<i32, AppError> {
+    let existing: Option<i32> = sqlx::query_scalar(
+        "SELECT id FROM categories
+         WHERE name = $1 AND parent_id IS NOT DISTINCT FROM $2 AND is_system = TRUE
+         ORDER BY id LIMIT 1",
+    )
+    .bind(name)
+    .bind(parent_id)
+    .fetch_optional(&mut *conn)
+    .await?;
+    if let Some(id) = existing {
+        return Ok(id);
+    }
+
+    sqlx::query_scalar(
+        "INSERT INTO categories (name, parent_id, type, sort_order, is_system)
+         VALUES ($1, $2, $3::category_type,
+                 (SELECT COALESCE(MAX(sort_order), 0) + 1 FROM categories WHERE parent_id IS NOT DISTINCT FROM $2 AND deleted_at IS NULL),
+                 TRUE)
+         RETURNING id",
+    )
+    .bind(name)
+    .bind(parent_id)
+    .bind(category_type)
+    .fetch_one(conn)
+    .await
+    .map_err(AppError::from)
+}
+
+// Correction > Positive (income) and Negative (expense), used by balance corrections.
+// Returns (positive_id, negative_id).
+pub async fn ensure_correction_categories(conn: &mut sqlx::PgConnection) -> Result<(i32, i32), AppError> {
+    let correction_id = ensure_system_category(conn, "Correction", None, "expense").await?;
+    let positive_id = ensure_system_category(conn, "Positive", Some(correction_id), "income").await?;
+    let negative_id = ensure_system_category(conn, "Negative", Some(correction_id), "expense").await?;
+    Ok((positive_id, negative_id))
+}
+
+pub async fn create_category(State(state): State<AppState>, ValidJson(payload): ValidJson<CreateCategory>) -> Result<Json<Category>, AppError> {
+    let mut conn = state.pool.acquire().await?;
+    validate_parent(&mut conn, None, payload.parent_id).await?;
+
     // Calculate next sort order
     let max_order: i32 = sqlx::query_scalar::<_, i32>(
-         r#" SELECT COALESCE(MAX(sort_order), 0) FROM categories WHERE $1::int IS NULL OR parent_id = $1 "# 
+         r#" SELECT COALESCE(MAX(sort_order), 0) FROM categories WHERE ($1::int IS NULL OR parent_id = $1) AND deleted_at IS NULL "# 
     )
     .bind(payload.parent_id)
-    .fetch_one(&state.pool)
-    .await
-    .map_err(db_err)?;
+    .fetch_one(&mut *conn)
+    .await?;
     
     let next_order = max_order + 1;
 
     let cat = sqlx::query_as::<_, Category>(
         "INSERT INTO categories (name, parent_id, type, sort_order, is_system) VALUES ($1, $2, $3::category_type, $4, FALSE)
          RETURNING id, name, parent_id, type::text, sort_order, is_system, is_hidden"
-    ).bind(&payload.name).bind(&payload.parent_id).bind(&payload.r#type).bind(next_order)
-     .fetch_one(&state.pool).await.map_err(db_err)?;
+    ).bind(&payload.name).bind(payload.parent_id).bind(payload.r#type.as_str()).bind(next_order)
+     .fetch_one(&mut *conn).await?;
     Ok(Json(cat))
 }
 
-pub async fn list_categories(State(state): State<AppState>) -> Result<Json<Vec<Category>>, (axum::http::StatusCode, String)> {
+pub async fn list_categories(State(state): State<AppState>) -> Result<Json<Vec<Category>>, AppError> {
     let rows = sqlx::query_as::<_, Category>(
-        "SELECT id, name, parent_id, type::text, sort_order, is_system, is_hidden FROM categories ORDER BY parent_id NULLS FIRST, sort_order, id"
-    ).fetch_all(&state.pool).await.map_err(db_err)?;
+        "SELECT id, name, parent_id, type::text, sort_order, is_system, is_hidden FROM categories WHERE deleted_at IS NULL ORDER BY parent_id NULLS FIRST, sort_order, id"
+    ).fetch_all(&state.pool).await?;
     Ok(Json(rows))
 }
 
-pub async fn get_category(State(state): State<AppState>, Path(id): Path<i32>) -> Result<Json<Category>, (axum::http::StatusCode, String)> {
+pub async fn get_category(State(state): State<AppState>, Path(id): Path<i32>) -> Result<Json<Category>, AppError> {
     let row = sqlx::query_as::<_, Category>(
-        "SELECT id, name, parent_id, type::text, sort_order, is_system, is_hidden FROM categories WHERE id = $1"
-    ).bind(id).fetch_one(&state.pool).await.map_err(db_err)?;
+        "SELECT id, name, parent_id, type::text, sort_order, is_system, is_hidden FROM categories WHERE id = $1 AND deleted_at IS NULL"
+    ).bind(id).fetch_one(&state.pool).await?;
     Ok(Json(row))
 }
-pub async fn is_category_used(State(state): State<AppState>) -> Result<Json<Vec<CategoryUsed>>, (axum::http::StatusCode, String)> {
+pub async fn is_category_used(State(state): State<AppState>) -> Result<Json<Vec<CategoryUsed>>, AppError> {
     let rows = sqlx::query_as::<_,CategoryUsed>(
-        "SELECT category_id as id, COUNT(*) > 0 as is_used FROM operations GROUP BY category_id"
-    ).fetch_all(&state.pool).await.map_err(db_err)?;
+        "SELECT category_id as id, COUNT(*) > 0 as is_used FROM operations WHERE deleted_at IS NULL GROUP BY category_id"
+    ).fetch_all(&state.pool).await?;
     Ok(Json(rows))
 }
 
-pub async fn update_category(State(state): State<AppState>, Path(id): Path<i32>, Json(payload): Json<CreateCategory>) -> Result<Json<Category>, (axum::http::StatusCode, String)> {
+pub async fn update_category(State(state): State<AppState>, Path(id): Path<i32>, ValidJson(payload): ValidJson<CreateCategory>) -> Result<Json<Category>, AppError> {
+    let mut tx = state.pool.begin().await?;
+    let current = fetch_category(&mut tx, id).await?;
+
+    // Operations must keep the type of their category
+    if payload.r#type.as_str() != current.r#type {
+        let in_use: i64 = sqlx::query_scalar(
+            "SELECT (SELECT COUNT(*) FROM operations WHERE category_id = $1 AND deleted_at IS NULL)
+                  + (SELECT COUNT(*) FROM recurring_operations WHERE category_id = $1)"
+        )
+        .bind(id)
+        .fetch_one(&mut *tx)
+        .await?;
+        if in_use > 0 {
+            return Err(AppError::BadRequest(
+                format!("Category type can't change while {} operations use it; move them first", in_use),
+            ));
+        }
+    }
+
+    // A new parent puts the category at the end of its new siblings
+    move_category_to(&mut tx, &current, payload.parent_id, None).await?;
+
     let row = sqlx::query_as::<_, Category>(
-        "UPDATE categories SET name = $1, parent_id = $2, type = $3::category_type WHERE id = $4
+        "UPDATE categories SET name = $1, type = $2::category_type WHERE id = $3
          RETURNING id, name, parent_id, type::text, sort_order, is_system, is_hidden"
-    ).bind(&payload.name).bind(&payload.parent_id).bind(&payload.r#type).bind(id)
-     .fetch_one(&state.pool).await.map_err(db_err)?;
+    ).bind(&payload.name).bind(payload.r#type.as_str()).bind(id)
+     .fetch_one(&mut *tx).await?;
+
+    tx.commit().await?;
     Ok(Json(row))
 }
 
-pub async fn delete_category(State(state): State<AppState>, Path(id): Path<i32>) -> Result<(), (axum::http::StatusCode, String)> {
-    // Check if category is system category
-    let is_system: bool = sqlx::query_scalar("SELECT is_system FROM categories WHERE id = $1")
-        .bind(id)
-        .fetch_one(&state.pool)
-        .await
-        .map_err(db_err)?;
-    
-    if is_system {
-        return Err((axum::http::StatusCode::FORBIDDEN, "Cannot delete system category".to_string()));
+// Move a category with its subcategories under another parent (or to the top
+// level) at the given position
+pub async fn move_category(State(state): State<AppState>, Path(id): Path<i32>, ValidJson(payload): ValidJson<MoveCategory>) -> Result<Json<Category>, AppError> {
+    let mut tx = state.pool.begin().await?;
+    let current = fetch_category(&mut tx, id).await?;
+    move_category_to(&mut tx, &current, payload.parent_id, payload.position).await?;
+    let row = fetch_category(&mut tx, id).await?;
+    tx.commit().await?;
+    Ok(Json(row))
+}
+
+pub async fn merge_category(State(state): State<AppState>, Path((id, target_id)): Path<(i32, i32)>) -> Result<Json<CategoryMergeResult>, AppError> {
+    let mut tx = state.pool.begin().await?;
+    let result = merge_category_into(&mut tx, id, target_id).await?;
+    tx.commit().await?;
+    Ok(Json(result))
+}
+
+// A category that is still used (operations, budgets, recurring operations or
+// subcategories) can only be deleted by reassigning everything to another
+// category with ?reassign_to=<id>
+pub async fn delete_category(State(state): State<AppState>, Path(id): Path<i32>, Query(params): Query<DeleteCategoryQuery>) -> Result<(), AppError> {
+    let mut tx = state.pool.begin().await?;
+    let category = fetch_category(&mut tx, id).await?;
+
+    if category.is_system {
+        return Err(AppError::Forbidden("Cannot delete system category".to_string()));
     }
-    
-    sqlx::query("DELETE FROM categories WHERE id = $1").bind(id).execute(&state.pool).await.map_err(db_err)?;
+
+    if let Some(target_id) = params.reassign_to {
+        merge_category_into(&mut tx, id, target_id).await?;
+        tx.commit().await?;
+        return Ok(());
+    }
+
+    let (operations, budgets, recurring, subcategories): (i64, i64, i64, i64) = sqlx::query_as(
+        "SELECT
+            (SELECT COUNT(*) FROM operations WHERE category_id = $1 AND deleted_at IS NULL),
+            (SELECT COUNT(*) FROM budgets WHERE category_id = $1),
+            (SELECT COUNT(*) FROM recurring_operations WHERE category_id = $1),
+            (SELECT COUNT(*) FROM categories WHERE parent_id = $1 AND deleted_at IS NULL)"
+    )
+    .bind(id)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    if operations + budgets + recurring + subcategories > 0 {
+        return Err(AppError::Conflict(format!(
+                "Category is used by {} operations, {} budgets, {} recurring operations and {} subcategories; pass reassign_to to move them",
+                operations, budgets, recurring, subcategories
+            )));
+    }
+
+    // To the trash; it comes back at the end of its siblings
+    sqlx::query("UPDATE categories SET deleted_at = CURRENT_TIMESTAMP WHERE id = $1").bind(id).execute(&mut *tx).await?;
+    let siblings = sibling_ids(&mut tx, category.parent_id, id).await?;
+    set_sibling_order(&mut tx, &siblings).await?;
+
+    tx.commit().await?;
     Ok(())
 }
 
-pub async fn reorder_categories(State(state): State<AppState>, Json(payload): Json<ReorderCategories>) -> Result<(), (axum::http::StatusCode, String)> {
-    let mut tx = state.pool.begin().await.map_err(db_err)?;
+pub async fn reorder_categories(State(state): State<AppState>, ValidJson(payload): ValidJson<ReorderCategories>) -> Result<(), AppError> {
+    let mut tx = state.pool.begin().await?;
     
     for item in payload.items {
         sqlx::query("UPDATE categories SET sort_order = $1 WHERE id = $2")
             .bind(item.sort_order)
             .bind(item.id)
             .execute(&mut *tx)
-            .await
-            .map_err(db_err)?;
+            .await?;
     }
     
-    tx.commit().await.map_err(db_err)?;
+    tx.commit().await?;
     Ok(())
 }
 
-pub async fn toggle_category_hidden(State(state): State<AppState>, Path(id): Path<i32>) -> Result<Json<Category>, (axum::http::StatusCode, String)> {
+pub async fn toggle_category_hidden(State(state): State<AppState>, Path(id): Path<i32>) -> Result<Json<Category>, AppError> {
     // Check if category is system category
-    let is_system: bool = sqlx::query_scalar("SELECT is_system FROM categories WHERE id = $1")
+    let is_system: bool = sqlx::query_scalar("SELECT is_system FROM categories WHERE id = $1 AND deleted_at IS NULL")
         .bind(id)
         .fetch_one(&state.pool)
-        .await
-        .map_err(db_err)?;
+        .await?;
     
     if is_system {
-        return Err((axum::http::StatusCode::FORBIDDEN, "Cannot hide system category".to_string()));
+        return Err(AppError::Forbidden("Cannot hide system category".to_string()));
     }
     
     let row = sqlx::query_as::<_, Category>(
         "UPDATE categories SET is_hidden = NOT is_hidden WHERE id = $1
          RETURNING id, name, parent_id, type::text, sort_order, is_system, is_hidden"
-    ).bind(id).fetch_one(&state.pool).await.map_err(db_err)?;
+    ).bind(id).fetch_one(&state.pool).await?;
     Ok(Json(row))
 }
@@ -0,0 +1,166 @@
+// Balance corrections: an operation in the system Correction category that
+// brings an asset to a given balance on a given day, recorded with a note so
+// the history of corrections can be reviewed per asset
+use crate::{
+    AppState, balances,
+    error::AppError,
+    handlers::categories::ensure_correction_categories,
+    models::*,
+    money::AMOUNT_DIGITS,
+    validation::{ValidJson, Validate, Validator},
+};
+use axum::{
+    Json,
+    extract::{Path, Query, State},
+};
+
+const CORRECTION_COLUMNS: &str = "bc.id, o.asset_id, bc.operation_id, bc.correction_date, bc.balance_before,
+     bc.target_balance, o.amount, bc.note, bc.created_date";
+
+impl Validate for CorrectBalanceRequest {
+    fn validate(&self, v: &mut Validator) {
+        v.check(!self.note.trim().is_empty(), "note", "A note explaining the correction is required");
+        v.amount("target_balance", &self.target_balance, AMOUNT_DIGITS);
+    }
+}
+
+pub async fn correct_balance(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+    ValidJson(payload): ValidJson<CorrectBalanceRequest>,
+) -> Result<Json<Asset>, AppError> {
+    let note = payload.note.trim();
+    let target = payload.target_balance;
+
+    let mut tx = state.pool.begin().await?;
+
+    // "Today" as the database sees it, like the other CURRENT_DATE defaults
+    let today: chrono::NaiveDate = sqlx::query_scalar("SELECT CURRENT_DATE")
+        .fetch_one(&mut *tx)
+        .await?;
+    let correction_date = payload.correction_date.unwrap_or(today);
+    if correction_date > today {
+        return Err(AppError::BadRequest("A correction can't be dated in the future".to_string()));
+    }
+
+    // Corrections of the same asset are serialized on the asset row
+    let found: Option<i32> = sqlx::query_scalar(
+        "SELECT a.id FROM assets a
+         INNER JOIN asset_types at ON a.asset_type_id = at.id
+         WHERE a.id = $1 AND at.category IN ('liquid', 'liability') AND a.deleted_at IS NULL
+         FOR UPDATE OF a",
+    )
+    .bind(id)
+    .fetch_optional(&mut *tx)
+    .await?;
+    if found.is_none() {
+        return Err(AppError::NotFound("Asset not found or not a liquid or liability asset".to_string()));
+    }
+
+    // Balance from the operations at the end of the correction day
+    let balance_before = balances::balance_at(&mut tx, id, Some(correction_date))
+        .await?;
+    let difference = &target - &balance_before;
+
+    if !difference.is_zero() {
+        let (positive_id, negative_id) = ensure_correction_categories(&mut tx).await?;
+        let (operation_type, category_id) = if difference.is_positive() {
+            ("income", positive_id)
+        } else {
+            ("expense", negative_id)
+        };
+
+        let operation_id: i32 = sqlx::query_scalar(
+            "INSERT INTO operations (asset_id, amount, operation_type, operation_date, description, category_id)
+             VALUES ($1, $2, $3::operation_type, $4, $5, $6)
+             RETURNING id",
+        )
+        .bind(id)
+        .bind(&difference)
+        .bind(operation_type)
+        .bind(correction_date)
+        .bind(format!("Korekta salda: {}", note))
+        .bind(category_id)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        sqlx::query(
+            "INSERT INTO balance_corrections (operation_id, correction_date, balance_before, target_balance, note)
+             VALUES ($1, $2, $3, $4, $5)",
+        )
+        .bind(operation_id)
+        .bind(correction_date)
+        .bind(&balance_before)
+        .bind(&target)
+        .bind(note)
+        .execute(&mut *tx)
+        .await?;
+
+        balances::recalculate(&mut tx, &[id])
+            .await?;
+    }
+
+    let asset = sqlx::query_as::<_, Asset>(
+        "SELECT id, user_id, asset_type_id, name, description, account_number, quantity, average_purchase_price, current_valuation, currency, is_active, created_date, sort_order
+         FROM assets WHERE id = $1",
+    )
+    .bind(id)
+    .fetch_one(&mut *tx)
+    .await?;
+    tx.commit().await?;
+    crate::notifications::check_after_write(&state);
+
+    Ok(Json(asset))
+}
+
+// Corrections of an asset in date order with the cumulative amount
+pub async fn list_asset_corrections(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+) -> Result<Json<Vec<BalanceCorrection>>, AppError> {
+    let mut corrections = sqlx::query_as::<_, BalanceCorrection>(&format!(
+        "SELECT {CORRECTION_COLUMNS}
+         FROM balance_corrections bc
+         INNER JOIN operations o ON o.id = bc.operation_id
+         WHERE o.asset_id = $1 AND o.deleted_at IS NULL
+         ORDER BY bc.correction_date, bc.id"
+    ))
+    .bind(id)
+    .fetch_all(&state.pool)
+    .await?;
+
+    let cumulative = balances::running_balances(&Money::zero(), corrections.iter().map(|c| &c.amount));
+    for (correction, amount) in corrections.iter_mut().zip(cumulative) {
+        correction.cumulative_amount = amount;
+    }
+    Ok(Json(corrections))
+}
+
+// Corrections per asset within an optional date range
+pub async fn get_correction_report(
+    State(state): State<AppState>,
+    Query(params): Query<CorrectionReportQuery>,
+) -> Result<Json<Vec<CorrectionReportRow>>, AppError> {
+    let rows = sqlx::query_as::<_, CorrectionReportRow>(
+        "SELECT a.id AS asset_id, a.name AS asset_name, a.currency,
+                COUNT(*) AS correction_count,
+                COALESCE(SUM(o.amount) FILTER (WHERE o.amount > 0), 0) AS total_positive,
+                COALESCE(SUM(o.amount) FILTER (WHERE o.amount < 0), 0) AS total_negative,
+                SUM(o.amount) AS net_amount,
+                MAX(bc.correction_date) AS last_correction_date
+         FROM balance_corrections bc
+         INNER JOIN operations o ON o.id = bc.operation_id
+         INNER JOIN assets a ON a.id = o.asset_id
+         WHERE o.deleted_at IS NULL AND a.deleted_at IS NULL
+           AND ($1::date IS NULL OR bc.correction_date >= $1)
+           AND ($2::date IS NULL OR bc.correction_date <= $2)
+         GROUP BY a.id
+         ORDER BY a.sort_order, a.id",
+    )
+    .bind(params.date_from)
+    .bind(params.date_to)
+    .fetch_all(&state.pool)
+    .await?;
+
+    Ok(Json(rows))
+}
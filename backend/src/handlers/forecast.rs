@@ -0,0 +1,25 @@
+use crate::{AppState, error::AppError, forecast, models::*};
+use axum::{
+    Json,
+    extract::{Query, State},
+};
+
+const MAX_FORECAST_DAYS: i64 = 366;
+
+pub async fn get_forecast(
+    State(state): State<AppState>,
+    Query(params): Query<ForecastQuery>,
+) -> Result<Json<ForecastResponse>, AppError> {
+    let days = params.days.unwrap_or(90);
+    if !(1..=MAX_FORECAST_DAYS).contains(&days) {
+        return Err(AppError::BadRequest(format!("days must be between 1 and {}", MAX_FORECAST_DAYS)));
+    }
+
+    let mut conn = state.pool.acquire().await?;
+    let today: chrono::NaiveDate = sqlx::query_scalar("SELECT CURRENT_DATE")
+        .fetch_one(&mut *conn)
+        .await?;
+    let forecast = forecast::forecast(&mut conn, today, days)
+        .await?;
+    Ok(Json(forecast))
+}
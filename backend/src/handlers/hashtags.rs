@@ -1,50 +1,83 @@
-use crate::{AppState, models::*, utils::db_err};
+use crate::{
+    AppState,
+    error::AppError,
+    handlers::operations::get_operation_hashtags,
+    models::*,
+    money::BALANCE_DIGITS,
+    validation::{ValidJson, Validate, Validator},
+};
 use axum::{
     Json,
-    extract::{Path, State},
+    extract::{Path, Query, State},
 };
 
 // Validation: hashtag can only contain alphanumeric and underscore characters
-fn is_valid_hashtag(name: &str) -> bool {
+pub(crate) fn is_valid_hashtag(name: &str) -> bool {
     !name.is_empty() && name.len() <= 50 && name.chars().all(|c| c.is_alphanumeric() || c == '_')
 }
 
+const INVALID_HASHTAG: &str = "Hashtag can only contain alphanumeric characters and underscore, max 50 chars";
+
+// "#Travel " is stored as "travel"
+fn clean_hashtag(name: &str) -> String {
+    name.trim().trim_start_matches('#').to_lowercase()
+}
+
+impl Validate for CreateHashtag {
+    fn validate(&self, v: &mut Validator) {
+        v.check(is_valid_hashtag(&clean_hashtag(&self.name)), "name", INVALID_HASHTAG);
+    }
+}
+
+impl Validate for TagOperationRequest {
+    fn validate(&self, v: &mut Validator) {
+        v.check(!self.hashtags.is_empty(), "hashtags", "At least one hashtag is required");
+        for (index, name) in self.hashtags.iter().enumerate() {
+            v.check(is_valid_hashtag(&clean_hashtag(name)), &format!("hashtags[{}]", index), INVALID_HASHTAG);
+        }
+    }
+}
+
+impl Validate for ExtractHashtagsRequest {}
+
+impl Validate for MergeHashtagsRequest {}
+
+impl Validate for SetHashtagBudgetCap {
+    fn validate(&self, v: &mut Validator) {
+        v.check(
+            self.budget_cap.as_ref().is_none_or(Money::is_positive),
+            "budget_cap",
+            "Budget cap must be greater than zero",
+        );
+        v.optional_amount("budget_cap", self.budget_cap.as_ref(), BALANCE_DIGITS);
+    }
+}
+
 pub async fn get_hashtags(
     State(state): State<AppState>,
-) -> Result<Json<Vec<Hashtag>>, (axum::http::StatusCode, String)> {
+) -> Result<Json<Vec<Hashtag>>, AppError> {
     let rows = sqlx::query_as::<_, Hashtag>(
-        "SELECT id, name, created_date, usage_count FROM hashtags ORDER BY name",
+        "SELECT id, name, created_date, usage_count, budget_cap FROM hashtags ORDER BY name",
     )
     .fetch_all(&state.pool)
-    .await
-    .map_err(db_err)?;
+    .await?;
     Ok(Json(rows))
 }
 
 pub async fn create_hashtag(
     State(state): State<AppState>,
-    Json(payload): Json<CreateHashtag>,
-) -> Result<Json<Hashtag>, (axum::http::StatusCode, String)> {
-    // Validate hashtag format
-    if !is_valid_hashtag(&payload.name) {
-        return Err((
-            axum::http::StatusCode::BAD_REQUEST,
-            "Hashtag can only contain alphanumeric characters and underscore, max 50 chars"
-                .to_string(),
-        ));
-    }
-
-    let name_lower = payload.name.to_lowercase();
+    ValidJson(payload): ValidJson<CreateHashtag>,
+) -> Result<Json<Hashtag>, AppError> {
+    let name_lower = clean_hashtag(&payload.name);
 
     let hashtag = sqlx::query_as::<_, Hashtag>(
         "INSERT INTO hashtags (name, usage_count) VALUES ($1, 0)
          ON CONFLICT (name) DO UPDATE SET name = EXCLUDED.name
-         RETURNING id, name, created_date, usage_count",
+         RETURNING id, name, created_date, usage_count, budget_cap",
     )
     .bind(&name_lower)
     .fetch_one(&state.pool)
-    .await
-    .map_err(db_err)?;
+    .await?;
 
     Ok(Json(hashtag))
 }
@@ -52,47 +85,406 @@ pub async fn create_hashtag(
 pub async fn delete_hashtag(
     State(state): State<AppState>,
     Path(id): Path<i32>,
-) -> Result<(), (axum::http::StatusCode, String)> {
+) -> Result<(), AppError> {
     // Check if hashtag is used in any operations (usage_count > 0)
     let hashtag = sqlx::query_as::<_, Hashtag>(
-        "SELECT id, name, created_date, usage_count FROM hashtags WHERE id = $1",
+        "SELECT id, name, created_date, usage_count, budget_cap FROM hashtags WHERE id = $1",
     )
     .bind(id)
     .fetch_optional(&state.pool)
-    .await
-    .map_err(db_err)?;
+    .await?;
 
     match hashtag {
-        Some(h) if h.usage_count > 0 => Err((
-            axum::http::StatusCode::CONFLICT,
+        Some(h) if h.usage_count > 0 => Err(AppError::Conflict(
             "Cannot delete hashtag that is used in operations".to_string(),
         )),
         Some(_) => {
             sqlx::query("DELETE FROM hashtags WHERE id = $1")
                 .bind(id)
                 .execute(&state.pool)
-                .await
-                .map_err(db_err)?;
+                .await?;
             Ok(())
         }
-        None => Err((
-            axum::http::StatusCode::NOT_FOUND,
-            "Hashtag not found".to_string(),
-        )),
+        None => Err(AppError::NotFound("Hashtag not found".to_string())),
     }
 }
 
 pub async fn extract_hashtags_from_text(
     State(_state): State<AppState>,
-    Json(payload): Json<serde_json::Value>,
-) -> Result<Json<Vec<String>>, (axum::http::StatusCode, String)> {
-    let text = payload.get("text").and_then(|v| v.as_str()).ok_or((
-        axum::http::StatusCode::BAD_REQUEST,
-        "Missing 'text' field".to_string(),
-    ))?;
-
+    ValidJson(payload): ValidJson<ExtractHashtagsRequest>,
+) -> Result<Json<Vec<String>>, AppError> {
     // Extract hashtags from text (words starting with #)
-    let hashtags = crate::handlers::operations::extract_hashtags(text);
+    let hashtags = crate::handlers::operations::extract_hashtags(&payload.text);
+
+    Ok(Json(hashtags))
+}
+
+fn normalize_hashtag(name: &str) -> Result<String, AppError> {
+    let name = clean_hashtag(name);
+    if !is_valid_hashtag(&name) {
+        return Err(AppError::BadRequest(INVALID_HASHTAG.to_string()));
+    }
+    Ok(name)
+}
+
+async fn fetch_hashtag(
+    conn: &mut sqlx::PgConnection,
+    id: i32,
+) -> Result<Hashtag, AppError> {
+    sqlx::query_as::<_, Hashtag>(
+        "SELECT id, name, created_date, usage_count, budget_cap FROM hashtags WHERE id = $1 FOR UPDATE",
+    )
+    .bind(id)
+    .fetch_optional(conn)
+    .await?
+    .ok_or(AppError::NotFound("Hashtag not found".to_string()))
+}
+
+// Replace "#from" with "#to" in descriptions of operations linked to the
+// hashtag, so the text keeps matching the links. Names only contain
+// letters, digits and underscores, so they are safe inside the pattern.
+async fn rewrite_descriptions(
+    conn: &mut sqlx::PgConnection,
+    hashtag_id: i32,
+    from: &str,
+    to: &str,
+) -> Result<(), AppError> {
+    sqlx::query(
+        "UPDATE operations o
+         SET description = regexp_replace(o.description, '(^|\\s)#+' || $1 || '(?![[:alnum:]_])', '\\1#' || $2, 'gi')
+         FROM operation_hashtags oh
+         WHERE oh.operation_id = o.id
+           AND oh.hashtag_id = $3
+           AND o.description ~* ('(^|\\s)#+' || $1 || '(?![[:alnum:]_])')",
+    )
+    .bind(from)
+    .bind(to)
+    .bind(hashtag_id)
+    .execute(conn)
+    .await?;
+    Ok(())
+}
 
+async fn ensure_operation_exists(
+    state: &AppState,
+    operation_id: i32,
+) -> Result<(), AppError> {
+    let exists: bool = sqlx::query_scalar("SELECT EXISTS(SELECT 1 FROM operations WHERE id = $1 AND deleted_at IS NULL)")
+        .bind(operation_id)
+        .fetch_one(&state.pool)
+        .await?;
+    if !exists {
+        return Err(AppError::NotFound("Operation not found".to_string()));
+    }
+    Ok(())
+}
+
+pub async fn get_operation_hashtag_links(
+    State(state): State<AppState>,
+    Path(operation_id): Path<i32>,
+) -> Result<Json<Vec<Hashtag>>, AppError> {
+    ensure_operation_exists(&state, operation_id).await?;
+    let hashtags = get_operation_hashtags(&state.pool, operation_id)
+        .await?;
     Ok(Json(hashtags))
 }
+
+// Link hashtags to an operation without touching its description; missing
+// hashtags are created
+pub async fn tag_operation(
+    State(state): State<AppState>,
+    Path(operation_id): Path<i32>,
+    ValidJson(payload): ValidJson<TagOperationRequest>,
+) -> Result<Json<Vec<Hashtag>>, AppError> {
+    let names = payload
+        .hashtags
+        .iter()
+        .map(|name| normalize_hashtag(name))
+        .collect::<Result<Vec<_>, _>>()?;
+    ensure_operation_exists(&state, operation_id).await?;
+
+    let mut tx = state.pool.begin().await?;
+    sqlx::query("SELECT link_operation_hashtags($1, $2)")
+        .bind(operation_id)
+        .bind(&names)
+        .execute(&mut *tx)
+        .await?;
+    let hashtags = get_operation_hashtags(&mut *tx, operation_id)
+        .await?;
+    tx.commit().await?;
+
+    Ok(Json(hashtags))
+}
+
+// Remove a link; the description is left as it is
+pub async fn untag_operation(
+    State(state): State<AppState>,
+    Path((operation_id, hashtag_id)): Path<(i32, i32)>,
+) -> Result<(), AppError> {
+    let result = sqlx::query("DELETE FROM operation_hashtags WHERE operation_id = $1 AND hashtag_id = $2")
+        .bind(operation_id)
+        .bind(hashtag_id)
+        .execute(&state.pool)
+        .await?;
+    if result.rows_affected() == 0 {
+        return Err(AppError::NotFound("Operation is not tagged with this hashtag".to_string()));
+    }
+    Ok(())
+}
+
+// Rename a hashtag; links are kept and descriptions of linked operations are
+// rewritten to the new name
+pub async fn rename_hashtag(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+    ValidJson(payload): ValidJson<CreateHashtag>,
+) -> Result<Json<Hashtag>, AppError> {
+    let name = normalize_hashtag(&payload.name)?;
+
+    let mut tx = state.pool.begin().await?;
+    let current = fetch_hashtag(&mut tx, id).await?;
+
+    let hashtag = sqlx::query_as::<_, Hashtag>(
+        "UPDATE hashtags SET name = $1 WHERE id = $2
+         RETURNING id, name, created_date, usage_count, budget_cap",
+    )
+    .bind(&name)
+    .bind(id)
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|e| match &e {
+        sqlx::Error::Database(db) if db.is_unique_violation() => AppError::Conflict(
+            "Hashtag with this name already exists; merge them instead".to_string(),
+        ),
+        _ => AppError::from(e),
+    })?;
+
+    rewrite_descriptions(&mut tx, id, &current.name, &name).await?;
+
+    tx.commit().await?;
+    Ok(Json(hashtag))
+}
+
+// Merge source hashtags into the target: their operations are linked to the
+// target, descriptions are rewritten and the sources are deleted
+pub async fn merge_hashtags(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+    ValidJson(payload): ValidJson<MergeHashtagsRequest>,
+) -> Result<Json<Hashtag>, AppError> {
+    let source_ids: Vec<i32> = payload.source_ids.into_iter().filter(|s| *s != id).collect();
+    if source_ids.is_empty() {
+        return Err(AppError::BadRequest(
+            "At least one source hashtag other than the target is required".to_string(),
+        ));
+    }
+
+    let mut tx = state.pool.begin().await?;
+    let target = fetch_hashtag(&mut tx, id).await?;
+
+    for source_id in &source_ids {
+        let source = fetch_hashtag(&mut tx, *source_id).await?;
+        rewrite_descriptions(&mut tx, source.id, &source.name, &target.name).await?;
+    }
+
+    sqlx::query(
+        "INSERT INTO operation_hashtags (operation_id, hashtag_id)
+         SELECT operation_id, $1 FROM operation_hashtags WHERE hashtag_id = ANY($2)
+         ON CONFLICT DO NOTHING",
+    )
+    .bind(id)
+    .bind(&source_ids)
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query("DELETE FROM operation_hashtags WHERE hashtag_id = ANY($1)")
+        .bind(&source_ids)
+        .execute(&mut *tx)
+        .await?;
+
+    sqlx::query("DELETE FROM hashtags WHERE id = ANY($1)")
+        .bind(&source_ids)
+        .execute(&mut *tx)
+        .await?;
+
+    let hashtag = fetch_hashtag(&mut tx, id).await?;
+    tx.commit().await?;
+    Ok(Json(hashtag))
+}
+
+pub async fn set_hashtag_budget_cap(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+    ValidJson(payload): ValidJson<SetHashtagBudgetCap>,
+) -> Result<Json<Hashtag>, AppError> {
+    let hashtag = sqlx::query_as::<_, Hashtag>(
+        "UPDATE hashtags SET budget_cap = $1 WHERE id = $2
+         RETURNING id, name, created_date, usage_count, budget_cap",
+    )
+    .bind(&payload.budget_cap)
+    .bind(id)
+    .fetch_optional(&state.pool)
+    .await?
+    .ok_or(AppError::NotFound("Hashtag not found".to_string()))?;
+    Ok(Json(hashtag))
+}
+
+// Operations counted for the hashtags in $1 within the optional date range
+// $2..$3: tagged operations outside the trash, and the children of tagged
+// split operations in place of their parent. Transfers only move money between assets and are
+// left out.
+const TAGGED_OPERATIONS: &str = "
+    tagged AS (
+        SELECT oh.hashtag_id, o.id, o.category_id, o.asset_id, o.amount, o.operation_date, o.linked_operation_id
+        FROM operation_hashtags oh
+        INNER JOIN operations o ON o.id = oh.operation_id
+        WHERE oh.hashtag_id = ANY($1) AND NOT o.is_split AND o.deleted_at IS NULL
+        UNION
+        SELECT oh.hashtag_id, c.id, c.category_id, c.asset_id, c.amount, c.operation_date, c.linked_operation_id
+        FROM operation_hashtags oh
+        INNER JOIN operations c ON c.parent_operation_id = oh.operation_id
+        WHERE oh.hashtag_id = ANY($1) AND c.deleted_at IS NULL
+    ),
+    scoped AS (
+        SELECT * FROM tagged
+        WHERE linked_operation_id IS NULL
+          AND ($2::date IS NULL OR operation_date >= $2)
+          AND ($3::date IS NULL OR operation_date <= $3)
+    )";
+
+const AMOUNT_TOTALS: &str = "
+    COUNT(t.id) as operation_count,
+    COALESCE(SUM(t.amount) FILTER (WHERE t.amount > 0), 0) as income,
+    COALESCE(SUM(t.amount) FILTER (WHERE t.amount < 0), 0) as expense,
+    COALESCE(SUM(t.amount), 0) as net";
+
+async fn hashtag_summaries(
+    pool: &sqlx::PgPool,
+    ids: &[i32],
+    date_from: Option<chrono::NaiveDate>,
+    date_to: Option<chrono::NaiveDate>,
+) -> Result<Vec<HashtagSummary>, sqlx::Error> {
+    let mut rows = sqlx::query_as::<_, HashtagSummary>(&format!(
+        "WITH {TAGGED_OPERATIONS}
+         SELECT h.id as hashtag_id, h.name, {AMOUNT_TOTALS},
+                MIN(t.operation_date) as first_date,
+                MAX(t.operation_date) as last_date,
+                h.budget_cap
+         FROM hashtags h
+         LEFT JOIN scoped t ON t.hashtag_id = h.id
+         WHERE h.id = ANY($1)
+         GROUP BY h.id, h.name, h.budget_cap
+         ORDER BY h.name"
+    ))
+    .bind(ids)
+    .bind(date_from)
+    .bind(date_to)
+    .fetch_all(pool)
+    .await?;
+
+    // Expenses are negative, so what is left of the cap is cap + expense
+    for row in &mut rows {
+        if let Some(cap) = &row.budget_cap {
+            let remaining = cap + &row.expense;
+            row.over_budget = remaining.is_negative();
+            row.budget_remaining = Some(remaining);
+        }
+    }
+    Ok(rows)
+}
+
+// Totals for one hashtag with breakdowns by category and asset and a daily
+// or weekly timeline
+pub async fn get_hashtag_report(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+    Query(params): Query<HashtagReportQuery>,
+) -> Result<Json<HashtagReport>, AppError> {
+    let interval = params.interval.as_deref().unwrap_or("day");
+    if !matches!(interval, "day" | "week") {
+        return Err(AppError::BadRequest("interval must be 'day' or 'week'".to_string()));
+    }
+
+    let summary = hashtag_summaries(&state.pool, &[id], params.date_from, params.date_to)
+        .await?
+        .pop()
+        .ok_or(AppError::NotFound("Hashtag not found".to_string()))?;
+
+    let by_category = sqlx::query_as::<_, HashtagBreakdownRow>(&format!(
+        "WITH {TAGGED_OPERATIONS}
+         SELECT t.category_id as id, c.name, {AMOUNT_TOTALS}
+         FROM scoped t
+         LEFT JOIN categories c ON t.category_id = c.id
+         GROUP BY t.category_id, c.name
+         ORDER BY expense ASC, c.name"
+    ))
+    .bind([id])
+    .bind(params.date_from)
+    .bind(params.date_to)
+    .fetch_all(&state.pool)
+    .await?;
+
+    let by_asset = sqlx::query_as::<_, HashtagBreakdownRow>(&format!(
+        "WITH {TAGGED_OPERATIONS}
+         SELECT t.asset_id as id, a.name::text as name, {AMOUNT_TOTALS}
+         FROM scoped t
+         LEFT JOIN assets a ON t.asset_id = a.id
+         GROUP BY t.asset_id, a.name
+         ORDER BY expense ASC, a.name"
+    ))
+    .bind([id])
+    .bind(params.date_from)
+    .bind(params.date_to)
+    .fetch_all(&state.pool)
+    .await?;
+
+    let timeline = sqlx::query_as::<_, HashtagTimelinePoint>(&format!(
+        "WITH {TAGGED_OPERATIONS}
+         SELECT date_trunc($4, t.operation_date)::date as period_start,
+                COALESCE(SUM(t.amount) FILTER (WHERE t.amount > 0), 0) as income,
+                COALESCE(SUM(t.amount) FILTER (WHERE t.amount < 0), 0) as expense,
+                COALESCE(SUM(t.amount), 0) as net,
+                SUM(COALESCE(SUM(t.amount) FILTER (WHERE t.amount < 0), 0))
+                    OVER (ORDER BY date_trunc($4, t.operation_date)) as cumulative_expense
+         FROM scoped t
+         GROUP BY date_trunc($4, t.operation_date)
+         ORDER BY period_start"
+    ))
+    .bind([id])
+    .bind(params.date_from)
+    .bind(params.date_to)
+    .bind(interval)
+    .fetch_all(&state.pool)
+    .await?;
+
+    Ok(Json(HashtagReport {
+        summary,
+        by_category,
+        by_asset,
+        timeline,
+    }))
+}
+
+// Side-by-side totals for several hashtags: GET /hashtags/compare?ids=1,2,3
+pub async fn compare_hashtags(
+    State(state): State<AppState>,
+    Query(params): Query<HashtagCompareQuery>,
+) -> Result<Json<Vec<HashtagSummary>>, AppError> {
+    let ids = params
+        .ids
+        .split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(|s| s.parse::<i32>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| {
+            AppError::BadRequest("ids must be a comma-separated list of hashtag ids".to_string())
+        })?;
+    if ids.is_empty() {
+        return Err(AppError::BadRequest("At least one hashtag id is required".to_string()));
+    }
+
+    let rows = hashtag_summaries(&state.pool, &ids, params.date_from, params.date_to)
+        .await?;
+    Ok(Json(rows))
+}
@@ -0,0 +1,67 @@
+use crate::{AppState, db, models::*};
+use axum::{Json, extract::State, http::StatusCode};
+use std::time::{Duration, Instant};
+
+// Longest wait for a connection before the database counts as unreachable
+const PING_TIMEOUT: Duration = Duration::from_secs(5);
+
+async fn report(state: &AppState) -> HealthReport {
+    let started = Instant::now();
+    let ping = tokio::time::timeout(PING_TIMEOUT, sqlx::query("SELECT 1").execute(&state.pool)).await;
+    let database = match ping {
+        Ok(Ok(_)) => DatabaseHealth {
+            connected: true,
+            latency_ms: Some(started.elapsed().as_millis() as i64),
+            error: None,
+        },
+        Ok(Err(e)) => DatabaseHealth { connected: false, latency_ms: None, error: Some(e.to_string()) },
+        Err(_) => DatabaseHealth {
+            connected: false,
+            latency_ms: None,
+            error: Some(format!("No answer within {} seconds", PING_TIMEOUT.as_secs())),
+        },
+    };
+
+    let (migrations, missing_system_data) = if database.connected {
+        let migrations = db::migration_status(&state.pool).await.ok();
+        let missing = db::missing_system_data(&state.pool)
+            .await
+            .unwrap_or_else(|e| vec![format!("system data could not be checked: {}", e)]);
+        (migrations, missing)
+    } else {
+        (None, Vec::new())
+    };
+
+    HealthReport {
+        status: "ok".to_string(),
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        database,
+        migrations,
+        missing_system_data,
+        pool: PoolStats {
+            size: state.pool.size(),
+            idle: state.pool.num_idle(),
+            max_connections: state.pool.options().get_max_connections(),
+        },
+    }
+}
+
+// Liveness: answers while the process runs, with the database state for information
+pub async fn health(State(state): State<AppState>) -> Json<HealthReport> {
+    Json(report(&state).await)
+}
+
+// Readiness: 503 until the database is reachable, migrated and has its system data
+pub async fn ready(State(state): State<AppState>) -> (StatusCode, Json<HealthReport>) {
+    let mut report = report(&state).await;
+    let migrated = report
+        .migrations
+        .as_ref()
+        .is_some_and(|m| m.pending.is_empty() && m.failed.is_empty());
+    if report.database.connected && migrated && report.missing_system_data.is_empty() {
+        (StatusCode::OK, Json(report))
+    } else {
+        report.status = "unavailable".to_string();
+        (StatusCode::SERVICE_UNAVAILABLE, Json(report))
+    }
+}
@@ -4,10 +4,31 @@ use axum::{
     Json,
 };
 use serde::Deserialize;
+use utoipa::IntoParams;
 
-use crate::{AppState, models::{ImportTemplate, CreateImportTemplate, UpdateImportTemplate}};
+use crate::{
+    AppState,
+    error::AppError,
+    models::{ImportTemplate, CreateImportTemplate, UpdateImportTemplate},
+    validation::{ValidJson, Validate, Validator},
+};
+
+impl Validate for CreateImportTemplate {
+    fn validate(&self, v: &mut Validator) {
+        v.required("name", &self.name, 100);
+    }
+}
 
-#[derive(Deserialize)]
+impl Validate for UpdateImportTemplate {
+    fn validate(&self, v: &mut Validator) {
+        if let Some(name) = &self.name {
+            v.required("name", name, 100);
+        }
+    }
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct ImportTemplateQuery {
     pub user_id: Option<i32>,
 }
@@ -15,9 +36,9 @@ pub struct ImportTemplateQuery {
 pub async fn create_import_template(
     State(state): State<AppState>,
     Query(params): Query<ImportTemplateQuery>,
-    Json(template): Json<CreateImportTemplate>,
-) -> Result<(StatusCode, Json<ImportTemplate>), StatusCode> {
-    let user_id = params.user_id.ok_or(StatusCode::BAD_REQUEST)?;
+    ValidJson(template): ValidJson<CreateImportTemplate>,
+) -> Result<(StatusCode, Json<ImportTemplate>), AppError> {
+    let user_id = params.user_id.ok_or_else(|| AppError::field("user_id", "user_id is required"))?;
 
     let result = sqlx::query_as::<_, ImportTemplate>(
         r#"
@@ -30,11 +51,7 @@ pub async fn create_import_template(
     .bind(&template.name)
     .bind(&template.template_data)
     .fetch_one(&state.pool)
-    .await
-    .map_err(|e| {
-        eprintln!("Error creating import template: {:?}", e);
-        StatusCode::INTERNAL_SERVER_ERROR
-    })?;
+    .await?;
 
     Ok((StatusCode::CREATED, Json(result)))
 }
@@ -42,8 +59,8 @@ pub async fn create_import_template(
 pub async fn list_import_templates(
     State(state): State<AppState>,
     Query(params): Query<ImportTemplateQuery>,
-) -> Result<Json<Vec<ImportTemplate>>, StatusCode> {
-    let user_id = params.user_id.ok_or(StatusCode::BAD_REQUEST)?;
+) -> Result<Json<Vec<ImportTemplate>>, AppError> {
+    let user_id = params.user_id.ok_or_else(|| AppError::field("user_id", "user_id is required"))?;
 
     let templates = sqlx::query_as::<_, ImportTemplate>(
         r#"
@@ -55,8 +72,7 @@ pub async fn list_import_templates(
     )
     .bind(user_id)
     .fetch_all(&state.pool)
-    .await
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    .await?;
 
     Ok(Json(templates))
 }
@@ -64,7 +80,7 @@ pub async fn list_import_templates(
 pub async fn get_import_template(
     State(state): State<AppState>,
     Path(id): Path<i32>,
-) -> Result<Json<ImportTemplate>, StatusCode> {
+) -> Result<Json<ImportTemplate>, AppError> {
     let template = sqlx::query_as::<_, ImportTemplate>(
         r#"
         SELECT id, user_id, name, template_data, created_at, updated_at
@@ -73,9 +89,9 @@ pub async fn get_import_template(
         "#
     )
     .bind(id)
-    .fetch_one(&state.pool)
-    .await
-    .map_err(|_| StatusCode::NOT_FOUND)?;
+    .fetch_optional(&state.pool)
+    .await?
+    .ok_or_else(|| AppError::NotFound("Import template not found".to_string()))?;
 
     Ok(Json(template))
 }
@@ -83,8 +99,8 @@ pub async fn get_import_template(
 pub async fn update_import_template(
     State(state): State<AppState>,
     Path(id): Path<i32>,
-    Json(update): Json<UpdateImportTemplate>,
-) -> Result<Json<ImportTemplate>, StatusCode> {
+    ValidJson(update): ValidJson<UpdateImportTemplate>,
+) -> Result<Json<ImportTemplate>, AppError> {
     // Build dynamic update query
     let mut query = String::from("UPDATE import_templates SET updated_at = CURRENT_TIMESTAMP");
     let mut params_count = 1;
@@ -110,9 +126,9 @@ pub async fn update_import_template(
     }
 
     let template = qry
-        .fetch_one(&state.pool)
-        .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        .fetch_optional(&state.pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Import template not found".to_string()))?;
 
     Ok(Json(template))
 }
@@ -120,12 +136,11 @@ pub async fn update_import_template(
 pub async fn delete_import_template(
     State(state): State<AppState>,
     Path(id): Path<i32>,
-) -> Result<StatusCode, StatusCode> {
+) -> Result<StatusCode, AppError> {
     sqlx::query("DELETE FROM import_templates WHERE id = $1")
         .bind(id)
         .execute(&state.pool)
-        .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        .await?;
 
     Ok(StatusCode::NO_CONTENT)
 }
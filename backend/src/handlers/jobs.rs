@@ -0,0 +1,71 @@
+use crate::{
+    AppState,
+    error::AppError,
+    jobs::{self, JOB_RUN_COLUMNS, JobError},
+    models::*,
+};
+use axum::{
+    Json,
+    extract::{Path, Query, State},
+};
+
+fn job_err(e: JobError) -> AppError {
+    match e {
+        JobError::UnknownJob(_) => AppError::NotFound(e.to_string()),
+        JobError::AlreadyRunning(_) => AppError::Conflict(e.to_string()),
+        JobError::Database(e) => AppError::from(e),
+    }
+}
+
+// Registered jobs with their schedule, next due time and latest run
+pub async fn list_jobs(State(state): State<AppState>) -> Result<Json<Vec<JobInfo>>, AppError> {
+    let now = chrono::Local::now().naive_local();
+    let mut result = Vec::new();
+    for job in jobs::registry() {
+        let last_run = sqlx::query_as::<_, JobRun>(&format!(
+            "SELECT {JOB_RUN_COLUMNS} FROM jobs WHERE name = $1 ORDER BY started_at DESC, id DESC LIMIT 1"
+        ))
+        .bind(job.name)
+        .fetch_optional(&state.pool)
+        .await?;
+
+        result.push(JobInfo {
+            name: job.name.to_string(),
+            schedule: job.schedule.to_string(),
+            description: job.description.to_string(),
+            next_run: jobs::next_run(&job, now),
+            last_run,
+        });
+    }
+    Ok(Json(result))
+}
+
+// Run history of a job, newest first
+pub async fn list_job_runs(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+    Query(params): Query<JobRunsQuery>,
+) -> Result<Json<Vec<JobRun>>, AppError> {
+    if !jobs::registry().iter().any(|job| job.name == name) {
+        return Err(job_err(JobError::UnknownJob(name)));
+    }
+    let limit = params.limit.unwrap_or(50).clamp(1, 500);
+    let runs = sqlx::query_as::<_, JobRun>(&format!(
+        "SELECT {JOB_RUN_COLUMNS} FROM jobs WHERE name = $1 ORDER BY started_at DESC, id DESC LIMIT $2"
+    ))
+    .bind(&name)
+    .bind(limit)
+    .fetch_all(&state.pool)
+    .await?;
+    Ok(Json(runs))
+}
+
+// Run a job right away and wait for it to finish. A failed job still returns
+// its run (with status "failed"); 409 means another run holds the job's lock.
+pub async fn run_job_now(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+) -> Result<Json<JobRun>, AppError> {
+    let run = jobs::run_job(&state, &name, "manual").await.map_err(job_err)?;
+    Ok(Json(run))
+}
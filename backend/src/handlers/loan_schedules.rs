@@ -0,0 +1,103 @@
+use crate::{AppState, error::AppError, models::*, money::AMOUNT_DIGITS, validation::{ValidJson, Validate, Validator}};
+use axum::{
+    Json,
+    extract::{Path, State},
+};
+
+const LOAN_SCHEDULE_COLUMNS: &str =
+    "id, liability_asset_id, payment_asset_id, installment_amount, day_of_month, start_date, end_date, created_date";
+
+impl Validate for UpsertLoanSchedule {
+    fn validate(&self, v: &mut Validator) {
+        v.check(self.installment_amount.is_positive(), "installment_amount", "installment_amount must be positive");
+        v.amount("installment_amount", &self.installment_amount, AMOUNT_DIGITS);
+        v.check((1..=31).contains(&self.day_of_month), "day_of_month", "day_of_month must be between 1 and 31");
+        v.check(
+            self.end_date.is_none_or(|end| end >= self.start_date),
+            "end_date",
+            "end_date can't be before start_date",
+        );
+    }
+}
+
+async fn asset_category(
+    conn: &mut sqlx::PgConnection,
+    asset_id: i32,
+) -> Result<Option<AssetCategory>, AppError> {
+    sqlx::query_scalar(
+        "SELECT at.category FROM assets a
+         INNER JOIN asset_types at ON a.asset_type_id = at.id
+         WHERE a.id = $1 AND a.deleted_at IS NULL",
+    )
+    .bind(asset_id)
+    .fetch_optional(conn)
+    .await
+    .map_err(AppError::from)
+}
+
+pub async fn get_loan_schedule(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+) -> Result<Json<LoanSchedule>, AppError> {
+    sqlx::query_as::<_, LoanSchedule>(&format!(
+        "SELECT {LOAN_SCHEDULE_COLUMNS} FROM loan_schedules WHERE liability_asset_id = $1"
+    ))
+    .bind(id)
+    .fetch_optional(&state.pool)
+    .await?
+    .map(Json)
+    .ok_or(AppError::NotFound("Loan schedule not found".to_string()))
+}
+
+// Create or replace the repayment schedule of a liability
+pub async fn upsert_loan_schedule(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+    ValidJson(payload): ValidJson<UpsertLoanSchedule>,
+) -> Result<Json<LoanSchedule>, AppError> {
+    let bad_request = |message: &str| AppError::BadRequest(message.to_string());
+    let mut conn = state.pool.acquire().await?;
+    match asset_category(&mut conn, id).await? {
+        Some(AssetCategory::Liability) => {}
+        Some(_) => return Err(bad_request("Only liabilities have a loan schedule")),
+        None => {
+            return Err(AppError::NotFound("Asset not found".to_string()));
+        }
+    }
+    if asset_category(&mut conn, payload.payment_asset_id).await? != Some(AssetCategory::Liquid) {
+        return Err(bad_request("Installments must be paid from a liquid asset"));
+    }
+
+    let schedule = sqlx::query_as::<_, LoanSchedule>(&format!(
+        "INSERT INTO loan_schedules (liability_asset_id, payment_asset_id, installment_amount, day_of_month, start_date, end_date)
+         VALUES ($1, $2, $3, $4, $5, $6)
+         ON CONFLICT (liability_asset_id) DO UPDATE
+         SET payment_asset_id = EXCLUDED.payment_asset_id,
+             installment_amount = EXCLUDED.installment_amount,
+             day_of_month = EXCLUDED.day_of_month,
+             start_date = EXCLUDED.start_date,
+             end_date = EXCLUDED.end_date
+         RETURNING {LOAN_SCHEDULE_COLUMNS}"
+    ))
+    .bind(id)
+    .bind(payload.payment_asset_id)
+    .bind(&payload.installment_amount)
+    .bind(payload.day_of_month)
+    .bind(payload.start_date)
+    .bind(payload.end_date)
+    .fetch_one(&mut *conn)
+    .await?;
+
+    Ok(Json(schedule))
+}
+
+pub async fn delete_loan_schedule(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+) -> Result<(), AppError> {
+    sqlx::query("DELETE FROM loan_schedules WHERE liability_asset_id = $1")
+        .bind(id)
+        .execute(&state.pool)
+        .await?;
+    Ok(())
+}
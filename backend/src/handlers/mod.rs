@@ -1,25 +1,53 @@
 // Handlers modules
 pub mod accounts_compat;
 pub mod assets;
+pub mod attachments;
+pub mod audit;
+pub mod balances;
 pub mod budgets;
+pub mod bulk_operations;
 pub mod categories;
+pub mod corrections;
+pub mod forecast;
 pub mod goals;
 pub mod hashtags;
+pub mod health;
 pub mod import_templates;
+pub mod jobs;
+pub mod loan_schedules;
+pub mod notifications;
+pub mod operation_rules;
 pub mod operations;
+pub mod payees;
+pub mod reconciliations;
 pub mod recurring_operations;
 pub mod transfers;
+pub mod trash;
 pub mod users;
 
 // Re-export handlers for easier access
 pub use accounts_compat::*;
 pub use assets::*;
+pub use attachments::*;
+pub use audit::*;
+pub use balances::*;
 pub use budgets::*;
+pub use bulk_operations::*;
 pub use categories::*;
+pub use corrections::*;
+pub use forecast::*;
 pub use goals::*;
 pub use hashtags::*;
+pub use health::*;
 pub use import_templates::*;
+pub use jobs::*;
+pub use loan_schedules::*;
+pub use notifications::*;
+pub use operation_rules::*;
 pub use operations::*;
+pub use payees::*;
+pub use reconciliations::*;
 pub use recurring_operations::*;
 pub use transfers::*;
+pub use trash::*;
 pub use users::*;
@@ -0,0 +1,235 @@
+use crate::{
+    AppState,
+    error::AppError,
+    models::*,
+    money::BALANCE_DIGITS,
+    notifications::{self, NOTIFICATION_COLUMNS, RULE_COLUMNS},
+    validation::{ValidJson, Validate, Validator},
+};
+use axum::{
+    Json,
+    extract::{Path, Query, State},
+};
+
+impl Validate for CreateNotificationRule {
+    fn validate(&self, v: &mut Validator) {
+        v.required("name", &self.name, 255);
+        let needs_threshold = matches!(
+            self.rule_type,
+            NotificationRuleType::BudgetThreshold | NotificationRuleType::LargeOperation | NotificationRuleType::LowBalance
+        );
+        match &self.threshold {
+            None if needs_threshold => v.error("threshold", format!("A {} rule needs a threshold", self.rule_type)),
+            Some(t) if self.rule_type != NotificationRuleType::LowBalance && !t.is_positive() => {
+                v.error("threshold", "threshold must be positive");
+            }
+            _ => {}
+        }
+        v.optional_amount("threshold", self.threshold.as_ref(), BALANCE_DIGITS);
+        v.check(
+            self.days_ahead.is_none_or(|d| (0..=366).contains(&d)),
+            "days_ahead",
+            "days_ahead must be between 0 and 366",
+        );
+        if let Some(url) = &self.webhook_url {
+            v.check(
+                url.starts_with("http://") || url.starts_with("https://"),
+                "webhook_url",
+                "webhook_url must be an http(s) URL",
+            );
+        }
+        if let Some(email) = &self.email_to {
+            v.check(
+                email.parse::<lettre::Address>().is_ok(),
+                "email_to",
+                "email_to is not a valid email address",
+            );
+            v.max_length("email_to", email, 255);
+        }
+    }
+}
+
+pub async fn create_notification_rule(
+    State(state): State<AppState>,
+    ValidJson(payload): ValidJson<CreateNotificationRule>,
+) -> Result<Json<NotificationRule>, AppError> {
+    let rule = sqlx::query_as::<_, NotificationRule>(&format!(
+        "INSERT INTO notification_rules (name, rule_type, category_id, asset_id, threshold, days_ahead, webhook_url, email_to, is_active)
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8, COALESCE($9, TRUE))
+         RETURNING {RULE_COLUMNS}"
+    ))
+    .bind(payload.name.trim())
+    .bind(payload.rule_type.as_str())
+    .bind(payload.category_id)
+    .bind(payload.asset_id)
+    .bind(&payload.threshold)
+    .bind(payload.days_ahead)
+    .bind(&payload.webhook_url)
+    .bind(&payload.email_to)
+    .bind(payload.is_active)
+    .fetch_one(&state.pool)
+    .await?;
+
+    notifications::check_after_write(&state);
+    Ok(Json(rule))
+}
+
+pub async fn list_notification_rules(
+    State(state): State<AppState>,
+) -> Result<Json<Vec<NotificationRule>>, AppError> {
+    let rules = sqlx::query_as::<_, NotificationRule>(&format!(
+        "SELECT {RULE_COLUMNS} FROM notification_rules ORDER BY id"
+    ))
+    .fetch_all(&state.pool)
+    .await?;
+    Ok(Json(rules))
+}
+
+pub async fn get_notification_rule(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+) -> Result<Json<NotificationRule>, AppError> {
+    sqlx::query_as::<_, NotificationRule>(&format!(
+        "SELECT {RULE_COLUMNS} FROM notification_rules WHERE id = $1"
+    ))
+    .bind(id)
+    .fetch_optional(&state.pool)
+    .await?
+    .map(Json)
+    .ok_or(AppError::NotFound("Notification rule not found".to_string()))
+}
+
+pub async fn update_notification_rule(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+    ValidJson(payload): ValidJson<CreateNotificationRule>,
+) -> Result<Json<NotificationRule>, AppError> {
+    let rule = sqlx::query_as::<_, NotificationRule>(&format!(
+        "UPDATE notification_rules
+         SET name = $1, rule_type = $2, category_id = $3, asset_id = $4, threshold = $5, days_ahead = $6,
+             webhook_url = $7, email_to = $8, is_active = COALESCE($9, is_active)
+         WHERE id = $10
+         RETURNING {RULE_COLUMNS}"
+    ))
+    .bind(payload.name.trim())
+    .bind(payload.rule_type.as_str())
+    .bind(payload.category_id)
+    .bind(payload.asset_id)
+    .bind(&payload.threshold)
+    .bind(payload.days_ahead)
+    .bind(&payload.webhook_url)
+    .bind(&payload.email_to)
+    .bind(payload.is_active)
+    .bind(id)
+    .fetch_optional(&state.pool)
+    .await?
+    .ok_or(AppError::NotFound("Notification rule not found".to_string()))?;
+
+    notifications::check_after_write(&state);
+    Ok(Json(rule))
+}
+
+// Deleting a rule deletes its notifications
+pub async fn delete_notification_rule(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+) -> Result<(), AppError> {
+    sqlx::query("DELETE FROM notification_rules WHERE id = $1")
+        .bind(id)
+        .execute(&state.pool)
+        .await?;
+    Ok(())
+}
+
+// Newest first, paged with before_id
+pub async fn list_notifications(
+    State(state): State<AppState>,
+    Query(params): Query<NotificationQuery>,
+) -> Result<Json<Vec<Notification>>, AppError> {
+    let limit = params.limit.unwrap_or(50).clamp(1, 500);
+    let rows = sqlx::query_as::<_, Notification>(&format!(
+        "SELECT {NOTIFICATION_COLUMNS}
+         FROM notifications n
+         INNER JOIN notification_rules r ON r.id = n.rule_id
+         WHERE ($1 IS NOT TRUE OR NOT n.is_read)
+           AND ($2::bigint IS NULL OR n.id < $2)
+         ORDER BY n.id DESC
+         LIMIT $3"
+    ))
+    .bind(params.unread_only)
+    .bind(params.before_id)
+    .bind(limit)
+    .fetch_all(&state.pool)
+    .await?;
+    Ok(Json(rows))
+}
+
+pub async fn get_unread_notification_count(
+    State(state): State<AppState>,
+) -> Result<Json<UnreadCount>, AppError> {
+    let unread: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM notifications WHERE NOT is_read")
+        .fetch_one(&state.pool)
+        .await?;
+    Ok(Json(UnreadCount { unread }))
+}
+
+async fn set_read(state: &AppState, id: i64, is_read: bool) -> Result<Json<Notification>, AppError> {
+    let found: Option<i64> = sqlx::query_scalar(
+        "UPDATE notifications
+         SET is_read = $1, read_at = CASE WHEN $1 THEN COALESCE(read_at, CURRENT_TIMESTAMP) END
+         WHERE id = $2
+         RETURNING id",
+    )
+    .bind(is_read)
+    .bind(id)
+    .fetch_optional(&state.pool)
+    .await?;
+    if found.is_none() {
+        return Err(AppError::NotFound("Notification not found".to_string()));
+    }
+
+    let notification = sqlx::query_as::<_, Notification>(&format!(
+        "SELECT {NOTIFICATION_COLUMNS}
+         FROM notifications n
+         INNER JOIN notification_rules r ON r.id = n.rule_id
+         WHERE n.id = $1"
+    ))
+    .bind(id)
+    .fetch_one(&state.pool)
+    .await?;
+    Ok(Json(notification))
+}
+
+pub async fn mark_notification_read(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<Json<Notification>, AppError> {
+    set_read(&state, id, true).await
+}
+
+pub async fn mark_notification_unread(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<Json<Notification>, AppError> {
+    set_read(&state, id, false).await
+}
+
+pub async fn mark_all_notifications_read(
+    State(state): State<AppState>,
+) -> Result<Json<UnreadCount>, AppError> {
+    sqlx::query("UPDATE notifications SET is_read = TRUE, read_at = CURRENT_TIMESTAMP WHERE NOT is_read")
+        .execute(&state.pool)
+        .await?;
+    Ok(Json(UnreadCount { unread: 0 }))
+}
+
+pub async fn delete_notification(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<(), AppError> {
+    sqlx::query("DELETE FROM notifications WHERE id = $1")
+        .bind(id)
+        .execute(&state.pool)
+        .await?;
+    Ok(())
+}
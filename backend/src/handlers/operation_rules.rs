@@ -0,0 +1,209 @@
+// Consistency rules shared by every path that writes operations (and recurring
+// templates): the sign of the amount follows the operation type, the category
+// has the same type as the operation, and hidden or system categories can't be
+// newly assigned. System flows (transfers, balance corrections, debt interest)
+// insert their rows directly and don't go through these checks.
+use crate::{AppState, error::AppError, handlers::bulk_operations::normalize_hashtags, models::*};
+use axum::{
+    Json,
+    extract::{Query, State},
+};
+
+#[derive(Debug)]
+pub(crate) enum RuleError {
+    Invalid(String),
+    Database(sqlx::Error),
+}
+
+impl std::fmt::Display for RuleError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RuleError::Invalid(message) => f.write_str(message),
+            RuleError::Database(e) => write!(f, "DB error: {}", e),
+        }
+    }
+}
+
+impl From<sqlx::Error> for RuleError {
+    fn from(e: sqlx::Error) -> Self {
+        RuleError::Database(e)
+    }
+}
+
+impl From<RuleError> for AppError {
+    fn from(e: RuleError) -> Self {
+        match e {
+            RuleError::Invalid(message) => AppError::BadRequest(message),
+            RuleError::Database(e) => AppError::from(e),
+        }
+    }
+}
+
+// Expenses are stored as negative amounts and income as positive ones
+pub(crate) fn check_sign(operation_type: &str, amount: &Money) -> Result<(), RuleError> {
+    let ok = match operation_type {
+        "expense" => !amount.is_positive(),
+        "income" => !amount.is_negative(),
+        _ => {
+            return Err(RuleError::Invalid(format!(
+                "Unknown operation type '{}'",
+                operation_type
+            )));
+        }
+    };
+    if !ok {
+        return Err(RuleError::Invalid(format!(
+            "An {} must have a {} amount, got {}",
+            operation_type,
+            if operation_type == "expense" { "negative" } else { "positive" },
+            amount
+        )));
+    }
+    Ok(())
+}
+
+#[derive(sqlx::FromRow)]
+struct CategoryRuleRow {
+    name: String,
+    category_type: String,
+    is_system: bool,
+    hidden: bool,
+}
+
+// `current_category_id` is what the row already has; keeping it is always
+// allowed so that existing operations in hidden or system categories can
+// still be edited. In a transaction the category stays as checked until it ends.
+pub(crate) async fn check_category(
+    conn: &mut sqlx::PgConnection,
+    category_id: Option<i32>,
+    operation_type: &str,
+    current_category_id: Option<i32>,
+) -> Result<(), RuleError> {
+    let Some(category_id) = category_id else {
+        return Ok(());
+    };
+
+    let category = sqlx::query_as::<_, CategoryRuleRow>(
+        "SELECT c.name, c.type::text AS category_type, c.is_system,
+                c.is_hidden OR COALESCE(p.is_hidden, FALSE) AS hidden
+         FROM categories c
+         LEFT JOIN categories p ON p.id = c.parent_id
+         WHERE c.id = $1 AND c.deleted_at IS NULL
+         FOR SHARE OF c",
+    )
+    .bind(category_id)
+    .fetch_optional(conn)
+    .await?
+    .ok_or_else(|| RuleError::Invalid(format!("Category {} not found", category_id)))?;
+
+    if category.category_type != operation_type {
+        return Err(RuleError::Invalid(format!(
+            "Category '{}' is an {} category and can't hold an {}",
+            category.name, category.category_type, operation_type
+        )));
+    }
+    if current_category_id == Some(category_id) {
+        return Ok(());
+    }
+    if category.is_system {
+        return Err(RuleError::Invalid(format!(
+            "Category '{}' is a system category and is assigned automatically",
+            category.name
+        )));
+    }
+    if category.hidden {
+        return Err(RuleError::Invalid(format!(
+            "Category '{}' is hidden; unhide it before assigning operations",
+            category.name
+        )));
+    }
+    Ok(())
+}
+
+pub(crate) async fn check_operation(
+    conn: &mut sqlx::PgConnection,
+    operation_type: &str,
+    amount: &Money,
+    category_id: Option<i32>,
+    current_category_id: Option<i32>,
+) -> Result<(), RuleError> {
+    check_sign(operation_type, amount)?;
+    check_category(conn, category_id, operation_type, current_category_id).await
+}
+
+// A split needs at least two parts that add up exactly to the parent amount.
+// Hashtags given for a part are normalized in place.
+pub(crate) fn check_split(amount: &Money, items: &mut [SplitItem]) -> Result<(), RuleError> {
+    if items.len() < 2 {
+        return Err(RuleError::Invalid("Split requires at least 2 items".to_string()));
+    }
+    let sum: Money = items.iter().map(|item| &item.amount).sum();
+    if &sum != amount {
+        return Err(RuleError::Invalid(format!(
+            "Sum of items ({}) does not match total amount ({})",
+            sum, amount
+        )));
+    }
+    for item in items.iter_mut() {
+        if let Some(hashtags) = &item.hashtags {
+            item.hashtags = if hashtags.is_empty() {
+                None
+            } else {
+                Some(normalize_hashtags(hashtags).map_err(|e| RuleError::Invalid(e.to_string()))?)
+            };
+        }
+    }
+    Ok(())
+}
+
+// Split items are checked against the parent's type
+pub(crate) async fn check_split_items(
+    conn: &mut sqlx::PgConnection,
+    operation_type: &str,
+    items: &[SplitItem],
+) -> Result<(), RuleError> {
+    for item in items {
+        check_operation(conn, operation_type, &item.amount, Some(item.category_id), None).await?;
+    }
+    Ok(())
+}
+
+// Existing operations that break the rules, with a suggested fix for each
+pub async fn list_operation_violations(
+    State(state): State<AppState>,
+    Query(params): Query<OperationViolationQuery>,
+) -> Result<Json<Vec<OperationViolation>>, AppError> {
+    let rows = sqlx::query_as::<_, OperationViolation>(
+        "SELECT o.id AS operation_id, o.asset_id, o.operation_date, o.description, o.amount,
+                o.operation_type::text AS operation_type, o.category_id,
+                c.name AS category_name, c.type::text AS category_type,
+                v.rule, v.suggested_fix
+         FROM operations o
+         LEFT JOIN categories c ON c.id = o.category_id
+         LEFT JOIN categories p ON p.id = c.parent_id
+         CROSS JOIN LATERAL (VALUES
+             ('sign_mismatch',
+              (o.operation_type = 'expense' AND o.amount > 0) OR (o.operation_type = 'income' AND o.amount < 0),
+              CASE
+                  WHEN c.type IS NOT NULL AND c.type::text <> o.operation_type::text
+                      THEN 'Change operation_type to ' || c.type::text
+                  ELSE 'Change amount to ' || (-o.amount)::text
+              END),
+             ('category_type_mismatch',
+              c.type IS NOT NULL AND c.type::text <> o.operation_type::text
+                  AND NOT ((o.operation_type = 'expense' AND o.amount > 0) OR (o.operation_type = 'income' AND o.amount < 0)),
+              'Move to an ' || o.operation_type::text || ' category'),
+             ('hidden_category',
+              c.is_hidden OR COALESCE(p.is_hidden, FALSE),
+              'Unhide category ' || c.name || ' or move the operation to a visible category')
+         ) AS v(rule, violated, suggested_fix)
+         WHERE v.violated AND o.deleted_at IS NULL
+           AND ($1::int IS NULL OR o.asset_id = $1)
+         ORDER BY o.operation_date DESC, o.id, v.rule",
+    )
+    .bind(params.asset_id)
+    .fetch_all(&state.pool)
+    .await?;
+
+    Ok(Json(rows))
+}
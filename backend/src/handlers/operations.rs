@@ -1,156 +1,129 @@
-use crate::{AppState, models::*, utils::db_err};
+use crate::{
+    AppState, balances,
+    error::AppError,
+    handlers::{
+        bulk_operations::add_hashtags_to_description,
+        operation_rules::{check_operation, check_split, check_split_items},
+    },
+    models::*,
+    money::AMOUNT_DIGITS,
+    trash,
+    validation::{ValidJson, Validate, Validator},
+};
 use axum::{
     Json,
     extract::{Path, State},
 };
 
+// Amounts are checked against the type and category by operation_rules
+impl Validate for CreateOperation {
+    fn validate(&self, v: &mut Validator) {
+        v.amount("amount", &self.amount, AMOUNT_DIGITS);
+        v.nested("split_items", &self.split_items);
+    }
+}
+impl Validate for SplitOperationRequest {
+    fn validate(&self, v: &mut Validator) {
+        v.nested("items", &self.items);
+    }
+}
+impl Validate for SplitItem {
+    fn validate(&self, v: &mut Validator) {
+        v.amount("amount", &self.amount, AMOUNT_DIGITS);
+    }
+}
+
 pub async fn create_operation(
     State(state): State<AppState>,
-    Json(payload): Json<CreateOperation>,
-) -> Result<Json<OperationWithHashtags>, (axum::http::StatusCode, String)> {
-    use bigdecimal::BigDecimal;
-    use std::str::FromStr;
+    ValidJson(payload): ValidJson<CreateOperation>,
+) -> Result<Json<OperationWithHashtags>, AppError> {
+    let mut tx = state.pool.begin().await?;
+
+    // Link a payee (given or matched by description) and apply its defaults
+    let assignment = crate::handlers::payees::assign_payee(
+        &mut *tx,
+        payload.payee_id.flatten(),
+        payload.category_id,
+        payload.description.as_deref(),
+        payload.operation_type.as_str(),
+        payload.split_items.is_some(),
+    )
+    .await?;
+    let mut payload = CreateOperation {
+        payee_id: Some(assignment.payee_id),
+        category_id: assignment.category_id,
+        description: assignment.description,
+        ..payload
+    };
 
-    // Check if this is a split operation
+    if let Some(split_items) = &mut payload.split_items {
+        check_split(&payload.amount, split_items)?;
+    }
+    // Checked in the transaction that inserts, so the categories can't change in between
+    check_operation(&mut tx, payload.operation_type.as_str(), &payload.amount, payload.category_id, None).await?;
     if let Some(split_items) = &payload.split_items {
-        // Validate minimum 2 items
-        if split_items.len() < 2 {
-            return Err((
-                axum::http::StatusCode::BAD_REQUEST,
-                "Split requires at least 2 items".to_string(),
-            ));
-        }
-
-        // Validate sum
-        let sum: BigDecimal = split_items.iter().map(|item| &item.amount).sum();
-        let tolerance = BigDecimal::from_str("0.01").unwrap();
-        let diff = (&payload.amount - &sum).abs();
-
-        if diff > tolerance {
-            return Err((
-                axum::http::StatusCode::BAD_REQUEST,
-                format!(
-                    "Sum of items ({}) does not match total amount ({})",
-                    sum, payload.amount
-                ),
-            ));
-        }
-
-        // Begin transaction
-        let mut tx = state.pool.begin().await.map_err(db_err)?;
+        check_split_items(&mut tx, payload.operation_type.as_str(), split_items).await?;
+    }
 
+    // Check if this is a split operation
+    if let Some(split_items) = &payload.split_items {
         // Create parent operation with is_split=true
         let parent = sqlx::query_as::<_, Operation>(
-            "INSERT INTO operations (category_id, description, asset_id, amount, operation_type, operation_date, is_split)
-             VALUES ($1, $2, $3, $4, $5::operation_type, $6::date, TRUE)
-             RETURNING id, creation_date, category_id, description, asset_id, amount, operation_type::text, operation_date, parent_operation_id, is_split, linked_operation_id"
+            "INSERT INTO operations (category_id, description, asset_id, amount, operation_type, operation_date, is_split, payee_id)
+             VALUES ($1, $2, $3, $4, $5::operation_type, $6::date, TRUE, $7)
+             RETURNING id, creation_date, category_id, description, asset_id, amount, operation_type::text, operation_date, parent_operation_id, is_split, linked_operation_id, payee_id, clearing_status::text"
         ).bind(payload.category_id)
          .bind(&payload.description)
          .bind(payload.asset_id)
          .bind(&payload.amount)
-         .bind(&payload.operation_type)
-         .bind(&payload.operation_date)
-         .fetch_one(&mut *tx).await.map_err(db_err)?;
-
-        // Create child operations
-        for item in split_items {
-            sqlx::query(
-                "INSERT INTO operations (category_id, description, asset_id, amount, operation_type, operation_date, parent_operation_id, is_split)
-                 VALUES ($1, $2, $3, $4, $5::operation_type, $6::date, $7, FALSE)"
-            )
-            .bind(item.category_id)
-            .bind(&item.description)
-            .bind(payload.asset_id)
-            .bind(&item.amount)
-            .bind(&payload.operation_type)
-            .bind(&payload.operation_date)
-            .bind(parent.id)
-            .execute(&mut *tx)
-            .await
-            .map_err(db_err)?;
-
-            // Extract and link hashtags from child description
-            if let Some(desc) = &item.description {
-                let extracted_hashtags = extract_hashtags(desc);
-                if !extracted_hashtags.is_empty() {
-                    // Note: We can't use the helper here since we're in a transaction
-                    // For simplicity, we'll skip hashtags in split items during creation
-                    // They can be added via split dialog later
-                }
-            }
-        }
+         .bind(payload.operation_type.as_str())
+         .bind(payload.operation_date)
+         .bind(payload.payee_id.flatten())
+         .fetch_one(&mut *tx).await?;
 
-        // Commit transaction BEFORE linking hashtags
-        tx.commit().await.map_err(db_err)?;
-
-        // Extract hashtags from parent description (trigger will handle creation/usage_count)
-        let hashtags = if let Some(desc) = &payload.description {
-            let extracted_hashtags = extract_hashtags(desc);
-            get_hashtags_by_names(&state.pool, &extracted_hashtags)
-                .await
-                .map_err(|e| db_err(e))?
-        } else {
-            Vec::new()
-        };
+        insert_split_children(&mut tx, &parent, split_items)
+            .await?;
+        balances::recalculate(&mut tx, &[parent.asset_id])
+            .await?;
+
+        tx.commit().await?;
+        crate::notifications::check_after_write(&state);
+
+        // Hashtags in descriptions are linked by a trigger
+        let hashtags = get_operation_hashtags(&state.pool, parent.id)
+            .await?;
 
-        return Ok(Json(OperationWithHashtags {
-            id: parent.id,
-            creation_date: parent.creation_date,
-            category_id: parent.category_id,
-            description: parent.description,
-            asset_id: parent.asset_id,
-            amount: parent.amount,
-            operation_type: parent.operation_type,
-            operation_date: parent.operation_date,
-            parent_operation_id: parent.parent_operation_id,
-            is_split: parent.is_split,
-            linked_operation_id: parent.linked_operation_id,
-            hashtags,
-        }));
+        return Ok(Json(with_hashtags(parent, hashtags)));
     }
 
     // Regular operation (not split)
     let op = sqlx::query_as::<_, Operation>(
-        "INSERT INTO operations (category_id, description, asset_id, amount, operation_type, operation_date)
-         VALUES ($1, $2, $3, $4, $5::operation_type, $6::date)
-         RETURNING id, creation_date, category_id, description, asset_id, amount, operation_type::text, operation_date, parent_operation_id, is_split, linked_operation_id"
+        "INSERT INTO operations (category_id, description, asset_id, amount, operation_type, operation_date, payee_id)
+         VALUES ($1, $2, $3, $4, $5::operation_type, $6::date, $7)
+         RETURNING id, creation_date, category_id, description, asset_id, amount, operation_type::text, operation_date, parent_operation_id, is_split, linked_operation_id, payee_id, clearing_status::text"
     ).bind(payload.category_id)
      .bind(&payload.description)
      .bind(payload.asset_id)
      .bind(payload.amount)
-     .bind(&payload.operation_type)
-     .bind(&payload.operation_date)
-     .fetch_one(&state.pool).await.map_err(db_err)?;
-
-    // Extract hashtags from description (trigger will handle creation/usage_count)
-    let hashtags = if let Some(desc) = &payload.description {
-        let extracted_hashtags = extract_hashtags(desc);
-        get_hashtags_by_names(&state.pool, &extracted_hashtags)
-            .await
-            .map_err(|e| db_err(e))?
-    } else {
-        Vec::new()
-    };
+     .bind(payload.operation_type.as_str())
+     .bind(payload.operation_date)
+     .bind(payload.payee_id.flatten())
+     .fetch_one(&mut *tx).await?;
+    balances::recalculate(&mut tx, &[op.asset_id])
+        .await?;
+    tx.commit().await?;
+    crate::notifications::check_after_write(&state);
+
+    // Hashtags in the description are linked by a trigger
+    let hashtags = get_operation_hashtags(&state.pool, op.id)
+        .await?;
 
-    Ok(Json(OperationWithHashtags {
-        id: op.id,
-        creation_date: op.creation_date,
-        category_id: op.category_id,
-        description: op.description,
-        asset_id: op.asset_id,
-        amount: op.amount,
-        operation_type: op.operation_type,
-        operation_date: op.operation_date,
-        parent_operation_id: op.parent_operation_id,
-        is_split: op.is_split,
-        linked_operation_id: op.linked_operation_id,
-        hashtags,
-    }))
+    Ok(Json(with_hashtags(op, hashtags)))
 }
 
 pub async fn list_operations(
     State(state): State<AppState>,
-) -> Result<Json<Vec<OperationWithDetails>>, (axum::http::StatusCode, String)> {
+) -> Result<Json<Vec<OperationWithDetails>>, AppError> {
     #[derive(sqlx::FromRow)]
     struct OperationRow {
         id: i32,
@@ -161,12 +134,15 @@ pub async fn list_operations(
         description: Option<String>,
         asset_id: i32,
         asset_name: Option<String>,
-        amount: bigdecimal::BigDecimal,
+        amount: Money,
         operation_type: Option<String>,
         operation_date: chrono::NaiveDate,
         parent_operation_id: Option<i32>,
         is_split: bool,
         linked_operation_id: Option<i32>,
+        payee_id: Option<i32>,
+        payee_name: Option<String>,
+        clearing_status: String,
     }
 
     // Use JOIN to get asset, category and parent category names in one query
@@ -187,17 +163,20 @@ pub async fn list_operations(
             o.operation_date,
             o.parent_operation_id,
             o.is_split,
-            o.linked_operation_id
+            o.linked_operation_id,
+            o.payee_id,
+            p.name as payee_name,
+            o.clearing_status::text as clearing_status
          FROM operations o
          INNER JOIN assets a ON o.asset_id = a.id
          LEFT JOIN categories c ON o.category_id = c.id
          LEFT JOIN categories pc ON c.parent_id = pc.id
-         WHERE o.parent_operation_id IS NULL
+         LEFT JOIN payees p ON o.payee_id = p.id
+         WHERE o.parent_operation_id IS NULL AND o.deleted_at IS NULL
          ORDER BY o.operation_date DESC, o.id DESC",
     )
     .fetch_all(&state.pool)
-    .await
-    .map_err(db_err)?;
+    .await?;
 
     if rows.is_empty() {
         return Ok(Json(Vec::new()));
@@ -206,8 +185,7 @@ pub async fn list_operations(
     // Batch fetch all hashtags for all operations
     let operation_ids: Vec<i32> = rows.iter().map(|op| op.id).collect();
     let all_hashtags = get_operations_hashtags_batch(&state.pool, &operation_ids)
-        .await
-        .map_err(|e| db_err(e))?;
+        .await?;
 
     let result: Vec<OperationWithDetails> = rows
         .into_iter()
@@ -228,6 +206,9 @@ pub async fn list_operations(
                 parent_operation_id: op.parent_operation_id,
                 is_split: op.is_split,
                 linked_operation_id: op.linked_operation_id,
+                payee_id: op.payee_id,
+                clearing_status: op.clearing_status,
+                payee_name: op.payee_name,
                 hashtags,
             }
         })
@@ -239,100 +220,123 @@ pub async fn list_operations(
 pub async fn get_operation(
     State(state): State<AppState>,
     Path(id): Path<i32>,
-) -> Result<Json<OperationWithHashtags>, (axum::http::StatusCode, String)> {
+) -> Result<Json<OperationWithHashtags>, AppError> {
     let op = sqlx::query_as::<_, Operation>(
-        "SELECT id, creation_date, category_id, description, asset_id, amount, operation_type::text, operation_date, parent_operation_id, is_split, linked_operation_id
-         FROM operations WHERE id = $1"
-    ).bind(id).fetch_one(&state.pool).await.map_err(db_err)?;
+        "SELECT id, creation_date, category_id, description, asset_id, amount, operation_type::text, operation_date, parent_operation_id, is_split, linked_operation_id, payee_id, clearing_status::text
+         FROM operations WHERE id = $1 AND deleted_at IS NULL"
+    ).bind(id).fetch_one(&state.pool).await?;
 
     let hashtags = get_operation_hashtags(&state.pool, op.id)
-        .await
-        .map_err(|_| db_err("Failed to fetch hashtags"))?;
+        .await?;
 
-    Ok(Json(OperationWithHashtags {
-        id: op.id,
-        creation_date: op.creation_date,
-        category_id: op.category_id,
-        description: op.description,
-        asset_id: op.asset_id,
-        amount: op.amount,
-        operation_type: op.operation_type,
-        operation_date: op.operation_date,
-        parent_operation_id: op.parent_operation_id,
-        is_split: op.is_split,
-        linked_operation_id: op.linked_operation_id,
-        hashtags,
-    }))
+    Ok(Json(with_hashtags(op, hashtags)))
 }
 
 pub async fn update_operation(
     State(state): State<AppState>,
     Path(id): Path<i32>,
-    Json(payload): Json<CreateOperation>,
-) -> Result<Json<OperationWithHashtags>, (axum::http::StatusCode, String)> {
+    ValidJson(mut payload): ValidJson<CreateOperation>,
+) -> Result<Json<OperationWithHashtags>, AppError> {
+    let mut tx = state.pool.begin().await?;
+    let current = fetch_operation_for_update(&mut tx, id).await?;
+
+    // A part of a split keeps its amount (the parts must add up to the parent)
+    // and follows the parent's asset, type and date
+    if let Some(parent_id) = current.parent_operation_id {
+        if payload.amount != current.amount {
+            return Err(AppError::BadRequest(format!(
+                    "The amount of a split part can't change on its own; use PUT /operations/{}/split",
+                    parent_id
+                )));
+        }
+        let parent = fetch_operation_for_update(&mut tx, parent_id).await?;
+        payload.asset_id = parent.asset_id;
+        payload.operation_type = parent.operation_type.parse().map_err(AppError::internal)?;
+        payload.operation_date = parent.operation_date;
+    }
+
+    check_operation(&mut tx, payload.operation_type.as_str(), &payload.amount, payload.category_id, current.category_id).await?;
+
+    // The parts of a split must still add up to the parent and fit its type
+    if current.is_split {
+        let children: Vec<(Money, Option<i32>)> = sqlx::query_as(
+            "SELECT amount, category_id FROM operations WHERE parent_operation_id = $1 AND deleted_at IS NULL ORDER BY id"
+        )
+        .bind(id)
+        .fetch_all(&mut *tx)
+        .await?;
+
+        let sum: Money = children.iter().map(|(amount, _)| amount).sum();
+        if sum != payload.amount {
+            return Err(AppError::BadRequest(format!(
+                    "Split parts add up to {}, not {}; change them with PUT /operations/{}/split",
+                    sum, payload.amount, id
+                )));
+        }
+        for (amount, category_id) in &children {
+            check_operation(&mut tx, payload.operation_type.as_str(), amount, *category_id, *category_id).await?;
+        }
+    }
+
     let op = sqlx::query_as::<_, Operation>(
         "UPDATE operations
-         SET category_id = $1, description = $2, asset_id = $3, amount = $4, operation_type = $5::operation_type, operation_date = $6::date
+         SET category_id = $1, description = $2, asset_id = $3, amount = $4, operation_type = $5::operation_type, operation_date = $6::date,
+             payee_id = CASE WHEN $9 THEN $8 ELSE payee_id END
          WHERE id = $7
-         RETURNING id, creation_date, category_id, description, asset_id, amount, operation_type::text, operation_date, parent_operation_id, is_split, linked_operation_id"
+         RETURNING id, creation_date, category_id, description, asset_id, amount, operation_type::text, operation_date, parent_operation_id, is_split, linked_operation_id, payee_id, clearing_status::text"
     ).bind(payload.category_id)
      .bind(&payload.description)
      .bind(payload.asset_id)
-     .bind(payload.amount)
-     .bind(&payload.operation_type)
-     .bind(&payload.operation_date)
+     .bind(&payload.amount)
+     .bind(payload.operation_type.as_str())
+     .bind(payload.operation_date)
      .bind(id)
-     .fetch_one(&state.pool).await.map_err(db_err)?;
+     .bind(payload.payee_id.flatten())
+     .bind(payload.payee_id.is_some())
+     .fetch_one(&mut *tx).await?;
 
-    // Update children operation dates if this is a split operation
+    // Split parts follow the parent's asset, type and date
     if op.is_split {
         sqlx::query(
             "UPDATE operations
-             SET operation_date = $1::date
-             WHERE parent_operation_id = $2",
+             SET operation_date = $1, asset_id = $2, operation_type = $3::operation_type
+             WHERE parent_operation_id = $4
+               AND (operation_date, asset_id, operation_type::text) IS DISTINCT FROM ($1, $2, $3)",
         )
-        .bind(&payload.operation_date)
+        .bind(op.operation_date)
+        .bind(op.asset_id)
+        .bind(&op.operation_type)
         .bind(id)
-        .execute(&state.pool)
-        .await
-        .map_err(db_err)?;
+        .execute(&mut *tx)
+        .await?;
     }
 
-    // Extract hashtags from description (trigger will handle creation/usage_count)
-    let hashtags = if let Some(desc) = &payload.description {
-        let extracted_hashtags = extract_hashtags(desc);
-        get_hashtags_by_names(&state.pool, &extracted_hashtags)
-            .await
-            .map_err(|e| db_err(e))?
-    } else {
-        Vec::new()
-    };
+    balances::recalculate(&mut tx, &[current.asset_id, op.asset_id])
+        .await?;
+    tx.commit().await?;
+    crate::notifications::check_after_write(&state);
 
-    Ok(Json(OperationWithHashtags {
-        id: op.id,
-        creation_date: op.creation_date,
-        category_id: op.category_id,
-        description: op.description,
-        asset_id: op.asset_id,
-        amount: op.amount,
-        operation_type: op.operation_type,
-        operation_date: op.operation_date,
-        parent_operation_id: op.parent_operation_id,
-        is_split: op.is_split,
-        linked_operation_id: op.linked_operation_id,
-        hashtags,
-    }))
+    // Hashtags in the description are linked by a trigger
+    let hashtags = get_operation_hashtags(&state.pool, op.id)
+        .await?;
+
+    Ok(Json(with_hashtags(op, hashtags)))
 }
 
 pub async fn delete_operation(
     State(state): State<AppState>,
     Path(id): Path<i32>,
-) -> Result<(), (axum::http::StatusCode, String)> {
-    sqlx::query("DELETE FROM operations WHERE id = $1")
-        .bind(id)
-        .execute(&state.pool)
-        .await
-        .map_err(db_err)?;
+) -> Result<(), AppError> {
+    let mut tx = state.pool.begin().await?;
+    // The other side of a transfer and the split parts go to the trash with it
+    let asset_ids = trash::trash_operations(&mut tx, &[id]).await?;
+    if asset_ids.is_empty() {
+        return Err(AppError::NotFound("Operation not found".to_string()));
+    }
+    balances::recalculate(&mut tx, &asset_ids)
+        .await?;
+    tx.commit().await?;
+    crate::notifications::check_after_write(&state);
     Ok(())
 }
 
@@ -359,256 +363,229 @@ pub fn extract_hashtags(text: &str) -> Vec<String> {
     hashtags
 }
 
-// Helper function to fetch hashtags by their names
-async fn get_hashtags_by_names(
-    pool: &sqlx::PgPool,
-    hashtag_names: &[String],
-) -> Result<Vec<Hashtag>, String> {
-    if hashtag_names.is_empty() {
-        return Ok(Vec::new());
-    }
-
-    let hashtags = sqlx::query_as::<_, Hashtag>(
-        "SELECT id, name, created_date, usage_count FROM hashtags WHERE name = ANY($1) ORDER BY name",
-    )
-    .bind(hashtag_names)
-    .fetch_all(pool)
-    .await
-    .map_err(|e| e.to_string())?;
-
-    Ok(hashtags)
-}
-
-// Helper function to fetch hashtags for an operation
-async fn get_operation_hashtags(
-    pool: &sqlx::PgPool,
+// Helper function to fetch hashtags linked to an operation
+pub(crate) async fn get_operation_hashtags<'e, E: sqlx::PgExecutor<'e>>(
+    executor: E,
     operation_id: i32,
-) -> Result<Vec<Hashtag>, String> {
-    let op = sqlx::query_scalar::<_, Option<String>>(
-        "SELECT description FROM operations WHERE id = $1"
+) -> Result<Vec<Hashtag>, sqlx::Error> {
+    sqlx::query_as::<_, Hashtag>(
+        "SELECT h.id, h.name, h.created_date, h.usage_count, h.budget_cap
+         FROM operation_hashtags oh
+         INNER JOIN hashtags h ON oh.hashtag_id = h.id
+         WHERE oh.operation_id = $1
+         ORDER BY h.name",
     )
     .bind(operation_id)
-    .fetch_optional(pool)
+    .fetch_all(executor)
     .await
-    .map_err(|e| e.to_string())?;
-
-    match op {
-        Some(Some(description)) => {
-            let hashtag_names = extract_hashtags(&description);
-            get_hashtags_by_names(pool, &hashtag_names).await
-        }
-        _ => Ok(Vec::new()),
-    }
 }
 
 // Helper function to fetch hashtags for multiple operations in one query (batch)
 async fn get_operations_hashtags_batch(
     pool: &sqlx::PgPool,
     operation_ids: &[i32],
-) -> Result<std::collections::HashMap<i32, Vec<Hashtag>>, String> {
-    use std::collections::HashMap;
+) -> Result<std::collections::HashMap<i32, Vec<Hashtag>>, sqlx::Error> {
+    #[derive(sqlx::FromRow)]
+    struct LinkRow {
+        operation_id: i32,
+        #[sqlx(flatten)]
+        hashtag: Hashtag,
+    }
 
-    let rows = sqlx::query!(
-        "SELECT id, description FROM operations WHERE id = ANY($1)",
-        operation_ids
+    let rows = sqlx::query_as::<_, LinkRow>(
+        "SELECT oh.operation_id, h.id, h.name, h.created_date, h.usage_count, h.budget_cap
+         FROM operation_hashtags oh
+         INNER JOIN hashtags h ON oh.hashtag_id = h.id
+         WHERE oh.operation_id = ANY($1)
+         ORDER BY h.name",
     )
+    .bind(operation_ids)
     .fetch_all(pool)
-    .await
-    .map_err(|e| e.to_string())?;
+    .await?;
 
-    let mut map: HashMap<i32, Vec<Hashtag>> = HashMap::new();
-    let mut all_hashtag_names: std::collections::HashSet<String> = std::collections::HashSet::new();
+    let mut map: std::collections::HashMap<i32, Vec<Hashtag>> = std::collections::HashMap::new();
+    for row in rows {
+        map.entry(row.operation_id).or_default().push(row.hashtag);
+    }
+    Ok(map)
+}
 
-    // Collect all unique hashtag names from all operations
-    for row in &rows {
-        if let Some(desc) = &row.description {
-            for hashtag in extract_hashtags(desc) {
-                all_hashtag_names.insert(hashtag);
-            }
-        }
+fn with_hashtags(op: Operation, hashtags: Vec<Hashtag>) -> OperationWithHashtags {
+    OperationWithHashtags {
+        id: op.id,
+        creation_date: op.creation_date,
+        category_id: op.category_id,
+        description: op.description,
+        asset_id: op.asset_id,
+        amount: op.amount,
+        operation_type: op.operation_type,
+        operation_date: op.operation_date,
+        parent_operation_id: op.parent_operation_id,
+        is_split: op.is_split,
+        linked_operation_id: op.linked_operation_id,
+        payee_id: op.payee_id,
+        clearing_status: op.clearing_status,
+        hashtags,
     }
+}
 
-    // Fetch all hashtags in one query
-    if !all_hashtag_names.is_empty() {
-        let hashtag_list: Vec<String> = all_hashtag_names.into_iter().collect();
-        let hashtags = get_hashtags_by_names(pool, &hashtag_list).await?;
-
-        // Build a map of hashtag name -> Hashtag for quick lookup
-        let hashtag_map: std::collections::HashMap<String, Hashtag> = hashtags
-            .into_iter()
-            .map(|h| (h.name.clone(), h))
-            .collect();
-
-        // For each operation, extract its hashtags from the map
-        for row in rows {
-            if let Some(desc) = row.description {
-                let op_hashtags: Vec<Hashtag> = extract_hashtags(&desc)
-                    .into_iter()
-                    .filter_map(|name| hashtag_map.get(&name).cloned())
-                    .collect();
-                map.insert(row.id, op_hashtags);
-            }
-        }
+// Insert the parts of a split operation. Parts take the parent's asset, type
+// and date; their own hashtags are appended to their description.
+pub(crate) async fn insert_split_children(
+    conn: &mut sqlx::PgConnection,
+    parent: &Operation,
+    items: &[SplitItem],
+) -> Result<Vec<OperationWithHashtags>, sqlx::Error> {
+    let mut children = Vec::new();
+    for item in items {
+        let description = match &item.hashtags {
+            Some(hashtags) => Some(add_hashtags_to_description(item.description.as_deref(), hashtags)),
+            None => item.description.clone(),
+        };
+
+        let child = sqlx::query_as::<_, Operation>(
+            "INSERT INTO operations (category_id, description, asset_id, amount, operation_type, operation_date, parent_operation_id, is_split)
+             VALUES ($1, $2, $3, $4, $5::operation_type, $6, $7, FALSE)
+             RETURNING id, creation_date, category_id, description, asset_id, amount, operation_type::text, operation_date, parent_operation_id, is_split, linked_operation_id, payee_id, clearing_status::text"
+        )
+        .bind(item.category_id)
+        .bind(description)
+        .bind(parent.asset_id)
+        .bind(&item.amount)
+        .bind(&parent.operation_type)
+        .bind(parent.operation_date)
+        .bind(parent.id)
+        .fetch_one(&mut *conn)
+        .await?;
+
+        // Hashtags in the description are linked by a trigger
+        let hashtags = get_operation_hashtags(&mut *conn, child.id).await?;
+        children.push(with_hashtags(child, hashtags));
     }
+    Ok(children)
+}
 
-    Ok(map)
+async fn fetch_operation_for_update(
+    conn: &mut sqlx::PgConnection,
+    id: i32,
+) -> Result<Operation, AppError> {
+    sqlx::query_as::<_, Operation>(
+        "SELECT id, creation_date, category_id, description, asset_id, amount, operation_type::text, operation_date, parent_operation_id, is_split, linked_operation_id, payee_id, clearing_status::text
+         FROM operations WHERE id = $1 AND deleted_at IS NULL FOR UPDATE"
+    )
+    .bind(id)
+    .fetch_optional(conn)
+    .await?
+    .ok_or(AppError::NotFound("Operation not found".to_string()))
+}
+
+// Move the live parts of a split operation to the trash; parts already there
+// stay as they are
+async fn trash_split_children(conn: &mut sqlx::PgConnection, id: i32) -> Result<(), sqlx::Error> {
+    let children: Vec<i32> =
+        sqlx::query_scalar("SELECT id FROM operations WHERE parent_operation_id = $1 AND deleted_at IS NULL")
+            .bind(id)
+            .fetch_all(&mut *conn)
+            .await?;
+    trash::trash_operations(conn, &children).await?;
+    Ok(())
 }
 
 // Split operations - create child operations from parent
 pub async fn split_operation(
     State(state): State<AppState>,
     Path(id): Path<i32>,
-    Json(payload): Json<SplitOperationRequest>,
-) -> Result<Json<Vec<OperationWithHashtags>>, (axum::http::StatusCode, String)> {
-    use std::str::FromStr;
-
-    // Validate: minimum 2 items
-    if payload.items.len() < 2 {
-        return Err((
-            axum::http::StatusCode::BAD_REQUEST,
-            "Split requires at least 2 items".to_string(),
-        ));
-    }
-
-    // Get parent operation
-    let parent = sqlx::query_as::<_, Operation>(
-        "SELECT id, creation_date, category_id, description, asset_id, amount, operation_type::text, operation_date, parent_operation_id, is_split, linked_operation_id
-         FROM operations WHERE id = $1"
-    ).bind(id).fetch_optional(&state.pool).await.map_err(db_err)?;
-
-    let parent = match parent {
-        Some(p) => p,
-        None => {
-            return Err((
-                axum::http::StatusCode::NOT_FOUND,
-                "Operation not found".to_string(),
-            ));
-        }
-    };
+    ValidJson(mut payload): ValidJson<SplitOperationRequest>,
+) -> Result<Json<Vec<OperationWithHashtags>>, AppError> {
+    // Begin transaction
+    let mut tx = state.pool.begin().await?;
+    let parent = fetch_operation_for_update(&mut tx, id).await?;
 
     // Validate: not already split
     if parent.is_split {
-        return Err((
-            axum::http::StatusCode::BAD_REQUEST,
-            "Operation is already split".to_string(),
-        ));
+        return Err(AppError::BadRequest("Operation is already split".to_string()));
     }
-
-    // Validate: sum of items equals parent amount
-    let sum: bigdecimal::BigDecimal = payload.items.iter().map(|item| &item.amount).sum();
-    let tolerance = bigdecimal::BigDecimal::from_str("0.01").unwrap();
-    let diff = (&parent.amount - &sum).abs();
-
-    if diff > tolerance {
-        return Err((
-            axum::http::StatusCode::BAD_REQUEST,
-            format!(
-                "Sum of items ({}) does not match parent amount ({})",
-                sum, parent.amount
-            ),
-        ));
+    if parent.parent_operation_id.is_some() {
+        return Err(AppError::BadRequest("A part of a split operation can't be split again".to_string()));
     }
 
-    // Begin transaction
-    let mut tx = state.pool.begin().await.map_err(db_err)?;
+    // Validate: sum of items equals parent amount
+    check_split(&parent.amount, &mut payload.items)?;
+    check_split_items(&mut tx, &parent.operation_type, &payload.items).await?;
 
     // Mark parent as split
     sqlx::query("UPDATE operations SET is_split = TRUE WHERE id = $1")
         .bind(id)
         .execute(&mut *tx)
-        .await
-        .map_err(db_err)?;
+        .await?;
 
-    // Create child operations
-    let mut children = Vec::new();
-    for item in &payload.items {
-        let child = sqlx::query_as::<_, Operation>(
-            "INSERT INTO operations (category_id, description, asset_id, amount, operation_type, operation_date, parent_operation_id, is_split)
-             VALUES ($1, $2, $3, $4, $5::operation_type, $6, $7, FALSE)
-             RETURNING id, creation_date, category_id, description, asset_id, amount, operation_type::text, operation_date, parent_operation_id, is_split, linked_operation_id"
-        )
-        .bind(item.category_id)
-        .bind(&item.description)
-        .bind(parent.asset_id)
-        .bind(&item.amount)
-        .bind(&parent.operation_type)
-        .bind(parent.operation_date)
-        .bind(id)
-        .fetch_one(&mut *tx)
-        .await
-        .map_err(db_err)?;
-
-        // Extract hashtags from description (trigger will handle creation/usage_count)
-        let hashtags = if let Some(desc) = &item.description {
-            let extracted_hashtags = extract_hashtags(desc);
-            get_hashtags_by_names(&state.pool, &extracted_hashtags)
-                .await
-                .map_err(|e| db_err(e))?
-        } else {
-            Vec::new()
-        };
+    let children = insert_split_children(&mut tx, &parent, &payload.items)
+        .await?;
+
+    tx.commit().await?;
+    crate::notifications::check_after_write(&state);
+
+    Ok(Json(children))
+}
+
+// Replace all parts of a split operation at once. An operation that isn't
+// split yet becomes split.
+pub async fn replace_split_operation(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+    ValidJson(mut payload): ValidJson<SplitOperationRequest>,
+) -> Result<Json<Vec<OperationWithHashtags>>, AppError> {
+    let mut tx = state.pool.begin().await?;
+    let parent = fetch_operation_for_update(&mut tx, id).await?;
 
-        children.push(OperationWithHashtags {
-            id: child.id,
-            creation_date: child.creation_date,
-            category_id: child.category_id,
-            description: child.description,
-            asset_id: child.asset_id,
-            amount: child.amount,
-            operation_type: child.operation_type,
-            operation_date: child.operation_date,
-            parent_operation_id: child.parent_operation_id,
-            is_split: child.is_split,
-            linked_operation_id: child.linked_operation_id,
-            hashtags,
-        });
+    if parent.parent_operation_id.is_some() {
+        return Err(AppError::BadRequest("A part of a split operation can't be split again".to_string()));
     }
 
-    tx.commit().await.map_err(db_err)?;
+    check_split(&parent.amount, &mut payload.items)?;
+    check_split_items(&mut tx, &parent.operation_type, &payload.items).await?;
+
+    trash_split_children(&mut tx, id).await?;
+
+    if !parent.is_split {
+        sqlx::query("UPDATE operations SET is_split = TRUE WHERE id = $1")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+    }
+
+    let children = insert_split_children(&mut tx, &parent, &payload.items)
+        .await?;
+
+    tx.commit().await?;
+    crate::notifications::check_after_write(&state);
 
     Ok(Json(children))
 }
 
-// Unsplit operation - delete children and restore parent
+// Unsplit operation - move children to the trash and restore parent
 pub async fn unsplit_operation(
     State(state): State<AppState>,
     Path(id): Path<i32>,
-) -> Result<(), (axum::http::StatusCode, String)> {
-    // Get parent operation
-    let parent = sqlx::query_as::<_, Operation>(
-        "SELECT id, creation_date, category_id, description, asset_id, amount, operation_type::text, operation_date, parent_operation_id, is_split, linked_operation_id
-         FROM operations WHERE id = $1"
-    ).bind(id).fetch_optional(&state.pool).await.map_err(db_err)?;
-
-    let parent = match parent {
-        Some(p) => p,
-        None => {
-            return Err((
-                axum::http::StatusCode::NOT_FOUND,
-                "Operation not found".to_string(),
-            ));
-        }
-    };
+) -> Result<(), AppError> {
+    let mut tx = state.pool.begin().await?;
+    let parent = fetch_operation_for_update(&mut tx, id).await?;
 
     // Validate: operation is split
     if !parent.is_split {
-        return Err((
-            axum::http::StatusCode::BAD_REQUEST,
-            "Operation is not split".to_string(),
-        ));
+        return Err(AppError::BadRequest("Operation is not split".to_string()));
     }
 
-    // Begin transaction
-    let mut tx = state.pool.begin().await.map_err(db_err)?;
+    trash_split_children(&mut tx, id).await?;
 
     // Restore parent
     sqlx::query("UPDATE operations SET is_split = FALSE WHERE id = $1")
         .bind(id)
         .execute(&mut *tx)
-        .await
-        .map_err(db_err)?;
+        .await?;
 
-    tx.commit().await.map_err(db_err)?;
+    tx.commit().await?;
+    crate::notifications::check_after_write(&state);
 
     Ok(())
 }
@@ -617,7 +594,7 @@ pub async fn unsplit_operation(
 pub async fn get_operation_children(
     State(state): State<AppState>,
     Path(id): Path<i32>,
-) -> Result<Json<Vec<OperationWithDetails>>, (axum::http::StatusCode, String)> {
+) -> Result<Json<Vec<OperationWithDetails>>, AppError> {
     #[derive(sqlx::FromRow)]
     struct OperationRow {
         id: i32,
@@ -628,12 +605,15 @@ pub async fn get_operation_children(
         description: Option<String>,
         asset_id: i32,
         asset_name: Option<String>,
-        amount: bigdecimal::BigDecimal,
+        amount: Money,
         operation_type: Option<String>,
         operation_date: chrono::NaiveDate,
         parent_operation_id: Option<i32>,
         is_split: bool,
         linked_operation_id: Option<i32>,
+        payee_id: Option<i32>,
+        payee_name: Option<String>,
+        clearing_status: String,
     }
 
     let rows = sqlx::query_as::<_, OperationRow>(
@@ -651,18 +631,21 @@ pub async fn get_operation_children(
             o.operation_date,
             o.parent_operation_id,
             o.is_split,
-            o.linked_operation_id
+            o.linked_operation_id,
+            o.payee_id,
+            p.name as payee_name,
+            o.clearing_status::text as clearing_status
          FROM operations o
          INNER JOIN assets a ON o.asset_id = a.id
          LEFT JOIN categories c ON o.category_id = c.id
          LEFT JOIN categories pc ON c.parent_id = pc.id
-         WHERE o.parent_operation_id = $1
+         LEFT JOIN payees p ON o.payee_id = p.id
+         WHERE o.parent_operation_id = $1 AND o.deleted_at IS NULL
          ORDER BY o.id",
     )
     .bind(id)
     .fetch_all(&state.pool)
-    .await
-    .map_err(db_err)?;
+    .await?;
 
     if rows.is_empty() {
         return Ok(Json(Vec::new()));
@@ -671,8 +654,7 @@ pub async fn get_operation_children(
     // Batch fetch hashtags
     let operation_ids: Vec<i32> = rows.iter().map(|op| op.id).collect();
     let all_hashtags = get_operations_hashtags_batch(&state.pool, &operation_ids)
-        .await
-        .map_err(|e| db_err(e))?;
+        .await?;
 
     let result: Vec<OperationWithDetails> = rows
         .into_iter()
@@ -693,6 +675,9 @@ pub async fn get_operation_children(
                 parent_operation_id: op.parent_operation_id,
                 is_split: op.is_split,
                 linked_operation_id: op.linked_operation_id,
+                payee_id: op.payee_id,
+                clearing_status: op.clearing_status,
+                payee_name: op.payee_name,
                 hashtags,
             }
         })
@@ -704,11 +689,10 @@ pub async fn get_operation_children(
 // Classify uncategorized operations as transfers
 pub async fn classify_uncategorized_operations(
     State(state): State<AppState>,
-) -> Result<Json<serde_json::Value>, (axum::http::StatusCode, String)> {
+) -> Result<Json<serde_json::Value>, AppError> {
     let result = sqlx::query("SELECT * FROM classify_uncategorized_as_transfers()")
         .fetch_all(&state.pool)
-        .await
-        .map_err(db_err)?;
+        .await?;
 
     Ok(Json(serde_json::json!({
         "classified_count": result.len(),
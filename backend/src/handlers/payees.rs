@@ -0,0 +1,312 @@
+use crate::{
+    AppState,
+    error::AppError,
+    handlers::{bulk_operations::add_hashtags_to_description, hashtags::is_valid_hashtag},
+    models::*,
+    validation::{ValidJson, Validate, Validator},
+};
+use axum::{
+    Json,
+    extract::{Path, Query, State},
+};
+
+const PAYEE_COLUMNS: &str = "id, name, aliases, default_category_id, default_hashtag, created_date";
+
+// Payee fields and defaults applied to a new operation
+pub(crate) struct PayeeAssignment {
+    pub payee_id: Option<i32>,
+    pub category_id: Option<i32>,
+    pub description: Option<String>,
+}
+
+// Use the given payee or match one from the description, then fill in its
+// default category (unless the operation is split, or the category is of the
+// other type, e.g. a refund from a shop) and default hashtag
+pub(crate) async fn assign_payee<'e, E: sqlx::PgExecutor<'e>>(
+    executor: E,
+    payee_id: Option<i32>,
+    category_id: Option<i32>,
+    description: Option<&str>,
+    operation_type: &str,
+    is_split: bool,
+) -> Result<PayeeAssignment, sqlx::Error> {
+    let payee = sqlx::query_as::<_, Payee>(
+        "SELECT p.id, p.name, p.aliases, c.id AS default_category_id, p.default_hashtag, p.created_date
+         FROM payees p
+         LEFT JOIN categories c ON c.id = p.default_category_id AND c.type::text = $3
+         WHERE p.id = COALESCE($1, match_payee($2))"
+    )
+    .bind(payee_id)
+    .bind(description)
+    .bind(operation_type)
+    .fetch_optional(executor)
+    .await?;
+
+    let Some(payee) = payee else {
+        return Ok(PayeeAssignment {
+            payee_id,
+            category_id,
+            description: description.map(str::to_string),
+        });
+    };
+
+    let category_id = if is_split {
+        category_id
+    } else {
+        category_id.or(payee.default_category_id)
+    };
+    let description = match &payee.default_hashtag {
+        Some(hashtag) => Some(add_hashtags_to_description(description, std::slice::from_ref(hashtag))),
+        None => description.map(str::to_string),
+    };
+
+    Ok(PayeeAssignment {
+        payee_id: Some(payee.id),
+        category_id,
+        description,
+    })
+}
+
+impl Validate for CreatePayee {
+    fn validate(&self, v: &mut Validator) {
+        v.required("name", self.name.trim(), 100);
+        if let Some(hashtag) = &self.default_hashtag {
+            v.check(
+                is_valid_hashtag(&hashtag.trim_start_matches('#').to_lowercase()),
+                "default_hashtag",
+                "Hashtag can only contain alphanumeric characters and underscore, max 50 chars",
+            );
+        }
+    }
+}
+
+impl Validate for MergePayeesRequest {}
+
+fn normalize_payee(payload: &mut CreatePayee) {
+    payload.name = payload.name.trim().to_string();
+    if let Some(hashtag) = &payload.default_hashtag {
+        payload.default_hashtag = Some(hashtag.trim_start_matches('#').to_lowercase());
+    }
+
+    if let Some(aliases) = &payload.aliases {
+        let mut cleaned: Vec<String> = Vec::new();
+        for alias in aliases {
+            let alias = alias.trim();
+            if !alias.is_empty() && !cleaned.iter().any(|a| a.eq_ignore_ascii_case(alias)) {
+                cleaned.push(alias.to_string());
+            }
+        }
+        payload.aliases = Some(cleaned);
+    }
+}
+
+fn unique_violation(e: sqlx::Error) -> AppError {
+    match &e {
+        sqlx::Error::Database(db) if db.is_unique_violation() => AppError::Conflict(
+            "Payee with this name already exists".to_string(),
+        ),
+        _ => AppError::from(e),
+    }
+}
+
+pub async fn list_payees(
+    State(state): State<AppState>,
+) -> Result<Json<Vec<Payee>>, AppError> {
+    let rows = sqlx::query_as::<_, Payee>(&format!(
+        "SELECT {PAYEE_COLUMNS} FROM payees ORDER BY name"
+    ))
+    .fetch_all(&state.pool)
+    .await?;
+    Ok(Json(rows))
+}
+
+pub async fn get_payee(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+) -> Result<Json<Payee>, AppError> {
+    let payee = sqlx::query_as::<_, Payee>(&format!(
+        "SELECT {PAYEE_COLUMNS} FROM payees WHERE id = $1"
+    ))
+    .bind(id)
+    .fetch_optional(&state.pool)
+    .await?
+    .ok_or(AppError::NotFound("Payee not found".to_string()))?;
+    Ok(Json(payee))
+}
+
+pub async fn create_payee(
+    State(state): State<AppState>,
+    ValidJson(mut payload): ValidJson<CreatePayee>,
+) -> Result<Json<Payee>, AppError> {
+    normalize_payee(&mut payload);
+
+    let payee = sqlx::query_as::<_, Payee>(&format!(
+        "INSERT INTO payees (name, aliases, default_category_id, default_hashtag)
+         VALUES ($1, $2, $3, $4)
+         RETURNING {PAYEE_COLUMNS}"
+    ))
+    .bind(&payload.name)
+    .bind(payload.aliases.unwrap_or_default())
+    .bind(payload.default_category_id)
+    .bind(&payload.default_hashtag)
+    .fetch_one(&state.pool)
+    .await
+    .map_err(unique_violation)?;
+
+    Ok(Json(payee))
+}
+
+pub async fn update_payee(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+    ValidJson(mut payload): ValidJson<CreatePayee>,
+) -> Result<Json<Payee>, AppError> {
+    normalize_payee(&mut payload);
+
+    let payee = sqlx::query_as::<_, Payee>(&format!(
+        "UPDATE payees
+         SET name = $1, aliases = COALESCE($2, aliases), default_category_id = $3, default_hashtag = $4
+         WHERE id = $5
+         RETURNING {PAYEE_COLUMNS}"
+    ))
+    .bind(&payload.name)
+    .bind(&payload.aliases)
+    .bind(payload.default_category_id)
+    .bind(&payload.default_hashtag)
+    .bind(id)
+    .fetch_optional(&state.pool)
+    .await
+    .map_err(unique_violation)?
+    .ok_or(AppError::NotFound("Payee not found".to_string()))?;
+
+    Ok(Json(payee))
+}
+
+pub async fn delete_payee(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+) -> Result<(), AppError> {
+    // Operations keep their data, only the payee link is cleared (ON DELETE SET NULL)
+    sqlx::query("DELETE FROM payees WHERE id = $1")
+        .bind(id)
+        .execute(&state.pool)
+        .await?;
+    Ok(())
+}
+
+// Merge source payees into the target: operations are reassigned, names and
+// aliases of the sources become aliases of the target
+pub async fn merge_payees(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+    ValidJson(payload): ValidJson<MergePayeesRequest>,
+) -> Result<Json<Payee>, AppError> {
+    let source_ids: Vec<i32> = payload.source_ids.into_iter().filter(|s| *s != id).collect();
+    if source_ids.is_empty() {
+        return Err(AppError::BadRequest(
+            "At least one source payee other than the target is required".to_string(),
+        ));
+    }
+
+    let mut tx = state.pool.begin().await?;
+
+    let target_exists: bool = sqlx::query_scalar("SELECT EXISTS(SELECT 1 FROM payees WHERE id = $1)")
+        .bind(id)
+        .fetch_one(&mut *tx)
+        .await?;
+    if !target_exists {
+        return Err(AppError::NotFound("Payee not found".to_string()));
+    }
+
+    sqlx::query("UPDATE operations SET payee_id = $1 WHERE payee_id = ANY($2)")
+        .bind(id)
+        .bind(&source_ids)
+        .execute(&mut *tx)
+        .await?;
+
+    // Keep target defaults, fall back to the first source that has one
+    let payee = sqlx::query_as::<_, Payee>(&format!(
+        "UPDATE payees t
+         SET aliases = ARRAY(
+                 SELECT DISTINCT alias FROM (
+                     SELECT unnest(t.aliases) AS alias
+                     UNION
+                     SELECT unnest(array_append(s.aliases, s.name::TEXT))
+                     FROM payees s WHERE s.id = ANY($2)
+                 ) merged
+                 WHERE LOWER(alias) <> LOWER(t.name)
+                 ORDER BY alias
+             ),
+             default_category_id = COALESCE(t.default_category_id,
+                 (SELECT default_category_id FROM payees WHERE id = ANY($2) AND default_category_id IS NOT NULL ORDER BY id LIMIT 1)),
+             default_hashtag = COALESCE(t.default_hashtag,
+                 (SELECT default_hashtag FROM payees WHERE id = ANY($2) AND default_hashtag IS NOT NULL ORDER BY id LIMIT 1))
+         WHERE t.id = $1
+         RETURNING {PAYEE_COLUMNS}"
+    ))
+    .bind(id)
+    .bind(&source_ids)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    sqlx::query("DELETE FROM payees WHERE id = ANY($1)")
+        .bind(&source_ids)
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+
+    Ok(Json(payee))
+}
+
+// Assign payees to existing operations that don't have one yet
+pub async fn rematch_payees(
+    State(state): State<AppState>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let result = sqlx::query(
+        "UPDATE operations SET payee_id = match_payee(description)
+         WHERE payee_id IS NULL AND description IS NOT NULL AND deleted_at IS NULL
+           AND match_payee(description) IS NOT NULL",
+    )
+    .execute(&state.pool)
+    .await?;
+
+    Ok(Json(serde_json::json!({
+        "matched_count": result.rows_affected(),
+        "message": format!("Assigned payees to {} operations", result.rows_affected())
+    })))
+}
+
+// Income and expense totals per payee; operations without a payee are grouped
+// under a null payee_id. Transfers and split children are excluded.
+pub async fn get_payee_report(
+    State(state): State<AppState>,
+    Query(params): Query<PayeeReportQuery>,
+) -> Result<Json<Vec<PayeeReportRow>>, AppError> {
+    let rows = sqlx::query_as::<_, PayeeReportRow>(
+        "SELECT
+            o.payee_id,
+            p.name as payee_name,
+            COUNT(*) as operation_count,
+            COALESCE(SUM(o.amount) FILTER (WHERE o.amount > 0), 0) as income,
+            COALESCE(SUM(o.amount) FILTER (WHERE o.amount < 0), 0) as expense,
+            COALESCE(SUM(o.amount), 0) as net
+         FROM operations o
+         LEFT JOIN payees p ON o.payee_id = p.id
+         WHERE o.parent_operation_id IS NULL
+           AND o.linked_operation_id IS NULL
+           AND o.deleted_at IS NULL
+           AND ($1::date IS NULL OR o.operation_date >= $1)
+           AND ($2::date IS NULL OR o.operation_date <= $2)
+           AND ($3::int IS NULL OR o.asset_id = $3)
+         GROUP BY o.payee_id, p.name
+         ORDER BY expense ASC, p.name",
+    )
+    .bind(params.date_from)
+    .bind(params.date_to)
+    .bind(params.asset_id)
+    .fetch_all(&state.pool)
+    .await?;
+
+    Ok(Json(rows))
+}
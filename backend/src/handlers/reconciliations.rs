@@ -0,0 +1,269 @@
+use crate::{AppState, error::AppError, models::*, money::BALANCE_DIGITS, validation::{ValidJson, Validate, Validator}};
+use axum::{
+    Json,
+    extract::{Path, State},
+};
+
+impl Validate for CreateReconciliation {
+    fn validate(&self, v: &mut Validator) {
+        v.amount("statement_balance", &self.statement_balance, BALANCE_DIGITS);
+    }
+}
+impl Validate for MarkClearedRequest {}
+
+const RECONCILIATION_COLUMNS: &str =
+    "id, asset_id, statement_date, statement_balance, status, created_date, finished_date";
+
+async fn fetch_reconciliation(
+    conn: &mut sqlx::PgConnection,
+    id: i32,
+) -> Result<Reconciliation, AppError> {
+    sqlx::query_as::<_, Reconciliation>(&format!(
+        "SELECT {RECONCILIATION_COLUMNS} FROM reconciliations WHERE id = $1 FOR UPDATE"
+    ))
+    .bind(id)
+    .fetch_optional(conn)
+    .await?
+    .ok_or(AppError::NotFound("Reconciliation not found".to_string()))
+}
+
+fn ensure_open(reconciliation: &Reconciliation) -> Result<(), AppError> {
+    if reconciliation.status != "open" {
+        return Err(AppError::Conflict("Reconciliation is already finished".to_string()));
+    }
+    Ok(())
+}
+
+// Compare the statement with top-level operations already cleared or reconciled
+async fn summarize(
+    conn: &mut sqlx::PgConnection,
+    reconciliation: Reconciliation,
+) -> Result<ReconciliationSummary, AppError> {
+    let (cleared_balance, cleared_count, uncleared_count): (Money, i64, i64) = sqlx::query_as(
+        "SELECT
+            COALESCE(SUM(amount) FILTER (WHERE clearing_status <> 'uncleared'), 0),
+            COUNT(*) FILTER (WHERE clearing_status = 'cleared'),
+            COUNT(*) FILTER (WHERE clearing_status = 'uncleared' AND operation_date <= $2)
+         FROM operations
+         WHERE asset_id = $1 AND parent_operation_id IS NULL AND deleted_at IS NULL",
+    )
+    .bind(reconciliation.asset_id)
+    .bind(reconciliation.statement_date)
+    .fetch_one(conn)
+    .await?;
+
+    let difference = &reconciliation.statement_balance - &cleared_balance;
+    Ok(ReconciliationSummary {
+        reconciliation,
+        cleared_balance,
+        difference,
+        cleared_count,
+        uncleared_count,
+    })
+}
+
+pub async fn create_reconciliation(
+    State(state): State<AppState>,
+    Path(asset_id): Path<i32>,
+    ValidJson(payload): ValidJson<CreateReconciliation>,
+) -> Result<Json<ReconciliationSummary>, AppError> {
+    let allows_operations: Option<bool> = sqlx::query_scalar(
+        "SELECT at.allows_operations FROM assets a
+         INNER JOIN asset_types at ON a.asset_type_id = at.id
+         WHERE a.id = $1 AND a.deleted_at IS NULL",
+    )
+    .bind(asset_id)
+    .fetch_optional(&state.pool)
+    .await?;
+
+    match allows_operations {
+        Some(true) => {}
+        Some(false) => {
+            return Err(AppError::BadRequest("Asset does not have operations to reconcile".to_string()));
+        }
+        None => {
+            return Err(AppError::NotFound("Asset not found".to_string()));
+        }
+    }
+
+    let mut conn = state.pool.acquire().await?;
+    let reconciliation = sqlx::query_as::<_, Reconciliation>(&format!(
+        "INSERT INTO reconciliations (asset_id, statement_date, statement_balance)
+         VALUES ($1, $2, $3)
+         RETURNING {RECONCILIATION_COLUMNS}"
+    ))
+    .bind(asset_id)
+    .bind(payload.statement_date)
+    .bind(&payload.statement_balance)
+    .fetch_one(&mut *conn)
+    .await
+    .map_err(|e| match &e {
+        sqlx::Error::Database(db) if db.is_unique_violation() => AppError::Conflict(
+            "This asset already has an open reconciliation".to_string(),
+        ),
+        _ => AppError::from(e),
+    })?;
+
+    Ok(Json(summarize(&mut conn, reconciliation).await?))
+}
+
+pub async fn list_reconciliations(
+    State(state): State<AppState>,
+    Path(asset_id): Path<i32>,
+) -> Result<Json<Vec<Reconciliation>>, AppError> {
+    let rows = sqlx::query_as::<_, Reconciliation>(&format!(
+        "SELECT {RECONCILIATION_COLUMNS} FROM reconciliations
+         WHERE asset_id = $1
+         ORDER BY statement_date DESC, id DESC"
+    ))
+    .bind(asset_id)
+    .fetch_all(&state.pool)
+    .await?;
+    Ok(Json(rows))
+}
+
+pub async fn get_reconciliation(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+) -> Result<Json<ReconciliationSummary>, AppError> {
+    let mut tx = state.pool.begin().await?;
+    let reconciliation = fetch_reconciliation(&mut tx, id).await?;
+    let summary = summarize(&mut tx, reconciliation).await?;
+    tx.commit().await?;
+    Ok(Json(summary))
+}
+
+// Top-level operations of the asset that are not reconciled yet, up to the statement date
+pub async fn list_reconciliation_operations(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+) -> Result<Json<Vec<Operation>>, AppError> {
+    let rows = sqlx::query_as::<_, Operation>(
+        "SELECT o.id, o.creation_date, o.category_id, o.description, o.asset_id, o.amount, o.operation_type::text, o.operation_date,
+                o.parent_operation_id, o.is_split, o.linked_operation_id, o.payee_id, o.clearing_status::text
+         FROM operations o
+         INNER JOIN reconciliations r ON r.asset_id = o.asset_id
+         WHERE r.id = $1
+           AND o.parent_operation_id IS NULL
+           AND o.deleted_at IS NULL
+           AND o.clearing_status <> 'reconciled'
+           AND o.operation_date <= r.statement_date
+         ORDER BY o.operation_date, o.id",
+    )
+    .bind(id)
+    .fetch_all(&state.pool)
+    .await?;
+    Ok(Json(rows))
+}
+
+// Mark operations as cleared (seen on the statement) or back to uncleared.
+// Split children follow their parent.
+pub async fn mark_reconciliation_operations(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+    ValidJson(payload): ValidJson<MarkClearedRequest>,
+) -> Result<Json<ReconciliationSummary>, AppError> {
+    let mut tx = state.pool.begin().await?;
+    let reconciliation = fetch_reconciliation(&mut tx, id).await?;
+    ensure_open(&reconciliation)?;
+
+    let mut requested = payload.operation_ids.clone();
+    requested.sort_unstable();
+    requested.dedup();
+
+    let valid: Vec<i32> = sqlx::query_scalar(
+        "SELECT id FROM operations
+         WHERE id = ANY($1) AND asset_id = $2 AND parent_operation_id IS NULL AND deleted_at IS NULL
+           AND clearing_status <> 'reconciled'
+         ORDER BY id",
+    )
+    .bind(&requested)
+    .bind(reconciliation.asset_id)
+    .fetch_all(&mut *tx)
+    .await?;
+
+    if valid.len() != requested.len() {
+        let invalid: Vec<String> = requested
+            .iter()
+            .filter(|id| !valid.contains(id))
+            .map(|id| id.to_string())
+            .collect();
+        return Err(AppError::BadRequest(format!(
+                "Operations {} don't belong to this asset, are split children or are already reconciled",
+                invalid.join(", ")
+            )));
+    }
+
+    let status = if payload.cleared { "cleared" } else { "uncleared" };
+    sqlx::query(
+        "UPDATE operations SET clearing_status = $1::clearing_status
+         WHERE id = ANY($2) OR parent_operation_id = ANY($2)",
+    )
+    .bind(status)
+    .bind(&valid)
+    .execute(&mut *tx)
+    .await?;
+
+    let summary = summarize(&mut tx, reconciliation).await?;
+    tx.commit().await?;
+    Ok(Json(summary))
+}
+
+// Finish when cleared operations add up to the statement balance. Cleared
+// operations become reconciled and locked against changes.
+pub async fn finish_reconciliation(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+) -> Result<Json<ReconciliationSummary>, AppError> {
+    let mut tx = state.pool.begin().await?;
+    let reconciliation = fetch_reconciliation(&mut tx, id).await?;
+    ensure_open(&reconciliation)?;
+
+    let summary = summarize(&mut tx, reconciliation).await?;
+    if !summary.difference.is_zero() {
+        return Err(AppError::Conflict(format!(
+                "Cleared balance {} differs from statement balance {} by {}",
+                summary.cleared_balance, summary.reconciliation.statement_balance, summary.difference
+            )));
+    }
+
+    sqlx::query(
+        "UPDATE operations SET clearing_status = 'reconciled', reconciliation_id = $1
+         WHERE asset_id = $2 AND clearing_status = 'cleared' AND deleted_at IS NULL",
+    )
+    .bind(id)
+    .bind(summary.reconciliation.asset_id)
+    .execute(&mut *tx)
+    .await?;
+
+    let reconciliation = sqlx::query_as::<_, Reconciliation>(&format!(
+        "UPDATE reconciliations SET status = 'finished', finished_date = CURRENT_TIMESTAMP
+         WHERE id = $1
+         RETURNING {RECONCILIATION_COLUMNS}"
+    ))
+    .bind(id)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    let summary = summarize(&mut tx, reconciliation).await?;
+    tx.commit().await?;
+    Ok(Json(summary))
+}
+
+// Cancel an open session; cleared marks on operations are kept
+pub async fn delete_reconciliation(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+) -> Result<(), AppError> {
+    let mut tx = state.pool.begin().await?;
+    let reconciliation = fetch_reconciliation(&mut tx, id).await?;
+    ensure_open(&reconciliation)?;
+
+    sqlx::query("DELETE FROM reconciliations WHERE id = $1")
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+    Ok(())
+}
@@ -1,19 +1,38 @@
 use axum::{extract::{State, Path}, Json};
-use crate::{AppState, models::*, utils::db_err};
+use crate::{
+    AppState, balances,
+    error::AppError,
+    handlers::operation_rules::check_operation,
+    models::*,
+    money::AMOUNT_DIGITS,
+    recurrence,
+    validation::{ValidJson, Validate, Validator},
+};
+use std::collections::BTreeSet;
+
+impl Validate for CreateRecurringOperation {
+    fn validate(&self, v: &mut Validator) {
+        v.check(
+            self.end_date.is_none_or(|end| end >= self.start_date),
+            "end_date",
+            "end_date can't be before start_date",
+        );
+        v.amount("amount", &self.amount, AMOUNT_DIGITS);
+    }
+}
+
+impl Validate for UpdateRecurringOperation {
+    fn validate(&self, v: &mut Validator) {
+        v.optional_amount("amount", self.amount.as_ref(), AMOUNT_DIGITS);
+    }
+}
 
 pub async fn create_recurring_operation(
     State(state): State<AppState>,
-    Json(payload): Json<CreateRecurringOperation>,
-) -> Result<Json<RecurringOperation>, (axum::http::StatusCode, String)> {
-    let start_date = chrono::NaiveDate::parse_from_str(&payload.start_date, "%Y-%m-%d")
-        .map_err(|_| (axum::http::StatusCode::BAD_REQUEST, "Invalid start_date format".to_string()))?;
-    
-    let end_date = if let Some(ed) = payload.end_date {
-        Some(chrono::NaiveDate::parse_from_str(&ed, "%Y-%m-%d")
-            .map_err(|_| (axum::http::StatusCode::BAD_REQUEST, "Invalid end_date format".to_string()))?)
-    } else {
-        None
-    };
+    ValidJson(payload): ValidJson<CreateRecurringOperation>,
+) -> Result<Json<RecurringOperation>, AppError> {
+    let mut conn = state.pool.acquire().await?;
+    check_operation(&mut conn, payload.operation_type.as_str(), &payload.amount, payload.category_id, None).await?;
 
     let recurring_op = sqlx::query_as::<_, RecurringOperation>(
         "INSERT INTO recurring_operations (asset_id, category_id, description, amount, operation_type, frequency, start_date, end_date, is_active)
@@ -24,18 +43,18 @@ pub async fn create_recurring_operation(
     .bind(payload.category_id)
     .bind(&payload.description)
     .bind(payload.amount)
-    .bind(&payload.operation_type)
-    .bind(&payload.frequency)
-    .bind(start_date)
-    .bind(end_date)
-    .fetch_one(&state.pool)
-    .await
-    .map_err(db_err)?;
+    .bind(payload.operation_type.as_str())
+    .bind(payload.frequency.as_str())
+    .bind(payload.start_date)
+    .bind(payload.end_date)
+    .fetch_one(&mut *conn)
+    .await?;
 
+    crate::notifications::check_after_write(&state);
     Ok(Json(recurring_op))
 }
 
-pub async fn list_recurring_operations(State(state): State<AppState>) -> Result<Json<Vec<RecurringOperationWithDetails>>, (axum::http::StatusCode, String)> {
+pub async fn list_recurring_operations(State(state): State<AppState>) -> Result<Json<Vec<RecurringOperationWithDetails>>, AppError> {
     #[derive(sqlx::FromRow)]
     struct RecurringOpRow {
         id: i32,
@@ -44,7 +63,7 @@ pub async fn list_recurring_operations(State(state): State<AppState>) -> Result<
         category_id: Option<i32>,
         category_name: Option<String>,
         description: Option<String>,
-        amount: bigdecimal::BigDecimal,
+        amount: Money,
         operation_type: String,
         frequency: String,
         start_date: chrono::NaiveDate,
@@ -73,9 +92,9 @@ pub async fn list_recurring_operations(State(state): State<AppState>) -> Result<
          FROM recurring_operations ro
          INNER JOIN assets a ON ro.asset_id = a.id
          LEFT JOIN categories c ON ro.category_id = c.id
-         WHERE ro.is_active = TRUE
+         WHERE ro.is_active = TRUE AND a.deleted_at IS NULL
          ORDER BY ro.start_date DESC"
-    ).fetch_all(&state.pool).await.map_err(db_err)?;
+    ).fetch_all(&state.pool).await?;
     
     let result: Vec<RecurringOperationWithDetails> = rows.into_iter().map(|row| {
         RecurringOperationWithDetails {
@@ -99,26 +118,38 @@ pub async fn list_recurring_operations(State(state): State<AppState>) -> Result<
     Ok(Json(result))
 }
 
-pub async fn get_recurring_operation(State(state): State<AppState>, Path(id): Path<i32>) -> Result<Json<RecurringOperation>, (axum::http::StatusCode, String)> {
+pub async fn get_recurring_operation(State(state): State<AppState>, Path(id): Path<i32>) -> Result<Json<RecurringOperation>, AppError> {
     let row = sqlx::query_as::<_, RecurringOperation>(
         "SELECT id, asset_id, category_id, description, amount, operation_type::text, frequency::text, start_date, end_date, is_active, creation_date, last_generated
          FROM recurring_operations
          WHERE id = $1"
-    ).bind(id).fetch_one(&state.pool).await.map_err(db_err)?;
+    ).bind(id).fetch_one(&state.pool).await?;
     Ok(Json(row))
 }
 
 pub async fn update_recurring_operation(
     State(state): State<AppState>,
     Path(id): Path<i32>,
-    Json(payload): Json<UpdateRecurringOperation>,
-) -> Result<Json<RecurringOperation>, (axum::http::StatusCode, String)> {
-    let end_date = if let Some(ed) = payload.end_date {
-        Some(chrono::NaiveDate::parse_from_str(&ed, "%Y-%m-%d")
-            .map_err(|_| (axum::http::StatusCode::BAD_REQUEST, "Invalid end_date format".to_string()))?)
-    } else {
-        None
-    };
+    ValidJson(payload): ValidJson<UpdateRecurringOperation>,
+) -> Result<Json<RecurringOperation>, AppError> {
+    let mut tx = state.pool.begin().await?;
+
+    // Check the template as it will be after the update
+    let (operation_type, amount, current_category_id): (String, Money, Option<i32>) = sqlx::query_as(
+        "SELECT operation_type::text, amount, category_id FROM recurring_operations WHERE id = $1 FOR UPDATE"
+    )
+    .bind(id)
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or(AppError::NotFound("Recurring operation not found".to_string()))?;
+    check_operation(
+        &mut tx,
+        &operation_type,
+        payload.amount.as_ref().unwrap_or(&amount),
+        payload.category_id.or(current_category_id),
+        current_category_id,
+    )
+    .await?;
 
     let recurring_op = sqlx::query_as::<_, RecurringOperation>(
         "UPDATE recurring_operations 
@@ -133,17 +164,67 @@ pub async fn update_recurring_operation(
     .bind(&payload.description)
     .bind(payload.amount)
     .bind(payload.category_id)
-    .bind(end_date)
+    .bind(payload.end_date)
     .bind(payload.is_active)
     .bind(id)
-    .fetch_one(&state.pool)
-    .await
-    .map_err(db_err)?;
+    .fetch_one(&mut *tx)
+    .await?;
 
+    tx.commit().await?;
+    crate::notifications::check_after_write(&state);
     Ok(Json(recurring_op))
 }
 
-pub async fn delete_recurring_operation(State(state): State<AppState>, Path(id): Path<i32>) -> Result<(), (axum::http::StatusCode, String)> {
-    sqlx::query("DELETE FROM recurring_operations WHERE id = $1").bind(id).execute(&state.pool).await.map_err(db_err)?;
+pub async fn delete_recurring_operation(State(state): State<AppState>, Path(id): Path<i32>) -> Result<(), AppError> {
+    sqlx::query("DELETE FROM recurring_operations WHERE id = $1").bind(id).execute(&state.pool).await?;
     Ok(())
 }
+
+// Create the operations of active templates that fell due up to `today` since
+// they were last generated. Returns how many operations were created.
+pub async fn generate_recurring_operations(pool: &sqlx::PgPool, today: chrono::NaiveDate) -> Result<usize, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    let templates = sqlx::query_as::<_, RecurringOperation>(
+        "SELECT id, asset_id, category_id, description, amount, operation_type::text, frequency::text, start_date, end_date, is_active, creation_date, last_generated
+         FROM recurring_operations
+         WHERE is_active IS NOT FALSE AND start_date <= $1 AND (last_generated IS NULL OR last_generated < $1)
+           AND asset_id NOT IN (SELECT id FROM assets WHERE deleted_at IS NOT NULL)
+         ORDER BY id
+         FOR UPDATE SKIP LOCKED"
+    )
+    .bind(today)
+    .fetch_all(&mut *tx)
+    .await?;
+
+    let mut created = 0;
+    let mut asset_ids = BTreeSet::new();
+    for template in &templates {
+        let from = template.last_generated.map_or(template.start_date, |d| d + chrono::Duration::days(1));
+        for date in recurrence::occurrences(&template.frequency, template.start_date, template.end_date, from, today) {
+            sqlx::query(
+                "INSERT INTO operations (category_id, description, asset_id, amount, operation_type, operation_date)
+                 VALUES ($1, $2, $3, $4, $5::operation_type, $6)"
+            )
+            .bind(template.category_id)
+            .bind(&template.description)
+            .bind(template.asset_id)
+            .bind(&template.amount)
+            .bind(&template.operation_type)
+            .bind(date)
+            .execute(&mut *tx)
+            .await?;
+            created += 1;
+            asset_ids.insert(template.asset_id);
+        }
+        sqlx::query("UPDATE recurring_operations SET last_generated = $1 WHERE id = $2")
+            .bind(today)
+            .bind(template.id)
+            .execute(&mut *tx)
+            .await?;
+    }
+
+    let asset_ids: Vec<i32> = asset_ids.into_iter().collect();
+    balances::recalculate(&mut tx, &asset_ids).await?;
+    tx.commit().await?;
+    Ok(created)
+}
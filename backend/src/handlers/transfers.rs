@@ -1,24 +1,62 @@
 use axum::{extract::State, Json};
-use crate::{AppState, models::*, utils::db_err};
+use crate::{AppState, balances, error::AppError, models::*, money::AMOUNT_DIGITS, validation::{ValidJson, Validate, Validator}};
 use crate::handlers::categories::ensure_debt_categories;
-use bigdecimal::{BigDecimal, FromPrimitive};
+
+impl Validate for NewAssetData {
+    fn validate(&self, v: &mut Validator) {
+        v.required("name", &self.name, 255);
+        v.optional_max_length("account_number", self.account_number.as_deref(), 50);
+        if let Some(currency) = &self.currency {
+            v.currency("currency", currency);
+        }
+    }
+}
+
+impl Validate for TransferRequest {
+    fn validate(&self, v: &mut Validator) {
+        v.check(self.amount.is_positive(), "amount", "amount must be positive");
+        v.amount("amount", &self.amount, AMOUNT_DIGITS);
+        v.optional_amount("interest_amount", self.interest_amount.as_ref(), AMOUNT_DIGITS);
+        match self.transfer_type {
+            TransferType::LiquidToLiquid | TransferType::LiquidToLiability => {
+                v.check(self.to_asset_id.is_some(), "to_asset_id", format!("to_asset_id is required for {}", self.transfer_type));
+            }
+            TransferType::LiquidToInvestment => {
+                v.check(
+                    self.to_asset_id.is_some() || self.new_asset.is_some(),
+                    "new_asset",
+                    "new_asset is required when to_asset_id is null",
+                );
+                v.check(
+                    self.investment_quantity.as_ref().is_some_and(Quantity::is_positive),
+                    "investment_quantity",
+                    "investment_quantity must be positive",
+                );
+            }
+            TransferType::LiquidToProperty | TransferType::LiquidToVehicle | TransferType::LiquidToValuable => {
+                v.check(self.new_asset.is_some(), "new_asset", format!("new_asset is required for {}", self.transfer_type));
+            }
+        }
+        v.nested("new_asset", &self.new_asset);
+    }
+}
 
 pub async fn transfer_operation(
     State(state): State<AppState>,
-    Json(payload): Json<TransferRequest>,
-) -> Result<Json<TransferResponse>, (axum::http::StatusCode, String)> {
+    ValidJson(payload): ValidJson<TransferRequest>,
+) -> Result<Json<TransferResponse>, AppError> {
     // Start transaction
-    let mut tx = state.pool.begin().await.map_err(db_err)?;
+    let mut tx = state.pool.begin().await?;
 
     // Verify source asset exists
     let from_asset = sqlx::query_as::<_, Asset>(
         "SELECT id, user_id, asset_type_id, name, description, account_number, quantity, average_purchase_price, current_valuation, currency, is_active, created_date, sort_order 
-         FROM assets WHERE id = $1"
+         FROM assets WHERE id = $1 AND deleted_at IS NULL"
     )
     .bind(payload.from_asset_id)
-    .fetch_one(&mut *tx)
-    .await
-    .map_err(|e| (axum::http::StatusCode::INTERNAL_SERVER_ERROR, format!("Error fetching from_asset: {}", e)))?;
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or_else(|| AppError::NotFound("Source asset not found".to_string()))?;
 
     let mut response = TransferResponse {
         success: false,
@@ -28,18 +66,22 @@ pub async fn transfer_operation(
         investment_transaction_id: None,
         interest_operation_id: None,
     };
+    // Assets that received operations and need their balance recalculated
+    let mut touched_asset_ids = vec![payload.from_asset_id];
 
-    match payload.transfer_type.as_str() {
-        "liquid_to_liquid" => {
+    match payload.transfer_type {
+        TransferType::LiquidToLiquid => {
             // Verify destination asset exists
             let to_asset_id = payload.to_asset_id
-                .ok_or_else(|| (axum::http::StatusCode::BAD_REQUEST, "to_asset_id required for liquid_to_liquid".to_string()))?;
+                .ok_or_else(|| AppError::BadRequest(
+                    "to_asset_id required for liquid_to_liquid".to_string(),
+                ))?;
             
-            sqlx::query("SELECT id FROM assets WHERE id = $1")
+            sqlx::query("SELECT id FROM assets WHERE id = $1 AND deleted_at IS NULL")
                 .bind(to_asset_id)
                 .fetch_one(&mut *tx)
                 .await
-                .map_err(|_| (axum::http::StatusCode::NOT_FOUND, "Destination asset not found".to_string()))?;
+                .map_err(|_| AppError::NotFound("Destination asset not found".to_string()))?;
 
             // Get Transfer category IDs
             let (outgoing_category_id, incoming_category_id): (Option<i32>, Option<i32>) = sqlx::query_as(
@@ -50,25 +92,23 @@ pub async fn transfer_operation(
                  WHERE c_parent.name = 'Transfer' AND c_parent.parent_id IS NULL"
             )
             .fetch_optional(&mut *tx)
-            .await
-            .map_err(db_err)?
+            .await?
             .unwrap_or((None, None));
 
             // Create outgoing operation
             let from_op = sqlx::query_as::<_, Operation>(
                 "INSERT INTO operations (category_id, description, asset_id, amount, operation_type, operation_date)
                  VALUES ($1, $2, $3, $4, $5::operation_type, $6::date)
-                 RETURNING id, creation_date, category_id, description, asset_id, amount, operation_type::text, operation_date, parent_operation_id, is_split, linked_operation_id"
+                 RETURNING id, creation_date, category_id, description, asset_id, amount, operation_type::text, operation_date, parent_operation_id, is_split, linked_operation_id, payee_id, clearing_status::text"
             )
             .bind(outgoing_category_id)
             .bind(payload.description.as_ref().unwrap_or(&format!("Przelew do aktywa #{}", to_asset_id)))
             .bind(payload.from_asset_id)
-            .bind(-payload.amount.clone())
+            .bind(-&payload.amount)
             .bind("expense")
-            .bind(&payload.operation_date)
+            .bind(payload.operation_date)
             .fetch_one(&mut *tx)
-            .await
-            .map_err(|e| (axum::http::StatusCode::INTERNAL_SERVER_ERROR, format!("Error creating from_op: {}", e)))?;
+            .await?;
 
             response.from_operation_id = Some(from_op.id);
 
@@ -76,54 +116,54 @@ pub async fn transfer_operation(
             let to_op = sqlx::query_as::<_, Operation>(
                 "INSERT INTO operations (category_id, description, asset_id, amount, operation_type, operation_date, linked_operation_id)
                  VALUES ($1, $2, $3, $4, $5::operation_type, $6::date, $7)
-                 RETURNING id, creation_date, category_id, description, asset_id, amount, operation_type::text, operation_date, parent_operation_id, is_split, linked_operation_id"
+                 RETURNING id, creation_date, category_id, description, asset_id, amount, operation_type::text, operation_date, parent_operation_id, is_split, linked_operation_id, payee_id, clearing_status::text"
             )
             .bind(incoming_category_id)
             .bind(payload.description.as_ref().unwrap_or(&format!("Przelew z aktywa #{}", payload.from_asset_id)))
             .bind(to_asset_id)
-            .bind(payload.amount.clone())
+            .bind(&payload.amount)
             .bind("income")
-            .bind(&payload.operation_date)
+            .bind(payload.operation_date)
             .bind(from_op.id)
             .fetch_one(&mut *tx)
-            .await
-            .map_err(db_err)?;
+            .await?;
 
             response.to_operation_id = Some(to_op.id);
+            touched_asset_ids.push(to_asset_id);
 
             // Update outgoing operation to link to incoming
             sqlx::query("UPDATE operations SET linked_operation_id = $1 WHERE id = $2")
                 .bind(to_op.id)
                 .bind(from_op.id)
                 .execute(&mut *tx)
-                .await
-                .map_err(db_err)?;
+                .await?;
         },
 
-        "liquid_to_investment" => {
+        TransferType::LiquidToInvestment => {
             // Check if we're adding to existing or creating new
             if let Some(to_asset_id) = payload.to_asset_id {
                 // Adding to existing investment
                 let existing_asset = sqlx::query_as::<_, Asset>(
                     "SELECT id, user_id, asset_type_id, name, description, account_number, quantity, average_purchase_price, current_valuation, currency, is_active, created_date, sort_order 
-                     FROM assets WHERE id = $1"
+                     FROM assets WHERE id = $1 AND deleted_at IS NULL"
                 )
                 .bind(to_asset_id)
                 .fetch_one(&mut *tx)
                 .await
-                .map_err(|_| (axum::http::StatusCode::NOT_FOUND, "Destination investment asset not found".to_string()))?;
-
-                let quantity = payload.investment_quantity
-                    .ok_or_else(|| (axum::http::StatusCode::BAD_REQUEST, "investment_quantity required".to_string()))?;
+                .map_err(|_| AppError::NotFound("Destination investment asset not found".to_string()))?;
 
-                let quantity_bd = BigDecimal::from_f64(quantity).unwrap_or_else(|| BigDecimal::from(0));
-                let price_per_unit = payload.amount.clone() / quantity_bd.clone();
+                let quantity_bd = payload.investment_quantity.clone()
+                    .ok_or_else(|| AppError::BadRequest("investment_quantity required".to_string()))?;
+                let price_per_unit = payload.amount.per_unit(&quantity_bd, &existing_asset.currency)
+                    .ok_or_else(|| AppError::BadRequest("investment_quantity can't be zero".to_string()))?;
 
-                // Calculate new average price
-                let old_quantity = existing_asset.quantity.clone().unwrap_or_else(|| BigDecimal::from(0));
-                let old_avg_price = existing_asset.average_purchase_price.clone().unwrap_or_else(|| BigDecimal::from(0));
+                // Calculate new average price from what was paid in total
+                let old_quantity = existing_asset.quantity.clone().unwrap_or_else(Quantity::zero);
+                let old_avg_price = existing_asset.average_purchase_price.clone().unwrap_or_else(Money::zero);
                 let new_quantity = &old_quantity + &quantity_bd;
-                let new_avg_price = ((&old_quantity * &old_avg_price) + (&quantity_bd * &price_per_unit)) / &new_quantity;
+                let new_avg_price = (old_avg_price.times(&old_quantity) + &payload.amount)
+                    .per_unit(&new_quantity, &existing_asset.currency)
+                    .unwrap_or_else(Money::zero);
 
                 // Update asset
                 sqlx::query(
@@ -133,8 +173,7 @@ pub async fn transfer_operation(
                 .bind(new_avg_price)
                 .bind(to_asset_id)
                 .execute(&mut *tx)
-                .await
-                .map_err(db_err)?;
+                .await?;
 
                 // Create investment transaction
                 let inv_tx = sqlx::query_as::<_, InvestmentTransaction>(
@@ -145,11 +184,10 @@ pub async fn transfer_operation(
                 .bind(to_asset_id)
                 .bind(quantity_bd)
                 .bind(price_per_unit)
-                .bind(payload.amount.clone())
-                .bind(&payload.operation_date)
+                .bind(&payload.amount)
+                .bind(payload.operation_date)
                 .fetch_one(&mut *tx)
-                .await
-                .map_err(db_err)?;
+                .await?;
 
                 response.investment_transaction_id = Some(inv_tx.id);
 
@@ -157,33 +195,34 @@ pub async fn transfer_operation(
                 let from_op = sqlx::query_as::<_, Operation>(
                     "INSERT INTO operations (asset_id, amount, operation_type, operation_date, description)
                      VALUES ($1, $2, 'expense'::operation_type, $3::date, $4)
-                     RETURNING id, creation_date, category_id, description, asset_id, amount, operation_type::text, operation_date, parent_operation_id, is_split, linked_operation_id"
+                     RETURNING id, creation_date, category_id, description, asset_id, amount, operation_type::text, operation_date, parent_operation_id, is_split, linked_operation_id, payee_id, clearing_status::text"
                 )
                 .bind(payload.from_asset_id)
-                .bind(-payload.amount.clone())
-                .bind(&payload.operation_date)
+                .bind(-&payload.amount)
+                .bind(payload.operation_date)
                 .bind(payload.description.as_ref().unwrap_or(&format!("Zakup inwestycji #{}", to_asset_id)))
                 .fetch_one(&mut *tx)
-                .await
-                .map_err(db_err)?;
+                .await?;
 
                 response.from_operation_id = Some(from_op.id);
 
             } else {
                 // Creating new investment asset
                 let new_asset_data = payload.new_asset
-                    .ok_or_else(|| (axum::http::StatusCode::BAD_REQUEST, "new_asset required when to_asset_id is null".to_string()))?;
+                    .ok_or_else(|| AppError::BadRequest(
+                        "new_asset required when to_asset_id is null".to_string(),
+                    ))?;
 
-                let quantity = payload.investment_quantity
-                    .ok_or_else(|| (axum::http::StatusCode::BAD_REQUEST, "investment_quantity required".to_string()))?;
-
-                let quantity_bd = BigDecimal::from_f64(quantity).unwrap_or_else(|| BigDecimal::from(0));
-                let price_per_unit = payload.amount.clone() / quantity_bd.clone();
+                let quantity_bd = payload.investment_quantity.clone()
+                    .ok_or_else(|| AppError::BadRequest("investment_quantity required".to_string()))?;
+                let currency = new_asset_data.currency.clone().unwrap_or_else(|| "PLN".to_string());
+                let price_per_unit = payload.amount.per_unit(&quantity_bd, &currency)
+                    .ok_or_else(|| AppError::BadRequest("investment_quantity can't be zero".to_string()))?;
 
                 // Create new asset
                 let new_asset = sqlx::query_as::<_, Asset>(
-                    "INSERT INTO assets (user_id, asset_type_id, name, description, account_number, quantity, average_purchase_price, currency)
-                     VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+                    "INSERT INTO assets (user_id, asset_type_id, name, description, account_number, quantity, average_purchase_price, currency, sort_order)
+                     VALUES ($1, $2, $3, $4, $5, $6, $7, $8, (SELECT COALESCE(MAX(sort_order), 0) + 1 FROM assets))
                      RETURNING id, user_id, asset_type_id, name, description, account_number, quantity, average_purchase_price, current_valuation, currency, is_active, created_date, sort_order"
                 )
                 .bind(from_asset.user_id)
@@ -193,10 +232,9 @@ pub async fn transfer_operation(
                 .bind(&new_asset_data.account_number)
                 .bind(&quantity_bd)
                 .bind(&price_per_unit)
-                .bind(new_asset_data.currency.as_ref().unwrap_or(&"PLN".to_string()))
+                .bind(&currency)
                 .fetch_one(&mut *tx)
-                .await
-                .map_err(db_err)?;
+                .await?;
 
                 response.new_asset_id = Some(new_asset.id);
 
@@ -209,11 +247,10 @@ pub async fn transfer_operation(
                 .bind(new_asset.id)
                 .bind(&quantity_bd)
                 .bind(&price_per_unit)
-                .bind(payload.amount.clone())
-                .bind(&payload.operation_date)
+                .bind(&payload.amount)
+                .bind(payload.operation_date)
                 .fetch_one(&mut *tx)
-                .await
-                .map_err(db_err)?;
+                .await?;
 
                 response.investment_transaction_id = Some(inv_tx.id);
 
@@ -221,29 +258,30 @@ pub async fn transfer_operation(
                 let from_op = sqlx::query_as::<_, Operation>(
                     "INSERT INTO operations (asset_id, amount, operation_type, operation_date, description)
                      VALUES ($1, $2, 'expense'::operation_type, $3::date, $4)
-                     RETURNING id, creation_date, category_id, description, asset_id, amount, operation_type::text, operation_date, parent_operation_id, is_split, linked_operation_id"
+                     RETURNING id, creation_date, category_id, description, asset_id, amount, operation_type::text, operation_date, parent_operation_id, is_split, linked_operation_id, payee_id, clearing_status::text"
                 )
                 .bind(payload.from_asset_id)
-                .bind(-payload.amount.clone())
-                .bind(&payload.operation_date)
+                .bind(-&payload.amount)
+                .bind(payload.operation_date)
                 .bind(payload.description.as_ref().unwrap_or(&format!("Zakup inwestycji: {}", new_asset.name)))
                 .fetch_one(&mut *tx)
-                .await
-                .map_err(db_err)?;
+                .await?;
 
                 response.from_operation_id = Some(from_op.id);
             }
         },
 
-        "liquid_to_property" | "liquid_to_vehicle" | "liquid_to_valuable" => {
+        TransferType::LiquidToProperty | TransferType::LiquidToVehicle | TransferType::LiquidToValuable => {
             // Creating new non-investment asset
             let new_asset_data = payload.new_asset
-                .ok_or_else(|| (axum::http::StatusCode::BAD_REQUEST, "new_asset required for this transfer type".to_string()))?;
+                .ok_or_else(|| AppError::BadRequest(
+                    "new_asset required for this transfer type".to_string(),
+                ))?;
 
             // Create new asset with valuation
             let new_asset = sqlx::query_as::<_, Asset>(
-                "INSERT INTO assets (user_id, asset_type_id, name, description, account_number, current_valuation, currency)
-                 VALUES ($1, $2, $3, $4, $5, $6, $7)
+                "INSERT INTO assets (user_id, asset_type_id, name, description, account_number, current_valuation, currency, sort_order)
+                 VALUES ($1, $2, $3, $4, $5, $6, $7, (SELECT COALESCE(MAX(sort_order), 0) + 1 FROM assets))
                  RETURNING id, user_id, asset_type_id, name, description, account_number, quantity, average_purchase_price, current_valuation, currency, is_active, created_date, sort_order"
             )
             .bind(from_asset.user_id)
@@ -251,11 +289,10 @@ pub async fn transfer_operation(
             .bind(&new_asset_data.name)
             .bind(&new_asset_data.description)
             .bind(&new_asset_data.account_number)
-            .bind(payload.amount.clone())
+            .bind(&payload.amount)
             .bind(new_asset_data.currency.as_ref().unwrap_or(&"PLN".to_string()))
             .fetch_one(&mut *tx)
-            .await
-            .map_err(db_err)?;
+            .await?;
 
             response.new_asset_id = Some(new_asset.id);
 
@@ -265,40 +302,40 @@ pub async fn transfer_operation(
                  VALUES ($1, $2, $3, $4)"
             )
             .bind(new_asset.id)
-            .bind(&payload.operation_date)
-            .bind(payload.amount.clone())
+            .bind(payload.operation_date)
+            .bind(&payload.amount)
             .bind("Początkowa wycena przy zakupie")
             .execute(&mut *tx)
-            .await
-            .map_err(db_err)?;
+            .await?;
 
             // Create outgoing operation from source
             let from_op = sqlx::query_as::<_, Operation>(
                 "INSERT INTO operations (asset_id, amount, operation_type, operation_date, description)
                  VALUES ($1, $2, 'expense'::operation_type, $3::date, $4)
-                 RETURNING id, creation_date, category_id, description, asset_id, amount, operation_type::text, operation_date, parent_operation_id, is_split, linked_operation_id"
+                 RETURNING id, creation_date, category_id, description, asset_id, amount, operation_type::text, operation_date, parent_operation_id, is_split, linked_operation_id, payee_id, clearing_status::text"
             )
             .bind(payload.from_asset_id)
-            .bind(-payload.amount.clone())
-            .bind(&payload.operation_date)
+            .bind(-&payload.amount)
+            .bind(payload.operation_date)
             .bind(payload.description.as_ref().unwrap_or(&format!("Zakup: {}", new_asset.name)))
             .fetch_one(&mut *tx)
-            .await
-            .map_err(db_err)?;
+            .await?;
 
             response.from_operation_id = Some(from_op.id);
         },
 
-        "liquid_to_liability" => {
+        TransferType::LiquidToLiability => {
             // Payment towards liability (debt payment)
             let to_asset_id = payload.to_asset_id
-                .ok_or_else(|| (axum::http::StatusCode::BAD_REQUEST, "to_asset_id required for liability payment".to_string()))?;
+                .ok_or_else(|| AppError::BadRequest(
+                    "to_asset_id required for liability payment".to_string(),
+                ))?;
             
-            sqlx::query("SELECT id FROM assets WHERE id = $1")
+            sqlx::query("SELECT id FROM assets WHERE id = $1 AND deleted_at IS NULL")
                 .bind(to_asset_id)
                 .fetch_one(&mut *tx)
                 .await
-                .map_err(|_| (axum::http::StatusCode::NOT_FOUND, "Liability asset not found".to_string()))?;
+                .map_err(|_| AppError::NotFound("Liability asset not found".to_string()))?;
 
             // Ensure debt categories exist and get interest category ID
             let (_, interest_category_id) = ensure_debt_categories(&state.pool).await?;
@@ -307,15 +344,14 @@ pub async fn transfer_operation(
             let from_op = sqlx::query_as::<_, Operation>(
                 "INSERT INTO operations (asset_id, amount, operation_type, operation_date, description)
                  VALUES ($1, $2, 'expense'::operation_type, $3::date, $4)
-                 RETURNING id, creation_date, category_id, description, asset_id, amount, operation_type::text, operation_date, parent_operation_id, is_split, linked_operation_id"
+                 RETURNING id, creation_date, category_id, description, asset_id, amount, operation_type::text, operation_date, parent_operation_id, is_split, linked_operation_id, payee_id, clearing_status::text"
             )
             .bind(payload.from_asset_id)
-            .bind(-payload.amount.clone())
-            .bind(&payload.operation_date)
+            .bind(-&payload.amount)
+            .bind(payload.operation_date)
             .bind(payload.description.as_ref().unwrap_or(&format!("Spłata zobowiązania #{}", to_asset_id)))
             .fetch_one(&mut *tx)
-            .await
-            .map_err(db_err)?;
+            .await?;
 
             response.from_operation_id = Some(from_op.id);
 
@@ -323,50 +359,46 @@ pub async fn transfer_operation(
             let to_op = sqlx::query_as::<_, Operation>(
                 "INSERT INTO operations (asset_id, amount, operation_type, operation_date, description)
                  VALUES ($1, $2, 'income'::operation_type, $3::date, $4)
-                 RETURNING id, creation_date, category_id, description, asset_id, amount, operation_type::text, operation_date, parent_operation_id, is_split, linked_operation_id"
+                 RETURNING id, creation_date, category_id, description, asset_id, amount, operation_type::text, operation_date, parent_operation_id, is_split, linked_operation_id, payee_id, clearing_status::text"
             )
             .bind(to_asset_id)
-            .bind(payload.amount.clone())
-            .bind(&payload.operation_date)
+            .bind(&payload.amount)
+            .bind(payload.operation_date)
             .bind(payload.description.as_ref().unwrap_or(&format!("Spłata z aktywa #{}", payload.from_asset_id)))
             .fetch_one(&mut *tx)
-            .await
-            .map_err(db_err)?;
+            .await?;
 
             response.to_operation_id = Some(to_op.id);
+            touched_asset_ids.push(to_asset_id);
 
             // If interest amount is provided, create separate interest operation
-            if let Some(interest) = payload.interest_amount {
-                if interest > 0.0 {
-                    let interest_bd = BigDecimal::from_f64(interest)
-                        .ok_or_else(|| (axum::http::StatusCode::BAD_REQUEST, "Invalid interest amount".to_string()))?;
-
-                    let interest_op = sqlx::query_as::<_, Operation>(
-                        "INSERT INTO operations (asset_id, amount, operation_type, operation_date, description, category_id)
-                         VALUES ($1, $2, 'expense'::operation_type, $3::date, $4, $5)
-                         RETURNING id, creation_date, category_id, description, asset_id, amount, operation_type::text, operation_date, parent_operation_id, is_split, linked_operation_id"
-                    )
-                    .bind(payload.from_asset_id)
-                    .bind(-interest_bd)
-                    .bind(&payload.operation_date)
-                    .bind(format!("Odsetki - {}", payload.description.as_ref().unwrap_or(&format!("zobowiązanie #{}", to_asset_id))))
-                    .bind(interest_category_id)
-                    .fetch_one(&mut *tx)
-                    .await
-                    .map_err(db_err)?;
-
-                    response.interest_operation_id = Some(interest_op.id);
-                }
+            if let Some(interest) = &payload.interest_amount
+                && interest.is_positive()
+            {
+                let interest_op = sqlx::query_as::<_, Operation>(
+                    "INSERT INTO operations (asset_id, amount, operation_type, operation_date, description, category_id)
+                     VALUES ($1, $2, 'expense'::operation_type, $3::date, $4, $5)
+                     RETURNING id, creation_date, category_id, description, asset_id, amount, operation_type::text, operation_date, parent_operation_id, is_split, linked_operation_id, payee_id, clearing_status::text"
+                )
+                .bind(payload.from_asset_id)
+                .bind(-interest)
+                .bind(payload.operation_date)
+                .bind(format!("Odsetki - {}", payload.description.as_ref().unwrap_or(&format!("zobowiązanie #{}", to_asset_id))))
+                .bind(interest_category_id)
+                .fetch_one(&mut *tx)
+                .await?;
+
+                response.interest_operation_id = Some(interest_op.id);
             }
         },
-
-        _ => {
-            return Err((axum::http::StatusCode::BAD_REQUEST, format!("Unknown transfer_type: {}", payload.transfer_type)));
-        }
     }
 
+    balances::recalculate(&mut tx, &touched_asset_ids)
+        .await?;
+
     // Commit transaction
-    tx.commit().await.map_err(db_err)?;
+    tx.commit().await?;
+    crate::notifications::check_after_write(&state);
 
     response.success = true;
     Ok(Json(response))
@@ -0,0 +1,44 @@
+use crate::{AppState, balances, error::AppError, models::*, trash};
+use axum::{
+    Json,
+    extract::{Path, State},
+};
+
+pub async fn list_trash(State(state): State<AppState>) -> Result<Json<Vec<TrashItem>>, AppError> {
+    let mut conn = state.pool.acquire().await?;
+    Ok(Json(trash::list(&mut conn).await?))
+}
+
+pub async fn restore_operation(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+) -> Result<Json<RestoreResponse>, AppError> {
+    let mut tx = state.pool.begin().await?;
+    let (restored, asset_ids) = trash::restore_operation(&mut tx, id).await?;
+    balances::recalculate(&mut tx, &asset_ids).await?;
+    tx.commit().await?;
+    crate::notifications::check_after_write(&state);
+    Ok(Json(restored))
+}
+
+pub async fn restore_asset(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+) -> Result<Json<RestoreResponse>, AppError> {
+    let mut tx = state.pool.begin().await?;
+    let (restored, asset_ids) = trash::restore_asset(&mut tx, id).await?;
+    balances::recalculate(&mut tx, &asset_ids).await?;
+    tx.commit().await?;
+    crate::notifications::check_after_write(&state);
+    Ok(Json(restored))
+}
+
+pub async fn restore_category(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+) -> Result<Json<RestoreResponse>, AppError> {
+    let mut tx = state.pool.begin().await?;
+    let category = trash::restore_category(&mut tx, id).await?;
+    tx.commit().await?;
+    Ok(Json(RestoreResponse { kind: "category".to_string(), id: category.id, operation_ids: Vec::new() }))
+}
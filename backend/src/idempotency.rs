@@ -0,0 +1,211 @@
+// Idempotency-Key support for the requests that create money movements. The
+// client sends a fresh key with each such request and the same key when it
+// retries it; the first response is stored with the key and a hash of the
+// request, so a retry gets that response again instead of creating a
+// duplicate. Keys are kept for RETENTION_HOURS, then a job purges them.
+use crate::{AppState, error::AppError};
+use axum::{
+    body::{Body, Bytes},
+    extract::{FromRequest, Request},
+    handler::Handler,
+    http::{HeaderValue, StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use sha2::{Digest, Sha256};
+use std::{future::Future, pin::Pin};
+
+pub const IDEMPOTENCY_KEY_HEADER: &str = "idempotency-key";
+// Set on a response replayed from an earlier request with the same key
+pub const IDEMPOTENT_REPLAYED_HEADER: &str = "idempotent-replayed";
+pub const RETENTION_HOURS: i32 = 24;
+// A key whose first request never finished (the server stopped) is released after this
+const ABANDONED_AFTER_MINUTES: i32 = 5;
+const MAX_KEY_LENGTH: usize = 255;
+
+// POST routes wrapped with `idempotent`, for the API docs, read from the
+// route table so the two can't disagree. Besides the requests that create
+// money movements these are the other POSTs that write operations: bulk
+// changes, splitting, reverting an audit entry, restoring from the trash and
+// running a job (generate_recurring creates operations). PUT requests leave the
+// same state when repeated and don't take a key.
+pub fn idempotent_paths() -> Vec<&'static str> {
+    include_str!("routes.rs")
+        .split(".route(")
+        .skip(1)
+        .filter(|route| route.contains("post(idempotent("))
+        .filter_map(|route| route.split('"').nth(1))
+        .collect()
+}
+
+// A handler that honours the Idempotency-Key header; requests without it are
+// handled as usual
+pub fn idempotent<H>(handler: H) -> Idempotent<H> {
+    Idempotent(handler)
+}
+
+#[derive(Clone)]
+pub struct Idempotent<H>(H);
+
+impl<H, T> Handler<T, AppState> for Idempotent<H>
+where
+    H: Handler<T, AppState>,
+    T: 'static,
+{
+    type Future = Pin<Box<dyn Future<Output = Response> + Send>>;
+
+    fn call(self, req: Request, state: AppState) -> Self::Future {
+        Box::pin(async move { handle(self.0, req, state).await.unwrap_or_else(IntoResponse::into_response) })
+    }
+}
+
+#[derive(sqlx::FromRow)]
+struct StoredRequest {
+    request_hash: String,
+    status_code: Option<i16>,
+    content_type: Option<String>,
+    response_body: Option<Vec<u8>>,
+}
+
+async fn handle<H, T>(handler: H, req: Request, state: AppState) -> Result<Response, AppError>
+where
+    H: Handler<T, AppState>,
+{
+    let Some(key) = req.headers().get(IDEMPOTENCY_KEY_HEADER) else {
+        return Ok(handler.call(req, state).await);
+    };
+    let key = key
+        .to_str()
+        .ok()
+        .map(str::trim)
+        .filter(|key| !key.is_empty() && key.len() <= MAX_KEY_LENGTH)
+        .ok_or_else(|| {
+            AppError::BadRequest(format!("Idempotency-Key must be 1 to {} visible characters", MAX_KEY_LENGTH))
+        })?
+        .to_string();
+
+    // The body is read here for the hash and handed on to the handler; the
+    // extensions carry the body size limit
+    let (parts, body) = req.into_parts();
+    let mut body_request = Request::new(body);
+    *body_request.extensions_mut() = parts.extensions.clone();
+    let body = Bytes::from_request(body_request, &state).await.map_err(|e| {
+        if e.status() == StatusCode::PAYLOAD_TOO_LARGE {
+            AppError::PayloadTooLarge(e.body_text())
+        } else {
+            AppError::BadRequest(e.body_text())
+        }
+    })?;
+    let mut hasher = Sha256::new();
+    hasher.update(format!("{} {}\n", parts.method, parts.uri.path()));
+    hasher.update(&body);
+    let request_hash: String = hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect();
+
+    // Expired keys and keys of requests that never finished are free again
+    sqlx::query(
+        "DELETE FROM idempotency_keys
+         WHERE key = $1
+           AND (created_at < CURRENT_TIMESTAMP - make_interval(hours => $2)
+                OR (status_code IS NULL AND created_at < CURRENT_TIMESTAMP - make_interval(mins => $3)))",
+    )
+    .bind(&key)
+    .bind(RETENTION_HOURS)
+    .bind(ABANDONED_AFTER_MINUTES)
+    .execute(&state.pool)
+    .await?;
+    let claimed = sqlx::query(
+        "INSERT INTO idempotency_keys (key, method, path, request_hash) VALUES ($1, $2, $3, $4)
+         ON CONFLICT (key) DO NOTHING",
+    )
+    .bind(&key)
+    .bind(parts.method.as_str())
+    .bind(parts.uri.path())
+    .bind(&request_hash)
+    .execute(&state.pool)
+    .await?
+    .rows_affected()
+        == 1;
+
+    if !claimed {
+        let stored = sqlx::query_as::<_, StoredRequest>(
+            "SELECT request_hash, status_code, content_type, response_body FROM idempotency_keys WHERE key = $1",
+        )
+        .bind(&key)
+        .fetch_optional(&state.pool)
+        .await?;
+        return match stored {
+            Some(stored) if stored.request_hash != request_hash => Err(AppError::Conflict(
+                "This Idempotency-Key was already used for a different request".to_string(),
+            )),
+            Some(StoredRequest { status_code: Some(status), content_type, response_body, .. }) => {
+                Ok(replay(status, content_type, response_body.unwrap_or_default()))
+            }
+            _ => Err(AppError::Conflict(
+                "A request with this Idempotency-Key is still being processed; retry later".to_string(),
+            )),
+        };
+    }
+
+    let response = handler.call(Request::from_parts(parts, Body::from(body)), state.clone()).await;
+    // A failure may go through when retried, so its key is released
+    if response.status().is_server_error() {
+        release(&state, &key).await;
+        return Ok(response);
+    }
+    let (parts, body) = response.into_parts();
+    let body = match axum::body::to_bytes(body, usize::MAX).await {
+        Ok(body) => body,
+        Err(e) => {
+            release(&state, &key).await;
+            return Err(AppError::internal(e));
+        }
+    };
+    let content_type = parts.headers.get(header::CONTENT_TYPE).and_then(|v| v.to_str().ok());
+    let stored = sqlx::query(
+        "UPDATE idempotency_keys
+         SET status_code = $2, content_type = $3, response_body = $4, completed_at = CURRENT_TIMESTAMP
+         WHERE key = $1",
+    )
+    .bind(&key)
+    .bind(parts.status.as_u16() as i16)
+    .bind(content_type)
+    .bind(body.as_ref())
+    .execute(&state.pool)
+    .await;
+    if let Err(e) = stored {
+        // The work is done, so the response still goes out; only a retry isn't covered
+        tracing::error!("Failed to store the response for Idempotency-Key {}: {}", key, e);
+        release(&state, &key).await;
+    }
+    Ok(Response::from_parts(parts, Body::from(body)))
+}
+
+fn replay(status: i16, content_type: Option<String>, body: Vec<u8>) -> Response {
+    let status = StatusCode::from_u16(status as u16).unwrap_or(StatusCode::OK);
+    let mut response = (status, body).into_response();
+    let headers = response.headers_mut();
+    match content_type.and_then(|value| HeaderValue::from_str(&value).ok()) {
+        Some(value) => headers.insert(header::CONTENT_TYPE, value),
+        None => headers.remove(header::CONTENT_TYPE),
+    };
+    headers.insert(IDEMPOTENT_REPLAYED_HEADER, HeaderValue::from_static("true"));
+    response
+}
+
+async fn release(state: &AppState, key: &str) {
+    if let Err(e) = sqlx::query("DELETE FROM idempotency_keys WHERE key = $1 AND status_code IS NULL")
+        .bind(key)
+        .execute(&state.pool)
+        .await
+    {
+        tracing::error!("Failed to release Idempotency-Key {}: {}", key, e);
+    }
+}
+
+// Removes keys past the retention window; returns how many
+pub async fn purge_expired_keys(pool: &sqlx::PgPool) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query("DELETE FROM idempotency_keys WHERE created_at < CURRENT_TIMESTAMP - make_interval(hours => $1)")
+        .bind(RETENTION_HOURS)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected())
+}
@@ -0,0 +1,246 @@
+// Bank statement import outside the browser. Reads a CSV file the way the
+// import dialog does and maps its columns with a saved import template
+// (template_data.columnMapping), giving operations for a batch create.
+use crate::models::{Asset, Category, Money, OperationType};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+
+// Column indexes as saved by the import dialog
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ColumnMapping {
+    pub amount: Option<usize>,
+    pub description: Option<usize>,
+    pub date: Option<usize>,
+    pub date_format: Option<String>,
+    pub source_account: Option<usize>,
+    pub category: Option<usize>,
+    pub operation_type: Option<usize>,
+}
+
+impl ColumnMapping {
+    pub fn from_template(template_data: &Value) -> Result<ColumnMapping, String> {
+        let mapping = template_data
+            .get("columnMapping")
+            .ok_or("The template has no column mapping")?;
+        serde_json::from_value(mapping.clone()).map_err(|e| format!("The template's column mapping is invalid: {}", e))
+    }
+}
+
+pub struct CsvFile {
+    pub headers: Vec<String>,
+    // Line number in the file and the cells of every non-empty row
+    pub rows: Vec<(u64, Vec<String>)>,
+}
+
+// Bank exports use ';' by default; cells lose surrounding spaces and quotes
+pub fn parse_csv(data: &[u8], delimiter: u8) -> Result<CsvFile, String> {
+    let data = data.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(data);
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(delimiter)
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(data);
+
+    let mut records = Vec::new();
+    for record in reader.records() {
+        let record = record.map_err(|e| format!("Invalid CSV: {}", e))?;
+        // The reader's own count skips blank lines, and its offset is where
+        // the previous record ended, before any of them
+        let mut offset = record.position().map_or(0, |p| p.byte() as usize);
+        while matches!(data.get(offset), Some(b'\r' | b'\n')) {
+            offset += 1;
+        }
+        let line = data[..offset].iter().filter(|&&b| b == b'\n').count() as u64 + 1;
+        let cells: Vec<String> = record.iter().map(clean_cell).collect();
+        if cells.iter().any(|cell| !cell.is_empty()) {
+            records.push((line, cells));
+        }
+    }
+    let mut records = records.into_iter();
+    let (_, headers) = records.next().ok_or("The CSV file is empty")?;
+    Ok(CsvFile { headers, rows: records.collect() })
+}
+
+fn clean_cell(value: &str) -> String {
+    let value = value.trim();
+    let value = value.strip_prefix(['\'', '"']).unwrap_or(value);
+    let value = value.strip_suffix(['\'', '"']).unwrap_or(value);
+    value.to_string()
+}
+
+// An operation read from one row of the file
+#[derive(Debug, Serialize)]
+pub struct ImportedOperation {
+    pub line: u64,
+    pub operation_date: NaiveDate,
+    pub amount: Money,
+    pub operation_type: OperationType,
+    pub asset_id: i32,
+    pub category_id: Option<i32>,
+    pub description: Option<String>,
+}
+
+impl ImportedOperation {
+    pub fn to_batch_item(&self) -> Value {
+        json!({
+            "operation_date": self.operation_date,
+            "amount": self.amount,
+            "operation_type": self.operation_type,
+            "asset_id": self.asset_id,
+            "category_id": self.category_id,
+            "description": self.description,
+        })
+    }
+}
+
+// Maps every row, or lists the problems of all rows that can't be mapped.
+// `asset_id` puts every operation on that asset instead of matching the
+// account column.
+pub fn map_rows(
+    file: &CsvFile,
+    mapping: &ColumnMapping,
+    assets: &[Asset],
+    categories: &[Category],
+    asset_id: Option<i32>,
+) -> Result<Vec<ImportedOperation>, Vec<String>> {
+    let (Some(amount_column), Some(date_column)) = (mapping.amount, mapping.date) else {
+        return Err(vec!["The template must map the amount and date columns".to_string()]);
+    };
+    if asset_id.is_none() && mapping.source_account.is_none() {
+        return Err(vec!["The template doesn't map an account column; choose the asset for all rows".to_string()]);
+    }
+    let date_format = mapping.date_format.as_deref().unwrap_or("YYYY-MM-DD");
+    let date_format = chrono_format(date_format).ok_or_else(|| vec![format!("Unknown date format '{}'", date_format)])?;
+
+    let mut operations = Vec::new();
+    let mut errors = Vec::new();
+    for (line, cells) in &file.rows {
+        let cell = |column: Option<usize>| column.and_then(|c| cells.get(c)).map(String::as_str).unwrap_or("");
+        let row = || -> Result<ImportedOperation, String> {
+            let amount = parse_amount(cell(Some(amount_column)))?;
+            let operation_date = parse_date(cell(Some(date_column)), date_format)?;
+
+            // A type column wins over the sign; the sign then follows the type
+            let operation_type = match mapping.operation_type {
+                Some(_) => {
+                    let text = cell(mapping.operation_type).to_lowercase();
+                    if text.contains("income") || text.contains("przychód") {
+                        OperationType::Income
+                    } else {
+                        OperationType::Expense
+                    }
+                }
+                None if amount.is_negative() => OperationType::Expense,
+                None => OperationType::Income,
+            };
+            let amount = match operation_type {
+                OperationType::Expense => -amount.abs(),
+                OperationType::Income => amount.abs(),
+            };
+
+            let asset_id = match asset_id {
+                Some(id) => id,
+                None => {
+                    let account = cell(mapping.source_account);
+                    match_asset(assets, account).ok_or_else(|| format!("no asset matches the account '{}'", account))?
+                }
+            };
+            let category_id = match_category(categories, cell(mapping.category), operation_type);
+            let description = Some(cell(mapping.description).to_string()).filter(|d| !d.is_empty());
+
+            Ok(ImportedOperation {
+                line: *line,
+                operation_date,
+                amount,
+                operation_type,
+                asset_id,
+                category_id,
+                description,
+            })
+        };
+        match row() {
+            Ok(operation) => operations.push(operation),
+            Err(e) => errors.push(format!("Line {}: {}", line, e)),
+        }
+    }
+    if errors.is_empty() { Ok(operations) } else { Err(errors) }
+}
+
+// Formats offered by the import dialog
+fn chrono_format(format: &str) -> Option<&'static str> {
+    match format {
+        "YYYY-MM-DD" => Some("%Y-%m-%d"),
+        "DD/MM/YYYY" => Some("%d/%m/%Y"),
+        "MM/DD/YYYY" => Some("%m/%d/%Y"),
+        "DD.MM.YYYY" => Some("%d.%m.%Y"),
+        "YYYY/MM/DD" => Some("%Y/%m/%d"),
+        _ => None,
+    }
+}
+
+fn parse_date(value: &str, format: &str) -> Result<NaiveDate, String> {
+    NaiveDate::parse_from_str(value, format)
+        .or_else(|_| NaiveDate::parse_from_str(value, "%Y-%m-%d"))
+        .map_err(|_| format!("'{}' is not a date", value))
+}
+
+// "-1 234,56 zł" is -1234.56: a decimal comma, anything but digits dropped
+fn parse_amount(value: &str) -> Result<Money, String> {
+    let cleaned: String = value
+        .replace(',', ".")
+        .chars()
+        .filter(|c| c.is_ascii_digit() || *c == '.' || *c == '-')
+        .collect();
+    Money::parse(&cleaned).map_err(|_| format!("'{}' is not an amount", value))
+}
+
+// Account columns hold the asset name or account number, often spaced
+// differently; an exact match wins over a partial one
+fn match_asset(assets: &[Asset], account: &str) -> Option<i32> {
+    let normalize = |value: &str| value.to_lowercase().split_whitespace().collect::<String>();
+    let account = normalize(account);
+    if account.is_empty() {
+        return None;
+    }
+    let candidates = |asset: &Asset| -> Vec<String> {
+        [Some(&asset.name), asset.account_number.as_ref()]
+            .into_iter()
+            .flatten()
+            .map(|candidate| normalize(candidate))
+            .filter(|candidate| !candidate.is_empty())
+            .collect()
+    };
+    assets
+        .iter()
+        .find(|asset| candidates(asset).contains(&account))
+        .or_else(|| {
+            assets
+                .iter()
+                .find(|asset| candidates(asset).iter().any(|c| c.contains(&account) || account.contains(c.as_str())))
+        })
+        .map(|asset| asset.id)
+}
+
+// Exact name first, then a partial one; only categories that can hold the operation
+fn match_category(categories: &[Category], name: &str, operation_type: OperationType) -> Option<i32> {
+    let name = name.trim().to_lowercase();
+    if name.is_empty() {
+        return None;
+    }
+    let candidates: Vec<&Category> = categories
+        .iter()
+        .filter(|c| c.r#type == operation_type.as_str() && !c.is_system)
+        .collect();
+    candidates
+        .iter()
+        .find(|c| c.name.to_lowercase() == name)
+        .or_else(|| {
+            candidates.iter().find(|c| {
+                let candidate = c.name.to_lowercase();
+                candidate.contains(&name) || name.contains(&candidate)
+            })
+        })
+        .map(|c| c.id)
+}
@@ -0,0 +1,293 @@
+// Background jobs. Each job has a cron schedule (local time) and runs inside
+// the backend; every run is recorded in the `jobs` table. A Postgres advisory
+// lock per job name makes sure only one backend instance runs a job at a time,
+// and a scheduled run records its time so each one happens once across instances.
+use crate::{
+    AppState, balances, cron::Schedule, handlers, idempotency, models::JobRun, notifications, request_context::RequestContext,
+    trash,
+};
+use chrono::{Duration, Local, NaiveDateTime, Timelike};
+use std::{future::Future, pin::Pin};
+
+pub type JobFuture = Pin<Box<dyn Future<Output = Result<String, String>> + Send>>;
+
+pub struct Job {
+    pub name: &'static str,
+    pub schedule: &'static str,
+    pub description: &'static str,
+    pub run: fn(AppState) -> JobFuture,
+}
+
+pub const JOB_RUN_COLUMNS: &str =
+    "id, name, trigger, status, scheduled_for, started_at, finished_at, duration_ms, summary, error";
+
+pub fn registry() -> Vec<Job> {
+    vec![
+        Job {
+            name: "generate_recurring",
+            schedule: "5 0 * * *",
+            description: "Create operations from recurring templates that fell due",
+            run: |state| {
+                Box::pin(async move {
+                    // "Today" as the database sees it, like the CURRENT_DATE defaults
+                    let today: chrono::NaiveDate = sqlx::query_scalar("SELECT CURRENT_DATE")
+                        .fetch_one(&state.pool)
+                        .await
+                        .map_err(|e| e.to_string())?;
+                    let created = handlers::recurring_operations::generate_recurring_operations(&state.pool, today)
+                        .await
+                        .map_err(|e| e.to_string())?;
+                    Ok(format!("{} operations created", created))
+                })
+            },
+        },
+        Job {
+            name: "recalculate_balances",
+            schedule: "30 3 * * *",
+            description: "Recalculate stored asset balances and repair drift",
+            run: |state| {
+                Box::pin(async move {
+                    let mut tx = state.pool.begin().await.map_err(|e| e.to_string())?;
+                    let (checked, repaired) = balances::recalculate_all(&mut tx).await.map_err(|e| e.to_string())?;
+                    tx.commit().await.map_err(|e| e.to_string())?;
+                    Ok(format!("{} assets checked, {} repaired", checked, repaired.len()))
+                })
+            },
+        },
+        Job {
+            name: "check_notifications",
+            schedule: "*/15 * * * *",
+            description: "Check notification rules and retry failed deliveries",
+            run: |state| Box::pin(async move { notifications::check_and_deliver(&state).await.map_err(|e| e.to_string()) }),
+        },
+        Job {
+            name: "purge_attachment_files",
+            schedule: "15 * * * *",
+            description: "Remove stored files of deleted attachments",
+            run: |state| {
+                Box::pin(async move {
+                    handlers::attachments::purge_deleted_attachment_files(&state).await;
+                    Ok("Deleted attachment files purged".to_string())
+                })
+            },
+        },
+        Job {
+            name: "purge_idempotency_keys",
+            schedule: "45 * * * *",
+            description: "Remove stored Idempotency-Key responses past their retention",
+            run: |state| {
+                Box::pin(async move {
+                    let purged = idempotency::purge_expired_keys(&state.pool).await.map_err(|e| e.to_string())?;
+                    Ok(format!("{} idempotency keys purged", purged))
+                })
+            },
+        },
+        Job {
+            name: "purge_trash",
+            schedule: "0 4 * * *",
+            description: "Delete operations, assets and categories that have been in the trash past the retention period",
+            run: |state| {
+                Box::pin(async move {
+                    let mut tx = state.pool.begin().await.map_err(|e| e.to_string())?;
+                    let (operations, assets, categories) =
+                        trash::purge_expired(&mut tx).await.map_err(|e| e.to_string())?;
+                    tx.commit().await.map_err(|e| e.to_string())?;
+                    // Attachments of the purged operations leave their files behind
+                    handlers::attachments::purge_deleted_attachment_files(&state).await;
+                    Ok(format!(
+                        "{} operations, {} assets and {} categories purged",
+                        operations, assets, categories
+                    ))
+                })
+            },
+        },
+    ]
+}
+
+#[derive(Debug)]
+pub enum JobError {
+    UnknownJob(String),
+    // Another run (possibly on another instance) holds the job's lock
+    AlreadyRunning(String),
+    Database(sqlx::Error),
+}
+
+impl std::fmt::Display for JobError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            JobError::UnknownJob(name) => write!(f, "Unknown job '{}'", name),
+            JobError::AlreadyRunning(name) => write!(f, "Job '{}' is already running", name),
+            JobError::Database(e) => write!(f, "DB error: {}", e),
+        }
+    }
+}
+
+impl From<sqlx::Error> for JobError {
+    fn from(e: sqlx::Error) -> Self {
+        JobError::Database(e)
+    }
+}
+
+// Run a job now and record the run. `trigger` is "schedule" or "manual".
+pub async fn run_job(state: &AppState, name: &str, trigger: &str) -> Result<JobRun, JobError> {
+    let run = run_locked(state, name, trigger, None).await?;
+    Ok(run.expect("a run without a scheduled time is always recorded"))
+}
+
+// Run a job for its scheduled time `scheduled_for`, unless a run for that
+// time was already recorded (by this or another instance); then None
+pub async fn run_scheduled(
+    state: &AppState,
+    name: &str,
+    scheduled_for: NaiveDateTime,
+) -> Result<Option<JobRun>, JobError> {
+    run_locked(state, name, "schedule", Some(scheduled_for)).await
+}
+
+async fn run_locked(
+    state: &AppState,
+    name: &str,
+    trigger: &str,
+    scheduled_for: Option<NaiveDateTime>,
+) -> Result<Option<JobRun>, JobError> {
+    let job = registry()
+        .into_iter()
+        .find(|job| job.name == name)
+        .ok_or_else(|| JobError::UnknownJob(name.to_string()))?;
+
+    // Session-level lock, held on this connection for the whole run
+    let mut lock_conn = state.pool.acquire().await?;
+    let locked: bool = sqlx::query_scalar("SELECT pg_try_advisory_lock(hashtextextended('home_budget.job.' || $1, 0))")
+        .bind(job.name)
+        .fetch_one(&mut *lock_conn)
+        .await?;
+    if !locked {
+        return Err(JobError::AlreadyRunning(job.name.to_string()));
+    }
+
+    let result = record_run(state, &job, trigger, scheduled_for).await;
+
+    if let Err(e) = sqlx::query("SELECT pg_advisory_unlock(hashtextextended('home_budget.job.' || $1, 0))")
+        .bind(job.name)
+        .execute(&mut *lock_conn)
+        .await
+    {
+        // Don't hand a connection that may still hold the lock back to the pool
+        tracing::warn!("Failed to release lock of job {}: {}", job.name, e);
+        lock_conn.detach();
+    }
+    result
+}
+
+async fn record_run(
+    state: &AppState,
+    job: &Job,
+    trigger: &str,
+    scheduled_for: Option<NaiveDateTime>,
+) -> Result<Option<JobRun>, JobError> {
+    // With the lock held, a run still marked as running was cut short
+    sqlx::query(
+        "UPDATE jobs SET status = 'failed', finished_at = CURRENT_TIMESTAMP, error = 'Interrupted before finishing'
+         WHERE name = $1 AND status = 'running'",
+    )
+    .bind(job.name)
+    .execute(&state.pool)
+    .await?;
+
+    // The lock is free again once a run finishes, so the scheduled time tells
+    // whether another instance already ran this one
+    let run_id: Option<i64> = sqlx::query_scalar(
+        "INSERT INTO jobs (name, trigger, status, scheduled_for) VALUES ($1, $2, 'running', $3)
+         ON CONFLICT (name, scheduled_for) WHERE scheduled_for IS NOT NULL DO NOTHING
+         RETURNING id",
+    )
+    .bind(job.name)
+    .bind(trigger)
+    .bind(scheduled_for)
+    .fetch_optional(&state.pool)
+    .await?;
+    let Some(run_id) = run_id else {
+        return Ok(None);
+    };
+
+    // Changes made by the job are audited under the run's request id
+    let context = RequestContext {
+        user_id: None,
+        request_id: format!("job-{}-{}", job.name, run_id),
+        client_request_id: None,
+    };
+    let started = std::time::Instant::now();
+    let outcome = tokio::spawn(context.scope((job.run)(state.clone())))
+        .await
+        .unwrap_or_else(|e| Err(format!("Job panicked: {}", e)));
+    let duration_ms = i64::try_from(started.elapsed().as_millis()).unwrap_or(i64::MAX);
+
+    let (status, summary, error) = match outcome {
+        Ok(summary) => ("succeeded", Some(summary), None),
+        Err(error) => {
+            tracing::warn!("Job {} failed: {}", job.name, error);
+            ("failed", None, Some(error))
+        }
+    };
+    let run = sqlx::query_as::<_, JobRun>(&format!(
+        "UPDATE jobs SET status = $1, finished_at = CURRENT_TIMESTAMP, duration_ms = $2, summary = $3, error = $4
+         WHERE id = $5
+         RETURNING {JOB_RUN_COLUMNS}"
+    ))
+    .bind(status)
+    .bind(duration_ms)
+    .bind(summary)
+    .bind(error)
+    .bind(run_id)
+    .fetch_one(&state.pool)
+    .await?;
+    Ok(Some(run))
+}
+
+// Next time a job is due after `after`, or None for an invalid schedule
+pub fn next_run(job: &Job, after: NaiveDateTime) -> Option<NaiveDateTime> {
+    Schedule::parse(job.schedule).ok()?.next_after(after)
+}
+
+// Start the scheduler loop: at the start of every minute, run the jobs due then
+pub fn spawn_scheduler(state: AppState) -> tokio::task::JoinHandle<()> {
+    let jobs: Vec<(Job, Schedule)> = registry()
+        .into_iter()
+        .filter_map(|job| match Schedule::parse(job.schedule) {
+            Ok(schedule) => Some((job, schedule)),
+            Err(e) => {
+                tracing::error!("Job {} has an invalid schedule: {}", job.name, e);
+                None
+            }
+        })
+        .collect();
+
+    tokio::spawn(async move {
+        loop {
+            let now = Local::now().naive_local();
+            let Some(next_minute) = now
+                .with_second(0)
+                .and_then(|t| t.with_nanosecond(0))
+                .map(|t| t + Duration::minutes(1))
+            else {
+                continue;
+            };
+            let wait = (next_minute - now).to_std().unwrap_or_default();
+            tokio::time::sleep(wait).await;
+
+            for (job, schedule) in &jobs {
+                if !schedule.matches(next_minute) {
+                    continue;
+                }
+                let state = state.clone();
+                let name = job.name;
+                tokio::spawn(async move {
+                    match run_scheduled(&state, name, next_minute).await {
+                        Ok(_) | Err(JobError::AlreadyRunning(_)) => {}
+                        Err(e) => tracing::warn!("Job {} could not run: {}", name, e),
+                    }
+                });
+            }
+        }
+    })
+}
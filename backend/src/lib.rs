@@ -0,0 +1,50 @@
+pub mod balances;
+pub mod cli;
+pub mod config;
+pub mod cron;
+pub mod db;
+pub mod delivery;
+pub mod error;
+pub mod export;
+pub mod forecast;
+pub mod frontend;
+pub mod idempotency;
+pub mod import;
+pub mod jobs;
+pub mod models;
+pub mod money;
+pub mod notifications;
+pub mod openapi;
+pub mod handlers;
+pub mod recurrence;
+pub mod request_context;
+pub mod routes;
+pub mod storage;
+pub mod trash;
+pub mod validation;
+
+use std::sync::Arc;
+
+#[derive(Clone)]
+pub struct AppState {
+    pub pool: sqlx::PgPool,
+    pub storage: Arc<dyn storage::AttachmentStorage>,
+    // Email delivery of notifications; None when SMTP isn't configured
+    pub smtp: Option<delivery::SmtpSettings>,
+}
+
+impl AppState {
+    // State with the default attachment directory and no SMTP
+    pub fn new(pool: sqlx::PgPool) -> Self {
+        Self::from_config(pool, &config::Config::default())
+    }
+
+    // State with attachment storage and SMTP from the configuration
+    pub fn from_config(pool: sqlx::PgPool, config: &config::Config) -> Self {
+        Self {
+            pool,
+            storage: Arc::new(storage::LocalStorage::new(&config.attachments.dir)),
+            smtp: config.smtp.as_ref().map(delivery::SmtpSettings::from_config),
+        }
+    }
+}
@@ -1,51 +1,86 @@
-use axum::Router;
+use anyhow::Context;
+use axum::extract::DefaultBodyLimit;
+use axum_server::tls_rustls::RustlsConfig;
 use dotenvy::dotenv;
-use std::net::SocketAddr;
-use sqlx::postgres::PgPoolOptions;
 use tracing_subscriber::{EnvFilter, fmt};
-use tower_http::cors::{CorsLayer};
 use tower_http::trace::TraceLayer;
 
-mod models;
-mod handlers;
-mod routes;
-mod utils;
-
-#[derive(Clone)]
-pub struct AppState {
-    pool: sqlx::PgPool,
-}
+use backend::{
+    AppState,
+    config::{Config, LogFormat},
+    db,
+    frontend::Frontend,
+    handlers, jobs, routes,
+};
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     dotenv().ok();
 
-    fmt()
-        .with_env_filter(EnvFilter::from_default_env())
-        .init();
+    let config = Config::load()?;
+
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(&config.log.filter));
+    let logger = fmt().with_env_filter(filter);
+    match config.log.format {
+        LogFormat::Pretty => logger.init(),
+        LogFormat::Json => logger.json().init(),
+    }
+
+    let pool = db::connect(&config.database).await?;
 
-    let db_url = std::env::var("DATABASE_URL")?;
-    let pool = PgPoolOptions::new()
-        .max_connections(10)
-        .connect(&db_url)
-        .await?;
+    if config.migrations.enabled {
+        db::MIGRATOR.run(&pool).await?;
+        let status = db::migration_status(&pool).await?;
+        tracing::info!("Database schema at migration {}", status.current.unwrap_or_default());
+    } else {
+        tracing::info!("Migrations skipped (migrations.enabled is off)");
+        let status = db::migration_status(&pool).await?;
+        if !status.pending.is_empty() {
+            tracing::warn!("{} migrations are not applied yet: {:?}", status.pending.len(), status.pending);
+        }
+    }
+    db::ensure_system_data(&pool).await?;
 
-    let state = AppState { pool };
+    let state = AppState::from_config(pool, &config);
 
-    let app = Router::new()
-        .merge(routes::router())
+    // Finish removing files of attachments deleted before the last shutdown
+    handlers::attachments::purge_deleted_attachment_files(&state).await;
+
+    // Periodic jobs, shared between instances through advisory locks
+    if config.jobs.enabled {
+        jobs::spawn_scheduler(state.clone());
+    } else {
+        tracing::info!("Job scheduler disabled (jobs.enabled is off)");
+    }
+
+    let frontend = Frontend::new(config.frontend.dir.clone());
+    match &frontend {
+        Some(frontend) => tracing::info!("Serving the frontend {}", frontend.describe()),
+        None => tracing::info!("No frontend to serve (frontend.dir is not set); the API is under {}", routes::API_PREFIX),
+    }
+
+    let app = routes::app(frontend)
         .with_state(state)
-        .layer(CorsLayer::permissive())
+        .layer(DefaultBodyLimit::max(config.limits.body_limit_bytes))
+        .layer(config.cors_layer())
         .layer(TraceLayer::new_for_http());
 
-    let port: u16 = std::env::var("PORT")
-        .unwrap_or_else(|_| "3000".to_string())
-        .parse()
-        .expect("PORT must be a valid u16");
-    let addr = SocketAddr::from(([127, 0, 0, 1], port));
-    let listener = tokio::net::TcpListener::bind(addr).await?;
-    tracing::info!("Listening on http://{}", addr);
-
-    axum::serve(listener, app).await?;
+    let addr = config.listen_addr();
+    match &config.server.tls {
+        Some(tls) => {
+            // rustls is built with ring only, so there's no other provider to pick
+            let _ = rustls::crypto::ring::default_provider().install_default();
+            let rustls = RustlsConfig::from_pem_file(&tls.cert_path, &tls.key_path)
+                .await
+                .context("Failed to load the TLS certificate or key")?;
+            tracing::info!("Listening on https://{}", addr);
+            axum_server::bind_rustls(addr, rustls).serve(app.into_make_service()).await?;
+        }
+        None => {
+            let listener = tokio::net::TcpListener::bind(addr).await?;
+            tracing::info!("Listening on http://{}", addr);
+            axum::serve(listener, app).await?;
+        }
+    }
     Ok(())
 }
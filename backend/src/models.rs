@@ -1,9 +1,114 @@
 use chrono::{NaiveDate, NaiveDateTime};
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
 use sqlx::FromRow;
-use sqlx::types::BigDecimal;
+use utoipa::{IntoParams, ToSchema};
 
-#[derive(Serialize, FromRow)]
+pub use crate::money::{Money, Quantity};
+
+// Fixed set of values sent as snake_case text in JSON and bound as text in SQL
+macro_rules! text_enum {
+    ($(#[$meta:meta])* $name:ident { $($variant:ident => $text:literal),+ $(,)? }) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+        pub enum $name {
+            $(#[serde(rename = $text)] $variant),+
+        }
+
+        impl $name {
+            pub const ALL: &[$name] = &[$($name::$variant),+];
+
+            pub fn as_str(&self) -> &'static str {
+                match self {
+                    $($name::$variant => $text),+
+                }
+            }
+        }
+
+        // Written by hand, the derive doesn't see renames made in a macro
+        impl utoipa::PartialSchema for $name {
+            fn schema() -> utoipa::openapi::RefOr<utoipa::openapi::schema::Schema> {
+                utoipa::openapi::schema::ObjectBuilder::new()
+                    .schema_type(utoipa::openapi::schema::Type::String)
+                    .enum_values(Some([$($text),+]))
+                    .into()
+            }
+        }
+
+        impl ToSchema for $name {}
+
+        impl std::fmt::Display for $name {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                f.write_str(self.as_str())
+            }
+        }
+
+        impl std::str::FromStr for $name {
+            type Err = String;
+
+            fn from_str(value: &str) -> Result<Self, String> {
+                $name::ALL
+                    .iter()
+                    .find(|v| v.as_str() == value)
+                    .copied()
+                    .ok_or_else(|| format!("Unknown {} '{}'", stringify!($name), value))
+            }
+        }
+    };
+}
+
+text_enum!(OperationType {
+    Income => "income",
+    Expense => "expense",
+});
+
+text_enum!(RecurringFrequency {
+    Daily => "daily",
+    Weekly => "weekly",
+    Biweekly => "biweekly",
+    Monthly => "monthly",
+    Quarterly => "quarterly",
+    Yearly => "yearly",
+});
+
+text_enum!(InvestmentTransactionType {
+    Buy => "buy",
+    Sell => "sell",
+    ValueIncrease => "value_increase",
+    ValueDecrease => "value_decrease",
+});
+
+text_enum!(
+    // asset_types.category
+    #[derive(sqlx::Type)]
+    #[sqlx(type_name = "varchar", rename_all = "snake_case")]
+    AssetCategory {
+        Liquid => "liquid",
+        Investment => "investment",
+        Property => "property",
+        Vehicle => "vehicle",
+        Valuable => "valuable",
+        Liability => "liability",
+    }
+);
+
+text_enum!(NotificationRuleType {
+    BudgetThreshold => "budget_threshold",
+    LargeOperation => "large_operation",
+    LowBalance => "low_balance",
+    GoalDeadline => "goal_deadline",
+    BillDue => "bill_due",
+});
+
+text_enum!(TransferType {
+    LiquidToLiquid => "liquid_to_liquid",
+    LiquidToInvestment => "liquid_to_investment",
+    LiquidToProperty => "liquid_to_property",
+    LiquidToVehicle => "liquid_to_vehicle",
+    LiquidToValuable => "liquid_to_valuable",
+    LiquidToLiability => "liquid_to_liability",
+});
+
+#[derive(Serialize, FromRow, ToSchema)]
 pub struct User {
     pub id: i32,
     pub full_name: String,
@@ -11,13 +116,13 @@ pub struct User {
     pub creation_date: Option<NaiveDateTime>,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, ToSchema)]
 pub struct CreateUser {
     pub full_name: String,
     pub nick: String,
 }
 
-#[derive(Serialize, FromRow)]
+#[derive(Serialize, FromRow, ToSchema)]
 pub struct Category {
     pub id: i32,
     pub name: String,
@@ -29,48 +134,71 @@ pub struct Category {
     pub is_hidden: bool,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, ToSchema)]
 pub struct CreateCategory {
     pub name: String,
     pub parent_id: Option<i32>,
-    pub r#type: String,
+    // Categories have the same types as operations
+    pub r#type: OperationType,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct MoveCategory {
+    pub parent_id: Option<i32>,
+    // Zero-based position among the new siblings, defaults to the end
+    pub position: Option<usize>,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct DeleteCategoryQuery {
+    pub reassign_to: Option<i32>,
 }
 
-#[derive(Deserialize)]
+#[derive(Serialize, ToSchema)]
+pub struct CategoryMergeResult {
+    pub category: Category,
+    pub operations_moved: u64,
+    pub budgets_moved: u64,
+    pub recurring_operations_moved: u64,
+    pub subcategories_moved: u64,
+}
+
+#[derive(Deserialize, ToSchema)]
 pub struct ReorderCategories {
     pub items: Vec<ReorderItem>,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, ToSchema)]
 pub struct ReorderAssets {
     pub items: Vec<ReorderItem>,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, ToSchema)]
 pub struct ReorderItem {
     pub id: i32,
     pub sort_order: i32,
 }
 
-#[derive(Serialize, FromRow)]
+#[derive(Serialize, FromRow, ToSchema)]
 pub struct CategoryUsed {
     pub id: i32,
     pub is_used: bool,
 }
 
 // Asset Types
-#[derive(Serialize, FromRow)]
+#[derive(Serialize, FromRow, ToSchema)]
 pub struct AssetType {
     pub id: i32,
     pub name: String,
-    pub category: String,
+    pub category: AssetCategory,
     pub icon: Option<String>,
     pub allows_operations: bool,
     pub created_date: Option<NaiveDateTime>,
 }
 
 // Assets (replaces Account)
-#[derive(Serialize, FromRow)]
+#[derive(Serialize, FromRow, ToSchema)]
 pub struct Asset {
     pub id: i32,
     pub user_id: i32,
@@ -78,76 +206,139 @@ pub struct Asset {
     pub name: String,
     pub description: Option<String>,
     pub account_number: Option<String>,
-    pub quantity: Option<BigDecimal>,
-    pub average_purchase_price: Option<BigDecimal>,
-    pub current_valuation: Option<BigDecimal>,
+    pub quantity: Option<Quantity>,
+    pub average_purchase_price: Option<Money>,
+    pub current_valuation: Option<Money>,
     pub currency: String,
     pub is_active: bool,
     pub created_date: Option<NaiveDateTime>,
     pub sort_order: i32,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, ToSchema)]
 pub struct CreateAsset {
     pub user_id: i32,
     pub asset_type_id: i32,
     pub name: String,
     pub description: Option<String>,
     pub account_number: Option<String>,
-    pub quantity: Option<f64>,
-    pub average_purchase_price: Option<f64>,
-    pub current_valuation: Option<f64>,
+    pub quantity: Option<Quantity>,
+    pub average_purchase_price: Option<Money>,
+    pub current_valuation: Option<Money>,
     pub currency: Option<String>,
-    pub initial_balance: Option<f64>,
+    pub initial_balance: Option<Money>,
+}
+
+// Balances
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct AssetBalanceQuery {
+    pub at: Option<NaiveDate>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct AssetBalance {
+    pub asset_id: i32,
+    pub at: Option<NaiveDate>,
+    pub balance: Money,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct LedgerQuery {
+    pub date_from: Option<NaiveDate>,
+    pub date_to: Option<NaiveDate>,
+}
+
+#[derive(Serialize, FromRow, ToSchema)]
+pub struct LedgerEntry {
+    pub operation_id: i32,
+    pub operation_date: NaiveDate,
+    pub description: Option<String>,
+    pub category_id: Option<i32>,
+    pub category_name: Option<String>,
+    pub operation_type: String,
+    pub amount: Money,
+    pub linked_operation_id: Option<i32>,
+    pub clearing_status: String,
+    // Balance after this operation
+    #[sqlx(skip)]
+    pub balance: Money,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct AssetLedger {
+    pub asset_id: i32,
+    // Balance before the first entry
+    pub opening_balance: Money,
+    pub closing_balance: Money,
+    pub entries: Vec<LedgerEntry>,
+}
+
+// An asset whose stored balance didn't match its operations
+#[derive(Serialize, FromRow, ToSchema)]
+pub struct BalanceDrift {
+    pub asset_id: i32,
+    pub asset_name: String,
+    pub stored_balance: Option<Money>,
+    pub calculated_balance: Money,
+    #[sqlx(skip)]
+    pub difference: Money,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct RecalculateBalancesResponse {
+    pub checked: usize,
+    pub repaired: Vec<BalanceDrift>,
 }
 
 // Investment Transactions
-#[derive(Serialize, FromRow)]
+#[derive(Serialize, FromRow, ToSchema)]
 pub struct InvestmentTransaction {
     pub id: i32,
     pub asset_id: i32,
     pub transaction_type: String,
-    pub quantity: Option<BigDecimal>,
-    pub price_per_unit: Option<BigDecimal>,
-    pub total_value: BigDecimal,
+    pub quantity: Option<Quantity>,
+    pub price_per_unit: Option<Money>,
+    pub total_value: Money,
     pub transaction_date: NaiveDate,
     pub notes: Option<String>,
     pub created_date: Option<NaiveDateTime>,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, ToSchema)]
 pub struct CreateInvestmentTransaction {
     pub asset_id: i32,
-    pub transaction_type: String,
-    pub quantity: Option<BigDecimal>,
-    pub price_per_unit: Option<BigDecimal>,
-    pub total_value: BigDecimal,
-    pub transaction_date: String,
+    pub transaction_type: InvestmentTransactionType,
+    pub quantity: Option<Quantity>,
+    pub price_per_unit: Option<Money>,
+    pub total_value: Money,
+    pub transaction_date: NaiveDate,
     pub notes: Option<String>,
 }
 
 // Asset Valuations
-#[derive(Serialize, FromRow)]
+#[derive(Serialize, FromRow, ToSchema)]
 pub struct AssetValuation {
     pub id: i32,
     pub asset_id: i32,
     pub valuation_date: NaiveDate,
-    pub value: BigDecimal,
+    pub value: Money,
     pub notes: Option<String>,
     pub created_date: Option<NaiveDateTime>,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, ToSchema)]
 pub struct CreateAssetValuation {
     pub asset_id: i32,
-    pub valuation_date: String,
-    pub value: BigDecimal,
+    pub valuation_date: NaiveDate,
+    pub value: Money,
     pub notes: Option<String>,
 }
 
 // Legacy Account structs (for backwards compatibility during migration)
 #[allow(dead_code)]
-#[derive(Serialize, FromRow)]
+#[derive(Serialize, FromRow, ToSchema)]
 pub struct Account {
     pub id: i32,
     pub user_id: i32,
@@ -157,46 +348,50 @@ pub struct Account {
 }
 
 #[allow(dead_code)]
-#[derive(Deserialize)]
+#[derive(Deserialize, ToSchema)]
 pub struct CreateAccount {
     pub user_id: i32,
     pub name: String,
     pub account_number: Option<String>,
 }
 
-#[derive(Serialize, FromRow)]
+#[derive(Serialize, FromRow, ToSchema)]
 pub struct Operation {
     pub id: i32,
     pub creation_date: Option<NaiveDateTime>,
     pub category_id: Option<i32>,
     pub description: Option<String>,
     pub asset_id: i32,
-    pub amount: BigDecimal,
+    pub amount: Money,
     pub operation_type: String,
     pub operation_date: NaiveDate,
     pub parent_operation_id: Option<i32>,
     pub is_split: bool,
     pub linked_operation_id: Option<i32>,
+    pub payee_id: Option<i32>,
+    pub clearing_status: String,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 pub struct OperationWithHashtags {
     pub id: i32,
     pub creation_date: Option<NaiveDateTime>,
     pub category_id: Option<i32>,
     pub description: Option<String>,
     pub asset_id: i32,
-    pub amount: BigDecimal,
+    pub amount: Money,
     pub operation_type: String,
     pub operation_date: NaiveDate,
     pub parent_operation_id: Option<i32>,
     pub is_split: bool,
     pub linked_operation_id: Option<i32>,
+    pub payee_id: Option<i32>,
+    pub clearing_status: String,
     pub hashtags: Vec<Hashtag>,
 }
 
 // Extended operation with JOINed data for frontend
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 pub struct OperationWithDetails {
     pub id: i32,
     pub creation_date: Option<NaiveDateTime>,
@@ -206,61 +401,95 @@ pub struct OperationWithDetails {
     pub description: Option<String>,
     pub asset_id: i32,
     pub asset_name: Option<String>,
-    pub amount: BigDecimal,
+    pub amount: Money,
     pub operation_type: String,
     pub operation_date: NaiveDate,
     pub parent_operation_id: Option<i32>,
     pub is_split: bool,
     pub linked_operation_id: Option<i32>,
+    pub payee_id: Option<i32>,
+    pub payee_name: Option<String>,
+    pub clearing_status: String,
     pub hashtags: Vec<Hashtag>,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, ToSchema)]
 pub struct CreateOperation {
-    #[allow(dead_code)]
-    pub creation_date: Option<String>,
     pub category_id: Option<i32>,
     pub description: Option<String>,
     pub asset_id: i32,
-    pub amount: BigDecimal,
-    pub operation_type: String,
-    pub operation_date: String,
+    pub amount: Money,
+    pub operation_type: OperationType,
+    pub operation_date: NaiveDate,
     pub split_items: Option<Vec<SplitItem>>,
+    // On update a missing payee_id keeps the payee and null removes it
+    #[serde(default, deserialize_with = "explicit_null")]
+    #[schema(value_type = Option<i32>)]
+    pub payee_id: Option<Option<i32>>,
+}
+
+// Some(None) for a field sent as null, None (by default) for a missing one
+fn explicit_null<'de, D: Deserializer<'de>, T: Deserialize<'de>>(deserializer: D) -> Result<Option<Option<T>>, D::Error> {
+    Option::<T>::deserialize(deserializer).map(Some)
 }
 
 // Split operations
-#[derive(Deserialize)]
+#[derive(Deserialize, ToSchema)]
 pub struct SplitOperationRequest {
     pub items: Vec<SplitItem>,
 }
 
-#[derive(Deserialize, Clone)]
+#[derive(Deserialize, Clone, ToSchema)]
 pub struct SplitItem {
     pub category_id: i32,
-    pub amount: BigDecimal,
+    pub amount: Money,
     pub description: Option<String>,
+    // Appended to the part's description as #tags
+    pub hashtags: Option<Vec<String>>,
 }
 
-#[derive(Serialize, FromRow, Deserialize)]
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct OperationViolationQuery {
+    pub asset_id: Option<i32>,
+}
+
+// An existing operation that breaks a consistency rule
+#[derive(Serialize, FromRow, ToSchema)]
+pub struct OperationViolation {
+    pub operation_id: i32,
+    pub asset_id: i32,
+    pub operation_date: NaiveDate,
+    pub description: Option<String>,
+    pub amount: Money,
+    pub operation_type: String,
+    pub category_id: Option<i32>,
+    pub category_name: Option<String>,
+    pub category_type: Option<String>,
+    pub rule: String,
+    pub suggested_fix: String,
+}
+
+#[derive(Serialize, FromRow, Deserialize, ToSchema)]
 pub struct Budget {
     pub id: i32,
     pub category_id: i32,
     pub month: NaiveDate,
-    pub planned_amount: BigDecimal,
+    pub planned_amount: Money,
     pub description: Option<String>,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, ToSchema)]
 pub struct CreateBudget {
     pub id: Option<i32>,
     pub category_id: i32,
     pub month: NaiveDate,
-    pub planned_amount: BigDecimal,
+    pub planned_amount: Money,
     pub description: Option<String>,
 }
 
 // Budget with JOINed category data for frontend
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 pub struct BudgetWithCategory {
     pub id: i32,
     pub category_id: i32,
@@ -268,66 +497,150 @@ pub struct BudgetWithCategory {
     pub category_type: String,
     pub parent_id: Option<i32>,
     pub month: NaiveDate,
-    pub planned_amount: BigDecimal,
+    pub planned_amount: Money,
     pub description: Option<String>,
 }
 
 // Complete budget data response with spending
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 pub struct BudgetDataResponse {
     pub budgets: Vec<BudgetWithCategory>,
     pub spending: Vec<CategorySpending>,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 pub struct CategorySpending {
     pub category_id: i32,
-    pub amount: BigDecimal,
+    pub amount: Money,
 }
 
-#[derive(Serialize, FromRow)]
+#[derive(Serialize, FromRow, ToSchema)]
 pub struct Goal {
     pub id: i32,
     pub user_id: i32,
     pub asset_id: i32,
     pub name: String,
-    pub target_amount: BigDecimal,
-    pub current_amount: BigDecimal,
+    pub target_amount: Money,
+    pub current_amount: Money,
     pub target_date: NaiveDate,
     pub created_date: NaiveDateTime,
     pub completed_date: Option<NaiveDateTime>,
     pub is_completed: bool,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, ToSchema)]
 pub struct CreateGoal {
     pub user_id: i32,
     pub asset_id: i32,
     pub name: String,
-    pub target_amount: BigDecimal,
-    pub target_date: String,
+    pub target_amount: Money,
+    pub target_date: NaiveDate,
 }
 
-#[derive(Serialize, FromRow, Clone)]
+#[derive(Serialize, FromRow, Clone, ToSchema)]
 pub struct Hashtag {
     pub id: i32,
     pub name: String,
     pub created_date: Option<NaiveDateTime>,
     pub usage_count: i32,
+    pub budget_cap: Option<Money>,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, ToSchema)]
 pub struct CreateHashtag {
     pub name: String,
 }
 
-#[derive(Serialize, FromRow)]
+#[derive(Deserialize, ToSchema)]
+pub struct TagOperationRequest {
+    pub hashtags: Vec<String>,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct ExtractHashtagsRequest {
+    pub text: String,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct MergeHashtagsRequest {
+    pub source_ids: Vec<i32>,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct SetHashtagBudgetCap {
+    pub budget_cap: Option<Money>,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct HashtagReportQuery {
+    pub date_from: Option<NaiveDate>,
+    pub date_to: Option<NaiveDate>,
+    // "day" (default) or "week"
+    pub interval: Option<String>,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct HashtagCompareQuery {
+    // Comma-separated hashtag ids
+    pub ids: String,
+    pub date_from: Option<NaiveDate>,
+    pub date_to: Option<NaiveDate>,
+}
+
+#[derive(Serialize, FromRow, ToSchema)]
+pub struct HashtagSummary {
+    pub hashtag_id: i32,
+    pub name: String,
+    pub operation_count: i64,
+    pub income: Money,
+    pub expense: Money,
+    pub net: Money,
+    pub first_date: Option<NaiveDate>,
+    pub last_date: Option<NaiveDate>,
+    pub budget_cap: Option<Money>,
+    #[sqlx(skip)]
+    pub budget_remaining: Option<Money>,
+    #[sqlx(skip)]
+    pub over_budget: bool,
+}
+
+#[derive(Serialize, FromRow, ToSchema)]
+pub struct HashtagBreakdownRow {
+    pub id: Option<i32>,
+    pub name: Option<String>,
+    pub operation_count: i64,
+    pub income: Money,
+    pub expense: Money,
+    pub net: Money,
+}
+
+#[derive(Serialize, FromRow, ToSchema)]
+pub struct HashtagTimelinePoint {
+    pub period_start: NaiveDate,
+    pub income: Money,
+    pub expense: Money,
+    pub net: Money,
+    pub cumulative_expense: Money,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct HashtagReport {
+    #[serde(flatten)]
+    pub summary: HashtagSummary,
+    pub by_category: Vec<HashtagBreakdownRow>,
+    pub by_asset: Vec<HashtagBreakdownRow>,
+    pub timeline: Vec<HashtagTimelinePoint>,
+}
+
+#[derive(Serialize, FromRow, ToSchema)]
 pub struct RecurringOperation {
     pub id: i32,
     pub asset_id: i32,
     pub category_id: Option<i32>,
     pub description: Option<String>,
-    pub amount: BigDecimal,
+    pub amount: Money,
     pub operation_type: String,
     pub frequency: String,
     pub start_date: NaiveDate,
@@ -338,7 +651,7 @@ pub struct RecurringOperation {
 }
 
 // Extended recurring operation with JOINed data
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 pub struct RecurringOperationWithDetails {
     pub id: i32,
     pub asset_id: i32,
@@ -346,7 +659,7 @@ pub struct RecurringOperationWithDetails {
     pub category_id: Option<i32>,
     pub category_name: Option<String>,
     pub description: Option<String>,
-    pub amount: BigDecimal,
+    pub amount: Money,
     pub operation_type: String,
     pub frequency: String,
     pub start_date: NaiveDate,
@@ -356,29 +669,95 @@ pub struct RecurringOperationWithDetails {
     pub last_generated: Option<NaiveDate>,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, ToSchema)]
 pub struct CreateRecurringOperation {
     pub asset_id: i32,
     pub category_id: Option<i32>,
     pub description: Option<String>,
-    pub amount: BigDecimal,
-    pub operation_type: String,
-    pub frequency: String,
-    pub start_date: String,
-    pub end_date: Option<String>,
+    pub amount: Money,
+    pub operation_type: OperationType,
+    pub frequency: RecurringFrequency,
+    pub start_date: NaiveDate,
+    pub end_date: Option<NaiveDate>,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, ToSchema)]
 pub struct UpdateRecurringOperation {
     pub description: Option<String>,
-    pub amount: Option<BigDecimal>,
+    pub amount: Option<Money>,
     pub category_id: Option<i32>,
-    pub end_date: Option<String>,
+    pub end_date: Option<NaiveDate>,
     pub is_active: Option<bool>,
 }
 
+// Loan schedules
+#[derive(Serialize, FromRow, ToSchema)]
+pub struct LoanSchedule {
+    pub id: i32,
+    pub liability_asset_id: i32,
+    pub payment_asset_id: i32,
+    pub installment_amount: Money,
+    pub day_of_month: i32,
+    pub start_date: NaiveDate,
+    pub end_date: Option<NaiveDate>,
+    pub created_date: Option<NaiveDateTime>,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct UpsertLoanSchedule {
+    pub payment_asset_id: i32,
+    pub installment_amount: Money,
+    pub day_of_month: i32,
+    pub start_date: NaiveDate,
+    pub end_date: Option<NaiveDate>,
+}
+
+// Cash-flow forecast
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ForecastQuery {
+    pub days: Option<i64>,
+}
+
+#[derive(Serialize, Clone, ToSchema)]
+pub struct ForecastItem {
+    // "recurring", "budget" or "installment"
+    pub source: String,
+    pub reference_id: Option<i32>,
+    pub description: Option<String>,
+    pub amount: Money,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ForecastDay {
+    pub date: NaiveDate,
+    pub items: Vec<ForecastItem>,
+    pub balance: Money,
+    pub is_negative: bool,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct AccountForecast {
+    pub asset_id: i32,
+    pub asset_name: String,
+    pub currency: String,
+    pub starting_balance: Money,
+    pub ending_balance: Money,
+    pub lowest_balance: Money,
+    pub lowest_balance_date: Option<NaiveDate>,
+    pub first_negative_date: Option<NaiveDate>,
+    pub days: Vec<ForecastDay>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ForecastResponse {
+    pub start_date: NaiveDate,
+    pub end_date: NaiveDate,
+    pub accounts: Vec<AccountForecast>,
+}
+
 // Transfer structures
-#[derive(Deserialize)]
+#[derive(Deserialize, ToSchema)]
 pub struct NewAssetData {
     pub asset_type_id: i32,
     pub name: String,
@@ -387,26 +766,26 @@ pub struct NewAssetData {
     pub currency: Option<String>,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, ToSchema)]
 pub struct TransferRequest {
     pub from_asset_id: i32,
     pub to_asset_id: Option<i32>,
-    pub amount: f64,
-    pub transfer_type: String, // "liquid_to_liquid", "liquid_to_investment", "liquid_to_property", etc.
+    pub amount: Money,
+    pub transfer_type: TransferType,
     pub description: Option<String>,
-    pub operation_date: String,
+    pub operation_date: NaiveDate,
 
     // For creating new assets
     pub new_asset: Option<NewAssetData>,
 
     // For investment transactions
-    pub investment_quantity: Option<f64>,
+    pub investment_quantity: Option<Quantity>,
 
     // For liability payments - interest amount
-    pub interest_amount: Option<f64>,
+    pub interest_amount: Option<Money>,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 pub struct TransferResponse {
     pub success: bool,
     pub from_operation_id: Option<i32>,
@@ -417,7 +796,7 @@ pub struct TransferResponse {
 }
 
 // Import Templates
-#[derive(Serialize, FromRow)]
+#[derive(Serialize, FromRow, ToSchema)]
 pub struct ImportTemplate {
     pub id: i32,
     pub user_id: i32,
@@ -427,19 +806,400 @@ pub struct ImportTemplate {
     pub updated_at: Option<NaiveDateTime>,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, ToSchema)]
 pub struct CreateImportTemplate {
     pub name: String,
     pub template_data: sqlx::types::JsonValue,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, ToSchema)]
 pub struct UpdateImportTemplate {
     pub name: Option<String>,
     pub template_data: Option<sqlx::types::JsonValue>,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, ToSchema)]
 pub struct CorrectBalanceRequest {
-    pub target_balance: f64,
+    pub target_balance: Money,
+    // Balance at the end of this day (defaults to today)
+    pub correction_date: Option<NaiveDate>,
+    pub note: String,
+}
+
+#[derive(Serialize, FromRow, ToSchema)]
+pub struct BalanceCorrection {
+    pub id: i32,
+    pub asset_id: i32,
+    pub operation_id: i32,
+    pub correction_date: NaiveDate,
+    pub balance_before: Money,
+    pub target_balance: Money,
+    pub amount: Money,
+    pub note: String,
+    pub created_date: Option<NaiveDateTime>,
+    // Sum of this and all earlier corrections of the asset
+    #[sqlx(skip)]
+    pub cumulative_amount: Money,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct CorrectionReportQuery {
+    pub date_from: Option<NaiveDate>,
+    pub date_to: Option<NaiveDate>,
+}
+
+#[derive(Serialize, FromRow, ToSchema)]
+pub struct CorrectionReportRow {
+    pub asset_id: i32,
+    pub asset_name: String,
+    pub currency: String,
+    pub correction_count: i64,
+    pub total_positive: Money,
+    pub total_negative: Money,
+    pub net_amount: Money,
+    pub last_correction_date: Option<NaiveDate>,
+}
+
+// Bulk operations
+#[derive(Deserialize, ToSchema)]
+pub struct BulkOperationRequest {
+    pub ids: Option<Vec<i32>>,
+    pub filter: Option<OperationFilter>,
+    pub action: BulkAction,
+    pub atomic: Option<bool>,
+}
+
+#[derive(Deserialize, Default, ToSchema)]
+pub struct OperationFilter {
+    pub asset_id: Option<i32>,
+    pub category_id: Option<i32>,
+    pub uncategorized: Option<bool>,
+    pub operation_type: Option<OperationType>,
+    pub date_from: Option<NaiveDate>,
+    pub date_to: Option<NaiveDate>,
+    pub description_contains: Option<String>,
+    pub payee_id: Option<i32>,
+}
+
+#[derive(Deserialize, ToSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BulkAction {
+    SetCategory { category_id: Option<i32> },
+    AddHashtags { hashtags: Vec<String> },
+    RemoveHashtags { hashtags: Vec<String> },
+    MoveToAsset { asset_id: i32 },
+    SetPayee { payee_id: Option<i32> },
+    ShiftDates { days: i32 },
+    Delete,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct BulkItemResult {
+    pub id: i32,
+    pub success: bool,
+    pub error: Option<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct BulkOperationResponse {
+    pub matched: usize,
+    pub succeeded: usize,
+    pub failed: usize,
+    pub committed: bool,
+    pub results: Vec<BulkItemResult>,
+    pub recalculated_asset_ids: Vec<i32>,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct BatchCreateOperationsRequest {
+    // Read one by one, so a malformed item fails on its own
+    pub items: Vec<serde_json::Value>,
+    pub atomic: Option<bool>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct BatchCreateItemResult {
+    pub index: usize,
+    pub success: bool,
+    pub operation: Option<Operation>,
+    pub error: Option<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct BatchCreateOperationsResponse {
+    pub created: usize,
+    pub failed: usize,
+    pub committed: bool,
+    pub results: Vec<BatchCreateItemResult>,
+    pub recalculated_asset_ids: Vec<i32>,
+}
+
+// Payees
+#[derive(Serialize, FromRow, ToSchema)]
+pub struct Payee {
+    pub id: i32,
+    pub name: String,
+    pub aliases: Vec<String>,
+    pub default_category_id: Option<i32>,
+    pub default_hashtag: Option<String>,
+    pub created_date: Option<NaiveDateTime>,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct CreatePayee {
+    pub name: String,
+    pub aliases: Option<Vec<String>>,
+    pub default_category_id: Option<i32>,
+    pub default_hashtag: Option<String>,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct MergePayeesRequest {
+    pub source_ids: Vec<i32>,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct PayeeReportQuery {
+    pub date_from: Option<NaiveDate>,
+    pub date_to: Option<NaiveDate>,
+    pub asset_id: Option<i32>,
+}
+
+#[derive(Serialize, FromRow, ToSchema)]
+pub struct PayeeReportRow {
+    pub payee_id: Option<i32>,
+    pub payee_name: Option<String>,
+    pub operation_count: i64,
+    pub income: Money,
+    pub expense: Money,
+    pub net: Money,
+}
+
+// Attachments
+#[derive(Serialize, FromRow, ToSchema)]
+pub struct Attachment {
+    pub id: i32,
+    pub operation_id: i32,
+    pub file_name: String,
+    pub content_type: String,
+    pub size_bytes: i64,
+    pub checksum_sha256: String,
+    #[serde(skip_serializing)]
+    pub storage_key: String,
+    pub created_date: Option<NaiveDateTime>,
+}
+
+// Reconciliation
+#[derive(Serialize, FromRow, ToSchema)]
+pub struct Reconciliation {
+    pub id: i32,
+    pub asset_id: i32,
+    pub statement_date: NaiveDate,
+    pub statement_balance: Money,
+    pub status: String,
+    pub created_date: Option<NaiveDateTime>,
+    pub finished_date: Option<NaiveDateTime>,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct CreateReconciliation {
+    pub statement_date: NaiveDate,
+    pub statement_balance: Money,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct MarkClearedRequest {
+    pub operation_ids: Vec<i32>,
+    pub cleared: bool,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ReconciliationSummary {
+    pub reconciliation: Reconciliation,
+    pub cleared_balance: Money,
+    pub difference: Money,
+    pub cleared_count: i64,
+    pub uncleared_count: i64,
+}
+
+// Audit log
+#[derive(Serialize, FromRow, ToSchema)]
+pub struct AuditEntry {
+    pub id: i64,
+    pub user_id: Option<i32>,
+    pub action: String,
+    pub table_name: String,
+    pub record_id: i32,
+    pub action_date: NaiveDateTime,
+    pub before_data: Option<serde_json::Value>,
+    pub after_data: Option<serde_json::Value>,
+    pub request_id: Option<String>,
+    pub client_request_id: Option<String>,
+    pub reverted_at: Option<NaiveDateTime>,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct AuditQuery {
+    pub table_name: Option<String>,
+    pub record_id: Option<i32>,
+    pub action: Option<String>,
+    pub user_id: Option<i32>,
+    pub request_id: Option<String>,
+    pub client_request_id: Option<String>,
+    pub date_from: Option<NaiveDate>,
+    pub date_to: Option<NaiveDate>,
+    pub before_id: Option<i64>,
+    pub limit: Option<i64>,
+}
+
+// Background jobs
+#[derive(Serialize, FromRow, ToSchema)]
+pub struct JobRun {
+    pub id: i64,
+    pub name: String,
+    pub trigger: String,
+    pub status: String,
+    pub scheduled_for: Option<NaiveDateTime>,
+    pub started_at: NaiveDateTime,
+    pub finished_at: Option<NaiveDateTime>,
+    pub duration_ms: Option<i64>,
+    pub summary: Option<String>,
+    pub error: Option<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct JobInfo {
+    pub name: String,
+    pub schedule: String,
+    pub description: String,
+    pub next_run: Option<NaiveDateTime>,
+    pub last_run: Option<JobRun>,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct JobRunsQuery {
+    pub limit: Option<i64>,
+}
+
+// Notification rules and notifications
+#[derive(Serialize, FromRow, ToSchema)]
+pub struct NotificationRule {
+    pub id: i32,
+    pub name: String,
+    pub rule_type: String,
+    pub category_id: Option<i32>,
+    pub asset_id: Option<i32>,
+    pub threshold: Option<Money>,
+    pub days_ahead: Option<i32>,
+    pub webhook_url: Option<String>,
+    pub email_to: Option<String>,
+    pub is_active: bool,
+    pub created_date: NaiveDateTime,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct CreateNotificationRule {
+    pub name: String,
+    pub rule_type: NotificationRuleType,
+    pub category_id: Option<i32>,
+    pub asset_id: Option<i32>,
+    pub threshold: Option<Money>,
+    pub days_ahead: Option<i32>,
+    pub webhook_url: Option<String>,
+    pub email_to: Option<String>,
+    pub is_active: Option<bool>,
+}
+
+#[derive(Serialize, FromRow, ToSchema)]
+pub struct Notification {
+    pub id: i64,
+    pub rule_id: i32,
+    pub rule_name: String,
+    pub rule_type: String,
+    pub title: String,
+    pub message: String,
+    pub is_read: bool,
+    pub read_at: Option<NaiveDateTime>,
+    pub delivery_status: String,
+    pub delivery_error: Option<String>,
+    pub created_date: NaiveDateTime,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct NotificationQuery {
+    pub unread_only: Option<bool>,
+    pub before_id: Option<i64>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct UnreadCount {
+    pub unread: i64,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct MigrationStatus {
+    // Newest migration applied to the database
+    pub current: Option<i64>,
+    // Newest migration built into the backend
+    pub latest: Option<i64>,
+    pub pending: Vec<i64>,
+    pub failed: Vec<i64>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct PoolStats {
+    pub size: u32,
+    pub idle: usize,
+    pub max_connections: u32,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct DatabaseHealth {
+    pub connected: bool,
+    pub latency_ms: Option<i64>,
+    pub error: Option<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct HealthReport {
+    // "ok", or "unavailable" when /ready refuses traffic
+    pub status: String,
+    pub version: String,
+    pub database: DatabaseHealth,
+    pub migrations: Option<MigrationStatus>,
+    pub missing_system_data: Vec<String>,
+    pub pool: PoolStats,
+}
+
+// An operation, asset or category in the trash. Operations deleted with their
+// asset, split parent or the other side of a transfer are counted in
+// `related_count` of that item instead of being listed.
+#[derive(Serialize, FromRow, ToSchema)]
+pub struct TrashItem {
+    // "operation", "asset" or "category"
+    pub kind: String,
+    pub id: i32,
+    // Description of an operation, name of an asset or category
+    pub name: Option<String>,
+    pub amount: Option<Money>,
+    pub operation_date: Option<NaiveDate>,
+    pub asset_name: Option<String>,
+    pub deleted_at: NaiveDateTime,
+    pub related_count: i64,
+    // When the purge_trash job removes it for good
+    pub purge_after: NaiveDateTime,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct RestoreResponse {
+    pub kind: String,
+    pub id: i32,
+    // Operations that came back with the item
+    pub operation_ids: Vec<i32>,
 }
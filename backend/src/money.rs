@@ -0,0 +1,311 @@
+// Exact decimal values used by the API instead of f64 and bare BigDecimal.
+// `Money` is an amount with two decimal places, like the NUMERIC(_, 2) columns
+// it's stored in; `Quantity` is a number of investment units, DECIMAL(18, 8).
+// Both are read from JSON strings ("100.10") or numbers (100.1). A number is
+// taken by its shortest decimal form, so 100.1 never becomes 100.0999999.
+// More decimal places than the column holds is an error, not a silent
+// rounding by the database. Amounts are always sent back as strings.
+use bigdecimal::{BigDecimal, RoundingMode, Zero};
+use serde::{Deserialize, Deserializer, Serialize, Serializer, de};
+use std::{fmt, iter::Sum, ops, str::FromStr};
+use utoipa::{
+    PartialSchema, ToSchema,
+    openapi::{
+        RefOr,
+        schema::{ObjectBuilder, Schema, SchemaFormat, Type},
+    },
+};
+
+// Active ISO 4217 currency codes, without precious metals and testing codes
+const CURRENCY_CODES: &[&str] = &[
+    "AED", "AFN", "ALL", "AMD", "AOA", "ARS", "AUD", "AWG", "AZN", "BAM", "BBD", "BDT", "BGN",
+    "BHD", "BIF", "BMD", "BND", "BOB", "BOV", "BRL", "BSD", "BTN", "BWP", "BYN", "BZD", "CAD",
+    "CDF", "CHE", "CHF", "CHW", "CLF", "CLP", "CNY", "COP", "COU", "CRC", "CUP", "CVE", "CZK",
+    "DJF", "DKK", "DOP", "DZD", "EGP", "ERN", "ETB", "EUR", "FJD", "FKP", "GBP", "GEL", "GHS",
+    "GIP", "GMD", "GNF", "GTQ", "GYD", "HKD", "HNL", "HTG", "HUF", "IDR", "ILS", "INR", "IQD",
+    "IRR", "ISK", "JMD", "JOD", "JPY", "KES", "KGS", "KHR", "KMF", "KPW", "KRW", "KWD", "KYD",
+    "KZT", "LAK", "LBP", "LKR", "LRD", "LSL", "LYD", "MAD", "MDL", "MGA", "MKD", "MMK", "MNT",
+    "MOP", "MRU", "MUR", "MVR", "MWK", "MXN", "MXV", "MYR", "MZN", "NAD", "NGN", "NIO", "NOK",
+    "NPR", "NZD", "OMR", "PAB", "PEN", "PGK", "PHP", "PKR", "PLN", "PYG", "QAR", "RON", "RSD",
+    "RUB", "RWF", "SAR", "SBD", "SCR", "SDG", "SEK", "SGD", "SHP", "SLE", "SOS", "SRD", "SSP",
+    "STN", "SVC", "SYP", "SZL", "THB", "TJS", "TMT", "TND", "TOP", "TRY", "TTD", "TWD", "TZS",
+    "UAH", "UGX", "USD", "USN", "UYI", "UYU", "UYW", "UZS", "VED", "VES", "VND", "VUV", "WST",
+    "XAF", "XCD", "XCG", "XOF", "XPF", "YER", "ZAR", "ZMW", "ZWG",
+];
+
+pub fn is_currency_code(code: &str) -> bool {
+    CURRENCY_CODES.contains(&code)
+}
+
+// Minor units of the currencies that don't use two decimal places (ISO 4217)
+pub fn currency_minor_units(currency: &str) -> i64 {
+    match currency.to_ascii_uppercase().as_str() {
+        "BIF" | "CLP" | "DJF" | "GNF" | "ISK" | "JPY" | "KMF" | "KRW" | "PYG" | "RWF" | "UGX" | "UYI" | "VND"
+        | "VUV" | "XAF" | "XOF" | "XPF" => 0,
+        "BHD" | "IQD" | "JOD" | "KWD" | "LYD" | "OMR" | "TND" => 3,
+        _ => 2,
+    }
+}
+
+macro_rules! exact_decimal {
+    // Operator for every combination of owned and borrowed operands
+    (@binary $name:ident, $trait:ident, $method:ident) => {
+        impl ops::$trait<$name> for $name {
+            type Output = $name;
+
+            fn $method(self, other: $name) -> $name {
+                $name(ops::$trait::$method(self.0, other.0))
+            }
+        }
+
+        impl ops::$trait<&$name> for $name {
+            type Output = $name;
+
+            fn $method(self, other: &$name) -> $name {
+                $name(ops::$trait::$method(self.0, &other.0))
+            }
+        }
+
+        impl ops::$trait<$name> for &$name {
+            type Output = $name;
+
+            fn $method(self, other: $name) -> $name {
+                $name(ops::$trait::$method(&self.0, other.0))
+            }
+        }
+
+        impl ops::$trait<&$name> for &$name {
+            type Output = $name;
+
+            fn $method(self, other: &$name) -> $name {
+                $name(ops::$trait::$method(&self.0, &other.0))
+            }
+        }
+    };
+    ($name:ident, $scale:expr, $digits:expr, $what:expr) => {
+        #[derive(Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash, sqlx::Type)]
+        #[sqlx(transparent)]
+        pub struct $name(BigDecimal);
+
+        impl $name {
+            pub const SCALE: i64 = $scale;
+
+            // Parses a decimal, rejecting more decimal places or digits than the
+            // column holds
+            pub fn parse(value: &str) -> Result<Self, String> {
+                let parsed = BigDecimal::from_str(value.trim())
+                    .map_err(|_| format!("'{}' is not a valid {}", value, $what))?;
+                Self::exact(parsed)
+            }
+
+            pub fn exact(value: BigDecimal) -> Result<Self, String> {
+                let value = value.normalized();
+                if value.fractional_digit_count() > $scale {
+                    return Err(format!("{} can have at most {} decimal places", $what, $scale));
+                }
+                let value = value.with_scale($scale);
+                if value.digits() > $digits {
+                    return Err(format!("{} {} is too large", $what, value));
+                }
+                Ok(Self(value))
+            }
+
+            // For computed values (shares, averages): rounds half away from zero
+            pub fn rounded(value: BigDecimal) -> Self {
+                Self(value.with_scale_round($scale, RoundingMode::HalfUp))
+            }
+
+            pub fn zero() -> Self {
+                Self(BigDecimal::zero().with_scale($scale))
+            }
+
+            pub fn is_zero(&self) -> bool {
+                self.0.is_zero()
+            }
+
+            pub fn is_positive(&self) -> bool {
+                self.0 > BigDecimal::zero()
+            }
+
+            pub fn is_negative(&self) -> bool {
+                self.0 < BigDecimal::zero()
+            }
+
+            pub fn abs(&self) -> Self {
+                Self(self.0.abs())
+            }
+
+            pub fn as_decimal(&self) -> &BigDecimal {
+                &self.0
+            }
+
+            pub fn into_decimal(self) -> BigDecimal {
+                self.0
+            }
+        }
+
+        impl From<i64> for $name {
+            fn from(value: i64) -> Self {
+                Self(BigDecimal::from(value).with_scale($scale))
+            }
+        }
+
+        impl FromStr for $name {
+            type Err = String;
+
+            fn from_str(value: &str) -> Result<Self, String> {
+                Self::parse(value)
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str(&self.0.with_scale_round($scale, RoundingMode::HalfUp).to_plain_string())
+            }
+        }
+
+        impl Serialize for $name {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.collect_str(self)
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $name {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                struct Visitor;
+
+                impl de::Visitor<'_> for Visitor {
+                    type Value = $name;
+
+                    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                        write!(f, "{} as a decimal string or number", $what)
+                    }
+
+                    fn visit_str<E: de::Error>(self, value: &str) -> Result<$name, E> {
+                        $name::parse(value).map_err(E::custom)
+                    }
+
+                    fn visit_i64<E: de::Error>(self, value: i64) -> Result<$name, E> {
+                        $name::exact(BigDecimal::from(value)).map_err(E::custom)
+                    }
+
+                    fn visit_u64<E: de::Error>(self, value: u64) -> Result<$name, E> {
+                        $name::exact(BigDecimal::from(value)).map_err(E::custom)
+                    }
+
+                    // Display gives the shortest decimal that reads back as the
+                    // same f64, i.e. what the client wrote
+                    fn visit_f64<E: de::Error>(self, value: f64) -> Result<$name, E> {
+                        if !value.is_finite() {
+                            return Err(E::custom(format!("{} must be a finite number", $what)));
+                        }
+                        $name::parse(&value.to_string()).map_err(E::custom)
+                    }
+                }
+
+                deserializer.deserialize_any(Visitor)
+            }
+        }
+
+        // Documented as the string it's sent as
+        impl PartialSchema for $name {
+            fn schema() -> RefOr<Schema> {
+                ObjectBuilder::new()
+                    .schema_type(Type::String)
+                    .format(Some(SchemaFormat::Custom("decimal".to_string())))
+                    .pattern(Some(format!(r"^-?\d+(\.\d{{1,{}}})?$", $scale)))
+                    .description(Some(format!(
+                        "{} with up to {} decimal places; a JSON number is accepted too",
+                        $what, $scale
+                    )))
+                    .into()
+            }
+        }
+
+        impl ToSchema for $name {}
+
+        impl ops::Neg for $name {
+            type Output = $name;
+
+            fn neg(self) -> $name {
+                $name(-self.0)
+            }
+        }
+
+        impl ops::Neg for &$name {
+            type Output = $name;
+
+            fn neg(self) -> $name {
+                $name(-&self.0)
+            }
+        }
+
+        exact_decimal!(@binary $name, Add, add);
+        exact_decimal!(@binary $name, Sub, sub);
+
+        impl ops::AddAssign<&$name> for $name {
+            fn add_assign(&mut self, other: &$name) {
+                self.0 += &other.0;
+            }
+        }
+
+        impl ops::SubAssign<&$name> for $name {
+            fn sub_assign(&mut self, other: &$name) {
+                self.0 -= &other.0;
+            }
+        }
+
+        impl Sum for $name {
+            fn sum<I: Iterator<Item = $name>>(iter: I) -> $name {
+                iter.fold($name::zero(), |total, value| total + value)
+            }
+        }
+
+        impl<'a> Sum<&'a $name> for $name {
+            fn sum<I: Iterator<Item = &'a $name>>(iter: I) -> $name {
+                iter.fold($name::zero(), |total, value| &total + value)
+            }
+        }
+    };
+}
+
+// Widest money column is DECIMAL(18, 2); payloads check the narrower ones
+// with `Validator::amount`
+exact_decimal!(Money, 2, 18, "amount");
+exact_decimal!(Quantity, 8, 18, "quantity");
+
+// Precision of the narrower money columns: operations, budgets, goals, loan
+// installments and corrections, and reconciliations, hashtag budget caps and
+// notification thresholds
+pub const AMOUNT_DIGITS: u64 = 12;
+pub const BALANCE_DIGITS: u64 = 15;
+
+impl Money {
+    // Whether it fits a NUMERIC(digits, 2) column
+    pub fn fits(&self, digits: u64) -> bool {
+        self.0.digits() <= digits
+    }
+
+    // Rounds half away from zero to the currency's minor unit. Currencies with
+    // three decimal places keep two, like the columns.
+    pub fn round_for(&self, currency: &str) -> Money {
+        let places = currency_minor_units(currency).min(Self::SCALE);
+        Money(
+            self.0
+                .with_scale_round(places, RoundingMode::HalfUp)
+                .with_scale(Self::SCALE),
+        )
+    }
+
+    // Price of one unit, rounded for the currency
+    pub fn per_unit(&self, quantity: &Quantity, currency: &str) -> Option<Money> {
+        if quantity.is_zero() {
+            return None;
+        }
+        Some(Money::rounded(&self.0 / &quantity.0).round_for(currency))
+    }
+
+    pub fn times(&self, quantity: &Quantity) -> Money {
+        Money::rounded(&self.0 * &quantity.0)
+    }
+}
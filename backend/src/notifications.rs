@@ -0,0 +1,453 @@
+// Notification rules are checked after writes that can change what they watch
+// and by a scheduled job. A rule raises at most one notification per subject
+// (dedup_key): a budget, an operation, a goal deadline, a bill due date, or an
+// asset per day while its balance stays low. Notifications are kept for the
+// app; rules with a webhook URL or email address also deliver them there in
+// the background, and failed deliveries are retried by the scheduled job.
+use crate::{AppState, delivery, forecast, models::*, recurrence};
+use bigdecimal::BigDecimal;
+use chrono::{Datelike, Duration, Months, NaiveDate, NaiveDateTime};
+use sqlx::PgConnection;
+
+pub const RULE_COLUMNS: &str =
+    "id, name, rule_type, category_id, asset_id, threshold, days_ahead, webhook_url, email_to, is_active, created_date";
+
+pub const NOTIFICATION_COLUMNS: &str = "n.id, n.rule_id, r.name AS rule_name, r.rule_type, n.title, n.message,
+     n.is_read, n.read_at, n.delivery_status, n.delivery_error, n.created_date";
+
+const DEFAULT_GOAL_DAYS: i32 = 7;
+const DEFAULT_BILL_DAYS: i32 = 3;
+const MAX_DELIVERY_ATTEMPTS: i32 = 5;
+
+struct Alert {
+    dedup_key: String,
+    title: String,
+    message: String,
+}
+
+#[derive(sqlx::FromRow)]
+struct BudgetUsageRow {
+    id: i32,
+    category_name: String,
+    planned_amount: Money,
+    spent: Money,
+}
+
+#[derive(sqlx::FromRow)]
+struct LargeOperationRow {
+    id: i32,
+    amount: Money,
+    operation_date: NaiveDate,
+    description: Option<String>,
+    asset_name: String,
+    currency: String,
+}
+
+#[derive(sqlx::FromRow)]
+struct GoalRow {
+    id: i32,
+    name: String,
+    target_amount: Money,
+    current_amount: Money,
+    target_date: NaiveDate,
+}
+
+#[derive(sqlx::FromRow)]
+struct BillRow {
+    id: i32,
+    description: Option<String>,
+    amount: Money,
+    frequency: String,
+    start_date: NaiveDate,
+    end_date: Option<NaiveDate>,
+    last_generated: Option<NaiveDate>,
+    asset_name: String,
+    currency: String,
+}
+
+#[derive(sqlx::FromRow)]
+struct DeliveryRow {
+    id: i64,
+    title: String,
+    message: String,
+    created_date: NaiveDateTime,
+    rule_id: i32,
+    rule_name: String,
+    rule_type: String,
+    webhook_url: Option<String>,
+    email_to: Option<String>,
+}
+
+async fn budget_alerts(
+    conn: &mut PgConnection,
+    rule: &NotificationRule,
+    threshold: &Money,
+    today: NaiveDate,
+) -> Result<Vec<Alert>, sqlx::Error> {
+    let month_start = today.with_day(1).unwrap_or(today);
+    let month_end = month_start
+        .checked_add_months(Months::new(1))
+        .and_then(|d| d.pred_opt())
+        .unwrap_or(today);
+    let budgets = sqlx::query_as::<_, BudgetUsageRow>(
+        "SELECT b.id, c.name AS category_name, b.planned_amount,
+                COALESCE((
+                    SELECT -SUM(o.amount)
+                    FROM operations o
+                    INNER JOIN categories oc ON oc.id = o.category_id
+                    WHERE (oc.id = b.category_id OR oc.parent_id = b.category_id)
+                      AND o.is_split IS NOT TRUE AND o.deleted_at IS NULL
+                      AND o.operation_date >= $1 AND o.operation_date <= $2
+                ), 0) AS spent
+         FROM budgets b
+         INNER JOIN categories c ON c.id = b.category_id
+         WHERE b.month >= $1 AND b.month <= $2 AND c.type = 'expense' AND b.planned_amount > 0
+           AND ($3::int IS NULL OR b.category_id = $3)
+         ORDER BY b.id",
+    )
+    .bind(month_start)
+    .bind(month_end)
+    .bind(rule.category_id)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    Ok(budgets
+        .into_iter()
+        .filter_map(|b| {
+            let percent = (b.spent.as_decimal() * BigDecimal::from(100) / b.planned_amount.as_decimal()).round(0);
+            (percent >= *threshold.as_decimal()).then(|| Alert {
+                dedup_key: format!("budget:{}", b.id),
+                title: format!("Budget {} at {}%", b.category_name, percent),
+                message: format!(
+                    "Spent {} of {} planned for {} this month",
+                    b.spent,
+                    b.planned_amount,
+                    b.category_name
+                ),
+            })
+        })
+        .collect())
+}
+
+async fn large_operation_alerts(
+    conn: &mut PgConnection,
+    rule: &NotificationRule,
+    threshold: &Money,
+) -> Result<Vec<Alert>, sqlx::Error> {
+    // Only operations entered after the rule, so a new rule doesn't flag history
+    let operations = sqlx::query_as::<_, LargeOperationRow>(
+        "SELECT o.id, o.amount, o.operation_date, o.description, a.name AS asset_name,
+                COALESCE(a.currency, 'PLN') AS currency
+         FROM operations o
+         INNER JOIN assets a ON a.id = o.asset_id
+         LEFT JOIN categories c ON c.id = o.category_id
+         WHERE o.parent_operation_id IS NULL AND o.deleted_at IS NULL
+           AND ABS(o.amount) >= $1 AND o.creation_date >= $2
+           AND ($3::int IS NULL OR o.asset_id = $3)
+           AND ($4::int IS NULL OR c.id = $4 OR c.parent_id = $4)
+         ORDER BY o.id",
+    )
+    .bind(threshold)
+    .bind(rule.created_date)
+    .bind(rule.asset_id)
+    .bind(rule.category_id)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    Ok(operations
+        .into_iter()
+        .map(|o| Alert {
+            dedup_key: format!("operation:{}", o.id),
+            title: format!("Large operation: {} {}", o.amount, o.currency),
+            message: format!(
+                "{} {} on {} in {}{}",
+                o.amount,
+                o.currency,
+                o.operation_date,
+                o.asset_name,
+                o.description.map(|d| format!(": {}", d)).unwrap_or_default()
+            ),
+        })
+        .collect())
+}
+
+fn low_balance_alerts(
+    projection: &ForecastResponse,
+    rule: &NotificationRule,
+    threshold: &Money,
+    today: NaiveDate,
+) -> Vec<Alert> {
+    let window = usize::try_from(rule.days_ahead.unwrap_or(0)).unwrap_or(0);
+    projection
+        .accounts
+        .iter()
+        .filter(|a| rule.asset_id.is_none_or(|id| id == a.asset_id))
+        .filter_map(|a| {
+            let dedup_key = format!("asset:{}:{}", a.asset_id, today);
+            if a.starting_balance < *threshold {
+                return Some(Alert {
+                    dedup_key,
+                    title: format!("Low balance: {}", a.asset_name),
+                    message: format!(
+                        "Balance of {} is {} {}, below {}",
+                        a.asset_name,
+                        a.starting_balance,
+                        a.currency,
+                        threshold
+                    ),
+                });
+            }
+            let day = a.days.iter().take(window).find(|d| d.balance < *threshold)?;
+            Some(Alert {
+                dedup_key,
+                title: format!("Low balance ahead: {}", a.asset_name),
+                message: format!(
+                    "Balance of {} is projected to fall to {} {} on {}, below {}",
+                    a.asset_name,
+                    day.balance,
+                    a.currency,
+                    day.date,
+                    threshold
+                ),
+            })
+        })
+        .collect()
+}
+
+async fn goal_alerts(
+    conn: &mut PgConnection,
+    rule: &NotificationRule,
+    today: NaiveDate,
+) -> Result<Vec<Alert>, sqlx::Error> {
+    let days = rule.days_ahead.unwrap_or(DEFAULT_GOAL_DAYS);
+    let goals = sqlx::query_as::<_, GoalRow>(
+        "SELECT id, name, target_amount, current_amount, target_date
+         FROM goals
+         WHERE is_completed IS NOT TRUE AND current_amount < target_amount
+           AND target_date >= $1 AND target_date <= $2
+           AND ($3::int IS NULL OR asset_id = $3)
+         ORDER BY target_date, id",
+    )
+    .bind(today)
+    .bind(today + Duration::days(i64::from(days)))
+    .bind(rule.asset_id)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    Ok(goals
+        .into_iter()
+        .map(|g| Alert {
+            dedup_key: format!("goal:{}:{}", g.id, g.target_date),
+            title: format!("Goal {} due on {}", g.name, g.target_date),
+            message: format!(
+                "{} of {} saved for {}; {} still missing",
+                g.current_amount,
+                g.target_amount,
+                g.name,
+                &g.target_amount - &g.current_amount
+            ),
+        })
+        .collect())
+}
+
+async fn bill_alerts(
+    conn: &mut PgConnection,
+    rule: &NotificationRule,
+    today: NaiveDate,
+) -> Result<Vec<Alert>, sqlx::Error> {
+    let days = rule.days_ahead.unwrap_or(DEFAULT_BILL_DAYS);
+    let bills = sqlx::query_as::<_, BillRow>(
+        "SELECT r.id, r.description, r.amount, r.frequency::text AS frequency, r.start_date, r.end_date,
+                r.last_generated, a.name AS asset_name, COALESCE(a.currency, 'PLN') AS currency
+         FROM recurring_operations r
+         INNER JOIN assets a ON a.id = r.asset_id
+         LEFT JOIN categories c ON c.id = r.category_id
+         WHERE r.is_active IS NOT FALSE AND r.amount < 0 AND a.deleted_at IS NULL
+           AND ($1::int IS NULL OR r.asset_id = $1)
+           AND ($2::int IS NULL OR c.id = $2 OR c.parent_id = $2)
+         ORDER BY r.id",
+    )
+    .bind(rule.asset_id)
+    .bind(rule.category_id)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    let last = today + Duration::days(i64::from(days));
+    let mut alerts = Vec::new();
+    for bill in bills {
+        // Occurrences up to the last generated one are already operations
+        let from = bill.last_generated.map_or(today, |d| (d + Duration::days(1)).max(today));
+        let name = bill.description.clone().unwrap_or_else(|| "Recurring payment".to_string());
+        for date in recurrence::occurrences(&bill.frequency, bill.start_date, bill.end_date, from, last) {
+            alerts.push(Alert {
+                dedup_key: format!("recurring:{}:{}", bill.id, date),
+                title: format!("{} due on {}", name, date),
+                message: format!("{} {} from {} is due on {}", bill.amount, bill.currency, bill.asset_name, date),
+            });
+        }
+    }
+    Ok(alerts)
+}
+
+// Check every active rule and store the notifications not raised before.
+// Returns how many were raised and the ids of those still to be delivered.
+pub async fn evaluate(conn: &mut PgConnection, today: NaiveDate) -> Result<(usize, Vec<i64>), sqlx::Error> {
+    let rules = sqlx::query_as::<_, NotificationRule>(&format!(
+        "SELECT {RULE_COLUMNS} FROM notification_rules WHERE is_active ORDER BY id"
+    ))
+    .fetch_all(&mut *conn)
+    .await?;
+
+    // One projection serves all low-balance rules
+    let mut projection = None;
+    let projection_days = rules
+        .iter()
+        .filter(|r| r.rule_type == "low_balance")
+        .map(|r| i64::from(r.days_ahead.unwrap_or(0)))
+        .max();
+
+    let mut created = 0;
+    let mut pending = Vec::new();
+    for rule in &rules {
+        let threshold = rule.threshold.clone().unwrap_or_else(Money::zero);
+        let alerts = match rule.rule_type.as_str() {
+            "budget_threshold" => budget_alerts(conn, rule, &threshold, today).await?,
+            "large_operation" => large_operation_alerts(conn, rule, &threshold).await?,
+            "low_balance" => {
+                if projection.is_none() {
+                    let days = projection_days.unwrap_or(0).max(1);
+                    projection = Some(forecast::forecast(conn, today, days).await?);
+                }
+                projection
+                    .as_ref()
+                    .map(|p| low_balance_alerts(p, rule, &threshold, today))
+                    .unwrap_or_default()
+            }
+            "goal_deadline" => goal_alerts(conn, rule, today).await?,
+            "bill_due" => bill_alerts(conn, rule, today).await?,
+            _ => Vec::new(),
+        };
+
+        let delivers = rule.webhook_url.is_some() || rule.email_to.is_some();
+        for alert in alerts {
+            let id: Option<i64> = sqlx::query_scalar(
+                "INSERT INTO notifications (rule_id, dedup_key, title, message, delivery_status)
+                 VALUES ($1, $2, $3, $4, $5)
+                 ON CONFLICT (rule_id, dedup_key) DO NOTHING
+                 RETURNING id",
+            )
+            .bind(rule.id)
+            .bind(&alert.dedup_key)
+            .bind(&alert.title)
+            .bind(&alert.message)
+            .bind(if delivers { "pending" } else { "not_required" })
+            .fetch_optional(&mut *conn)
+            .await?;
+            if let Some(id) = id {
+                created += 1;
+                if delivers {
+                    pending.push(id);
+                }
+            }
+        }
+    }
+    Ok((created, pending))
+}
+
+// Send notifications to their rule's webhook and/or email address. With ids,
+// only those are sent; without, every pending or failed one is retried.
+// Returns how many were delivered.
+pub async fn deliver(state: &AppState, ids: Option<&[i64]>) -> Result<usize, sqlx::Error> {
+    // Claiming rows first keeps two senders from delivering the same one
+    let claimed = sqlx::query_as::<_, DeliveryRow>(
+        "UPDATE notifications n
+         SET delivery_status = 'sending', delivery_attempts = n.delivery_attempts + 1,
+             last_attempt_at = CURRENT_TIMESTAMP
+         FROM notification_rules r
+         WHERE r.id = n.rule_id
+           AND ($1::bigint[] IS NULL OR n.id = ANY($1))
+           AND n.delivery_attempts < $2
+           AND (n.delivery_status IN ('pending', 'failed')
+                OR (n.delivery_status = 'sending' AND n.last_attempt_at < CURRENT_TIMESTAMP - INTERVAL '10 minutes'))
+         RETURNING n.id, n.title, n.message, n.created_date, r.id AS rule_id, r.name AS rule_name,
+                   r.rule_type, r.webhook_url, r.email_to",
+    )
+    .bind(ids)
+    .bind(MAX_DELIVERY_ATTEMPTS)
+    .fetch_all(&state.pool)
+    .await?;
+
+    let mut delivered = 0;
+    for row in claimed {
+        let mut errors = Vec::new();
+        if let Some(url) = &row.webhook_url {
+            let payload = serde_json::json!({
+                "id": row.id,
+                "rule_id": row.rule_id,
+                "rule_name": row.rule_name,
+                "rule_type": row.rule_type,
+                "title": row.title,
+                "message": row.message,
+                "created_date": row.created_date,
+            });
+            if let Err(e) = delivery::send_webhook(url, &payload).await {
+                errors.push(format!("webhook: {}", e));
+            }
+        }
+        if let Some(to) = &row.email_to {
+            let sent = match &state.smtp {
+                Some(smtp) => delivery::send_email(smtp, to, &row.title, &row.message).await,
+                None => Err("SMTP is not configured".to_string()),
+            };
+            if let Err(e) = sent {
+                errors.push(format!("email: {}", e));
+            }
+        }
+
+        let error = (!errors.is_empty()).then(|| errors.join("; "));
+        if let Some(error) = &error {
+            tracing::warn!("Failed to deliver notification {}: {}", row.id, error);
+        } else {
+            delivered += 1;
+        }
+        sqlx::query("UPDATE notifications SET delivery_status = $1, delivery_error = $2 WHERE id = $3")
+            .bind(if error.is_some() { "failed" } else { "sent" })
+            .bind(&error)
+            .bind(row.id)
+            .execute(&state.pool)
+            .await?;
+    }
+    Ok(delivered)
+}
+
+async fn evaluate_today(state: &AppState) -> Result<(usize, Vec<i64>), sqlx::Error> {
+    let mut conn = state.pool.acquire().await?;
+    // "Today" as the database sees it, like the CURRENT_DATE defaults
+    let today: NaiveDate = sqlx::query_scalar("SELECT CURRENT_DATE").fetch_one(&mut *conn).await?;
+    evaluate(&mut conn, today).await
+}
+
+// Check the rules after a write. The check (a forecast for each low_balance
+// rule) and the delivery run in the background, so the write doesn't wait for
+// them; failures are logged and never fail the write.
+pub fn check_after_write(state: &AppState) {
+    let state = state.clone();
+    tokio::spawn(async move {
+        match evaluate_today(&state).await {
+            Ok((_, ids)) if !ids.is_empty() => {
+                if let Err(e) = deliver(&state, Some(&ids)).await {
+                    tracing::warn!("Failed to deliver notifications: {}", e);
+                }
+            }
+            Ok(_) => {}
+            Err(e) => tracing::warn!("Failed to check notification rules: {}", e),
+        }
+    });
+}
+
+// Scheduled check: evaluate the rules and (re)send undelivered notifications
+pub async fn check_and_deliver(state: &AppState) -> Result<String, sqlx::Error> {
+    let (created, _) = evaluate_today(state).await?;
+    let delivered = deliver(state, None).await?;
+    Ok(format!("{} notifications raised, {} delivered", created, delivered))
+}
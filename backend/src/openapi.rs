@@ -0,0 +1,448 @@
+// OpenAPI 3 description of the REST API, served at /api/openapi.json with
+// Swagger UI at /api/docs. Schemas are derived from the types in models.rs;
+// every route of routes.rs is listed below with its request and response
+// types, in the same order, and a test checks that the two lists match.
+// Summaries are made from the handler names, so keep those descriptive.
+use crate::{
+    error::ErrorBody,
+    handlers::import_templates::ImportTemplateQuery,
+    idempotency::{self, IDEMPOTENCY_KEY_HEADER},
+    models::*,
+    request_context::{REQUEST_ID_HEADER, USER_ID_HEADER},
+    routes::API_PREFIX,
+};
+use utoipa::{
+    IntoParams, PartialSchema, ToSchema,
+    openapi::{
+        Components, Content, HttpMethod, InfoBuilder, OpenApi, OpenApiBuilder, Ref, RefOr, Required, Response,
+        ResponseBuilder, Server,
+        path::{OperationBuilder, ParameterBuilder, ParameterIn, Paths},
+        request_body::RequestBodyBuilder,
+        schema::{ArrayBuilder, KnownFormat, ObjectBuilder, Schema, SchemaFormat, Type},
+    },
+};
+
+type Schemas = Vec<(String, RefOr<Schema>)>;
+
+// Schema of a body: `()` for none, `[T]` for a list, `json` for any JSON,
+// `file` for raw bytes and `multipart` for a file upload
+macro_rules! content {
+    ($schemas:ident,) => {
+        None
+    };
+    ($schemas:ident, ()) => {
+        None
+    };
+    ($schemas:ident, json) => {
+        Some(("application/json", RefOr::T(Schema::Object(ObjectBuilder::new().build()))))
+    };
+    ($schemas:ident, file) => {
+        Some(("application/octet-stream", binary()))
+    };
+    ($schemas:ident, multipart) => {
+        Some((
+            "multipart/form-data",
+            ObjectBuilder::new().property("file", binary()).required("file").into(),
+        ))
+    };
+    ($schemas:ident, [json]) => {
+        Some(("application/json", ArrayBuilder::new().items(ObjectBuilder::new()).into()))
+    };
+    ($schemas:ident, [String]) => {
+        Some(("application/json", ArrayBuilder::new().items(String::schema()).into()))
+    };
+    ($schemas:ident, [$t:ty]) => {
+        Some(("application/json", ArrayBuilder::new().items(reference::<$t>($schemas)).into()))
+    };
+    ($schemas:ident, $t:ty) => {
+        Some(("application/json", reference::<$t>($schemas)))
+    };
+}
+
+macro_rules! query_params {
+    () => {
+        Vec::new()
+    };
+    ($query:ty) => {
+        <$query as IntoParams>::into_params(|| Some(ParameterIn::Query))
+    };
+}
+
+macro_rules! status {
+    () => {
+        "200"
+    };
+    ($status:literal) => {
+        stringify!($status)
+    };
+}
+
+macro_rules! api {
+    ($($tag:literal {
+        $($method:ident $path:literal $handler:ident $(($body:tt))? $(? $query:ty)? => $response:tt $(, $status:literal)?;)*
+    })*) => {
+        fn paths(schemas: &mut Schemas) -> Paths {
+            let mut paths = Paths::new();
+            $($({
+                let operation = operation(
+                    $tag,
+                    stringify!($handler),
+                    $path,
+                    content!(schemas, $($body)?),
+                    query_params!($($query)?),
+                    status!($($status)?),
+                    content!(schemas, $response),
+                );
+                paths.add_path_operation(openapi_path($path), vec![method(stringify!($method))], operation);
+            })*)*
+            paths
+        }
+    };
+}
+
+api! {
+    "Health" {
+        get "/health" health => HealthReport;
+        get "/ready" ready => HealthReport;
+    }
+    "Users" {
+        post "/users" create_user (CreateUser) => User;
+        get "/users" list_users => [User];
+        get "/users/:id" get_user => User;
+        put "/users/:id" update_user (CreateUser) => User;
+        delete "/users/:id" delete_user => ();
+    }
+    "Categories" {
+        post "/categories" create_category (CreateCategory) => Category;
+        get "/categories" list_categories => [Category];
+        post "/categories/reorder" reorder_categories (ReorderCategories) => ();
+        get "/categories/:id" get_category => Category;
+        put "/categories/:id" update_category (CreateCategory) => Category;
+        delete "/categories/:id" delete_category ?DeleteCategoryQuery => ();
+        post "/categories/:id/toggle-hidden" toggle_category_hidden => Category;
+        post "/categories/:id/move" move_category (MoveCategory) => Category;
+        post "/categories/:id/merge-into/:target" merge_category => CategoryMergeResult;
+        get "/categories/in-use" is_category_used => [CategoryUsed];
+    }
+    "Accounts" {
+        get "/accounts" list_accounts_compat => [json];
+        post "/accounts/:id/toggle-closed" toggle_account_closed_compat => json;
+    }
+    "Assets" {
+        get "/asset-types" list_asset_types => [AssetType];
+        post "/assets" create_asset (CreateAsset) => Asset;
+        get "/assets" list_assets => [Asset];
+        post "/assets/reorder" reorder_assets (ReorderAssets) => ();
+        post "/assets/recalculate" recalculate_asset_balances => RecalculateBalancesResponse;
+        get "/assets/:id" get_asset => Asset;
+        put "/assets/:id" update_asset (CreateAsset) => Asset;
+        delete "/assets/:id" delete_asset => ();
+        post "/assets/:id/toggle-active" toggle_asset_active => Asset;
+        post "/assets/:id/correct-balance" correct_balance (CorrectBalanceRequest) => Asset;
+        get "/assets/:id/corrections" list_asset_corrections => [BalanceCorrection];
+        get "/corrections/report" get_correction_report ?CorrectionReportQuery => [CorrectionReportRow];
+        get "/assets/:id/loan-schedule" get_loan_schedule => LoanSchedule;
+        put "/assets/:id/loan-schedule" upsert_loan_schedule (UpsertLoanSchedule) => LoanSchedule;
+        delete "/assets/:id/loan-schedule" delete_loan_schedule => ();
+        get "/assets/:id/balance" get_asset_balance ?AssetBalanceQuery => AssetBalance;
+        get "/assets/:id/ledger" get_asset_ledger ?LedgerQuery => AssetLedger;
+    }
+    "Reconciliation" {
+        post "/assets/:id/reconciliations" create_reconciliation (CreateReconciliation) => ReconciliationSummary;
+        get "/assets/:id/reconciliations" list_reconciliations => [Reconciliation];
+        get "/reconciliations/:id" get_reconciliation => ReconciliationSummary;
+        delete "/reconciliations/:id" delete_reconciliation => ();
+        get "/reconciliations/:id/operations" list_reconciliation_operations => [Operation];
+        post "/reconciliations/:id/mark" mark_reconciliation_operations (MarkClearedRequest) => ReconciliationSummary;
+        post "/reconciliations/:id/finish" finish_reconciliation => ReconciliationSummary;
+    }
+    "Investments" {
+        post "/investment-transactions" create_investment_transaction (CreateInvestmentTransaction)
+            => InvestmentTransaction;
+        get "/assets/:id/investment-transactions" list_investment_transactions => [InvestmentTransaction];
+        delete "/investment-transactions/:id" delete_investment_transaction => ();
+        post "/asset-valuations" create_asset_valuation (CreateAssetValuation) => AssetValuation;
+        get "/assets/:id/valuations" list_asset_valuations => [AssetValuation];
+        delete "/asset-valuations/:id" delete_asset_valuation => ();
+    }
+    "Operations" {
+        post "/operations" create_operation (CreateOperation) => OperationWithHashtags;
+        get "/operations" list_operations => [OperationWithDetails];
+        post "/operations/classify-transfers" classify_uncategorized_operations => json;
+        post "/operations/transfer" transfer_operation (TransferRequest) => TransferResponse;
+        post "/operations/bulk" bulk_update_operations (BulkOperationRequest) => BulkOperationResponse;
+        post "/operations/batch" batch_create_operations (BatchCreateOperationsRequest)
+            => BatchCreateOperationsResponse;
+        get "/operations/violations" list_operation_violations ?OperationViolationQuery => [OperationViolation];
+        post "/operations/:id/split" split_operation (SplitOperationRequest) => [OperationWithHashtags];
+        put "/operations/:id/split" replace_split_operation (SplitOperationRequest) => [OperationWithHashtags];
+        delete "/operations/:id/unsplit" unsplit_operation => ();
+        get "/operations/:id/children" get_operation_children => [OperationWithDetails];
+        get "/operations/:id" get_operation => OperationWithHashtags;
+        put "/operations/:id" update_operation (CreateOperation) => OperationWithHashtags;
+        delete "/operations/:id" delete_operation => ();
+    }
+    "Attachments" {
+        get "/operations/:id/attachments" list_attachments => [Attachment];
+        post "/operations/:id/attachments" upload_attachment (multipart) => Attachment;
+        get "/operations/:id/attachments/:attachment_id" download_attachment => file;
+        delete "/operations/:id/attachments/:attachment_id" delete_attachment => ();
+    }
+    "Hashtags" {
+        get "/operations/:id/hashtags" get_operation_hashtag_links => [Hashtag];
+        post "/operations/:id/hashtags" tag_operation (TagOperationRequest) => [Hashtag];
+        delete "/operations/:id/hashtags/:hashtag_id" untag_operation => ();
+    }
+    "Budgets" {
+        post "/budgets" create_budget (CreateBudget) => Budget;
+        get "/budgets" list_budgets => [Budget];
+        get "/budgets/data/:month" get_budget_data_for_month => BudgetDataResponse;
+        get "/budgets/:id" get_budget => Budget;
+        delete "/budgets/:id" delete_budget => ();
+        post "/budgets/update" update_budgets ([CreateBudget]) => [Budget];
+    }
+    "Goals" {
+        post "/goals" create_goal (CreateGoal) => Goal;
+        get "/goals" list_goals => [Goal];
+        get "/goals/:id" get_goal => Goal;
+        put "/goals/:id" update_goal (CreateGoal) => Goal;
+        delete "/goals/:id" delete_goal => ();
+        post "/goals/:id/complete" complete_goal => Goal;
+    }
+    "Hashtags" {
+        post "/hashtags" create_hashtag (CreateHashtag) => Hashtag;
+        get "/hashtags" get_hashtags => [Hashtag];
+        put "/hashtags/:id" rename_hashtag (CreateHashtag) => Hashtag;
+        delete "/hashtags/:id" delete_hashtag => ();
+        get "/hashtags/compare" compare_hashtags ?HashtagCompareQuery => [HashtagSummary];
+        post "/hashtags/:id/merge" merge_hashtags (MergeHashtagsRequest) => Hashtag;
+        put "/hashtags/:id/budget-cap" set_hashtag_budget_cap (SetHashtagBudgetCap) => Hashtag;
+        get "/hashtags/:id/report" get_hashtag_report ?HashtagReportQuery => HashtagReport;
+        post "/hashtags/extract" extract_hashtags_from_text (ExtractHashtagsRequest) => [String];
+    }
+    "Payees" {
+        post "/payees" create_payee (CreatePayee) => Payee;
+        get "/payees" list_payees => [Payee];
+        get "/payees/report" get_payee_report ?PayeeReportQuery => [PayeeReportRow];
+        post "/payees/rematch" rematch_payees => json;
+        get "/payees/:id" get_payee => Payee;
+        put "/payees/:id" update_payee (CreatePayee) => Payee;
+        delete "/payees/:id" delete_payee => ();
+        post "/payees/:id/merge" merge_payees (MergePayeesRequest) => Payee;
+    }
+    "Recurring operations" {
+        post "/recurring-operations" create_recurring_operation (CreateRecurringOperation) => RecurringOperation;
+        get "/recurring-operations" list_recurring_operations => [RecurringOperationWithDetails];
+        get "/recurring-operations/:id" get_recurring_operation => RecurringOperation;
+        put "/recurring-operations/:id" update_recurring_operation (UpdateRecurringOperation) => RecurringOperation;
+        delete "/recurring-operations/:id" delete_recurring_operation => ();
+    }
+    "Forecast" {
+        get "/forecast" get_forecast ?ForecastQuery => ForecastResponse;
+    }
+    "Import templates" {
+        post "/import-templates" create_import_template (CreateImportTemplate) ?ImportTemplateQuery
+            => ImportTemplate, 201;
+        get "/import-templates" list_import_templates ?ImportTemplateQuery => [ImportTemplate];
+        get "/import-templates/:id" get_import_template => ImportTemplate;
+        put "/import-templates/:id" update_import_template (UpdateImportTemplate) => ImportTemplate;
+        delete "/import-templates/:id" delete_import_template => (), 204;
+    }
+    "Trash" {
+        get "/trash" list_trash => [TrashItem];
+        post "/trash/operations/:id/restore" restore_operation => RestoreResponse;
+        post "/trash/assets/:id/restore" restore_asset => RestoreResponse;
+        post "/trash/categories/:id/restore" restore_category => RestoreResponse;
+    }
+    "Audit log" {
+        get "/audit" list_audit_entries ?AuditQuery => [AuditEntry];
+        post "/audit/:id/revert" revert_audit_entry => [AuditEntry];
+    }
+    "Notifications" {
+        post "/notification-rules" create_notification_rule (CreateNotificationRule) => NotificationRule;
+        get "/notification-rules" list_notification_rules => [NotificationRule];
+        get "/notification-rules/:id" get_notification_rule => NotificationRule;
+        put "/notification-rules/:id" update_notification_rule (CreateNotificationRule) => NotificationRule;
+        delete "/notification-rules/:id" delete_notification_rule => ();
+        get "/notifications" list_notifications ?NotificationQuery => [Notification];
+        get "/notifications/unread-count" get_unread_notification_count => UnreadCount;
+        post "/notifications/read-all" mark_all_notifications_read => UnreadCount;
+        delete "/notifications/:id" delete_notification => ();
+        post "/notifications/:id/read" mark_notification_read => Notification;
+        post "/notifications/:id/unread" mark_notification_unread => Notification;
+    }
+    "Jobs" {
+        get "/jobs" list_jobs => [JobInfo];
+        get "/jobs/:name/runs" list_job_runs ?JobRunsQuery => [JobRun];
+        post "/jobs/:name/run" run_job_now => JobRun;
+    }
+}
+
+pub fn spec() -> OpenApi {
+    let mut schemas = Schemas::new();
+    let mut paths = paths(&mut schemas);
+    // /ready answers 503 with the same report while it isn't ready
+    if let Some(operation) = paths.paths.get_mut("/ready").and_then(|item| item.get.as_mut()) {
+        operation.responses.responses.insert(
+            "503".to_string(),
+            RefOr::T(
+                ResponseBuilder::new()
+                    .description("Not ready")
+                    .content("application/json", Content::new(Some(Ref::from_schema_name("HealthReport"))))
+                    .build(),
+            ),
+        );
+    }
+    // Money movements take a key that makes retries safe
+    for path in idempotency::idempotent_paths() {
+        if let Some(operation) = paths.paths.get_mut(&openapi_path(path)).and_then(|item| item.post.as_mut()) {
+            operation.parameters.get_or_insert_with(Vec::new).push(
+                ParameterBuilder::new()
+                    .name(IDEMPOTENCY_KEY_HEADER)
+                    .parameter_in(ParameterIn::Header)
+                    .required(Required::False)
+                    .description(Some(
+                        "Unique key of this request; a retry with the same key and body gets the first \
+                         response again, a different body with it is a 409",
+                    ))
+                    .schema(Some(String::schema()))
+                    .build(),
+            );
+        }
+    }
+    reference::<ErrorBody>(&mut schemas);
+
+    let mut components = Components::new();
+    for (name, schema) in schemas {
+        components.schemas.entry(name).or_insert(schema);
+    }
+    components.responses.insert(
+        "Error".to_string(),
+        RefOr::T(
+            ResponseBuilder::new()
+                .description("Error with a stable `code`; see the README for the list")
+                .content("application/json", Content::new(Some(Ref::from_schema_name("ErrorBody"))))
+                .build(),
+        ),
+    );
+
+    OpenApiBuilder::new()
+        .info(
+            InfoBuilder::new()
+                .title("Home Budget API")
+                .version(env!("CARGO_PKG_VERSION"))
+                .build(),
+        )
+        .servers(Some(vec![Server::new(API_PREFIX)]))
+        .paths(paths)
+        .components(Some(components))
+        .build()
+}
+
+// Registers the schema of `T` with what it refers to and returns a reference
+fn reference<T: ToSchema>(schemas: &mut Schemas) -> RefOr<Schema> {
+    schemas.push((T::name().into_owned(), T::schema()));
+    T::schemas(schemas);
+    RefOr::Ref(Ref::from_schema_name(T::name()))
+}
+
+fn binary() -> RefOr<Schema> {
+    ObjectBuilder::new()
+        .schema_type(Type::String)
+        .format(Some(SchemaFormat::KnownFormat(KnownFormat::Binary)))
+        .into()
+}
+
+fn method(name: &str) -> HttpMethod {
+    match name {
+        "get" => HttpMethod::Get,
+        "post" => HttpMethod::Post,
+        "put" => HttpMethod::Put,
+        "delete" => HttpMethod::Delete,
+        _ => unreachable!("unknown method {}", name),
+    }
+}
+
+// "/users/:id" is "/users/{id}" in OpenAPI
+fn openapi_path(path: &str) -> String {
+    path.split('/')
+        .map(|part| match part.strip_prefix(':') {
+            Some(name) => format!("{{{}}}", name),
+            None => part.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+// "list_asset_corrections" is summarized as "List asset corrections"
+fn summary(handler: &str) -> String {
+    let text = handler.replace('_', " ");
+    let mut chars = text.chars();
+    chars.next().map(|first| first.to_uppercase().chain(chars).collect()).unwrap_or_default()
+}
+
+fn operation(
+    tag: &str,
+    handler: &str,
+    path: &str,
+    body: Option<(&str, RefOr<Schema>)>,
+    query: Vec<utoipa::openapi::path::Parameter>,
+    status: &str,
+    response: Option<(&str, RefOr<Schema>)>,
+) -> utoipa::openapi::path::Operation {
+    let mut builder = OperationBuilder::new()
+        .tag(tag)
+        .operation_id(Some(handler))
+        .summary(Some(summary(handler)));
+
+    // Path parameters are ids, except for job names and budget months
+    for name in path.split('/').filter_map(|part| part.strip_prefix(':')) {
+        let schema = match name {
+            "name" | "month" => String::schema(),
+            _ => i32::schema(),
+        };
+        builder = builder.parameter(
+            ParameterBuilder::new()
+                .name(name)
+                .parameter_in(ParameterIn::Path)
+                .required(Required::True)
+                .schema(Some(schema)),
+        );
+    }
+    for parameter in query {
+        builder = builder.parameter(parameter);
+    }
+    for (header, description) in [
+        (USER_ID_HEADER, "Id of the acting user, recorded in the audit log"),
+        (REQUEST_ID_HEADER, "Request id for the audit log; generated when missing and echoed back"),
+    ] {
+        builder = builder.parameter(
+            ParameterBuilder::new()
+                .name(header)
+                .parameter_in(ParameterIn::Header)
+                .required(Required::False)
+                .description(Some(description))
+                .schema(Some(if header == USER_ID_HEADER { i32::schema() } else { String::schema() })),
+        );
+    }
+
+    if let Some((content_type, schema)) = body {
+        builder = builder.request_body(Some(
+            RequestBodyBuilder::new()
+                .content(content_type, Content::new(Some(schema)))
+                .required(Some(Required::True))
+                .build(),
+        ));
+    }
+
+    let mut success = ResponseBuilder::new().description(if status == "204" { "No content" } else { "Success" });
+    if let Some((content_type, schema)) = response {
+        success = success.content(content_type, Content::new(Some(schema)));
+    }
+    let error: RefOr<Response> = RefOr::Ref(Ref::from_response_name("Error"));
+    builder
+        .response(status, success.build())
+        .response("4XX", error.clone())
+        .response("5XX", error)
+        .build()
+}
@@ -0,0 +1,103 @@
+// Dates on which a schedule falls due. Monthly steps are counted from the
+// start date (not from the previous occurrence), so a schedule starting on
+// the 31st falls on the last day of shorter months and returns to the 31st.
+use chrono::{Datelike, Months, NaiveDate};
+
+enum Step {
+    Days(i64),
+    Months(u32),
+}
+
+fn step(frequency: &str) -> Option<Step> {
+    match frequency {
+        "daily" => Some(Step::Days(1)),
+        "weekly" => Some(Step::Days(7)),
+        "biweekly" => Some(Step::Days(14)),
+        "monthly" => Some(Step::Months(1)),
+        "quarterly" => Some(Step::Months(3)),
+        "yearly" => Some(Step::Months(12)),
+        _ => None,
+    }
+}
+
+fn nth(start: NaiveDate, step: &Step, n: i64) -> Option<NaiveDate> {
+    match step {
+        Step::Days(days) => start.checked_add_signed(chrono::Duration::days(days * n)),
+        Step::Months(months) => {
+            start.checked_add_months(Months::new(months.checked_mul(u32::try_from(n).ok()?)?))
+        }
+    }
+}
+
+// Occurrences of a recurring-operation frequency from `start` (until `end`,
+// if set) that fall within `from..=to`. Unknown frequencies have none.
+pub fn occurrences(
+    frequency: &str,
+    start: NaiveDate,
+    end: Option<NaiveDate>,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Vec<NaiveDate> {
+    let Some(step) = step(frequency) else {
+        return Vec::new();
+    };
+    let last = end.map_or(to, |end| end.min(to));
+
+    // Skip ahead to the first occurrence that can fall within the range
+    let mut n = if from > start {
+        match step {
+            Step::Days(days) => (from - start).num_days() / days,
+            Step::Months(months) => {
+                let elapsed = (from.year() - start.year()) * 12 + from.month() as i32 - start.month() as i32;
+                (i64::from(elapsed) / i64::from(months) - 1).max(0)
+            }
+        }
+    } else {
+        0
+    };
+
+    let mut dates = Vec::new();
+    while let Some(date) = nth(start, &step, n) {
+        if date > last {
+            break;
+        }
+        if date >= from {
+            dates.push(date);
+        }
+        n += 1;
+    }
+    dates
+}
+
+// Monthly due dates on the given day of the month, moved to the last day in
+// shorter months
+pub fn monthly_on_day(
+    day: u32,
+    start: NaiveDate,
+    end: Option<NaiveDate>,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Vec<NaiveDate> {
+    let last = end.map_or(to, |end| end.min(to));
+    let Some(mut month) = NaiveDate::from_ymd_opt(from.year(), from.month(), 1) else {
+        return Vec::new();
+    };
+
+    let mut dates = Vec::new();
+    while month <= last {
+        let next_month = match month.checked_add_months(Months::new(1)) {
+            Some(next) => next,
+            None => break,
+        };
+        let last_day = next_month.pred_opt().map_or(28, |d| d.day());
+        if let Some(date) = month.with_day(day.clamp(1, last_day))
+            && date >= from
+            && date >= start
+            && date <= last
+        {
+            dates.push(date);
+        }
+        month = next_month;
+    }
+    dates
+}
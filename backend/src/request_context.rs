@@ -0,0 +1,98 @@
+// Who is making the current request, made available to the database so the
+// audit triggers can record it. The context lives in a task-local for the
+// duration of the request; the pool copies it into session settings whenever
+// a connection is handed out.
+use axum::{extract::Request, http::HeaderValue, middleware::Next, response::Response};
+use sqlx::postgres::{PgConnection, PgPoolOptions};
+
+pub const USER_ID_HEADER: &str = "x-user-id";
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+#[derive(Debug, Clone, Default)]
+pub struct RequestContext {
+    pub user_id: Option<i32>,
+    // Groups the audit entries of this request for revert; always ours
+    pub request_id: String,
+    // X-Request-Id sent by the client, recorded for tracing only
+    pub client_request_id: Option<String>,
+}
+
+tokio::task_local! {
+    static CONTEXT: RequestContext;
+}
+
+impl RequestContext {
+    pub fn current() -> Option<RequestContext> {
+        CONTEXT.try_with(|c| c.clone()).ok()
+    }
+
+    // Run a future (e.g. a background job) with the given context
+    pub async fn scope<F: std::future::Future>(self, f: F) -> F::Output {
+        CONTEXT.scope(self, f).await
+    }
+}
+
+// Reads X-User-Id and X-Request-Id, generates the request's own id and sends
+// it back in X-Request-Id on the response
+pub async fn request_context_middleware(req: Request, next: Next) -> Response {
+    let user_id = req
+        .headers()
+        .get(USER_ID_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim().parse::<i32>().ok());
+    let client_request_id = req
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.trim())
+        .filter(|v| !v.is_empty() && v.len() <= 64)
+        .map(str::to_string);
+    let request_id = uuid::Uuid::new_v4().to_string();
+
+    let context = RequestContext {
+        user_id,
+        request_id: request_id.clone(),
+        client_request_id,
+    };
+    let mut response = context.scope(next.run(req)).await;
+    if let Ok(value) = HeaderValue::from_str(&request_id) {
+        response.headers_mut().insert(REQUEST_ID_HEADER, value);
+    }
+    response
+}
+
+async fn apply_context(
+    conn: &mut PgConnection,
+    context: Option<RequestContext>,
+) -> Result<(), sqlx::Error> {
+    let context = context.unwrap_or_default();
+    sqlx::query(
+        "SELECT set_config('home_budget.user_id', $1, false),
+                set_config('home_budget.request_id', $2, false),
+                set_config('home_budget.client_request_id', $3, false)",
+    )
+    .bind(context.user_id.map(|id| id.to_string()).unwrap_or_default())
+    .bind(context.request_id)
+    .bind(context.client_request_id.unwrap_or_default())
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+// Pool options that tag every connection with the context of the task
+// acquiring it. Connections without a context get the settings cleared, so
+// nothing leaks from a previous request.
+pub fn pool_options() -> PgPoolOptions {
+    PgPoolOptions::new()
+        .after_connect(|conn, _| {
+            let context = RequestContext::current();
+            Box::pin(async move { apply_context(conn, context).await })
+        })
+        .before_acquire(|conn, _| {
+            let context = RequestContext::current();
+            Box::pin(async move {
+                apply_context(conn, context).await?;
+                Ok(true)
+            })
+        })
+}
@@ -1,14 +1,39 @@
 use crate::handlers::*;
 
 use axum::{
-    routing::{get, post, delete},
+    extract::DefaultBodyLimit,
+    http::{Method, Uri},
+    routing::{get, post, put, delete},
+    middleware,
     Router,
 };
 
-use crate::AppState;
+use utoipa_swagger_ui::SwaggerUi;
+
+use crate::{AppState, frontend::Frontend, idempotency::idempotent, openapi, request_context::request_context_middleware};
+
+pub const API_PREFIX: &str = "/api";
+
+// The API under /api with its docs, and the frontend on every other path
+pub fn app(frontend: Option<Frontend>) -> Router<AppState> {
+    let app = Router::new()
+        .nest(API_PREFIX, router())
+        // OpenAPI document and the Swagger UI rendering it
+        .merge(SwaggerUi::new(format!("{API_PREFIX}/docs")).url(format!("{API_PREFIX}/openapi.json"), openapi::spec()));
+    match frontend {
+        Some(frontend) => app.fallback(move |method: Method, uri: Uri| {
+            let frontend = frontend.clone();
+            async move { frontend.serve(method, uri).await }
+        }),
+        None => app,
+    }
+}
 
 pub fn router() -> Router<AppState> {
     Router::new()
+        // Liveness and readiness probes
+        .route("/health", get(health))
+        .route("/ready", get(ready))
         // Users
         .route("/users", post(create_user).get(list_users))
         .route("/users/:id", get(get_user).put(update_user).delete(delete_user))
@@ -17,19 +42,33 @@ pub fn router() -> Router<AppState> {
         .route("/categories/reorder", post(reorder_categories))
         .route("/categories/:id", get(get_category).put(update_category).delete(delete_category))
         .route("/categories/:id/toggle-hidden", post(toggle_category_hidden))
+        .route("/categories/:id/move", post(move_category))
+        .route("/categories/:id/merge-into/:target", post(merge_category))
         .route("/categories/in-use", get(is_category_used))
         // Accounts (backward compatibility - maps to liquid assets)
         .route("/accounts", get(list_accounts_compat))
         .route("/accounts/:id/toggle-closed", post(toggle_account_closed_compat))
         // Assets (new system)
         .route("/asset-types", get(list_asset_types))
-        .route("/assets", post(create_asset).get(list_assets))
+        .route("/assets", post(idempotent(create_asset)).get(list_assets))
         .route("/assets/reorder", post(reorder_assets))
+        .route("/assets/recalculate", post(recalculate_asset_balances))
         .route("/assets/:id", get(get_asset).put(update_asset).delete(delete_asset))
         .route("/assets/:id/toggle-active", post(toggle_asset_active))
-        .route("/assets/:id/correct-balance", post(correct_balance))
+        .route("/assets/:id/correct-balance", post(idempotent(correct_balance)))
+        .route("/assets/:id/corrections", get(list_asset_corrections))
+        .route("/corrections/report", get(get_correction_report))
+        .route("/assets/:id/loan-schedule", get(get_loan_schedule).put(upsert_loan_schedule).delete(delete_loan_schedule))
+        .route("/assets/:id/balance", get(get_asset_balance))
+        .route("/assets/:id/ledger", get(get_asset_ledger))
+        // Reconciliation
+        .route("/assets/:id/reconciliations", post(create_reconciliation).get(list_reconciliations))
+        .route("/reconciliations/:id", get(get_reconciliation).delete(delete_reconciliation))
+        .route("/reconciliations/:id/operations", get(list_reconciliation_operations))
+        .route("/reconciliations/:id/mark", post(mark_reconciliation_operations))
+        .route("/reconciliations/:id/finish", post(finish_reconciliation))
         // Investment Transactions
-        .route("/investment-transactions", post(create_investment_transaction))
+        .route("/investment-transactions", post(idempotent(create_investment_transaction)))
         .route("/assets/:id/investment-transactions", get(list_investment_transactions))
         .route("/investment-transactions/:id", delete(delete_investment_transaction))
         // Asset Valuations
@@ -37,13 +76,26 @@ pub fn router() -> Router<AppState> {
         .route("/assets/:id/valuations", get(list_asset_valuations))
         .route("/asset-valuations/:id", delete(delete_asset_valuation))
         // Operations
-        .route("/operations", post(create_operation).get(list_operations))
+        .route("/operations", post(idempotent(create_operation)).get(list_operations))
         .route("/operations/classify-transfers", post(classify_uncategorized_operations))
-        .route("/operations/transfer", post(transfer_operation))
-        .route("/operations/:id/split", post(split_operation))
+        .route("/operations/transfer", post(idempotent(transfer_operation)))
+        .route("/operations/bulk", post(idempotent(bulk_update_operations)))
+        .route("/operations/batch", post(idempotent(batch_create_operations)))
+        .route("/operations/violations", get(list_operation_violations))
+        .route("/operations/:id/split", post(idempotent(split_operation)).put(replace_split_operation))
         .route("/operations/:id/unsplit", delete(unsplit_operation))
         .route("/operations/:id/children", get(get_operation_children))
         .route("/operations/:id", get(get_operation).put(update_operation).delete(delete_operation))
+        .route(
+            "/operations/:id/attachments",
+            get(list_attachments)
+                .post(upload_attachment)
+                // Room for the file plus multipart framing
+                .layer(DefaultBodyLimit::max(MAX_ATTACHMENT_SIZE + 64 * 1024)),
+        )
+        .route("/operations/:id/attachments/:attachment_id", get(download_attachment).delete(delete_attachment))
+        .route("/operations/:id/hashtags", get(get_operation_hashtag_links).post(tag_operation))
+        .route("/operations/:id/hashtags/:hashtag_id", delete(untag_operation))
         // Budgets
         .route("/budgets", post(create_budget).get(list_budgets))
         .route("/budgets/data/:month", get(get_budget_data_for_month))
@@ -55,13 +107,48 @@ pub fn router() -> Router<AppState> {
         .route("/goals/:id/complete", post(complete_goal))
         // Hashtags
         .route("/hashtags", post(create_hashtag).get(get_hashtags))
-        .route("/hashtags/:id", delete(delete_hashtag))
+        .route("/hashtags/:id", put(rename_hashtag).delete(delete_hashtag))
+        .route("/hashtags/compare", get(compare_hashtags))
+        .route("/hashtags/:id/merge", post(merge_hashtags))
+        .route("/hashtags/:id/budget-cap", put(set_hashtag_budget_cap))
+        .route("/hashtags/:id/report", get(get_hashtag_report))
         .route("/hashtags/extract", post(extract_hashtags_from_text))
+        // Payees
+        .route("/payees", post(create_payee).get(list_payees))
+        .route("/payees/report", get(get_payee_report))
+        .route("/payees/rematch", post(rematch_payees))
+        .route("/payees/:id", get(get_payee).put(update_payee).delete(delete_payee))
+        .route("/payees/:id/merge", post(merge_payees))
         // Recurring Operations
         .route("/recurring-operations", post(create_recurring_operation).get(list_recurring_operations))
         .route("/recurring-operations/:id", get(get_recurring_operation).put(update_recurring_operation).delete(delete_recurring_operation))
+        // Cash-flow forecast
+        .route("/forecast", get(get_forecast))
         // Import Templates
         .route("/import-templates", post(create_import_template).get(list_import_templates))
         .route("/import-templates/:id", get(get_import_template).put(update_import_template).delete(delete_import_template))
+        // Trash of deleted operations, assets and categories
+        .route("/trash", get(list_trash))
+        .route("/trash/operations/:id/restore", post(idempotent(restore_operation)))
+        .route("/trash/assets/:id/restore", post(idempotent(restore_asset)))
+        .route("/trash/categories/:id/restore", post(idempotent(restore_category)))
+        // Audit log
+        .route("/audit", get(list_audit_entries))
+        .route("/audit/:id/revert", post(idempotent(revert_audit_entry)))
+        // Notification rules and notifications
+        .route("/notification-rules", post(create_notification_rule).get(list_notification_rules))
+        .route("/notification-rules/:id", get(get_notification_rule).put(update_notification_rule).delete(delete_notification_rule))
+        .route("/notifications", get(list_notifications))
+        .route("/notifications/unread-count", get(get_unread_notification_count))
+        .route("/notifications/read-all", post(mark_all_notifications_read))
+        .route("/notifications/:id", delete(delete_notification))
+        .route("/notifications/:id/read", post(mark_notification_read))
+        .route("/notifications/:id/unread", post(mark_notification_unread))
+        // Background jobs
+        .route("/jobs", get(list_jobs))
+        .route("/jobs/:name/runs", get(list_job_runs))
+        .route("/jobs/:name/run", post(idempotent(run_job_now)))
+        // Acting user and request id for the audit log
+        .layer(middleware::from_fn(request_context_middleware))
 }
 
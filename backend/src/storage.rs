@@ -0,0 +1,60 @@
+// Pluggable storage for attachment files. The database keeps metadata and a
+// storage key; the bytes live in whatever backend is configured here.
+use std::future::Future;
+use std::io;
+use std::path::PathBuf;
+use std::pin::Pin;
+
+pub type StorageFuture<'a, T> = Pin<Box<dyn Future<Output = io::Result<T>> + Send + 'a>>;
+
+pub trait AttachmentStorage: Send + Sync {
+    fn put<'a>(&'a self, key: &'a str, data: &'a [u8]) -> StorageFuture<'a, ()>;
+    fn get<'a>(&'a self, key: &'a str) -> StorageFuture<'a, Vec<u8>>;
+    /// Deleting a missing key is not an error
+    fn delete<'a>(&'a self, key: &'a str) -> StorageFuture<'a, ()>;
+}
+
+// Files stored flat in one directory, named by their storage key
+pub struct LocalStorage {
+    root: PathBuf,
+}
+
+impl LocalStorage {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    fn path(&self, key: &str) -> io::Result<PathBuf> {
+        // Keys are generated by us, but never let one escape the root
+        if key.is_empty() || !key.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '.') || key.starts_with('.') {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "Invalid storage key"));
+        }
+        Ok(self.root.join(key))
+    }
+}
+
+impl AttachmentStorage for LocalStorage {
+    fn put<'a>(&'a self, key: &'a str, data: &'a [u8]) -> StorageFuture<'a, ()> {
+        Box::pin(async move {
+            let path = self.path(key)?;
+            tokio::fs::create_dir_all(&self.root).await?;
+            // Write to a temp file first so a crash never leaves a partial file under the key
+            let tmp = path.with_extension("tmp");
+            tokio::fs::write(&tmp, data).await?;
+            tokio::fs::rename(&tmp, &path).await
+        })
+    }
+
+    fn get<'a>(&'a self, key: &'a str) -> StorageFuture<'a, Vec<u8>> {
+        Box::pin(async move { tokio::fs::read(self.path(key)?).await })
+    }
+
+    fn delete<'a>(&'a self, key: &'a str) -> StorageFuture<'a, ()> {
+        Box::pin(async move {
+            match tokio::fs::remove_file(self.path(key)?).await {
+                Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+                other => other,
+            }
+        })
+    }
+}
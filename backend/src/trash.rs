@@ -0,0 +1,260 @@
+// The trash. Deleting an operation, asset or category sets its `deleted_at`
+// instead of removing the row, and every list and total skips such rows.
+// Everything deleted in one request shares the time of its transaction: an
+// operation goes with its split parts and the other side of a transfer, an
+// asset with its operations. A restore brings back the rows of that time, and
+// the purge_trash job deletes them for good after RETENTION_DAYS.
+use crate::{
+    error::AppError,
+    models::{Category, RestoreResponse, TrashItem},
+};
+use chrono::NaiveDateTime;
+use sqlx::PgConnection;
+
+pub const RETENTION_DAYS: i32 = 30;
+
+// The given operations with their split parts and transfer partners (and
+// theirs), among the rows with the given `deleted_at` (None: not in the trash)
+async fn connected_operations(
+    conn: &mut PgConnection,
+    ids: &[i32],
+    deleted_at: Option<NaiveDateTime>,
+) -> Result<Vec<i32>, sqlx::Error> {
+    sqlx::query_scalar(
+        "WITH RECURSIVE connected AS (
+             SELECT id FROM operations WHERE id = ANY($1) AND deleted_at IS NOT DISTINCT FROM $2
+             UNION
+             SELECT o.id FROM connected c
+             INNER JOIN operations p ON p.id = c.id
+             INNER JOIN operations o
+                 ON o.id = p.linked_operation_id OR o.linked_operation_id = p.id OR o.parent_operation_id = p.id
+             WHERE o.deleted_at IS NOT DISTINCT FROM $2
+         )
+         SELECT id FROM connected ORDER BY id",
+    )
+    .bind(ids)
+    .bind(deleted_at)
+    .fetch_all(conn)
+    .await
+}
+
+// Move operations to the trash with their split parts and transfer partners.
+// Returns the assets whose balance changed.
+pub async fn trash_operations(conn: &mut PgConnection, ids: &[i32]) -> Result<Vec<i32>, sqlx::Error> {
+    let ids = connected_operations(&mut *conn, ids, None).await?;
+    sqlx::query_scalar(
+        "WITH trashed AS (
+             UPDATE operations SET deleted_at = CURRENT_TIMESTAMP WHERE id = ANY($1) RETURNING asset_id
+         )
+         SELECT DISTINCT asset_id FROM trashed ORDER BY asset_id",
+    )
+    .bind(&ids)
+    .fetch_all(conn)
+    .await
+}
+
+// Move an asset and all its operations to the trash. The asset goes first, so
+// its reconciled operations may follow. Returns the other assets whose balance
+// changed, those on the other side of its transfers.
+pub async fn trash_asset(conn: &mut PgConnection, id: i32) -> Result<Vec<i32>, AppError> {
+    let trashed = sqlx::query("UPDATE assets SET deleted_at = CURRENT_TIMESTAMP WHERE id = $1 AND deleted_at IS NULL")
+        .bind(id)
+        .execute(&mut *conn)
+        .await?;
+    if trashed.rows_affected() == 0 {
+        return Err(AppError::NotFound("Asset not found".to_string()));
+    }
+
+    let operation_ids: Vec<i32> =
+        sqlx::query_scalar("SELECT id FROM operations WHERE asset_id = $1 AND deleted_at IS NULL")
+            .bind(id)
+            .fetch_all(&mut *conn)
+            .await?;
+    let mut asset_ids = trash_operations(conn, &operation_ids).await?;
+    asset_ids.retain(|&asset_id| asset_id != id);
+    Ok(asset_ids)
+}
+
+// An operation can only come back next to a live asset, split parent and
+// category, and a split part only while its parent is still split
+async fn check_restorable(conn: &mut PgConnection, ids: &[i32], restored_asset: Option<i32>) -> Result<(), AppError> {
+    let problem: Option<String> = sqlx::query_scalar(
+        "SELECT CASE
+                    WHEN a.deleted_at IS NOT NULL AND a.id IS DISTINCT FROM $2
+                        THEN 'Asset ' || a.name || ' is in the trash; restore it first'
+                    WHEN p.deleted_at IS NOT NULL
+                        THEN 'Its split operation ' || p.id || ' is in the trash; restore that instead'
+                    WHEN NOT p.is_split
+                        THEN 'Operation ' || p.id || ' is no longer split'
+                    ELSE 'Category ' || c.name || ' is in the trash; restore it first'
+                END
+         FROM operations o
+         INNER JOIN assets a ON a.id = o.asset_id
+         LEFT JOIN operations p ON p.id = o.parent_operation_id AND p.id <> ALL($1)
+         LEFT JOIN categories c ON c.id = o.category_id
+         WHERE o.id = ANY($1)
+           AND ((a.deleted_at IS NOT NULL AND a.id IS DISTINCT FROM $2)
+                OR p.deleted_at IS NOT NULL
+                OR NOT p.is_split
+                OR c.deleted_at IS NOT NULL)
+         ORDER BY o.id
+         LIMIT 1",
+    )
+    .bind(ids)
+    .bind(restored_asset)
+    .fetch_optional(conn)
+    .await?;
+    match problem {
+        Some(message) => Err(AppError::Conflict(message)),
+        None => Ok(()),
+    }
+}
+
+async fn restore_operations(conn: &mut PgConnection, ids: &[i32]) -> Result<Vec<i32>, sqlx::Error> {
+    sqlx::query_scalar(
+        "WITH restored AS (
+             UPDATE operations SET deleted_at = NULL WHERE id = ANY($1) RETURNING asset_id
+         )
+         SELECT DISTINCT asset_id FROM restored ORDER BY asset_id",
+    )
+    .bind(ids)
+    .fetch_all(conn)
+    .await
+}
+
+// Bring an operation back with what was deleted with it. Returns the restored
+// operations and the assets whose balance changed.
+pub async fn restore_operation(conn: &mut PgConnection, id: i32) -> Result<(RestoreResponse, Vec<i32>), AppError> {
+    let deleted_at: NaiveDateTime =
+        sqlx::query_scalar("SELECT deleted_at FROM operations WHERE id = $1 AND deleted_at IS NOT NULL FOR UPDATE")
+            .bind(id)
+            .fetch_optional(&mut *conn)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Operation not found in the trash".to_string()))?;
+
+    let operation_ids = connected_operations(&mut *conn, &[id], Some(deleted_at)).await?;
+    check_restorable(&mut *conn, &operation_ids, None).await?;
+    let asset_ids = restore_operations(conn, &operation_ids).await?;
+    Ok((RestoreResponse { kind: "operation".to_string(), id, operation_ids }, asset_ids))
+}
+
+// Bring an asset back with the operations deleted with it. Its reconciled
+// operations are restored while the asset is still in the trash, the lock
+// doesn't apply to them then. Returns the assets whose balance changed.
+pub async fn restore_asset(conn: &mut PgConnection, id: i32) -> Result<(RestoreResponse, Vec<i32>), AppError> {
+    let deleted_at: NaiveDateTime =
+        sqlx::query_scalar("SELECT deleted_at FROM assets WHERE id = $1 AND deleted_at IS NOT NULL FOR UPDATE")
+            .bind(id)
+            .fetch_optional(&mut *conn)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Asset not found in the trash".to_string()))?;
+
+    let seeds: Vec<i32> = sqlx::query_scalar("SELECT id FROM operations WHERE asset_id = $1 AND deleted_at = $2")
+        .bind(id)
+        .bind(deleted_at)
+        .fetch_all(&mut *conn)
+        .await?;
+    let operation_ids = connected_operations(&mut *conn, &seeds, Some(deleted_at)).await?;
+    check_restorable(&mut *conn, &operation_ids, Some(id)).await?;
+    let mut asset_ids = restore_operations(&mut *conn, &operation_ids).await?;
+    sqlx::query("UPDATE assets SET deleted_at = NULL WHERE id = $1")
+        .bind(id)
+        .execute(&mut *conn)
+        .await?;
+    if !asset_ids.contains(&id) {
+        asset_ids.push(id);
+    }
+    Ok((RestoreResponse { kind: "asset".to_string(), id, operation_ids }, asset_ids))
+}
+
+// Bring a category back at the end of its siblings
+pub async fn restore_category(conn: &mut PgConnection, id: i32) -> Result<Category, AppError> {
+    let parent: Option<(Option<i32>, Option<NaiveDateTime>)> = sqlx::query_as(
+        "SELECT c.parent_id, p.deleted_at FROM categories c
+         LEFT JOIN categories p ON p.id = c.parent_id
+         WHERE c.id = $1 AND c.deleted_at IS NOT NULL
+         FOR UPDATE OF c",
+    )
+    .bind(id)
+    .fetch_optional(&mut *conn)
+    .await?;
+    let Some((parent_id, parent_deleted_at)) = parent else {
+        return Err(AppError::NotFound("Category not found in the trash".to_string()));
+    };
+    if parent_deleted_at.is_some() {
+        return Err(AppError::Conflict("Its parent category is in the trash; restore that first".to_string()));
+    }
+
+    sqlx::query_as::<_, Category>(
+        "UPDATE categories SET deleted_at = NULL,
+             sort_order = (SELECT COALESCE(MAX(sort_order), 0) + 1 FROM categories
+                           WHERE parent_id IS NOT DISTINCT FROM $2 AND deleted_at IS NULL)
+         WHERE id = $1
+         RETURNING id, name, parent_id, type::text, sort_order, is_system, is_hidden",
+    )
+    .bind(id)
+    .bind(parent_id)
+    .fetch_one(conn)
+    .await
+    .map_err(AppError::from)
+}
+
+// Newest first. An operation is listed on its own unless it was deleted with
+// its asset, its split parent or (for the higher id) the other side of its
+// transfer.
+pub async fn list(conn: &mut PgConnection) -> Result<Vec<TrashItem>, sqlx::Error> {
+    sqlx::query_as::<_, TrashItem>(
+        "SELECT 'operation'::text AS kind, o.id, o.description::text AS name, o.amount, o.operation_date,
+                a.name::text AS asset_name, o.deleted_at,
+                (SELECT COUNT(*) FROM operations r
+                 WHERE r.deleted_at = o.deleted_at
+                   AND (r.parent_operation_id = o.id OR r.linked_operation_id = o.id OR r.id = o.linked_operation_id)
+                ) AS related_count,
+                o.deleted_at + make_interval(days => $1) AS purge_after
+         FROM operations o
+         INNER JOIN assets a ON a.id = o.asset_id
+         WHERE o.deleted_at IS NOT NULL
+           AND a.deleted_at IS DISTINCT FROM o.deleted_at
+           AND NOT EXISTS (SELECT 1 FROM operations p WHERE p.id = o.parent_operation_id AND p.deleted_at = o.deleted_at)
+           AND NOT EXISTS (
+               SELECT 1 FROM operations t
+               INNER JOIN assets ta ON ta.id = t.asset_id
+               WHERE (t.id = o.linked_operation_id OR t.linked_operation_id = o.id)
+                 AND t.deleted_at = o.deleted_at
+                 AND (t.id < o.id OR ta.deleted_at = o.deleted_at)
+           )
+         UNION ALL
+         SELECT 'asset', a.id, a.name, a.current_valuation, NULL, NULL, a.deleted_at,
+                (SELECT COUNT(*) FROM operations o
+                 WHERE o.asset_id = a.id AND o.deleted_at = a.deleted_at AND o.parent_operation_id IS NULL),
+                a.deleted_at + make_interval(days => $1)
+         FROM assets a
+         WHERE a.deleted_at IS NOT NULL
+         UNION ALL
+         SELECT 'category', c.id, c.name, NULL, NULL, NULL, c.deleted_at, 0,
+                c.deleted_at + make_interval(days => $1)
+         FROM categories c
+         WHERE c.deleted_at IS NOT NULL
+         ORDER BY deleted_at DESC, kind, id",
+    )
+    .bind(RETENTION_DAYS)
+    .fetch_all(conn)
+    .await
+}
+
+// Delete what has been in the trash longer than RETENTION_DAYS. Operations go
+// first, while their asset is still there to lift the reconciliation lock.
+// Returns how many operations, assets and categories were removed.
+pub async fn purge_expired(conn: &mut PgConnection) -> Result<(u64, u64, u64), sqlx::Error> {
+    let mut purged = Vec::new();
+    for table in ["operations", "assets", "categories"] {
+        let result = sqlx::query(&format!(
+            "DELETE FROM {table} WHERE deleted_at < CURRENT_TIMESTAMP - make_interval(days => $1)"
+        ))
+        .bind(RETENTION_DAYS)
+        .execute(&mut *conn)
+        .await?;
+        purged.push(result.rows_affected());
+    }
+    Ok((purged[0], purged[1], purged[2]))
+}
@@ -1,4 +0,0 @@
-/// Simple error wrapper for database errors
-pub fn db_err<E: std::fmt::Display>(e: E) -> (axum::http::StatusCode, String) {
-    (axum::http::StatusCode::INTERNAL_SERVER_ERROR, format!("DB error: {}", e))
-}
@@ -0,0 +1,207 @@
+// Request payload validation. Handlers take JSON bodies as `ValidJson<T>`,
+// which deserializes the body and runs `T::validate` before the handler is
+// called, so nothing invalid reaches the database. Every problem is reported
+// with the path of the field it belongs to, e.g. "new_asset.currency" or
+// "items[2].amount", in a single `validation_failed` error.
+use crate::{
+    error::{AppError, FieldError},
+    money::{self, Money},
+};
+use axum::{
+    async_trait,
+    body::Bytes,
+    extract::{FromRequest, Request},
+    http::header::CONTENT_TYPE,
+};
+use serde::de::DeserializeOwned;
+
+// Payloads without rules beyond their types keep the default
+pub trait Validate {
+    fn validate(&self, _v: &mut Validator) {}
+}
+
+#[derive(Default)]
+pub struct Validator {
+    prefix: String,
+    errors: Vec<FieldError>,
+}
+
+impl Validator {
+    pub fn error(&mut self, field: &str, message: impl Into<String>) {
+        self.errors.push(FieldError {
+            field: format!("{}{}", self.prefix, field),
+            message: message.into(),
+        });
+    }
+
+    pub fn check(&mut self, ok: bool, field: &str, message: impl Into<String>) {
+        if !ok {
+            self.error(field, message);
+        }
+    }
+
+    // Non-blank text of at most `max` characters
+    pub fn required(&mut self, field: &str, value: &str, max: usize) {
+        if value.trim().is_empty() {
+            self.error(field, format!("{} is required", field));
+        } else {
+            self.max_length(field, value, max);
+        }
+    }
+
+    pub fn max_length(&mut self, field: &str, value: &str, max: usize) {
+        let length = value.chars().count();
+        if length > max {
+            self.error(field, format!("{} can be at most {} characters long, got {}", field, max, length));
+        }
+    }
+
+    pub fn optional_max_length(&mut self, field: &str, value: Option<&str>, max: usize) {
+        if let Some(value) = value {
+            self.max_length(field, value, max);
+        }
+    }
+
+    // An amount that fits a NUMERIC(digits, 2) column
+    pub fn amount(&mut self, field: &str, value: &Money, digits: u64) {
+        self.check(
+            value.fits(digits),
+            field,
+            format!("{} can have at most {} digits before the decimal point", field, digits - 2),
+        );
+    }
+
+    pub fn optional_amount(&mut self, field: &str, value: Option<&Money>, digits: u64) {
+        if let Some(value) = value {
+            self.amount(field, value, digits);
+        }
+    }
+
+    pub fn currency(&mut self, field: &str, value: &str) {
+        self.check(
+            money::is_currency_code(value),
+            field,
+            format!("'{}' is not an ISO 4217 currency code", value),
+        );
+    }
+
+    // Validates a nested payload, reporting its fields under `field.`
+    pub fn nested<T: Validate + ?Sized>(&mut self, field: &str, value: &T) {
+        let outer = self.prefix.clone();
+        self.prefix = format!("{}{}.", outer, field);
+        value.validate(self);
+        self.prefix = outer;
+    }
+
+    fn item<T: Validate>(&mut self, index: usize, value: &T) {
+        let outer = self.prefix.clone();
+        let base = outer.strip_suffix('.').unwrap_or(&outer);
+        self.prefix = format!("{}[{}].", base, index);
+        value.validate(self);
+        self.prefix = outer;
+    }
+
+    pub fn finish(self) -> Result<(), AppError> {
+        if self.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(AppError::Validation(self.errors))
+        }
+    }
+}
+
+impl<T: Validate> Validate for Vec<T> {
+    fn validate(&self, v: &mut Validator) {
+        for (index, item) in self.iter().enumerate() {
+            v.item(index, item);
+        }
+    }
+}
+
+impl<T: Validate> Validate for Option<T> {
+    fn validate(&self, v: &mut Validator) {
+        if let Some(value) = self {
+            value.validate(v);
+        }
+    }
+}
+
+pub fn validate<T: Validate + ?Sized>(value: &T) -> Result<(), AppError> {
+    let mut v = Validator::default();
+    value.validate(&mut v);
+    v.finish()
+}
+
+// Reads and validates a payload that's part of a larger body, like an item of
+// a batch that succeeds or fails on its own
+pub fn from_value<T: DeserializeOwned + Validate>(value: serde_json::Value) -> Result<T, AppError> {
+    let value: T = serde_path_to_error::deserialize(value).map_err(json_error)?;
+    validate(&value)?;
+    Ok(value)
+}
+
+// JSON body extractor that validates the payload
+pub struct ValidJson<T>(pub T);
+
+#[async_trait]
+impl<S, T> FromRequest<S> for ValidJson<T>
+where
+    S: Send + Sync,
+    T: DeserializeOwned + Validate,
+{
+    type Rejection = AppError;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, AppError> {
+        let is_json = req
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.split(';').next())
+            .is_some_and(|mime| {
+                let mime = mime.trim();
+                mime == "application/json" || (mime.starts_with("application/") && mime.ends_with("+json"))
+            });
+        if !is_json {
+            return Err(AppError::UnsupportedMediaType(
+                "Expected a request with Content-Type: application/json".to_string(),
+            ));
+        }
+
+        let bytes = Bytes::from_request(req, state).await.map_err(|e| {
+            if e.status() == axum::http::StatusCode::PAYLOAD_TOO_LARGE {
+                AppError::PayloadTooLarge(e.body_text())
+            } else {
+                AppError::BadRequest(e.body_text())
+            }
+        })?;
+
+        let mut deserializer = serde_json::Deserializer::from_slice(&bytes);
+        let value: T = serde_path_to_error::deserialize(&mut deserializer).map_err(json_error)?;
+        deserializer
+            .end()
+            .map_err(|e| AppError::BadRequest(format!("Invalid JSON: {}", e)))?;
+
+        validate(&value)?;
+        Ok(ValidJson(value))
+    }
+}
+
+// Malformed JSON is a bad request; a value of the wrong type or a missing
+// field is reported as a field error
+fn json_error(e: serde_path_to_error::Error<serde_json::Error>) -> AppError {
+    let path = e.path().to_string();
+    let inner = e.into_inner();
+    if inner.is_syntax() || inner.is_eof() {
+        return AppError::BadRequest(format!("Invalid JSON: {}", inner));
+    }
+
+    let text = inner.to_string();
+    let message = text.rsplit_once(" at line ").map_or(text.as_str(), |(message, _)| message);
+    let parent = if path == "." { String::new() } else { format!("{}.", path) };
+    let (field, message) = match message.strip_prefix("missing field `").and_then(|m| m.strip_suffix('`')) {
+        Some(missing) => (format!("{}{}", parent, missing), format!("{} is required", missing)),
+        None if path == "." => ("body".to_string(), message.to_string()),
+        None => (path, message.to_string()),
+    };
+    AppError::Validation(vec![FieldError { field, message }])
+}
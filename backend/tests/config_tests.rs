@@ -0,0 +1,185 @@
+use axum::{
+    Router,
+    body::Body,
+    http::{Request, StatusCode},
+    routing::get,
+};
+use backend::{
+    config::{Config, LogFormat},
+    delivery::{SmtpSettings, SmtpTls},
+};
+use tower::ServiceExt;
+
+// Environment made of the given variables only
+fn env(vars: &'static [(&'static str, &'static str)]) -> impl Fn(&str) -> Option<String> {
+    move |name| vars.iter().find(|(key, _)| *key == name).map(|(_, value)| value.to_string())
+}
+
+fn parse(text: &str) -> Config {
+    toml::from_str(text).expect("Failed to parse config")
+}
+
+#[test]
+fn test_config_defaults_and_overrides() {
+    let config = Config::default()
+        .resolve(&env(&[("DATABASE_URL", "postgres://localhost/budget")]))
+        .unwrap();
+    assert_eq!(config.listen_addr().to_string(), "127.0.0.1:3000");
+    assert_eq!(config.database.max_connections, 10);
+    assert_eq!(config.log.format, LogFormat::Pretty);
+    assert!(config.server.tls.is_none());
+
+    // The file sets everything, the environment wins over it
+    let config = parse(
+        r#"
+        [server]
+        host = "0.0.0.0"
+        port = 8080
+
+        [cors]
+        allowed_origins = ["https://budget.example.com"]
+
+        [database]
+        url = "postgres://db/budget"
+        max_connections = 4
+        min_connections = 1
+        acquire_timeout_secs = 5
+
+        [limits]
+        body_limit_bytes = 1048576
+
+        [log]
+        format = "json"
+        filter = "warn,backend=info"
+        "#,
+    )
+    .resolve(&env(&[("PORT", "9000"), ("CORS_ORIGINS", "https://a.example.com, https://b.example.com"), ("DB_MAX_CONNECTIONS", "")]))
+    .unwrap();
+    assert_eq!(config.listen_addr().to_string(), "0.0.0.0:9000");
+    assert_eq!(config.cors.allowed_origins, vec!["https://a.example.com", "https://b.example.com"]);
+    assert_eq!(config.database.url, "postgres://db/budget");
+    // Empty variables count as unset
+    assert_eq!(config.database.max_connections, 4);
+    assert_eq!(config.limits.body_limit_bytes, 1048576);
+    assert_eq!(config.log.format, LogFormat::Json);
+}
+
+#[test]
+fn test_config_errors_are_reported_together() {
+    let error = parse(
+        r#"
+        [server]
+        host = "budget.local"
+        tls = { cert_path = "/nonexistent/cert.pem", key_path = "/nonexistent/key.pem" }
+
+        [cors]
+        allowed_origins = ["budget.example.com", "https://ok.example.com/"]
+
+        [database]
+        max_connections = 2
+        min_connections = 3
+        "#,
+    )
+    .resolve(&env(&[("PORT", "http"), ("LOG_FORMAT", "xml"), ("TLS_KEY_PATH", "/tmp/key.pem")]))
+    .unwrap_err();
+
+    let problems = error.0.join("\n");
+    for expected in [
+        "PORT 'http'",
+        "LOG_FORMAT 'xml': expected pretty or json",
+        "TLS_CERT_PATH and TLS_KEY_PATH must be set together",
+        "database.url (DATABASE_URL) is required",
+        "server.host 'budget.local' is not an IP address",
+        "server.tls.cert_path '/nonexistent/cert.pem' is not a file",
+        "cors.allowed_origins 'budget.example.com'",
+        "cors.allowed_origins 'https://ok.example.com/'",
+        "database.min_connections can't be above max_connections",
+    ] {
+        assert!(problems.contains(expected), "missing '{}' in:\n{}", expected, problems);
+    }
+    assert!(error.to_string().starts_with("Invalid configuration:\n  - "));
+
+    // Typos in the file aren't ignored
+    let path = std::env::temp_dir().join(format!("home-budget-config-{}.toml", std::process::id()));
+    std::fs::write(&path, "[server]\nprot = 8080\n").unwrap();
+    let error = Config::from_file(&path).unwrap_err();
+    std::fs::remove_file(&path).unwrap();
+    assert!(error.to_string().contains("unknown field `prot`"), "{}", error);
+}
+
+#[tokio::test]
+async fn test_cors_allows_configured_origins_only() {
+    let config = Config::default()
+        .resolve(&env(&[("DATABASE_URL", "postgres://localhost/budget"), ("CORS_ORIGINS", "https://budget.example.com")]))
+        .unwrap();
+    let app = Router::new().route("/", get(|| async { "ok" })).layer(config.cors_layer());
+
+    for (origin, allowed) in [("https://budget.example.com", true), ("https://evil.example.com", false)] {
+        let response = app
+            .clone()
+            .oneshot(Request::builder().uri("/").header("origin", origin).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let header = response.headers().get("access-control-allow-origin");
+        assert_eq!(header.is_some(), allowed, "{}", origin);
+    }
+}
+
+#[test]
+fn test_config_attachments_smtp_and_switches() {
+    let config = Config::default()
+        .resolve(&env(&[("DATABASE_URL", "postgres://localhost/budget")]))
+        .unwrap();
+    assert!(config.migrations.enabled && config.jobs.enabled);
+    assert_eq!(config.attachments.dir.to_str(), Some("attachments"));
+    assert!(config.smtp.is_none());
+
+    let config = parse(
+        r#"
+        [database]
+        url = "postgres://db/budget"
+
+        [migrations]
+        enabled = false
+
+        [attachments]
+        dir = "/var/lib/home-budget/attachments"
+
+        [smtp]
+        host = "smtp.example.com"
+        tls = "tls"
+        "#,
+    )
+    .resolve(&env(&[("JOBS_ENABLED", "false"), ("SMTP_FROM", "budget@example.com")]))
+    .unwrap();
+    assert!(!config.migrations.enabled);
+    assert!(!config.jobs.enabled);
+    assert_eq!(config.attachments.dir.to_str(), Some("/var/lib/home-budget/attachments"));
+    let smtp = SmtpSettings::from_config(config.smtp.as_ref().unwrap());
+    assert_eq!((smtp.host.as_str(), smtp.port, smtp.tls), ("smtp.example.com", 465, SmtpTls::Tls));
+    assert_eq!(smtp.from, "budget@example.com");
+
+    let error = Config::default()
+        .resolve(&env(&[
+            ("DATABASE_URL", "postgres://localhost/budget"),
+            ("MIGRATIONS_ENABLED", "no"),
+            ("SMTP_TLS", "ssl"),
+            ("SMTP_USERNAME", "budget"),
+            ("SMTP_FROM", "not an address"),
+        ]))
+        .unwrap_err();
+    let problems = error.0.join("\n");
+    for expected in [
+        "MIGRATIONS_ENABLED 'no'",
+        "SMTP_TLS 'ssl': expected none, starttls or tls",
+        "smtp.host (SMTP_HOST) is required",
+        "smtp.username and smtp.password must be set together",
+        "smtp.from 'not an address'",
+    ] {
+        assert!(problems.contains(expected), "missing '{}' in:\n{}", expected, problems);
+    }
+
+    // The example lists only known settings
+    Config::from_file(std::path::Path::new("config.example.toml")).unwrap();
+}
@@ -0,0 +1,136 @@
+use backend::cron::Schedule;
+use chrono::NaiveDateTime;
+
+fn at(text: &str) -> NaiveDateTime {
+    NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M:%S").expect("Failed to parse time")
+}
+
+fn schedule(spec: &str) -> Schedule {
+    Schedule::parse(spec).unwrap_or_else(|e| panic!("'{}' should parse: {}", spec, e))
+}
+
+// Minutes of one hour at which the schedule fires
+fn minutes(spec: &str) -> Vec<u32> {
+    let schedule = schedule(spec);
+    (0..60)
+        .filter(|minute| schedule.matches(at(&format!("2025-12-15 10:{:02}:00", minute))))
+        .collect()
+}
+
+fn next(spec: &str, after: &str) -> Option<NaiveDateTime> {
+    schedule(spec).next_after(at(after))
+}
+
+#[test]
+fn test_cron_field_forms() {
+    assert_eq!(minutes("* * * * *").len(), 60);
+    assert_eq!(minutes("7 * * * *"), [7]);
+    assert_eq!(minutes("58-59 * * * *"), [58, 59]);
+    assert_eq!(minutes("*/15 * * * *"), [0, 15, 30, 45]);
+    assert_eq!(minutes("0-30/10 * * * *"), [0, 10, 20, 30]);
+    // A value with a step runs from it to the end of the field
+    assert_eq!(minutes("5/20 * * * *"), [5, 25, 45]);
+    assert_eq!(minutes("1,3,5 * * * *"), [1, 3, 5]);
+    assert_eq!(minutes("0,10-12,*/30 * * * *"), [0, 10, 11, 12, 30]);
+    assert_eq!(minutes("0 9 * * *"), Vec::<u32>::new());
+
+    // Whole field ranges at both ends
+    let hours = schedule("0 0,23 * * *");
+    assert!(hours.matches(at("2025-12-15 00:00:00")));
+    assert!(hours.matches(at("2025-12-15 23:00:00")));
+    assert!(!hours.matches(at("2025-12-15 12:00:00")));
+    let months = schedule("0 0 1 1,12 *");
+    assert!(months.matches(at("2025-01-01 00:00:00")));
+    assert!(months.matches(at("2025-12-01 00:00:00")));
+    assert!(!months.matches(at("2025-11-01 00:00:00")));
+    // Seconds don't matter, the whole minute matches
+    assert!(schedule("30 10 * * *").matches(at("2025-12-15 10:30:59")));
+}
+
+#[test]
+fn test_cron_day_of_week() {
+    // 2025-12-14 is a Sunday
+    let sunday = at("2025-12-14 08:00:00");
+    let monday = at("2025-12-15 08:00:00");
+    let saturday = at("2025-12-13 08:00:00");
+    for spec in ["0 8 * * 0", "0 8 * * 7"] {
+        assert!(schedule(spec).matches(sunday), "{}", spec);
+        assert!(!schedule(spec).matches(monday), "{}", spec);
+    }
+    let weekdays = schedule("0 8 * * 1-5");
+    assert!(weekdays.matches(monday));
+    assert!(!weekdays.matches(saturday));
+    assert!(!weekdays.matches(sunday));
+    let weekend = schedule("0 8 * * 6-7");
+    assert!(weekend.matches(saturday));
+    assert!(weekend.matches(sunday));
+
+    // With both day fields restricted either one is enough
+    let either = schedule("0 8 15 * 0");
+    assert!(either.matches(monday));
+    assert!(either.matches(sunday));
+    assert!(!either.matches(saturday));
+    // With one restricted only that one counts
+    assert!(!schedule("0 8 15 * *").matches(sunday));
+    assert!(!schedule("0 8 * * 0").matches(monday));
+}
+
+#[test]
+fn test_cron_invalid_expressions() {
+    for (spec, error) in [
+        ("* * * *", "Expected 5 fields"),
+        ("* * * * * *", "Expected 5 fields"),
+        ("", "Expected 5 fields"),
+        ("*/0 * * * *", "Invalid step"),
+        ("*/x * * * *", "Invalid step"),
+        ("a * * * *", "Invalid value 'a'"),
+        ("1,,2 * * * *", "Invalid value ''"),
+        ("1-x * * * *", "Invalid range"),
+        ("60 * * * *", "out of range 0-59"),
+        ("5-1 * * * *", "out of range 0-59"),
+        ("0 24 * * *", "out of range 0-23"),
+        ("0 0 0 * *", "out of range 1-31"),
+        ("0 0 32 * *", "out of range 1-31"),
+        ("0 0 * 0 *", "out of range 1-12"),
+        ("0 0 * 13 *", "out of range 1-12"),
+        ("0 0 * * 8", "out of range 0-7"),
+    ] {
+        let result = Schedule::parse(spec).map(|_| ());
+        let message = result.expect_err(spec);
+        assert!(message.contains(error), "'{}' gave '{}'", spec, message);
+    }
+}
+
+#[test]
+fn test_cron_next_after_boundaries() {
+    // Strictly after, and from the start of the next minute
+    assert_eq!(next("* * * * *", "2025-12-15 10:00:00"), Some(at("2025-12-15 10:01:00")));
+    assert_eq!(next("* * * * *", "2025-12-15 10:00:59"), Some(at("2025-12-15 10:01:00")));
+    assert_eq!(next("30 10 * * *", "2025-12-15 10:30:00"), Some(at("2025-12-16 10:30:00")));
+
+    // Minute, hour, day, month and year rollovers
+    assert_eq!(next("*/15 * * * *", "2025-12-15 10:59:00"), Some(at("2025-12-15 11:00:00")));
+    assert_eq!(next("0 * * * *", "2025-12-15 23:59:00"), Some(at("2025-12-16 00:00:00")));
+    assert_eq!(next("5 0 * * *", "2025-01-31 00:05:00"), Some(at("2025-02-01 00:05:00")));
+    assert_eq!(next("0 0 1 * *", "2025-12-31 23:59:00"), Some(at("2026-01-01 00:00:00")));
+    assert_eq!(next("59 23 31 12 *", "2025-12-31 23:58:00"), Some(at("2025-12-31 23:59:00")));
+
+    // Months too short for the day are skipped
+    assert_eq!(next("0 0 31 * *", "2025-01-31 00:00:00"), Some(at("2025-03-31 00:00:00")));
+    assert_eq!(next("0 0 31 * *", "2025-03-31 00:00:00"), Some(at("2025-05-31 00:00:00")));
+    assert_eq!(next("0 0 29 2 *", "2025-03-01 00:00:00"), Some(at("2028-02-29 00:00:00")));
+    assert_eq!(next("0 0 30 2 *", "2025-03-01 00:00:00"), None);
+
+    // Day of week across a month end: the first Monday after Sunday 2025-11-30
+    assert_eq!(next("0 9 * * 1", "2025-11-30 12:00:00"), Some(at("2025-12-01 09:00:00")));
+
+    // Every result matches the schedule
+    let every_fifth = schedule("*/5 22-23 * * *");
+    let mut time = at("2025-12-15 21:00:00");
+    for expected in ["2025-12-15 22:00:00", "2025-12-15 22:05:00"] {
+        time = every_fifth.next_after(time).unwrap();
+        assert_eq!(time, at(expected));
+        assert!(every_fifth.matches(time));
+    }
+    assert_eq!(every_fifth.next_after(at("2025-12-15 23:55:00")), Some(at("2025-12-16 22:00:00")));
+}
@@ -185,7 +185,7 @@ const SortableRow: React.FC<SortableRowProps> = ({
             </IconButton>
           )}
           
-          {assetType?.category === 'liquid' && onCorrectBalance && (
+          {(assetType?.category === 'liquid' || assetType?.category === 'liability') && onCorrectBalance && (
             <IconButton
               size="small"
               color="secondary"
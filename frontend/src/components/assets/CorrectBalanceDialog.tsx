@@ -4,9 +4,13 @@ import {
   Box,
   Button,
   Stack,
+  TextField,
   Typography,
 } from '@mui/material'
+import { DatePicker } from '@mui/x-date-pickers/DatePicker'
+import dayjs from 'dayjs'
 import StyledModal from '../common/StyledModal'
+import { DatePickerProvider, useDateFormat } from '../common/DatePickerProvider'
 import CalcTextField from '../common/ui/CalcTextField'
 import type { Asset } from '../../lib/api'
 
@@ -16,6 +20,10 @@ interface CorrectBalanceDialogProps {
   asset: Asset | null
   targetBalance: string
   onTargetBalanceChange: (value: string) => void
+  correctionDate: string
+  onCorrectionDateChange: (value: string) => void
+  note: string
+  onNoteChange: (value: string) => void
   onConfirm: () => void
   formatValue: (value: number | string | null | undefined, currency: string) => string
 }
@@ -26,10 +34,15 @@ const CorrectBalanceDialog: React.FC<CorrectBalanceDialogProps> = ({
   asset,
   targetBalance,
   onTargetBalanceChange,
+  correctionDate,
+  onCorrectionDateChange,
+  note,
+  onNoteChange,
   onConfirm,
   formatValue,
 }) => {
   const { t } = useTranslation()
+  const dateFormat = useDateFormat()
 
   return (
     <StyledModal
@@ -58,6 +71,27 @@ const CorrectBalanceDialog: React.FC<CorrectBalanceDialogProps> = ({
           autoFocus
         />
 
+        <DatePickerProvider>
+          <DatePicker
+            label={t('assets.correctBalance.date') ?? 'Saldo na dzień'}
+            value={correctionDate ? dayjs(correctionDate) : null}
+            onChange={(d) => onCorrectionDateChange(d ? d.format('YYYY-MM-DD') : '')}
+            format={dateFormat}
+            disableFuture
+            slotProps={{ textField: { fullWidth: true, InputLabelProps: { shrink: true } } }}
+          />
+        </DatePickerProvider>
+
+        <TextField
+          label={t('assets.correctBalance.note') ?? 'Powód korekty'}
+          value={note}
+          onChange={(e) => onNoteChange(e.target.value)}
+          fullWidth
+          required
+          multiline
+          rows={2}
+        />
+
         <Stack direction="row" spacing={2} justifyContent="flex-end">
           <Button onClick={onClose}>
             {t('common.cancel') ?? 'Anuluj'}
@@ -65,7 +99,7 @@ const CorrectBalanceDialog: React.FC<CorrectBalanceDialogProps> = ({
           <Button
             variant="contained"
             onClick={onConfirm}
-            disabled={!targetBalance}
+            disabled={!targetBalance || !note.trim()}
           >
             {t('assets.correctBalance.confirm') ?? 'Wyrównaj'}
           </Button>
@@ -6,7 +6,8 @@ import {
   deleteAsset, 
   toggleAssetActive,
   correctAssetBalance,
-  reorderAssets
+  reorderAssets,
+  type CorrectBalancePayload
 } from '../lib/api'
 
 export function useAssets() {
@@ -46,11 +47,12 @@ export function useAssets() {
   })
 
   const correctBalanceMutation = useMutation({
-    mutationFn: ({ id, target_balance }: { id: number; target_balance: number }) => 
-      correctAssetBalance(id, target_balance),
+    mutationFn: ({ id, ...payload }: { id: number } & CorrectBalancePayload) =>
+      correctAssetBalance(id, payload),
     onSuccess: () => {
       queryClient.invalidateQueries({ queryKey: ['assets'] })
       queryClient.invalidateQueries({ queryKey: ['operations'] })
+      queryClient.invalidateQueries({ queryKey: ['corrections'] })
     },
   })
 
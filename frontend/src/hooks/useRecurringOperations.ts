@@ -1,6 +1,6 @@
 import { useQuery, useMutation, useQueryClient } from '@tanstack/react-query'
 
-const API = import.meta.env.VITE_BACKEND_URL
+const API = import.meta.env.VITE_BACKEND_URL ?? '/api'
 
 export interface RecurringOperation {
   id: number
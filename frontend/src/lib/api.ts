@@ -1,13 +1,38 @@
-const API = import.meta.env.VITE_BACKEND_URL;
+// Same origin by default: the backend serves the built app, the dev server proxies /api
+const API = import.meta.env.VITE_BACKEND_URL ?? '/api';
+
+// Error body returned by the backend; `code` is stable, `message` is for people
+export interface ApiErrorBody {
+  code: string;
+  message: string;
+  fields: { field: string; message: string }[];
+}
+
+export class ApiError extends Error {
+  code: string;
+  status: number;
+  fields: ApiErrorBody['fields'];
+
+  constructor(body: ApiErrorBody, status: number) {
+    super(body.message);
+    this.name = 'ApiError';
+    this.code = body.code;
+    this.status = status;
+    this.fields = body.fields ?? [];
+  }
+}
 
 async function fetchJson(input: RequestInfo, init?: RequestInit) {
   const res = await fetch(input, init);
   const text = await res.text();
   if (!res.ok) {
-    let err = text;
+    let err: unknown = text;
     try {
       err = JSON.parse(text);
     } catch {}
+    if (err && typeof err === 'object' && 'message' in err) {
+      throw new ApiError(err as ApiErrorBody, res.status);
+    }
     throw new Error(typeof err === 'string' ? err : JSON.stringify(err));
   }
   try {
@@ -17,6 +42,34 @@ async function fetchJson(input: RequestInfo, init?: RequestInit) {
   }
 }
 
+// Requests that create money movements carry an Idempotency-Key. One that
+// gets no answer (e.g. the Wi-Fi dropped) is sent again with the same key, and
+// the backend answers a request it already handled with the first response.
+const NETWORK_ATTEMPTS = 3;
+
+function newIdempotencyKey(): string {
+  // crypto.randomUUID needs a secure context, which a LAN address over http isn't
+  const bytes = crypto.getRandomValues(new Uint8Array(16));
+  return Array.from(bytes, (b) => b.toString(16).padStart(2, '0')).join('');
+}
+
+async function postIdempotent(url: string, payload: unknown) {
+  const init: RequestInit = {
+    method: 'POST',
+    headers: { 'Content-Type': 'application/json', 'Idempotency-Key': newIdempotencyKey() },
+    body: JSON.stringify(payload),
+  };
+  for (let attempt = 1; ; attempt++) {
+    try {
+      return await fetchJson(url, init);
+    } catch (err) {
+      // fetch rejects with a TypeError when no response arrived
+      if (!(err instanceof TypeError) || attempt >= NETWORK_ATTEMPTS) throw err;
+      await new Promise((resolve) => setTimeout(resolve, 500 * attempt));
+    }
+  }
+}
+
 // --- Asset Types
 export type AssetCategory =
   | 'liquid'
@@ -74,11 +127,7 @@ export const getAssets = async (): Promise<Asset[]> => {
 };
 
 export const createAsset = async (payload: CreateAssetPayload): Promise<Asset> => {
-  return fetchJson(`${API}/assets`, {
-    method: 'POST',
-    headers: { 'Content-Type': 'application/json' },
-    body: JSON.stringify(payload),
-  });
+  return postIdempotent(`${API}/assets`, payload);
 };
 
 export const updateAsset = async ({
@@ -103,12 +152,106 @@ export const toggleAssetActive = async (id: number): Promise<Asset> => {
   return fetchJson(`${API}/assets/${id}/toggle-active`, { method: 'POST' });
 };
 
-export const correctAssetBalance = async (id: number, target_balance: number): Promise<Asset> => {
-  return fetchJson(`${API}/assets/${id}/correct-balance`, {
-    method: 'POST',
-    headers: { 'Content-Type': 'application/json' },
-    body: JSON.stringify({ target_balance }),
-  });
+export type CorrectBalancePayload = {
+  target_balance: number;
+  note: string;
+  correction_date?: string;
+};
+
+export const correctAssetBalance = async (id: number, payload: CorrectBalancePayload): Promise<Asset> => {
+  return postIdempotent(`${API}/assets/${id}/correct-balance`, payload);
+};
+
+export type BalanceCorrection = {
+  id: number;
+  asset_id: number;
+  operation_id: number;
+  correction_date: string;
+  balance_before: string;
+  target_balance: string;
+  amount: string;
+  note: string;
+  created_date: string | null;
+  cumulative_amount: string;
+};
+
+export type CorrectionReportRow = {
+  asset_id: number;
+  asset_name: string;
+  currency: string;
+  correction_count: number;
+  total_positive: string;
+  total_negative: string;
+  net_amount: string;
+  last_correction_date: string | null;
+};
+
+export const getAssetCorrections = async (id: number): Promise<BalanceCorrection[]> => {
+  return fetchJson(`${API}/assets/${id}/corrections`);
+};
+
+export const getCorrectionReport = async (
+  params: { date_from?: string; date_to?: string } = {}
+): Promise<CorrectionReportRow[]> => {
+  const query = new URLSearchParams();
+  if (params.date_from) query.set('date_from', params.date_from);
+  if (params.date_to) query.set('date_to', params.date_to);
+  const qs = query.toString();
+  return fetchJson(`${API}/corrections/report${qs ? `?${qs}` : ''}`);
+};
+
+export type AssetBalance = {
+  asset_id: number;
+  at: string | null;
+  balance: string;
+};
+
+export type LedgerEntry = {
+  operation_id: number;
+  operation_date: string;
+  description: string | null;
+  category_id: number | null;
+  category_name: string | null;
+  operation_type: string;
+  amount: string;
+  linked_operation_id: number | null;
+  clearing_status: string;
+  balance: string;
+};
+
+export type AssetLedger = {
+  asset_id: number;
+  opening_balance: string;
+  closing_balance: string;
+  entries: LedgerEntry[];
+};
+
+export type BalanceDrift = {
+  asset_id: number;
+  asset_name: string;
+  stored_balance: string | null;
+  calculated_balance: string;
+  difference: string;
+};
+
+export const getAssetBalance = async (id: number, at?: string): Promise<AssetBalance> => {
+  const query = at ? `?at=${at}` : '';
+  return fetchJson(`${API}/assets/${id}/balance${query}`);
+};
+
+export const getAssetLedger = async (
+  id: number,
+  params: { date_from?: string; date_to?: string } = {}
+): Promise<AssetLedger> => {
+  const query = new URLSearchParams();
+  if (params.date_from) query.set('date_from', params.date_from);
+  if (params.date_to) query.set('date_to', params.date_to);
+  const qs = query.toString();
+  return fetchJson(`${API}/assets/${id}/ledger${qs ? `?${qs}` : ''}`);
+};
+
+export const recalculateBalances = async (): Promise<{ checked: number; repaired: BalanceDrift[] }> => {
+  return fetchJson(`${API}/assets/recalculate`, { method: 'POST' });
 };
 
 export const reorderAssets = async (items: { id: number; sort_order: number }[]): Promise<void> => {
@@ -119,6 +262,110 @@ export const reorderAssets = async (items: { id: number; sort_order: number }[])
   });
 };
 
+// --- Reconciliation
+export type Reconciliation = {
+  id: number;
+  asset_id: number;
+  statement_date: string;
+  statement_balance: number | string;
+  status: 'open' | 'finished';
+  created_date?: string | null;
+  finished_date?: string | null;
+};
+
+export type ReconciliationSummary = {
+  reconciliation: Reconciliation;
+  cleared_balance: number | string;
+  difference: number | string;
+  cleared_count: number;
+  uncleared_count: number;
+};
+
+export const getReconciliations = async (assetId: number): Promise<Reconciliation[]> => {
+  return fetchJson(`${API}/assets/${assetId}/reconciliations`);
+};
+
+export const createReconciliation = async (
+  assetId: number,
+  payload: { statement_date: string; statement_balance: number | string }
+): Promise<ReconciliationSummary> => {
+  return fetchJson(`${API}/assets/${assetId}/reconciliations`, {
+    method: 'POST',
+    headers: { 'Content-Type': 'application/json' },
+    body: JSON.stringify(payload),
+  });
+};
+
+export const getReconciliation = async (id: number): Promise<ReconciliationSummary> => {
+  return fetchJson(`${API}/reconciliations/${id}`);
+};
+
+export const getReconciliationOperations = async (id: number): Promise<Operation[]> => {
+  return fetchJson(`${API}/reconciliations/${id}/operations`);
+};
+
+export const markReconciliationOperations = async (
+  id: number,
+  operationIds: number[],
+  cleared: boolean
+): Promise<ReconciliationSummary> => {
+  return fetchJson(`${API}/reconciliations/${id}/mark`, {
+    method: 'POST',
+    headers: { 'Content-Type': 'application/json' },
+    body: JSON.stringify({ operation_ids: operationIds, cleared }),
+  });
+};
+
+export const finishReconciliation = async (id: number): Promise<ReconciliationSummary> => {
+  return fetchJson(`${API}/reconciliations/${id}/finish`, { method: 'POST' });
+};
+
+export const deleteReconciliation = async (id: number): Promise<void> => {
+  await fetchJson(`${API}/reconciliations/${id}`, { method: 'DELETE' });
+};
+
+// --- Audit log
+export type AuditEntry = {
+  id: number;
+  user_id: number | null;
+  action: 'create' | 'update' | 'delete';
+  table_name: 'operations' | 'assets' | 'budgets' | 'categories';
+  record_id: number;
+  action_date: string;
+  before_data: Record<string, unknown> | null;
+  after_data: Record<string, unknown> | null;
+  request_id: string | null;
+  client_request_id: string | null;
+  reverted_at: string | null;
+};
+
+export type AuditQuery = {
+  table_name?: string;
+  record_id?: number;
+  action?: 'create' | 'update' | 'delete';
+  user_id?: number;
+  request_id?: string;
+  client_request_id?: string;
+  date_from?: string;
+  date_to?: string;
+  before_id?: number;
+  limit?: number;
+};
+
+export const getAuditEntries = async (query: AuditQuery = {}): Promise<AuditEntry[]> => {
+  const params = new URLSearchParams();
+  Object.entries(query).forEach(([key, value]) => {
+    if (value !== undefined && value !== null && value !== '') params.set(key, String(value));
+  });
+  const qs = params.toString();
+  return fetchJson(`${API}/audit${qs ? `?${qs}` : ''}`);
+};
+
+// Reverts the whole request the entry belongs to; returns the reverted entries
+export const revertAuditEntry = async (id: number): Promise<AuditEntry[]> => {
+  return fetchJson(`${API}/audit/${id}/revert`, { method: 'POST' });
+};
+
 // --- Investment Transactions
 export type InvestmentTransactionType = 'buy' | 'sell' | 'value_increase' | 'value_decrease';
 
@@ -147,11 +394,7 @@ export type CreateInvestmentTransactionPayload = {
 export const createInvestmentTransaction = async (
   payload: CreateInvestmentTransactionPayload
 ): Promise<InvestmentTransaction> => {
-  return fetchJson(`${API}/investment-transactions`, {
-    method: 'POST',
-    headers: { 'Content-Type': 'application/json' },
-    body: JSON.stringify(payload),
-  });
+  return postIdempotent(`${API}/investment-transactions`, payload);
 };
 
 export const getInvestmentTransactions = async (
@@ -267,6 +510,7 @@ export const deleteUser = async (id: number): Promise<void> => {
 
 // --- Operations
 export type OperationType = 'income' | 'expense';
+export type ClearingStatus = 'uncleared' | 'cleared' | 'reconciled';
 
 export type Operation = {
   id: number;
@@ -282,6 +526,9 @@ export type Operation = {
   operation_date: string;
   parent_operation_id?: number | null;
   is_split: boolean;
+  payee_id?: number | null;
+  payee_name?: string | null; // JOINed from backend
+  clearing_status?: ClearingStatus;
   hashtags?: Hashtag[];
 };
 
@@ -290,7 +537,6 @@ export const getOperations = async (): Promise<Operation[]> => {
 };
 
 export type CreateOperationPayload = {
-  creation_date?: string | null;
   category_id?: number | null;
   description?: string | null;
   asset_id: number;
@@ -298,14 +544,11 @@ export type CreateOperationPayload = {
   operation_type: OperationType;
   operation_date: string;
   split_items?: SplitItem[];
+  payee_id?: number | null;
 };
 
 export const createOperation = async (payload: CreateOperationPayload): Promise<Operation> => {
-  return fetchJson(`${API}/operations`, {
-    method: 'POST',
-    headers: { 'Content-Type': 'application/json' },
-    body: JSON.stringify(payload),
-  });
+  return postIdempotent(`${API}/operations`, payload);
 };
 
 export const updateOperation = async (
@@ -333,11 +576,31 @@ export const classifyUncategorizedOperations = async (): Promise<{
   });
 };
 
+export type OperationViolation = {
+  operation_id: number;
+  asset_id: number;
+  operation_date: string;
+  description?: string | null;
+  amount: string;
+  operation_type: OperationType;
+  category_id?: number | null;
+  category_name?: string | null;
+  category_type?: OperationType | null;
+  rule: 'sign_mismatch' | 'category_type_mismatch' | 'hidden_category';
+  suggested_fix: string;
+};
+
+export const getOperationViolations = async (assetId?: number): Promise<OperationViolation[]> => {
+  const query = assetId != null ? `?asset_id=${assetId}` : '';
+  return fetchJson(`${API}/operations/violations${query}`);
+};
+
 // --- Split Operations
 export type SplitItem = {
   category_id: number;
   amount: number;
   description?: string | null;
+  hashtags?: string[] | null;
 };
 
 export type SplitOperationRequest = {
@@ -355,6 +618,18 @@ export const splitOperation = async (
   });
 };
 
+// Replace all parts of a split operation (or split it) in one request
+export const replaceSplit = async (
+  id: number,
+  request: SplitOperationRequest
+): Promise<Operation[]> => {
+  return fetchJson(`${API}/operations/${id}/split`, {
+    method: 'PUT',
+    headers: { 'Content-Type': 'application/json' },
+    body: JSON.stringify(request),
+  });
+};
+
 export const unsplitOperation = async (id: number): Promise<void> => {
   await fetchJson(`${API}/operations/${id}/unsplit`, { method: 'DELETE' });
 };
@@ -363,6 +638,96 @@ export const getOperationChildren = async (id: number): Promise<Operation[]> =>
   return fetchJson(`${API}/operations/${id}/children`);
 };
 
+// --- Attachments
+export type Attachment = {
+  id: number;
+  operation_id: number;
+  file_name: string;
+  content_type: string;
+  size_bytes: number;
+  checksum_sha256: string;
+  created_date?: string | null;
+};
+
+export const getAttachments = async (operationId: number): Promise<Attachment[]> => {
+  return fetchJson(`${API}/operations/${operationId}/attachments`);
+};
+
+export const uploadAttachment = async (operationId: number, file: File): Promise<Attachment> => {
+  const form = new FormData();
+  form.append('file', file);
+  return fetchJson(`${API}/operations/${operationId}/attachments`, {
+    method: 'POST',
+    body: form,
+  });
+};
+
+export const getAttachmentUrl = (operationId: number, attachmentId: number): string => {
+  return `${API}/operations/${operationId}/attachments/${attachmentId}`;
+};
+
+export const deleteAttachment = async (operationId: number, attachmentId: number): Promise<void> => {
+  await fetchJson(`${API}/operations/${operationId}/attachments/${attachmentId}`, { method: 'DELETE' });
+};
+
+// --- Bulk Operations
+export type OperationFilter = {
+  asset_id?: number;
+  category_id?: number;
+  uncategorized?: boolean;
+  operation_type?: OperationType;
+  date_from?: string;
+  date_to?: string;
+  description_contains?: string;
+  payee_id?: number;
+};
+
+export type BulkAction =
+  | { type: 'set_category'; category_id: number | null }
+  | { type: 'add_hashtags'; hashtags: string[] }
+  | { type: 'remove_hashtags'; hashtags: string[] }
+  | { type: 'move_to_asset'; asset_id: number }
+  | { type: 'set_payee'; payee_id: number | null }
+  | { type: 'shift_dates'; days: number }
+  | { type: 'delete' };
+
+export type BulkOperationRequest = {
+  ids?: number[];
+  filter?: OperationFilter;
+  action: BulkAction;
+  atomic?: boolean;
+};
+
+export type BulkOperationResponse = {
+  matched: number;
+  succeeded: number;
+  failed: number;
+  committed: boolean;
+  results: { id: number; success: boolean; error?: string | null }[];
+  recalculated_asset_ids: number[];
+};
+
+export const bulkUpdateOperations = async (
+  request: BulkOperationRequest
+): Promise<BulkOperationResponse> => {
+  return postIdempotent(`${API}/operations/bulk`, request);
+};
+
+export type BatchCreateOperationsResponse = {
+  created: number;
+  failed: number;
+  committed: boolean;
+  results: { index: number; success: boolean; operation?: Operation | null; error?: string | null }[];
+  recalculated_asset_ids: number[];
+};
+
+export const batchCreateOperations = async (
+  items: CreateOperationPayload[],
+  atomic = false
+): Promise<BatchCreateOperationsResponse> => {
+  return postIdempotent(`${API}/operations/batch`, { items, atomic });
+};
+
 // --- Transfers
 export type TransferRequest = {
   from_asset_id: number;
@@ -391,11 +756,7 @@ export type TransferResponse = {
 };
 
 export const createTransfer = async (payload: TransferRequest): Promise<TransferResponse> => {
-  return fetchJson(`${API}/operations/transfer`, {
-    method: 'POST',
-    headers: { 'Content-Type': 'application/json' },
-    body: JSON.stringify(payload),
-  });
+  return postIdempotent(`${API}/operations/transfer`, payload);
 };
 
 // --- Categories
@@ -440,8 +801,33 @@ export const updateCategory = async ({
   });
 };
 
-export const deleteCategory = async (id: number): Promise<void> => {
-  await fetchJson(`${API}/categories/${id}`, { method: 'DELETE' });
+export type CategoryMergeResult = {
+  category: Category;
+  operations_moved: number;
+  budgets_moved: number;
+  recurring_operations_moved: number;
+  subcategories_moved: number;
+};
+
+// A category in use can only be deleted by reassigning its data to another one
+export const deleteCategory = async (id: number, reassignTo?: number): Promise<void> => {
+  const query = reassignTo != null ? `?reassign_to=${reassignTo}` : '';
+  await fetchJson(`${API}/categories/${id}${query}`, { method: 'DELETE' });
+};
+
+export const moveCategory = async (
+  id: number,
+  payload: { parent_id: number | null; position?: number }
+): Promise<Category> => {
+  return fetchJson(`${API}/categories/${id}/move`, {
+    method: 'POST',
+    headers: { 'Content-Type': 'application/json' },
+    body: JSON.stringify(payload),
+  });
+};
+
+export const mergeCategory = async (id: number, targetId: number): Promise<CategoryMergeResult> => {
+  return fetchJson(`${API}/categories/${id}/merge-into/${targetId}`, { method: 'POST' });
 };
 
 export const reorderCategories = async (
@@ -589,7 +975,13 @@ export const completeGoal = async (id: number): Promise<Goal> => {
 };
 
 // --- Hashtags
-export type Hashtag = { id: number; name: string; created_date?: string; usage_count: number };
+export type Hashtag = {
+  id: number;
+  name: string;
+  created_date?: string;
+  usage_count: number;
+  budget_cap?: number | string | null;
+};
 
 export const getHashtags = async (): Promise<Hashtag[]> => {
   return fetchJson(`${API}/hashtags`);
@@ -607,6 +999,107 @@ export const deleteHashtag = async (id: number): Promise<void> => {
   await fetchJson(`${API}/hashtags/${id}`, { method: 'DELETE' });
 };
 
+// Renaming also rewrites the hashtag in descriptions of linked operations
+export const renameHashtag = async (id: number, name: string): Promise<Hashtag> => {
+  return fetchJson(`${API}/hashtags/${id}`, {
+    method: 'PUT',
+    headers: { 'Content-Type': 'application/json' },
+    body: JSON.stringify({ name }),
+  });
+};
+
+export const mergeHashtags = async (targetId: number, sourceIds: number[]): Promise<Hashtag> => {
+  return fetchJson(`${API}/hashtags/${targetId}/merge`, {
+    method: 'POST',
+    headers: { 'Content-Type': 'application/json' },
+    body: JSON.stringify({ source_ids: sourceIds }),
+  });
+};
+
+export const setHashtagBudgetCap = async (
+  id: number,
+  budgetCap: number | string | null
+): Promise<Hashtag> => {
+  return fetchJson(`${API}/hashtags/${id}/budget-cap`, {
+    method: 'PUT',
+    headers: { 'Content-Type': 'application/json' },
+    body: JSON.stringify({ budget_cap: budgetCap }),
+  });
+};
+
+export type HashtagSummary = {
+  hashtag_id: number;
+  name: string;
+  operation_count: number;
+  income: number | string;
+  expense: number | string;
+  net: number | string;
+  first_date: string | null;
+  last_date: string | null;
+  budget_cap: number | string | null;
+  budget_remaining: number | string | null;
+  over_budget: boolean;
+};
+
+export type HashtagBreakdownRow = {
+  id: number | null;
+  name: string | null;
+  operation_count: number;
+  income: number | string;
+  expense: number | string;
+  net: number | string;
+};
+
+export type HashtagReport = HashtagSummary & {
+  by_category: HashtagBreakdownRow[];
+  by_asset: HashtagBreakdownRow[];
+  timeline: {
+    period_start: string;
+    income: number | string;
+    expense: number | string;
+    net: number | string;
+    cumulative_expense: number | string;
+  }[];
+};
+
+export const getHashtagReport = async (
+  id: number,
+  params: { date_from?: string; date_to?: string; interval?: 'day' | 'week' } = {}
+): Promise<HashtagReport> => {
+  const query = new URLSearchParams();
+  if (params.date_from) query.set('date_from', params.date_from);
+  if (params.date_to) query.set('date_to', params.date_to);
+  if (params.interval) query.set('interval', params.interval);
+  const qs = query.toString();
+  return fetchJson(`${API}/hashtags/${id}/report${qs ? `?${qs}` : ''}`);
+};
+
+export const compareHashtags = async (
+  ids: number[],
+  params: { date_from?: string; date_to?: string } = {}
+): Promise<HashtagSummary[]> => {
+  const query = new URLSearchParams({ ids: ids.join(',') });
+  if (params.date_from) query.set('date_from', params.date_from);
+  if (params.date_to) query.set('date_to', params.date_to);
+  return fetchJson(`${API}/hashtags/compare?${query.toString()}`);
+};
+
+export const getOperationHashtags = async (operationId: number): Promise<Hashtag[]> => {
+  return fetchJson(`${API}/operations/${operationId}/hashtags`);
+};
+
+export const tagOperation = async (operationId: number, hashtags: string[]): Promise<Hashtag[]> => {
+  return fetchJson(`${API}/operations/${operationId}/hashtags`, {
+    method: 'POST',
+    headers: { 'Content-Type': 'application/json' },
+    body: JSON.stringify({ hashtags }),
+  });
+};
+
+export const untagOperation = async (operationId: number, hashtagId: number): Promise<void> => {
+  await fetchJson(`${API}/operations/${operationId}/hashtags/${hashtagId}`, { method: 'DELETE' });
+};
+
 export const extractHashtagsFromText = async (text: string): Promise<string[]> => {
   return fetchJson(`${API}/hashtags/extract`, {
     method: 'POST',
@@ -615,6 +1108,271 @@ export const extractHashtagsFromText = async (text: string): Promise<string[]> =
   });
 };
 
+// --- Payees
+export type Payee = {
+  id: number;
+  name: string;
+  aliases: string[];
+  default_category_id?: number | null;
+  default_hashtag?: string | null;
+  created_date?: string | null;
+};
+
+export type CreatePayeePayload = {
+  name: string;
+  aliases?: string[];
+  default_category_id?: number | null;
+  default_hashtag?: string | null;
+};
+
+export type PayeeReportRow = {
+  payee_id: number | null;
+  payee_name: string | null;
+  operation_count: number;
+  income: number | string;
+  expense: number | string;
+  net: number | string;
+};
+
+export const getPayees = async (): Promise<Payee[]> => {
+  return fetchJson(`${API}/payees`);
+};
+
+export const createPayee = async (payload: CreatePayeePayload): Promise<Payee> => {
+  return fetchJson(`${API}/payees`, {
+    method: 'POST',
+    headers: { 'Content-Type': 'application/json' },
+    body: JSON.stringify(payload),
+  });
+};
+
+export const updatePayee = async (id: number, payload: CreatePayeePayload): Promise<Payee> => {
+  return fetchJson(`${API}/payees/${id}`, {
+    method: 'PUT',
+    headers: { 'Content-Type': 'application/json' },
+    body: JSON.stringify(payload),
+  });
+};
+
+export const deletePayee = async (id: number): Promise<void> => {
+  await fetchJson(`${API}/payees/${id}`, { method: 'DELETE' });
+};
+
+export const mergePayees = async (targetId: number, sourceIds: number[]): Promise<Payee> => {
+  return fetchJson(`${API}/payees/${targetId}/merge`, {
+    method: 'POST',
+    headers: { 'Content-Type': 'application/json' },
+    body: JSON.stringify({ source_ids: sourceIds }),
+  });
+};
+
+export const rematchPayees = async (): Promise<{ matched_count: number; message: string }> => {
+  return fetchJson(`${API}/payees/rematch`, { method: 'POST' });
+};
+
+export const getPayeeReport = async (params: {
+  date_from?: string;
+  date_to?: string;
+  asset_id?: number;
+} = {}): Promise<PayeeReportRow[]> => {
+  const query = new URLSearchParams();
+  Object.entries(params).forEach(([key, value]) => {
+    if (value !== undefined) query.append(key, String(value));
+  });
+  const qs = query.toString();
+  return fetchJson(`${API}/payees/report${qs ? `?${qs}` : ''}`);
+};
+
+// --- Loan Schedules
+export type LoanSchedule = {
+  id: number;
+  liability_asset_id: number;
+  payment_asset_id: number;
+  installment_amount: string;
+  day_of_month: number;
+  start_date: string;
+  end_date: string | null;
+  created_date: string | null;
+};
+
+export type LoanSchedulePayload = {
+  payment_asset_id: number;
+  installment_amount: number;
+  day_of_month: number;
+  start_date: string;
+  end_date?: string | null;
+};
+
+export const getLoanSchedule = async (liabilityId: number): Promise<LoanSchedule> => {
+  return fetchJson(`${API}/assets/${liabilityId}/loan-schedule`);
+};
+
+export const saveLoanSchedule = async (liabilityId: number, payload: LoanSchedulePayload): Promise<LoanSchedule> => {
+  return fetchJson(`${API}/assets/${liabilityId}/loan-schedule`, {
+    method: 'PUT',
+    headers: { 'Content-Type': 'application/json' },
+    body: JSON.stringify(payload),
+  });
+};
+
+export const deleteLoanSchedule = async (liabilityId: number): Promise<void> => {
+  await fetchJson(`${API}/assets/${liabilityId}/loan-schedule`, { method: 'DELETE' });
+};
+
+// --- Forecast
+export type ForecastItem = {
+  source: 'operation' | 'recurring' | 'budget' | 'installment';
+  reference_id: number | null;
+  description: string | null;
+  amount: string;
+};
+
+export type ForecastDay = {
+  date: string;
+  items: ForecastItem[];
+  balance: string;
+  is_negative: boolean;
+};
+
+export type AccountForecast = {
+  asset_id: number;
+  asset_name: string;
+  currency: string;
+  starting_balance: string;
+  ending_balance: string;
+  lowest_balance: string;
+  lowest_balance_date: string | null;
+  first_negative_date: string | null;
+  days: ForecastDay[];
+};
+
+export type ForecastResponse = {
+  start_date: string;
+  end_date: string;
+  accounts: AccountForecast[];
+};
+
+export const getForecast = async (days = 90): Promise<ForecastResponse> => {
+  return fetchJson(`${API}/forecast?days=${days}`);
+};
+
+// --- Notifications
+export type NotificationRuleType =
+  | 'budget_threshold'
+  | 'large_operation'
+  | 'low_balance'
+  | 'goal_deadline'
+  | 'bill_due';
+
+export type NotificationRule = {
+  id: number;
+  name: string;
+  rule_type: NotificationRuleType;
+  category_id?: number | null;
+  asset_id?: number | null;
+  threshold?: string | null;
+  days_ahead?: number | null;
+  webhook_url?: string | null;
+  email_to?: string | null;
+  is_active: boolean;
+  created_date: string;
+};
+
+export type NotificationRulePayload = Omit<NotificationRule, 'id' | 'created_date' | 'is_active'> & {
+  is_active?: boolean;
+};
+
+export type AppNotification = {
+  id: number;
+  rule_id: number;
+  rule_name: string;
+  rule_type: NotificationRuleType;
+  title: string;
+  message: string;
+  is_read: boolean;
+  read_at?: string | null;
+  delivery_status: 'not_required' | 'pending' | 'sending' | 'sent' | 'failed';
+  delivery_error?: string | null;
+  created_date: string;
+};
+
+export const getNotificationRules = async (): Promise<NotificationRule[]> => {
+  return fetchJson(`${API}/notification-rules`);
+};
+
+export const createNotificationRule = async (payload: NotificationRulePayload): Promise<NotificationRule> => {
+  return fetchJson(`${API}/notification-rules`, {
+    method: 'POST',
+    headers: { 'Content-Type': 'application/json' },
+    body: JSON.stringify(payload),
+  });
+};
+
+export const updateNotificationRule = async (id: number, payload: NotificationRulePayload): Promise<NotificationRule> => {
+  return fetchJson(`${API}/notification-rules/${id}`, {
+    method: 'PUT',
+    headers: { 'Content-Type': 'application/json' },
+    body: JSON.stringify(payload),
+  });
+};
+
+export const deleteNotificationRule = async (id: number): Promise<void> => {
+  await fetchJson(`${API}/notification-rules/${id}`, { method: 'DELETE' });
+};
+
+export const getNotifications = async (unreadOnly = false, beforeId?: number): Promise<AppNotification[]> => {
+  const params = new URLSearchParams();
+  if (unreadOnly) params.set('unread_only', 'true');
+  if (beforeId) params.set('before_id', String(beforeId));
+  return fetchJson(`${API}/notifications?${params.toString()}`);
+};
+
+export const getUnreadNotificationCount = async (): Promise<{ unread: number }> => {
+  return fetchJson(`${API}/notifications/unread-count`);
+};
+
+export const markNotificationRead = async (id: number, read = true): Promise<AppNotification> => {
+  return fetchJson(`${API}/notifications/${id}/${read ? 'read' : 'unread'}`, { method: 'POST' });
+};
+
+export const markAllNotificationsRead = async (): Promise<{ unread: number }> => {
+  return fetchJson(`${API}/notifications/read-all`, { method: 'POST' });
+};
+
+// --- Background jobs
+export type JobRun = {
+  id: number;
+  name: string;
+  trigger: 'schedule' | 'manual';
+  status: 'running' | 'succeeded' | 'failed';
+  scheduled_for?: string | null;
+  started_at: string;
+  finished_at?: string | null;
+  duration_ms?: number | null;
+  summary?: string | null;
+  error?: string | null;
+};
+
+export type JobInfo = {
+  name: string;
+  schedule: string;
+  description: string;
+  next_run?: string | null;
+  last_run?: JobRun | null;
+};
+
+export const getJobs = async (): Promise<JobInfo[]> => {
+  return fetchJson(`${API}/jobs`);
+};
+
+export const getJobRuns = async (name: string, limit = 50): Promise<JobRun[]> => {
+  return fetchJson(`${API}/jobs/${encodeURIComponent(name)}/runs?limit=${limit}`);
+};
+
+export const runJob = async (name: string): Promise<JobRun> => {
+  return fetchJson(`${API}/jobs/${encodeURIComponent(name)}/run`, { method: 'POST' });
+};
+
 // --- Import Templates
 export type ImportTemplate = {
   id: number;
@@ -67,6 +67,8 @@ const Assets: React.FC = () => {
   const [valuationsDialogOpen, setValuationsDialogOpen] = useState(false)
   const [correctBalanceDialogOpen, setCorrectBalanceDialogOpen] = useState(false)
   const [targetBalance, setTargetBalance] = useState('')
+  const [correctionDate, setCorrectionDate] = useState('')
+  const [correctionNote, setCorrectionNote] = useState('')
   const [selectedAsset, setSelectedAsset] = useState<Asset | null>(null)
   const [selectedAssetIds, setSelectedAssetIds] = useState<Set<number>>(new Set())
   const [summaryPeriod, setSummaryPeriod] = useState<string>('thisMonth')
@@ -139,11 +141,18 @@ const Assets: React.FC = () => {
   }
 
   const handleCorrectBalance = () => {
-    if (selectedAsset && targetBalance) {
-      correctBalance({ id: selectedAsset.id, target_balance: parseFloat(targetBalance) })
+    if (selectedAsset && targetBalance && correctionNote.trim()) {
+      correctBalance({
+        id: selectedAsset.id,
+        target_balance: parseFloat(targetBalance),
+        note: correctionNote.trim(),
+        correction_date: correctionDate || undefined,
+      })
       setCorrectBalanceDialogOpen(false)
       setSelectedAsset(null)
       setTargetBalance('')
+      setCorrectionDate('')
+      setCorrectionNote('')
     }
   }
 
@@ -335,6 +344,8 @@ const Assets: React.FC = () => {
                   onCorrectBalance={(asset: Asset) => {
                     setSelectedAsset(asset)
                     setTargetBalance('')
+                    setCorrectionDate(new Date().toISOString().split('T')[0])
+                    setCorrectionNote('')
                     setCorrectBalanceDialogOpen(true)
                   }}
                   onOpenValuations={(asset: Asset) => {
@@ -523,10 +534,16 @@ const Assets: React.FC = () => {
         asset={selectedAsset}
         targetBalance={targetBalance}
         onTargetBalanceChange={setTargetBalance}
+        correctionDate={correctionDate}
+        onCorrectionDateChange={setCorrectionDate}
+        note={correctionNote}
+        onNoteChange={setCorrectionNote}
         onClose={() => {
           setCorrectBalanceDialogOpen(false)
           setSelectedAsset(null)
           setTargetBalance('')
+          setCorrectionDate('')
+          setCorrectionNote('')
         }}
         onConfirm={handleCorrectBalance}
         formatValue={formatValue}
@@ -5,7 +5,11 @@ export default ({ mode }) => {
 
   return defineConfig({
     server: {
-      port: Number(env.VITE_PORT) || 5173
+      port: Number(env.VITE_PORT) || 5173,
+      // The API is same-origin in production; forward it to the backend in dev
+      proxy: {
+        '/api': env.VITE_API_PROXY || 'http://localhost:3000'
+      }
     }
   });
 };